@@ -0,0 +1,87 @@
+//! Dev-only watching of asset files, so art can be iterated on without recompiling.
+
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// Path of the snaek spritesheet on disk.
+pub const SNAEKSHEET_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/snaeksheet.png");
+
+/// Watches a spritesheet file for changes.
+pub struct SheetWatcher {
+	// the watcher stops watching as soon as it's dropped
+	_watcher: RecommendedWatcher,
+	events: Receiver<notify::Result<Event>>,
+}
+
+impl SheetWatcher {
+	pub fn new(path: impl AsRef<Path>) -> notify::Result<Self> {
+		let (tx, events) = mpsc::channel();
+
+		let mut watcher = notify::recommended_watcher(tx)?;
+		watcher.watch(path.as_ref(), RecursiveMode::NonRecursive)?;
+
+		Ok(Self {
+			_watcher: watcher,
+			events,
+		})
+	}
+
+	/// Whether the file was modified since the last call.
+	pub fn changed(&self) -> bool {
+		// drain every pending event so they don't pile up
+		(self.events.try_iter().flatten()).fold(false, |changed, event| {
+			changed | matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_))
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+	use std::thread;
+	use std::time::{Duration, Instant};
+
+	use super::*;
+
+	/// Polls a watcher until it reports a change, or gives up after a while.
+	fn wait_for_change(watcher: &SheetWatcher) -> bool {
+		let start = Instant::now();
+		while start.elapsed() < Duration::from_secs(5) {
+			if watcher.changed() {
+				return true;
+			}
+			thread::sleep(Duration::from_millis(10));
+		}
+
+		false
+	}
+
+	#[test]
+	fn watching_the_bundled_sheet_works() {
+		let watcher = SheetWatcher::new(SNAEKSHEET_PATH).unwrap();
+		assert!(!watcher.changed());
+	}
+
+	#[test]
+	fn writing_the_file_is_a_change() {
+		let path = std::env::temp_dir().join(format!("snaek-hot-reload-{}.png", std::process::id()));
+		fs::write(&path, b"before").unwrap();
+
+		let watcher = SheetWatcher::new(&path).unwrap();
+		assert!(!watcher.changed());
+
+		fs::write(&path, b"after").unwrap();
+		let changed = wait_for_change(&watcher);
+		fs::remove_file(&path).unwrap();
+
+		assert!(changed);
+	}
+
+	#[test]
+	fn watching_a_missing_file_fails() {
+		let path = std::env::temp_dir().join("snaek-hot-reload-missing.png");
+		assert!(SheetWatcher::new(path).is_err());
+	}
+}
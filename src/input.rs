@@ -0,0 +1,282 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use minifb::{InputCallback, Key};
+
+use crate::snake::Direction;
+
+/// Keys that steer the snake, along with the direction they steer it to.
+pub const DIRECTION_KEYS: [(Key, Direction); 8] = [
+	(Key::Up, Direction::Up),
+	(Key::W, Direction::Up),
+	(Key::Right, Direction::Right),
+	(Key::D, Direction::Right),
+	(Key::Down, Direction::Down),
+	(Key::S, Direction::Down),
+	(Key::Left, Direction::Left),
+	(Key::A, Direction::Left),
+];
+
+/// Direction a key steers the snake to, if any.
+pub fn key_direction(key: Key) -> Option<Direction> {
+	(DIRECTION_KEYS.iter())
+		.find(|&&(k, _)| k == key)
+		.map(|&(_, direction)| direction)
+}
+
+/// Resolves which direction to steer to from the keys that are currently held down.
+///
+/// Only held keys that would actually turn the snake count, meaning the current direction and its opposite are
+/// ignored. If several of them are held, the first one in [`DIRECTION_KEYS`] order wins.
+pub fn held_direction(held_keys: &[Key], current: Direction) -> Option<Direction> {
+	(DIRECTION_KEYS.iter())
+		.filter(|(key, _)| held_keys.contains(key))
+		.map(|&(_, direction)| direction)
+		.find(|&direction| direction != current && direction != current.opposite())
+}
+
+/// How analog stick input turns into directions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(unused)]
+pub struct InputConfig {
+	/// How far the stick has to be pushed, from 0 to 1, before it steers at all.
+	pub deadzone: f32,
+	/// Ignore the stick while it's pushed too diagonally, instead of steering along whichever axis it's pushed
+	/// further on. See [`DOMINANT_AXIS_RATIO`].
+	pub require_dominant_axis: bool,
+}
+
+impl Default for InputConfig {
+	fn default() -> Self {
+		Self {
+			deadzone: 0.25,
+			require_dominant_axis: false,
+		}
+	}
+}
+
+/// How many times further the stick has to be pushed along one axis than the other for it to count as dominant.
+///
+/// 2 means the stick has to be within about 26.6° of an axis.
+#[allow(unused)]
+pub const DOMINANT_AXIS_RATIO: f32 = 2.;
+
+/// Direction an analog stick steers the snake to, if any. `y` grows downwards, like on screen.
+///
+/// When the stick is pushed as far horizontally as vertically, the horizontal direction wins.
+#[allow(unused)]
+pub fn stick_direction(x: f32, y: f32, config: &InputConfig) -> Option<Direction> {
+	let magnitude = x.hypot(y);
+	if magnitude.is_nan() || magnitude == 0. || magnitude < config.deadzone {
+		return None;
+	}
+
+	let (major, minor) = (x.abs().max(y.abs()), x.abs().min(y.abs()));
+	if config.require_dominant_axis && major < minor * DOMINANT_AXIS_RATIO {
+		return None;
+	}
+
+	let direction = match x.abs() >= y.abs() {
+		true if x > 0. => Direction::Right,
+		true => Direction::Left,
+		false if y > 0. => Direction::Down,
+		false => Direction::Up,
+	};
+	Some(direction)
+}
+
+/// Keeps track of the order direction keys get pressed in, so that the most recent one wins.
+///
+/// Presses are stamped with an ever-increasing counter as they arrive from the window, so keys pressed within the
+/// same frame are still told apart, no matter the order they're checked in.
+#[derive(Debug, Clone, Default)]
+pub struct DirectionPresses {
+	next_stamp: u64,
+	/// Stamp of the last press of each direction, since they were last taken.
+	pressed_at: [Option<u64>; 4],
+	/// Direction keys that are down, so that key repeats don't count as new presses.
+	held: Vec<Key>,
+}
+
+impl DirectionPresses {
+	/// Records a key going down or up. Keys that don't steer are ignored.
+	pub fn set_key_state(&mut self, key: Key, down: bool) {
+		let Some(direction) = key_direction(key) else {
+			return;
+		};
+
+		let was_held = self.held.contains(&key);
+		if !down {
+			self.held.retain(|&k| k != key);
+		} else if !was_held {
+			self.held.push(key);
+			self.pressed_at[direction as usize] = Some(self.next_stamp);
+			self.next_stamp += 1;
+		}
+	}
+
+	/// Gives the direction that was pressed last since the previous call, and forgets about every press.
+	pub fn take_latest(&mut self) -> Option<Direction> {
+		const DIRECTIONS: [Direction; 4] = [Direction::Up, Direction::Right, Direction::Down, Direction::Left];
+
+		let latest = (DIRECTIONS.into_iter().zip(self.pressed_at))
+			.filter_map(|(direction, stamp)| Some((direction, stamp?)))
+			.max_by_key(|&(_, stamp)| stamp)
+			.map(|(direction, _)| direction);
+
+		self.pressed_at = [None; 4];
+		latest
+	}
+}
+
+/// Feeds the window's key presses into shared [`DirectionPresses`], in the order they happen.
+pub struct DirectionPressRecorder(pub Rc<RefCell<DirectionPresses>>);
+
+impl InputCallback for DirectionPressRecorder {
+	fn add_char(&mut self, _uni_char: u32) {}
+
+	fn set_key_state(&mut self, key: Key, state: bool) {
+		self.0.borrow_mut().set_key_state(key, state);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn nothing_held_doesnt_steer() {
+		assert_eq!(held_direction(&[], Direction::Right), None);
+		assert_eq!(held_direction(&[Key::Space, Key::Enter], Direction::Right), None);
+	}
+
+	#[test]
+	fn held_key_steers_to_its_direction() {
+		assert_eq!(held_direction(&[Key::Up], Direction::Right), Some(Direction::Up));
+		assert_eq!(held_direction(&[Key::S], Direction::Right), Some(Direction::Down));
+		assert_eq!(
+			held_direction(&[Key::Space, Key::A], Direction::Up),
+			Some(Direction::Left)
+		);
+	}
+
+	#[test]
+	fn held_keys_that_wouldnt_turn_are_ignored() {
+		// going the same way, or trying to go back
+		assert_eq!(held_direction(&[Key::Right, Key::D], Direction::Right), None);
+		assert_eq!(held_direction(&[Key::Left], Direction::Right), None);
+		assert_eq!(
+			held_direction(&[Key::Left, Key::Down], Direction::Right),
+			Some(Direction::Down)
+		);
+	}
+
+	#[test]
+	fn first_direction_key_wins_whatever_the_order_keys_are_held_in() {
+		assert_eq!(
+			held_direction(&[Key::Down, Key::Up], Direction::Right),
+			Some(Direction::Up)
+		);
+		assert_eq!(
+			held_direction(&[Key::Up, Key::Down], Direction::Right),
+			Some(Direction::Up)
+		);
+		assert_eq!(held_direction(&[Key::A, Key::D], Direction::Up), Some(Direction::Right));
+	}
+
+	/// The direction pressed last after pressing some keys in order, all within the same frame.
+	fn latest_after(keys: &[Key]) -> Option<Direction> {
+		let mut presses = DirectionPresses::default();
+		for &key in keys {
+			presses.set_key_state(key, true);
+		}
+		presses.take_latest()
+	}
+
+	#[test]
+	fn last_pressed_direction_wins() {
+		assert_eq!(latest_after(&[Key::Up, Key::Left]), Some(Direction::Left));
+		assert_eq!(latest_after(&[Key::Left, Key::Up]), Some(Direction::Up));
+		assert_eq!(latest_after(&[Key::Down, Key::Up]), Some(Direction::Up));
+		assert_eq!(latest_after(&[Key::Up, Key::Down]), Some(Direction::Down));
+		assert_eq!(latest_after(&[Key::W, Key::Space, Key::D]), Some(Direction::Right));
+		assert_eq!(latest_after(&[Key::Space]), None);
+		assert_eq!(latest_after(&[]), None);
+	}
+
+	#[test]
+	fn taking_the_latest_press_forgets_every_press() {
+		let mut presses = DirectionPresses::default();
+		presses.set_key_state(Key::Up, true);
+		assert_eq!(presses.take_latest(), Some(Direction::Up));
+		assert_eq!(presses.take_latest(), None);
+
+		// a key that's still down isn't pressed again by repeats, but is once it's released
+		presses.set_key_state(Key::Up, false);
+		presses.set_key_state(Key::Left, true);
+		presses.set_key_state(Key::Up, true);
+		assert_eq!(presses.take_latest(), Some(Direction::Up));
+		presses.set_key_state(Key::Left, false);
+		presses.set_key_state(Key::Left, true);
+		assert_eq!(presses.take_latest(), Some(Direction::Left));
+	}
+
+	#[test]
+	fn key_repeats_dont_count_as_new_presses() {
+		let mut presses = DirectionPresses::default();
+		presses.set_key_state(Key::Up, true);
+		presses.set_key_state(Key::Right, true);
+		// repeated while held, coming after the right press
+		presses.set_key_state(Key::Up, true);
+		assert_eq!(presses.take_latest(), Some(Direction::Right));
+	}
+
+	const SNAPPING: InputConfig = InputConfig {
+		deadzone: 0.25,
+		require_dominant_axis: false,
+	};
+	const STRICT: InputConfig = InputConfig {
+		deadzone: 0.25,
+		require_dominant_axis: true,
+	};
+
+	#[test]
+	fn sticks_within_the_deadzone_dont_steer() {
+		for config in [SNAPPING, STRICT] {
+			assert_eq!(stick_direction(0., 0., &config), None);
+			assert_eq!(stick_direction(0.2, 0., &config), None);
+			assert_eq!(stick_direction(-0.15, 0.15, &config), None);
+			assert_eq!(stick_direction(f32::NAN, 1., &config), None);
+
+			assert_eq!(stick_direction(0.25, 0., &config), Some(Direction::Right));
+			assert_eq!(stick_direction(0., -0.3, &config), Some(Direction::Up));
+		}
+
+		let no_deadzone = InputConfig {
+			deadzone: 0.,
+			..SNAPPING
+		};
+		assert_eq!(stick_direction(0., 0.01, &no_deadzone), Some(Direction::Down));
+		assert_eq!(stick_direction(0., 0., &no_deadzone), None);
+	}
+
+	#[test]
+	fn near_diagonal_sticks_snap_to_the_axis_pushed_further() {
+		assert_eq!(stick_direction(0.7, 0.6, &SNAPPING), Some(Direction::Right));
+		assert_eq!(stick_direction(-0.6, 0.7, &SNAPPING), Some(Direction::Down));
+		assert_eq!(stick_direction(-0.7, -0.6, &SNAPPING), Some(Direction::Left));
+		// dead on the diagonal, horizontal wins
+		assert_eq!(stick_direction(0.5, -0.5, &SNAPPING), Some(Direction::Right));
+	}
+
+	#[test]
+	fn near_diagonal_sticks_are_ignored_when_an_axis_has_to_dominate() {
+		assert_eq!(stick_direction(0.7, 0.6, &STRICT), None);
+		assert_eq!(stick_direction(0.5, -0.5, &STRICT), None);
+		assert_eq!(stick_direction(-0.6, 0.31, &STRICT), None);
+
+		// twice as far along one axis is enough
+		assert_eq!(stick_direction(-0.6, 0.3, &STRICT), Some(Direction::Left));
+		assert_eq!(stick_direction(0.1, -0.9, &STRICT), Some(Direction::Up));
+	}
+}
@@ -1,22 +1,38 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
 use std::error::Error;
+use std::fs;
+use std::ops::BitOr;
+use std::rc::Rc;
+use std::time::{Duration, Instant, SystemTime};
 
 use crate::snake::Direction;
 
-use self::math::pos::pos;
+use self::math::easing::Easing;
+use self::math::pos::{pos, Pos};
+use self::math::rect::Rect;
 use self::math::size::size;
 use self::render::bitmap::Bitmap;
 use self::render::color::{alphacomp, Color};
-use image::{ImageFormat, ImageResult};
+use self::render::sprite::Sprite;
+use input::{DirectionPressRecorder, DirectionPresses};
 use math::size::Size;
 use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Scale, ScaleMode, Window, WindowOptions};
 use owo_colors::OwoColorize;
 use render::{DrawCommand, Renderer, Rotate, SpritesheetId};
-use snake::{Banana, SnaekSheet, SnakeGame};
+use snake::{
+	Banana, CellVisual, GameEvent, GameOverReason, Ghost, Replay, ReplayRecorder, RestartKind, SnaekSheet, SnakeGame,
+	WrapConfig,
+};
+use ui::components::{RunStats, Theme};
 use ui::{
 	Anchor, FlexDirection, Mouse, UiContext, WidgetDim, WidgetFlags, WidgetId, WidgetLayout, WidgetPadding,
 	WidgetProps, WidgetSize, WidgetSprite,
 };
 
+#[cfg(feature = "hot-reload")]
+mod hot_reload;
+mod input;
 mod math;
 mod render;
 mod snake;
@@ -24,6 +40,16 @@ mod ui;
 
 const WIDTH: u16 = 97;
 const HEIGHT: u16 = 124;
+const WINDOW_SCALE: Scale = Scale::X4;
+
+/// Smallest board the +/- buttons can shrink the playfield to.
+const MIN_BOARD_SIZE: Size = size(5, 5);
+
+/// Columns at the end of the tongue sprite that make up its forked tip.
+const TONGUE_TIP_LEN: u16 = 3;
+
+/// Extra updates per frame while holding N in debug mode.
+const FAST_FORWARD_STEPS: u32 = 4;
 
 fn main() {
 	eprintln!("{}", "Snaek!!".yellow());
@@ -40,40 +66,314 @@ fn main() {
 const IMG_ASCII_CHARS: &[u8] = include_bytes!("../assets/ascii-chars.png");
 const IMG_SNAEKSHEET: &[u8] = include_bytes!("../assets/snaeksheet.png");
 
-/// Loads a PNG from memory into a raw ARGB8 bitmap.
-fn load_png_from_memory(png: &[u8]) -> ImageResult<Bitmap> {
-	let img = image::load_from_memory_with_format(png, ImageFormat::Png)?;
-
-	let size = size(img.width() as u16, img.height() as u16);
+/// Atlas definition of the snaek spritesheet, so its regions can be rearranged without recompiling.
+const SNAEKSHEET_ATLAS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/snaeksheet.toml");
 
-	let buffer = (img.into_rgba8().pixels())
-		.map(|pixel| {
-			let [r, g, b, a] = pixel.0;
-			u32::from_le_bytes([b, g, r, a])
-		})
-		.collect::<Vec<u32>>();
+/// Loads the snaek sheet's regions from its atlas definition, falling back on the built-in ones.
+fn load_snaek_sheet(sheet_size: Size) -> SnaekSheet {
+	let Ok(src) = fs::read_to_string(SNAEKSHEET_ATLAS_PATH) else {
+		return snake::snaek_sheet();
+	};
 
-	Ok(Bitmap::from_buffer(buffer, size))
+	SnaekSheet::from_atlas(&src, sheet_size).unwrap_or_else(|e| {
+		eprintln!("{} {}", "Couldn't load the spritesheet atlas:".red(), e);
+		snake::snaek_sheet()
+	})
 }
 
 const VIEWPORT_SIZE: Size = size(WIDTH, HEIGHT);
 
 const SNAEK_BLACK: Color = Color::from_hex(0xff181425);
+const DYING_RED: Color = Color::from_hex(0xffe43b44);
+/// How many times the snake blinks while the death animation plays.
+const DEATH_BLINKS: u32 = 3;
+/// Opacity of the ghost of the best run, drawn behind the live snake.
+const GHOST_OPACITY: u8 = 0x60;
+
+/// Overlays drawn on top of the snake game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(transparent)]
+struct RenderFlags(u32);
+
+#[rustfmt::skip]
+#[allow(unused)]
+impl RenderFlags {
+	const NONE:             Self = Self(0);
+	const GRID:             Self = Self(1 << 0);
+	const DIRECTION_ARROWS: Self = Self(1 << 1);
+	const CELL_COORDS:      Self = Self(1 << 2);
+}
+
+impl RenderFlags {
+	fn has(&self, flags: Self) -> bool {
+		self.0 & flags.0 == flags.0
+	}
+}
+
+impl BitOr for RenderFlags {
+	type Output = Self;
+
+	fn bitor(self, rhs: Self) -> Self::Output {
+		Self(self.0 | rhs.0)
+	}
+}
+
+/// Configuration of how the snake game is drawn.
+#[derive(Debug, Clone, Copy)]
+struct GameRenderConfig {
+	/// Size of a playfield cell, in pixels.
+	cell_size: u16,
+	/// Overlays to draw when debug mode is on.
+	debug_overlays: RenderFlags,
+	/// How many ticks the fading trail behind the snake lasts. 0 disables it.
+	trail_length: usize,
+	/// How many cells ahead of the head the tongue reaches when it's fully out.
+	tongue_reach: u16,
+}
+
+impl Default for GameRenderConfig {
+	fn default() -> Self {
+		Self {
+			cell_size: 7,
+			debug_overlays: RenderFlags::GRID | RenderFlags::DIRECTION_ARROWS,
+			trail_length: 3,
+			tongue_reach: 1,
+		}
+	}
+}
+
+impl GameRenderConfig {
+	/// Position of a playfield cell's top-left corner, relative to the playfield.
+	fn cell_pos(&self, slot_pos: Pos) -> Pos {
+		slot_pos * self.cell_size as i16
+	}
+
+	/// The same config, with cells shrunk so that a whole board fits in the available space if it doesn't already.
+	///
+	/// Cells never grow past the configured size, and an empty space (e.g. before the first layout) keeps it as is.
+	fn fitted(&self, board_size: Size, available: Size) -> Self {
+		if available.w == 0 || available.h == 0 || board_size.w == 0 || board_size.h == 0 {
+			return *self;
+		}
+
+		let cell_size = (self.cell_size)
+			.min(available.w / board_size.w)
+			.min(available.h / board_size.h)
+			.max(1);
+
+		Self { cell_size, ..*self }
+	}
+
+	/// Size of a whole board, in pixels.
+	fn board_pixel_size(&self, board_size: Size) -> Size {
+		size(
+			board_size.w.saturating_mul(self.cell_size),
+			board_size.h.saturating_mul(self.cell_size),
+		)
+	}
+
+	/// How many pixels past the head the tongue sticks out at some point of its animation, with `phase` going
+	/// from 0 to 1 over an update.
+	///
+	/// It goes out to its full reach and back, never getting shorter than its tip.
+	fn tongue_extension(&self, phase: f32, tip_len: u16) -> u16 {
+		let reach = self.tongue_reach.saturating_mul(self.cell_size).max(tip_len);
+		let out = 1. - (2. * phase.clamp(0., 1.) - 1.).abs();
+		tip_len + ((reach - tip_len) as f32 * out).round() as u16
+	}
+
+	/// Where to draw each one pixel thick slice of a tongue sticking out of the head by `extension` pixels, along
+	/// with the column of the tongue sprite to draw there, and the rotation to draw it with.
+	///
+	/// The sprite faces right with its tip in the last `tip_len` columns, and its first column is used for
+	/// the rest of the tongue. Slices that go past an edge of the board come back on the other side if the board wraps
+	/// there, and are left out if it doesn't.
+	fn tongue_slices(
+		&self,
+		head: Pos,
+		facing: Direction,
+		extension: u16,
+		tongue_size: Size,
+		board_size: Size,
+		wrap: WrapConfig,
+	) -> (Vec<(Pos, u16)>, Rotate) {
+		let cs = self.cell_size as i16;
+		let head_px = self.cell_pos(head);
+		let board_px = self.board_pixel_size(board_size);
+		let across = (cs - tongue_size.h as i16) / 2;
+
+		let rotate = match facing {
+			Direction::Up => Rotate::R270,
+			Direction::Right => Rotate::R0,
+			Direction::Down => Rotate::R90,
+			Direction::Left => Rotate::R180,
+		};
+
+		// a coordinate on the board along an axis, if it's on it or the axis wraps around
+		let on_board = |p: i16, len: u16, wraps: bool| match wraps {
+			true => Some(p.rem_euclid(len.max(1) as i16)),
+			false => (0..len as i16).contains(&p).then_some(p),
+		};
+
+		let slices = (0..extension)
+			.filter_map(|d| {
+				let column = (d + tongue_size.w).saturating_sub(extension);
+				let d = d as i16;
+				let slice_pos = match facing {
+					Direction::Up => pos(head_px.x + across, head_px.y - 1 - d),
+					Direction::Right => pos(head_px.x + cs + d, head_px.y + across),
+					Direction::Down => pos(head_px.x + across, head_px.y + cs + d),
+					Direction::Left => pos(head_px.x - 1 - d, head_px.y + across),
+				};
+				let x = on_board(slice_pos.x, board_px.w, wrap.horizontal)?;
+				let y = on_board(slice_pos.y, board_px.h, wrap.vertical)?;
+				Some((pos(x, y), column))
+			})
+			.collect();
+
+		(slices, rotate)
+	}
+}
+
+/// Keeps a requested board size between [`MIN_BOARD_SIZE`] and the biggest board that can be displayed.
+fn clamp_board_size(requested: Size, max: Size) -> Size {
+	requested.min_component(max).max_component(MIN_BOARD_SIZE)
+}
+
+const SNAKE_TRAIL_GREEN: Color = Color::from_hex(0xff3e8948);
+
+/// Where the snake was during the last few ticks, to draw a fading trail behind it.
+///
+/// It's purely cosmetic: the game itself knows nothing about it.
+struct SnakeTrail {
+	/// Positions of the snake on each previous tick, most recent first.
+	ticks: VecDeque<Vec<Pos>>,
+	len: usize,
+}
+
+impl SnakeTrail {
+	fn new(len: usize) -> Self {
+		Self {
+			ticks: VecDeque::with_capacity(len),
+			len,
+		}
+	}
+
+	/// Remembers where the snake was before it moved.
+	fn push(&mut self, positions: Vec<Pos>) {
+		if self.len == 0 {
+			return;
+		}
+
+		if self.ticks.len() == self.len {
+			self.ticks.pop_back();
+		}
+		self.ticks.push_front(positions);
+	}
+
+	fn clear(&mut self) {
+		self.ticks.clear();
+	}
+
+	/// How many ticks ago the snake was last at a position, starting at 0 for the previous tick.
+	fn age_at(&self, pos: Pos) -> Option<usize> {
+		self.ticks.iter().position(|positions| positions.contains(&pos))
+	}
+
+	/// Color of the trail at some age, fading out as it gets older.
+	fn color(&self, age: usize) -> Color {
+		let alpha = 0x80 * (self.len - age) / (self.len + 1);
+		Color {
+			a: alpha as u8,
+			..SNAKE_TRAIL_GREEN
+		}
+	}
+}
+
+/// Measures frames, telling the time spent on the game itself apart from the time spent presenting it.
+///
+/// Presenting can block on vsync, so it can take most of a frame without the game being slow.
+struct FrameClock {
+	last_tick: Instant,
+	present_start: Option<Instant>,
+	/// Time spent presenting since the last tick.
+	presenting: Duration,
+	last_logic: Duration,
+	last_present: Duration,
+}
+
+impl FrameClock {
+	fn new() -> Self {
+		Self {
+			last_tick: Instant::now(),
+			present_start: None,
+			presenting: Duration::ZERO,
+			last_logic: Duration::ZERO,
+			last_present: Duration::ZERO,
+		}
+	}
+
+	/// Marks the start of presenting the frame to the window.
+	fn begin_present(&mut self) {
+		self.present_start = Some(Instant::now());
+	}
+
+	/// Marks the end of presenting the frame to the window.
+	fn end_present(&mut self) {
+		if let Some(start) = self.present_start.take() {
+			self.presenting += start.elapsed();
+		}
+	}
+
+	/// Ends the frame, and gives the whole time it took, presenting included.
+	///
+	/// That's the time the game should advance by. Leaving the presenting time out would slow the game down to the
+	/// speed of its logic instead of the wall clock.
+	fn tick(&mut self) -> Duration {
+		let now = Instant::now();
+		let frame_time = now - self.last_tick;
+		self.last_tick = now;
+
+		(self.last_logic, self.last_present) = split_frame_time(frame_time, self.presenting);
+		self.presenting = Duration::ZERO;
+		frame_time
+	}
+
+	/// Time the last frame spent outside of presenting, on input, UI, game logic and rendering.
+	fn logic_time(&self) -> Duration {
+		self.last_logic
+	}
+
+	/// Time the last frame spent presenting to the window, waiting for vsync included.
+	fn present_time(&self) -> Duration {
+		self.last_present
+	}
+}
+
+/// Splits the time of a frame into its logic and presenting portions, as `(logic, present)`.
+fn split_frame_time(frame_time: Duration, presenting: Duration) -> (Duration, Duration) {
+	let present = presenting.min(frame_time);
+	(frame_time - present, present)
+}
 
 fn game() -> Result<(), Box<dyn Error>> {
-	let ascii_bitmap = load_png_from_memory(IMG_ASCII_CHARS)?;
+	let ascii_bitmap = Bitmap::from_png(IMG_ASCII_CHARS)?;
 
-	let mut renderer = Renderer::new(Bitmap::new(VIEWPORT_SIZE), ascii_bitmap);
+	let mut renderer = Renderer::new(Bitmap::new(VIEWPORT_SIZE)?, ascii_bitmap);
 	let mut ui = UiContext::new(VIEWPORT_SIZE);
 
-	let snaek_sheet_id = renderer.register_spritesheet(load_png_from_memory(IMG_SNAEKSHEET)?);
-	let snaek_sheet = snake::snaek_sheet();
+	let snaek_bitmap = Bitmap::from_png(IMG_SNAEKSHEET)?;
+	let snaek_sheet = load_snaek_sheet(snaek_bitmap.size());
+	let snaek_sheet_id = renderer.register_spritesheet_named("snaek", snaek_bitmap);
+
+	#[cfg(feature = "hot-reload")]
+	let sheet_watcher = hot_reload::SheetWatcher::new(hot_reload::SNAEKSHEET_PATH)?;
 
 	let options = WindowOptions {
 		borderless: true,
 		title: true,
 		resize: false,
-		scale: Scale::X4,
+		scale: WINDOW_SCALE,
 		scale_mode: ScaleMode::Stretch,
 		..Default::default()
 	};
@@ -81,17 +381,32 @@ fn game() -> Result<(), Box<dyn Error>> {
 	let mut window = Window::new("Snaek", WIDTH as usize, HEIGHT as usize, options)?;
 	window.set_target_fps(60);
 
+	let direction_presses = Rc::new(RefCell::new(DirectionPresses::default()));
+	window.set_input_callback(Box::new(DirectionPressRecorder(direction_presses.clone())));
+
+	let render_config = GameRenderConfig::default();
+	let theme = snaek_theme(snaek_sheet_id, &snaek_sheet);
+
 	let mut snake_game = SnakeGame::new(size(11, 11));
+	let mut snake_trail = SnakeTrail::new(render_config.trail_length);
 	let mut next_direction = snake_game.direction();
+	// biggest board that fits the playfield at full cell size, as solved last frame
+	let mut max_board_size = snake_game.size();
+	// the best run so far plays back as a ghost next to the live one
+	let mut best_replay: Option<Replay> = None;
+	let (mut recorder, mut ghost) = start_run_replay(&snake_game, best_replay.as_ref());
 
 	let mut debug = false;
 	let mut show_game_over = false;
+	// keep steering with whichever direction key is held down on each tick, instead of only on key presses
+	let mut hold_to_steer = false;
 
+	let mut game_events = Vec::new();
 	let mut draw_cmds = Vec::new();
 	let mut mouse = Mouse::default();
 	let mut unscaled_mouse_pos = None;
 
-	let mut frame_count: u64 = 0;
+	let mut clock = FrameClock::new();
 
 	'game_loop: while window.is_open() {
 		// input handling
@@ -99,24 +414,53 @@ fn game() -> Result<(), Box<dyn Error>> {
 			break;
 		}
 
-		if let Some(next_pos) = window.get_mouse_pos(MouseMode::Discard) {
-			mouse.x = next_pos.0;
-			mouse.y = next_pos.1;
+		#[cfg(feature = "hot-reload")]
+		if sheet_watcher.changed() {
+			match renderer.reload_spritesheet(snaek_sheet_id, hot_reload::SNAEKSHEET_PATH) {
+				Ok(()) => eprintln!("{}", "Reloaded the spritesheet".cyan()),
+				Err(e) => eprintln!("{} {}", "Couldn't reload the spritesheet:".red(), e),
+			}
+		}
+
+		// the cursor is kept in window pixels, and only mapped to the viewport when hit-testing
+		// the position is kept when the cursor leaves, so it doesn't jump around, but it stops hovering anything
+		let (window_w, window_h) = window.get_size();
+		match window.get_unscaled_mouse_pos(MouseMode::Pass) {
+			Some((x, y)) => {
+				mouse.x = x;
+				mouse.y = y;
+				mouse.inside = (0. ..window_w as f32).contains(&x) && (0. ..window_h as f32).contains(&y);
+			}
+			None => mouse.inside = false,
 		}
 
 		mouse.l_pressed = (window.get_mouse_down(MouseButton::Left), mouse.l_pressed.0);
 		mouse.r_pressed = (window.get_mouse_down(MouseButton::Right), mouse.r_pressed.0);
 		mouse.m_pressed = (window.get_mouse_down(MouseButton::Middle), mouse.m_pressed.0);
 
+		// print last frame's layout, to find out which widget is where
+		if debug && window.is_key_pressed(Key::L, KeyRepeat::No) {
+			eprint!("{}", ui.dump_layout(UiContext::ROOT_WIDGET));
+		}
+
+		// print how the last frame's time was spent
+		if debug && window.is_key_pressed(Key::F, KeyRepeat::No) {
+			eprintln!(
+				"logic: {:.2}ms, present: {:.2}ms",
+				clock.logic_time().as_secs_f64() * 1000.,
+				clock.present_time().as_secs_f64() * 1000.
+			);
+		}
+
+		if window.is_key_pressed(Key::H, KeyRepeat::No) {
+			hold_to_steer = !hold_to_steer;
+			eprintln!("Hold to steer: {}", if hold_to_steer { "on" } else { "off" });
+		}
+
 		// snake input
-		if window.is_key_pressed(Key::Up, KeyRepeat::No) || window.is_key_pressed(Key::W, KeyRepeat::No) {
-			next_direction = Direction::Up;
-		} else if window.is_key_pressed(Key::Right, KeyRepeat::No) || window.is_key_pressed(Key::D, KeyRepeat::No) {
-			next_direction = Direction::Right;
-		} else if window.is_key_pressed(Key::Down, KeyRepeat::No) || window.is_key_pressed(Key::S, KeyRepeat::No) {
-			next_direction = Direction::Down;
-		} else if window.is_key_pressed(Key::Left, KeyRepeat::No) || window.is_key_pressed(Key::A, KeyRepeat::No) {
-			next_direction = Direction::Left;
+		// the key pressed last wins, even within the same frame
+		if let Some(direction) = direction_presses.borrow_mut().take_latest() {
+			next_direction = direction;
 		}
 
 		draw_cmds.clear();
@@ -125,62 +469,86 @@ fn game() -> Result<(), Box<dyn Error>> {
 		// UI
 		let window_frame = ui.build_widget(
 			WidgetProps::new(wk!())
+				.with_debug_name("window_frame")
 				.with_flags(WidgetFlags::DRAW_BACKGROUND | WidgetFlags::DRAW_BORDER)
 				.with_color(Color::from_hex(0xffc0cbdc))
 				.with_border_color(Color::from_hex(0xff181425))
 				.with_border_width(1)
-				.with_acf(Some(alphacomp::dst))
+				.with_acf(Some(alphacomp::SRC))
 				.with_size(WidgetSize::fill())
 				.with_padding(WidgetPadding::all(1))
 				.with_layout(WidgetLayout::flex(FlexDirection::Vertical, 0)),
 		);
 		{
-			let navbar = ui.build_widget(
+			let mut close_clicked = false;
+			let navbar = ui.row(
 				WidgetProps::new(wk!())
+					.with_debug_name("navbar")
 					.with_flags(WidgetFlags::CAN_CLICK)
-					.with_size(WidgetSize::new(WidgetDim::Fill, WidgetDim::Fixed(8)))
-					.with_layout(WidgetLayout::flex(FlexDirection::Horizontal, 0)),
-			);
-			{
-				let snaek_icon = ui.build_widget(
-					WidgetProps::simple_sprite(wk!(), snaek_sheet_id, snaek_sheet.snaek_icon)
-						.with_size(WidgetSize::fixed(8, 8))
-						.with_draw_offset(pos(1, 1))
-						.with_layout(WidgetLayout::flex(FlexDirection::Horizontal, 0)),
-				);
-				ui.add_child(navbar.id(), snaek_icon.id());
+					.with_size(WidgetSize::new(WidgetDim::Fill, WidgetDim::Fixed(8))),
+				|navbar| {
+					navbar.build_widget(
+						WidgetProps::simple_sprite(wk!(), snaek_sheet_id, snaek_sheet.snaek_icon)
+							.with_size(WidgetSize::fixed(8, 8))
+							.with_draw_offset(pos(1, 1))
+							.with_layout(WidgetLayout::flex(FlexDirection::Horizontal, 0)),
+					);
 
-				let filler = ui.build_widget(
-					WidgetProps::new(wk!())
-						.with_size(WidgetSize::fill())
-						.with_padding(WidgetPadding::hv(2, 1)),
-				);
-				{
-					let title = ui.build_widget(
-						WidgetProps::text(wk!(), renderer.text("Snaek"))
-							.with_anchor_origin(Anchor::BOTTOM_LEFT, Anchor::BOTTOM_LEFT)
-							.with_mask_and(Some(SNAEK_BLACK)),
+					navbar.with_children(
+						WidgetProps::new(wk!())
+							.with_size(WidgetSize::fill())
+							.with_padding(WidgetPadding::hv(2, 1)),
+						|filler| {
+							filler.build_widget(
+								WidgetProps::text(wk!(), renderer.text_cached("Snaek"))
+									.with_anchor_origin(Anchor::BOTTOM_LEFT, Anchor::BOTTOM_LEFT)
+									.with_mask_and(Some(SNAEK_BLACK)),
+							);
+						},
 					);
-					ui.add_child(filler.id(), title.id());
-				}
-				ui.add_child(navbar.id(), filler.id());
 
-				let btn_close = ui.btn_icon(
-					WidgetProps::new(wk!()).with_size(WidgetSize::fixed(7, 7)),
-					WidgetProps::simple_sprite(wk!(), snaek_sheet_id, snaek_sheet.icon_close)
-						.with_mask_and(Some(SNAEK_BLACK)),
-					Color::from_hex(0xffe43b44),
-				);
-				ui.add_child(navbar.id(), btn_close.id());
+					// growing or shrinking the board starts a new round on it
+					for (i, label, step) in [(0, "-", -1), (1, "+", 1)] {
+						let btn_resize = navbar.ui().btn_icon(
+							WidgetProps::new(wk!(i)).with_size(WidgetSize::fixed(7, 7)),
+							WidgetProps::text(wk!(i), renderer.text_cached(label)).with_mask_and(Some(SNAEK_BLACK)),
+							Color::from_hex(0xff63c74d),
+						);
+						navbar.add_child(btn_resize.id());
 
-				if btn_close.clicked() {
-					break 'game_loop;
-				}
-			}
+						let current = snake_game.size();
+						let requested = size(
+							current.w.saturating_add_signed(step),
+							current.h.saturating_add_signed(step),
+						);
+						let board_size = clamp_board_size(requested, max_board_size);
+						if btn_resize.clicked() && board_size != current {
+							snake_game.resize(board_size);
+							snake_trail.clear();
+							show_game_over = false;
+							next_direction = snake_game.direction();
+							(recorder, ghost) = start_run_replay(&snake_game, best_replay.as_ref());
+						}
+					}
+
+					let btn_close = navbar.ui().btn_icon(
+						WidgetProps::new(wk!()).with_size(WidgetSize::fixed(7, 7)),
+						WidgetProps::simple_sprite(wk!(), snaek_sheet_id, snaek_sheet.icon_close)
+							.with_mask_and(Some(SNAEK_BLACK)),
+						Color::from_hex(0xffe43b44),
+					);
+					navbar.add_child(btn_close.id());
+					close_clicked = btn_close.clicked();
+				},
+			);
 			ui.add_child(window_frame.id(), navbar.id());
 
+			if close_clicked {
+				break 'game_loop;
+			}
+
 			if navbar.pressed() {
-				let (cpx, cpy) = window.get_unscaled_mouse_pos(MouseMode::Pass).unwrap_or_default();
+				let (cpx, cpy) = (mouse.x, mouse.y);
 				let (mpx, mpy) = unscaled_mouse_pos.unwrap_or((cpx, cpy));
 
 				let (wpx, wpy) = window.get_position();
@@ -193,7 +561,8 @@ fn game() -> Result<(), Box<dyn Error>> {
 
 			let game_frame = ui.build_widget(
 				WidgetProps::nine_slice_sprite(wk!(), snaek_sheet_id, snaek_sheet.box_embossed)
-					.with_acf(Some(alphacomp::dst))
+					.with_debug_name("game_frame")
+					.with_acf(Some(alphacomp::SRC))
 					.with_size(WidgetSize::fill())
 					.with_padding(WidgetPadding::trbl(4, 5, 5, 5))
 					.with_layout(WidgetLayout::flex(FlexDirection::Vertical, 2)),
@@ -207,7 +576,7 @@ fn game() -> Result<(), Box<dyn Error>> {
 				{
 					let big_display = ui.big_3digits_display(
 						wk!(),
-						snake_game.bananas_eaten() as usize,
+						snake_game.score_of(0) as usize,
 						snaek_sheet_id,
 						snaek_sheet.box_num_display,
 						snaek_sheet.bignum_placeholder,
@@ -215,104 +584,150 @@ fn game() -> Result<(), Box<dyn Error>> {
 					);
 					ui.add_child(display_frame.id(), big_display.id());
 
-					let middle_frame = ui.build_widget(
+					let middle_frame = ui.column(
 						WidgetProps::new(wk!())
 							.with_size(WidgetSize::hug())
 							.with_layout(WidgetLayout::flex(FlexDirection::Vertical, 2)),
-					);
-					{
-						let icon_restart = ui.build_widget(
-							WidgetProps::simple_sprite(wk!(), snaek_sheet_id, snaek_sheet.icon_restart)
-								.with_anchor_origin(Anchor::CENTER, Anchor::CENTER)
-								.with_acf(Some(alphacomp::xor)),
-						);
-						let btn_restart = ui.btn_box(
-							WidgetProps::new(wk!())
-								.with_size(WidgetSize::hug())
-								.with_padding(WidgetPadding::hv(3, 2)),
-							WidgetSprite::NineSlice(snaek_sheet_id, snaek_sheet.box_embossed),
-							WidgetSprite::NineSlice(snaek_sheet_id, snaek_sheet.box_carved),
-							icon_restart.id(),
-						);
-						ui.add_child(middle_frame.id(), btn_restart.id());
-
-						if btn_restart.clicked() {
-							snake_game.restart();
-							show_game_over = false;
-							next_direction = snake_game.direction();
-						}
-
-						let icon_playpause = {
-							let sprite = match debug {
-								true => snaek_sheet.icon_play,
-								false => snaek_sheet.icon_debug,
-							};
-
-							ui.build_widget(
-								WidgetProps::simple_sprite(wk!(), snaek_sheet_id, sprite)
+						|column| {
+							let icon_restart = column.ui().build_widget(
+								WidgetProps::simple_sprite(wk!(), snaek_sheet_id, snaek_sheet.icon_restart)
 									.with_anchor_origin(Anchor::CENTER, Anchor::CENTER)
-									.with_acf(Some(alphacomp::xor)),
-							)
-						};
-						let btn_playdebug = ui.btn_box(
-							WidgetProps::new(wk!())
-								.with_size(WidgetSize::hug())
-								.with_padding(WidgetPadding::hv(3, 2)),
-							WidgetSprite::NineSlice(snaek_sheet_id, snaek_sheet.box_embossed),
-							WidgetSprite::NineSlice(snaek_sheet_id, snaek_sheet.box_carved),
-							icon_playpause.id(),
-						);
-						ui.add_child(middle_frame.id(), btn_playdebug.id());
+									.with_acf(Some(alphacomp::XOR)),
+							);
+							let btn_restart = column.ui().btn_box(
+								WidgetProps::new(wk!())
+									.with_shortcut(Key::R)
+									.with_size(WidgetSize::hug())
+									.with_padding(WidgetPadding::hv(3, 2)),
+								WidgetSprite::NineSlice(snaek_sheet_id, snaek_sheet.box_embossed),
+								WidgetSprite::NineSlice(snaek_sheet_id, snaek_sheet.box_carved),
+								icon_restart.id(),
+							);
+							column.add_child(btn_restart.id());
+
+							if btn_restart.clicked() {
+								snake_game.restart(RestartKind::Fresh);
+								snake_trail.clear();
+								show_game_over = false;
+								next_direction = snake_game.direction();
+								(recorder, ghost) = start_run_replay(&snake_game, best_replay.as_ref());
+							}
+
+							let label_daily = column.ui().build_widget(
+								WidgetProps::text(wk!(), renderer.text_cached("D"))
+									.with_anchor_origin(Anchor::CENTER, Anchor::CENTER)
+									.with_mask_and(Some(SNAEK_BLACK)),
+							);
+							let btn_daily = column.ui().btn_box(
+								WidgetProps::new(wk!())
+									.with_shortcut(Key::T)
+									.with_size(WidgetSize::hug())
+									.with_padding(WidgetPadding::hv(3, 2)),
+								WidgetSprite::NineSlice(snaek_sheet_id, snaek_sheet.box_embossed),
+								WidgetSprite::NineSlice(snaek_sheet_id, snaek_sheet.box_carved),
+								label_daily.id(),
+							);
+							column.add_child(btn_daily.id());
+
+							// today's daily run is the same game for everyone
+							if btn_daily.clicked() {
+								snake_game = SnakeGame::with_seed(snake_game.size(), snake::daily_seed(today_utc()));
+								snake_trail.clear();
+								show_game_over = false;
+								next_direction = snake_game.direction();
+								(recorder, ghost) = start_run_replay(&snake_game, best_replay.as_ref());
+							}
+
+							let icon_playpause = {
+								let sprite = match debug {
+									true => snaek_sheet.icon_play,
+									false => snaek_sheet.icon_debug,
+								};
+
+								column.ui().build_widget(
+									WidgetProps::simple_sprite(wk!(), snaek_sheet_id, sprite)
+										.with_anchor_origin(Anchor::CENTER, Anchor::CENTER)
+										.with_acf(Some(alphacomp::XOR)),
+								)
+							};
+							let btn_playdebug = column.ui().btn_box(
+								WidgetProps::new(wk!())
+									.with_size(WidgetSize::hug())
+									.with_padding(WidgetPadding::hv(3, 2)),
+								WidgetSprite::NineSlice(snaek_sheet_id, snaek_sheet.box_embossed),
+								WidgetSprite::NineSlice(snaek_sheet_id, snaek_sheet.box_carved),
+								icon_playpause.id(),
+							);
+							column.add_child(btn_playdebug.id());
 
-						if btn_playdebug.clicked() {
-							debug = !debug;
-						}
-					}
+							if btn_playdebug.clicked() {
+								debug = !debug;
+							}
+						},
+					);
 					ui.add_child(display_frame.id(), middle_frame.id());
 
-					let right_frame = ui.build_widget(
+					let right_frame = ui.column(
 						WidgetProps::new(wk!())
 							.with_size(WidgetSize::fill())
 							.with_layout(WidgetLayout::flex(FlexDirection::Vertical, 2)),
-					);
-					{
-						let text_holder = ui.build_widget(WidgetProps::new(wk!()).with_size(WidgetSize::fill()));
-						{
-							let text = ui.build_widget(
-								WidgetProps::text(wk!(), renderer.text("Speykious"))
-									.with_anchor_origin(Anchor::BOTTOM_LEFT, Anchor::BOTTOM_LEFT)
-									.with_mask_and(Some(SNAEK_BLACK)),
+						|column| {
+							column.with_children(
+								WidgetProps::new(wk!()).with_size(WidgetSize::fill()),
+								|text_holder| {
+									text_holder.build_widget(
+										WidgetProps::text(wk!(), renderer.text_cached("Speykious"))
+											.with_anchor_origin(Anchor::BOTTOM_LEFT, Anchor::BOTTOM_LEFT)
+											.with_mask_and(Some(SNAEK_BLACK)),
+									);
+								},
 							);
-							ui.add_child(text_holder.id(), text.id());
-						}
-						ui.add_child(right_frame.id(), text_holder.id());
 
-						let time_display = ui.time_display(
-							wk!(),
-							snake_game.duration(),
-							snaek_sheet_id,
-							snaek_sheet.box_num_display,
-							snaek_sheet.num_colon,
-							&snaek_sheet.nums,
-						);
-						ui.add_child(right_frame.id(), time_display.id());
-					}
+							let time_display = column.ui().time_display(
+								wk!(),
+								snake_game.duration(),
+								snaek_sheet_id,
+								snaek_sheet.box_num_display,
+								snaek_sheet.num_colon,
+								&snaek_sheet.nums,
+							);
+							column.add_child(time_display.id());
+						},
+					);
 					ui.add_child(display_frame.id(), right_frame.id());
 				}
 				ui.add_child(game_frame.id(), display_frame.id());
 
 				let playfield = ui.build_widget(
 					WidgetProps::nine_slice_sprite(wk!(), snaek_sheet_id, snaek_sheet.box_playfield)
+						.with_debug_name("playfield")
 						.with_size(WidgetSize::fill())
 						.with_padding(WidgetPadding::all(4)),
 				);
 				{
-					let snake_container = ui.build_widget(
+					// the space the board can take, as solved last frame
+					let snake_area = ui.build_widget(
 						WidgetProps::new(wk!())
-							.with_flags(WidgetFlags::DRAW_BACKGROUND)
-							.with_color(Color::from_hex(0xff262b44)),
+							.with_debug_name("snake_area")
+							.with_size(WidgetSize::fill()),
 					);
+					let available = (ui.widget_rect(snake_area.id())).map_or(Size::ZERO, |rect| rect.size());
+					if available != Size::ZERO {
+						let cell_size = render_config.cell_size;
+						// the board at launch may already need smaller cells, growing it shouldn't shrink it
+						max_board_size =
+							size(available.w / cell_size, available.h / cell_size).max_component(snake_game.size());
+					}
+					let render_config = render_config.fitted(snake_game.size(), available);
+
+					let board_size = render_config.board_pixel_size(snake_game.size());
+					let snake_container = ui.build_widget(playfield_container_props(&theme, board_size));
 					{
+						let overlays = match debug {
+							true => render_config.debug_overlays,
+							false => RenderFlags::NONE,
+						};
+
 						draw_snake_game(
 							&snake_game,
 							&mut ui,
@@ -320,41 +735,83 @@ fn game() -> Result<(), Box<dyn Error>> {
 							snake_container.id(),
 							snaek_sheet_id,
 							&snaek_sheet,
-							debug,
+							&render_config,
+							&snake_trail,
+							ghost.as_ref(),
+							overlays,
+							&theme,
 							&mut show_game_over,
 						);
 					}
-					ui.add_child(playfield.id(), snake_container.id());
+					ui.add_child(snake_area.id(), snake_container.id());
+					ui.add_child(playfield.id(), snake_area.id());
 				}
 				ui.add_child(game_frame.id(), playfield.id());
 			}
 			ui.add_child(window_frame.id(), game_frame.id());
 		}
 		ui.solve_layout();
-		ui.draw_widgets(&mut draw_cmds);
+		ui.draw_widgets_into(&mut draw_cmds);
 		ui.free_untouched_widgets();
-		ui.react(&mouse);
+		ui.feed_keys(&window.get_keys_pressed(KeyRepeat::No));
+		ui.react(&mouse, WINDOW_SCALE);
+
+		let frame_dt = clock.tick();
 
 		snake_game.update_duration();
-		if frame_count % (60 / 3) == 0 {
-			let was_dead = snake_game.is_dead();
 
-			snake_game.change_direction(next_direction);
-			snake_game.update();
+		if hold_to_steer {
+			if let Some(direction) = input::held_direction(&window.get_keys(), snake_game.direction()) {
+				next_direction = direction;
+			}
+		}
+		recorder.change_direction(&mut snake_game, 0, next_direction);
+
+		let trail_positions = snake_game.recent_positions(snake_game.snake_len());
+		game_events.clear();
+		let mut updates = snake_game.advance(frame_dt, &mut game_events);
+		// fast-forward while held, to get to a late game quickly
+		if debug && window.is_key_down(Key::N) {
+			snake_game.step_n(FAST_FORWARD_STEPS, &mut game_events);
+			updates += FAST_FORWARD_STEPS;
+		}
+
+		if updates > 0 {
+			snake_trail.push(trail_positions);
 			next_direction = snake_game.direction();
+			if let Some(ghost) = &mut ghost {
+				ghost.sync_to(snake_game.tick_count());
+			}
+
+			for event in &game_events {
+				// dying plays an animation before the game is over, winning doesn't
+				if let GameEvent::Died(GameOverReason::Won) | GameEvent::DeathAnimationEnded = event {
+					show_game_over = true;
+
+					let replay = recorder.finish(&snake_game);
+					if best_replay.as_ref().is_none_or(|best| replay.score() > best.score()) {
+						best_replay = Some(replay);
+					}
+				}
 
-			if snake_game.is_dead() && !was_dead {
-				show_game_over = true;
+				match event {
+					GameEvent::SnakeDied(_, _) => {
+						renderer.flash(DYING_RED, 12);
+						renderer.shake(2., 12, Easing::OutQuad);
+					}
+					GameEvent::BananaEaten(Banana::Cyan, _) => renderer.flash(Color::WHITE, 6),
+					_ => {}
+				}
 			}
 		}
 
-		renderer.draw(&draw_cmds);
+		renderer.draw(&draw_cmds)?;
 
+		clock.begin_present();
 		window
-			.update_with_buffer(renderer.first_framebuffer().pixels(), WIDTH as usize, HEIGHT as usize)
+			.update_with_buffer(renderer.present(), WIDTH as usize, HEIGHT as usize)
 			.unwrap();
-
-		frame_count += 1;
+		clock.end_present();
 	}
 
 	Ok(())
@@ -368,99 +825,98 @@ fn draw_snake_game(
 	container_id: WidgetId,
 	snaek_sheet_id: SpritesheetId,
 	snaek_sheet: &SnaekSheet,
-	debug: bool,
+	config: &GameRenderConfig,
+	trail: &SnakeTrail,
+	ghost: Option<&Ghost>,
+	overlays: RenderFlags,
+	theme: &Theme,
 	show_game_over: &mut bool,
 ) {
-	let playfield_size = snake_game.size();
-	for y in 0..playfield_size.h as i16 {
-		for x in 0..playfield_size.w as i16 {
+	// the snake blinks while the death animation plays
+	let snake_hidden = (snake_game.death_animation_progress())
+		.is_some_and(|progress| progress < 1. && (progress * (2 * DEATH_BLINKS) as f32) as u32 % 2 == 1);
+
+	let model = snake_game.render_model();
+	let mut cells = model.cells.iter().peekable();
+	// only the ghost's snake shows, its bananas aren't the live game's
+	let ghost_model = ghost.map(Ghost::render_model);
+	let mut ghost_cells = (ghost_model.iter())
+		.flat_map(|model| &model.cells)
+		.filter(|(_, visual)| !matches!(visual, CellVisual::Banana(_)))
+		.peekable();
+	for y in 0..model.size.h as i16 {
+		for x in 0..model.size.w as i16 {
 			let slot_pos = pos(x, y);
 			let slot = snake_game.slot_at(slot_pos);
 
 			let (ikey_x, ikey_y) = (slot_pos.x as u64, slot_pos.y as u64);
 			let mut holder_props = WidgetProps::new(wk!(ikey_x, ikey_y))
-				.with_size(WidgetSize::fixed(7, 7))
-				.with_pos(slot_pos * 7);
+				.with_size(WidgetSize::fixed(config.cell_size, config.cell_size))
+				.with_pos(config.cell_pos(slot_pos));
 
-			if debug {
+			if overlays.has(RenderFlags::GRID) {
 				holder_props = holder_props
 					.with_flags(WidgetFlags::DRAW_BORDER)
 					.with_border_color(Color::from_hex(0xff333333))
 					.with_border_width(1)
-					.with_acf(Some(alphacomp::add));
+					.with_acf(Some(alphacomp::ADD));
 			}
 
 			let sprite_holder = ui.build_widget(holder_props);
 			{
-				if let Some(banana) = slot.banana() {
-					let banana_sprite = match banana {
-						Banana::Yellow => snaek_sheet.banana_yellow,
-						Banana::Red => snaek_sheet.banana_red,
-						Banana::Cyan => snaek_sheet.banana_cyan,
-					};
+				// the trail only shows where the snake isn't anymore
+				let trail_age = (slot.snake_role().is_none()).then(|| trail.age_at(slot_pos)).flatten();
+				if let Some(age) = trail_age {
+					let trail_cell = ui.build_widget(
+						WidgetProps::new(wk!(ikey_x, ikey_y))
+							.with_flags(WidgetFlags::DRAW_BACKGROUND)
+							.with_color(trail.color(age))
+							.with_size(WidgetSize::fill()),
+					);
+					ui.add_child(sprite_holder.id(), trail_cell.id());
+				}
 
-					let sprite = ui.build_widget(
-						WidgetProps::simple_sprite(wk!(), snaek_sheet_id, banana_sprite)
-							.with_anchor_origin(Anchor::CENTER, Anchor::CENTER),
+				while let Some(&(_, visual)) = ghost_cells.next_if(|(cell_pos, _)| *cell_pos == slot_pos) {
+					let (sprite, rotate) = snaek_sheet.cell_sprite(visual);
+					let ghost_sprite = ui.build_widget(
+						WidgetProps::simple_sprite(wk!(ikey_x, ikey_y), snaek_sheet_id, sprite)
+							.with_rotate(rotate)
+							.with_anchor_origin(Anchor::CENTER, Anchor::CENTER)
+							.with_opacity(GHOST_OPACITY),
 					);
-					ui.add_child(sprite_holder.id(), sprite.id());
+					ui.add_child(sprite_holder.id(), ghost_sprite.id());
 				}
 
-				let is_straight = slot.direction_next() == slot.direction_prev().opposite();
+				while let Some(&(_, visual)) = cells.next_if(|(cell_pos, _)| *cell_pos == slot_pos) {
+					let (sprite, rotate) = snaek_sheet.cell_sprite(visual);
 
-				let snake_sprite = match (slot.has_snake_head(), slot.has_snake_tail()) {
-					(true, true) if is_straight => {
-						let rotate = match slot.direction_next() {
-							Direction::Up => Rotate::R270,
-							Direction::Right => Rotate::R0,
-							Direction::Down => Rotate::R90,
-							Direction::Left => Rotate::R180,
-						};
-						Some((snaek_sheet.snake_straight, rotate))
-					}
-					(true, true) => {
-						use Direction as D;
-						let rotate = match (slot.direction_next(), slot.direction_prev()) {
-							(D::Up, D::Right) | (D::Right, D::Up) => Rotate::R270,
-							(D::Right, D::Down) | (D::Down, D::Right) => Rotate::R0,
-							(D::Down, D::Left) | (D::Left, D::Down) => Rotate::R90,
-							(D::Left, D::Up) | (D::Up, D::Left) => Rotate::R180,
-							_ => Rotate::R0,
-						};
-						Some((snaek_sheet.snake_gay, rotate))
-					}
-					(true, false) => {
-						let rotate = match slot.direction_prev() {
-							Direction::Up => Rotate::R90,
-							Direction::Right => Rotate::R180,
-							Direction::Down => Rotate::R270,
-							Direction::Left => Rotate::R0,
-						};
-						Some((snaek_sheet.snake_head, rotate))
+					if let CellVisual::Banana(_) = visual {
+						let sprite = ui.build_widget(
+							WidgetProps::simple_sprite(wk!(), snaek_sheet_id, sprite)
+								.with_anchor_origin(Anchor::CENTER, Anchor::CENTER),
+						);
+						ui.add_child(sprite_holder.id(), sprite.id());
+						continue;
 					}
-					(false, true) => {
-						let rotate = match slot.direction_next() {
-							Direction::Up => Rotate::R0,
-							Direction::Right => Rotate::R90,
-							Direction::Down => Rotate::R180,
-							Direction::Left => Rotate::R270,
-						};
-						Some((snaek_sheet.snake_end, rotate))
+
+					if snake_hidden {
+						continue;
 					}
-					(false, false) => None,
-				};
 
-				if let Some((snake_sprite, rotate)) = snake_sprite {
+					// the head turns red when the snake is about to die
+					let mask_and = (snake_game.is_dying() && slot_pos == snake_game.snake_head()).then_some(DYING_RED);
+
 					let sprite = ui.build_widget(
-						WidgetProps::simple_sprite(wk!(ikey_x, ikey_y), snaek_sheet_id, snake_sprite)
+						WidgetProps::simple_sprite(wk!(ikey_x, ikey_y), snaek_sheet_id, sprite)
 							.with_rotate(rotate)
-							.with_anchor_origin(Anchor::CENTER, Anchor::CENTER),
+							.with_anchor_origin(Anchor::CENTER, Anchor::CENTER)
+							.with_mask_and(mask_and),
 					);
 					ui.add_child(sprite_holder.id(), sprite.id());
 				}
 
 				// debug sprites
-				if debug {
+				if overlays.has(RenderFlags::DIRECTION_ARROWS) {
 					// direction next
 					let (anchor, w, h) = match slot.direction_next() {
 						Direction::Up => (Anchor::TOP_CENTER, 1, 2),
@@ -475,7 +931,7 @@ fn draw_snake_game(
 							.with_color(Color::from_hex(0xff116611))
 							.with_size(WidgetSize::fixed(w, h))
 							.with_anchor_origin(anchor, anchor)
-							.with_acf(Some(alphacomp::add)),
+							.with_acf(Some(alphacomp::ADD)),
 					);
 					ui.add_child(sprite_holder.id(), sprite.id());
 
@@ -493,80 +949,588 @@ fn draw_snake_game(
 							.with_color(Color::from_hex(0xff661111))
 							.with_size(WidgetSize::fixed(w, h))
 							.with_anchor_origin(anchor, anchor)
-							.with_acf(Some(alphacomp::add)),
+							.with_acf(Some(alphacomp::ADD)),
 					);
 					ui.add_child(sprite_holder.id(), sprite.id());
 				}
+
+				// column numbers along the top row, row numbers along the left column
+				if overlays.has(RenderFlags::CELL_COORDS) && (x == 0 || y == 0) {
+					let n = if y == 0 { x } else { y };
+
+					let coord = ui.build_widget(
+						WidgetProps::simple_sprite(
+							wk!(ikey_x, ikey_y),
+							snaek_sheet_id,
+							snaek_sheet.nums[n as usize % 10],
+						)
+						.with_anchor_origin(Anchor::TOP_LEFT, Anchor::TOP_LEFT)
+						.with_pos(pos(1, 1))
+						.with_acf(Some(alphacomp::ADD)),
+					);
+					ui.add_child(sprite_holder.id(), coord.id());
+				}
 			}
 			ui.add_child(container_id, sprite_holder.id());
 		}
 	}
 
+	// the tongue goes out and back in before the next update, one slice at a time so it can wrap around
 	if snake_game.ate_banana() {
-		let head_pos = snake_game.snake_head();
-
-		let (rotate, anchor) = match snake_game.slot_at(head_pos).direction_prev() {
-			Direction::Up => (Rotate::R90, Anchor::TOP_CENTER),
-			Direction::Right => (Rotate::R180, Anchor::CENTER_RIGHT),
-			Direction::Down => (Rotate::R270, Anchor::BOTTOM_CENTER),
-			Direction::Left => (Rotate::R0, Anchor::CENTER_LEFT),
-		};
-
-		let tongue_pos = head_pos + snake_game.direction().pos_offset();
-		let tongue_holder = ui.build_widget(
-			WidgetProps::new(wk!())
-				.with_size(WidgetSize::fixed(7, 7))
-				.with_pos(tongue_pos * 7),
+		let tongue = snaek_sheet.snake_tongue;
+		let extension = config.tongue_extension(snake_game.animation_phase(), TONGUE_TIP_LEN.min(tongue.w));
+		let (slices, rotate) = config.tongue_slices(
+			snake_game.snake_head(),
+			snake_game.head_facing(),
+			extension,
+			tongue.size(),
+			snake_game.size(),
+			snake_game.wrap(),
 		);
-		{
-			let tongue = ui.build_widget(
-				WidgetProps::simple_sprite(wk!(), snaek_sheet_id, snaek_sheet.snake_tongue)
-					.with_anchor_origin(anchor, anchor)
-					.with_rotate(rotate),
+
+		for (i, (slice_pos, column)) in (0..).zip(slices) {
+			let slice = Sprite::new(Rect::from_xywh(tongue.x + column as i16, tongue.y, 1, tongue.h));
+			let slice_widget = ui.build_widget(
+				WidgetProps::simple_sprite(wk!(i), snaek_sheet_id, slice)
+					.with_rotate(rotate)
+					.with_pos(slice_pos),
 			);
-			ui.add_child(tongue_holder.id(), tongue.id());
+			ui.add_child(container_id, slice_widget.id());
 		}
-		ui.add_child(container_id, tongue_holder.id());
 	}
 
 	if *show_game_over {
 		let game_over_overlay = ui.build_widget(
 			WidgetProps::new(wk!())
 				.with_flags(WidgetFlags::DRAW_BACKGROUND)
-				.with_color(Color::from_hex(0x80ffffff & SNAEK_BLACK.to_u32()))
+				.with_color(theme.overlay_background())
 				.with_size(WidgetSize::fill()),
 		);
 		{
-			let column = ui.build_widget(
+			let [yellow, red, cyan] = snake_game.bananas_by_color();
+			let stats = RunStats {
+				won: snake_game.is_won(),
+				score: snake_game.score_of(0) as usize,
+				time: snake_game.duration(),
+				length: snake_game.snake_len(),
+				bananas: [yellow as usize, red as usize, cyan as usize],
+			};
+			let panel = ui.game_over_panel(wk!(), renderer, &stats, theme);
+			if panel.clicked() {
+				*show_game_over = false;
+			}
+			ui.add_child(game_over_overlay.id(), panel.id());
+		}
+		ui.add_child(container_id, game_over_overlay.id());
+	}
+}
+
+/// Starts recording a fresh run, along with a ghost of the best run to race against if it was on the same board.
+fn start_run_replay(snake_game: &SnakeGame, best_replay: Option<&Replay>) -> (ReplayRecorder, Option<Ghost>) {
+	let ghost = best_replay.filter(|replay| replay.fits(snake_game)).map(Ghost::new);
+	(ReplayRecorder::new(snake_game), ghost)
+}
+
+/// The look of the game's components, drawn from the snaek sheet.
+fn snaek_theme(sheet_id: SpritesheetId, sheet: &SnaekSheet) -> Theme<'_> {
+	Theme {
+		sheet_id,
+		button_box: sheet.box_embossed,
+		button_pressed_box: sheet.box_carved,
+		button_text_color: SNAEK_BLACK,
+		title_outline: Some(SNAEK_BLACK),
+		display_box: sheet.box_num_display,
+		digits: &sheet.nums,
+		colon: sheet.num_colon,
+		bananas: [sheet.banana_yellow, sheet.banana_red, sheet.banana_cyan],
+		playfield_background: Color::from_hex(0xff262b44),
+		playfield_border: None,
+		overlay_color: SNAEK_BLACK,
+		overlay_dim: 0x80,
+	}
+}
+
+/// Props of the widget the board is drawn in, centered in the playfield and colored by the theme.
+fn playfield_container_props(theme: &Theme, board_size: Size) -> WidgetProps {
+	let props = WidgetProps::new(wk!())
+		.with_debug_name("snake_container")
+		.with_flags(WidgetFlags::DRAW_BACKGROUND)
+		.with_color(theme.playfield_background)
+		.with_size(WidgetSize::fixed(board_size.w, board_size.h))
+		.with_anchor_origin(Anchor::CENTER, Anchor::CENTER);
+
+	match theme.playfield_border {
+		Some(border_color) => props
+			.with_flags(WidgetFlags::DRAW_BACKGROUND | WidgetFlags::DRAW_BORDER)
+			.with_border_color(border_color)
+			.with_border_width(1),
+		None => props,
+	}
+}
+
+/// Today's date in UTC, as `(year, month, day)`.
+fn today_utc() -> (i32, u32, u32) {
+	let secs = (SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)).map_or(0, |d| d.as_secs());
+	let days = (secs / 86400) as i64;
+
+	// days since 1970-01-01 to a civil date, see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+	let z = days + 719_468;
+	let era = z.div_euclid(146_097);
+	let doe = z.rem_euclid(146_097);
+	let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let day = doy - (153 * mp + 2) / 5 + 1;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 };
+	let year = yoe + era * 400 + (month <= 2) as i64;
+
+	(year as i32, month as u32, day as u32)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::ui::layout::LayoutNode;
+
+	/// Builds a frame of a fresh game on a small board, with some overlays on.
+	fn built_game(overlays: RenderFlags) -> LayoutNode {
+		let mut renderer = Renderer::new(
+			Bitmap::new(VIEWPORT_SIZE).unwrap(),
+			Bitmap::from_png(IMG_ASCII_CHARS).unwrap(),
+		);
+		let sheet_id = renderer.register_spritesheet(Bitmap::from_png(IMG_SNAEKSHEET).unwrap());
+		let sheet = snake::snaek_sheet();
+		let theme = snaek_theme(sheet_id, &sheet);
+
+		let snake_game = SnakeGame::new(size(4, 4));
+		let mut ui = UiContext::new(VIEWPORT_SIZE);
+		let container = ui.build_widget(WidgetProps::new(wk!()).with_size(WidgetSize::fill()));
+		draw_snake_game(
+			&snake_game,
+			&mut ui,
+			&renderer,
+			container.id(),
+			sheet_id,
+			&sheet,
+			&GameRenderConfig::default(),
+			&SnakeTrail::new(0),
+			None,
+			overlays,
+			&theme,
+			&mut false,
+		);
+
+		ui.solve_layout();
+		ui.dump_layout(container.id())
+	}
+
+	/// How many widgets of a layout (the root excluded) have some flags.
+	fn count_with(node: &LayoutNode, flags: WidgetFlags) -> usize {
+		(node.children.iter())
+			.map(|child| child.flags.has(flags) as usize + count_with(child, flags))
+			.sum()
+	}
+
+	#[test]
+	fn cells_are_laid_out_by_their_size() {
+		let config = GameRenderConfig {
+			cell_size: 10,
+			..GameRenderConfig::default()
+		};
+
+		assert_eq!(config.cell_pos(pos(0, 0)), pos(0, 0));
+		assert_eq!(config.cell_pos(pos(1, 0)), pos(10, 0));
+		assert_eq!(config.cell_pos(pos(3, 5)), pos(30, 50));
+		assert_eq!(config.board_pixel_size(size(8, 6)), size(80, 60));
+	}
+
+	#[test]
+	fn only_enabled_overlays_are_built() {
+		// a 4x4 board has 16 cells, the snake's 2 slots and a banana have a sprite
+		const CELLS: usize = 16;
+		const GAME_SPRITES: usize = 3;
+		// one coordinate per column along the top row, and one per row along the left column
+		const COORDS: usize = 4 + 3;
+
+		for (overlays, grid, arrows, coords) in [
+			(RenderFlags::NONE, 0, 0, 0),
+			(RenderFlags::GRID, CELLS, 0, 0),
+			(RenderFlags::DIRECTION_ARROWS, 0, 2 * CELLS, 0),
+			(RenderFlags::CELL_COORDS, 0, 0, COORDS),
+			(RenderFlags::GRID | RenderFlags::CELL_COORDS, CELLS, 0, COORDS),
+		] {
+			let layout = built_game(overlays);
+			assert_eq!(count_with(&layout, WidgetFlags::DRAW_BORDER), grid, "{overlays:?}");
+			assert_eq!(
+				count_with(&layout, WidgetFlags::DRAW_BACKGROUND),
+				arrows,
+				"{overlays:?}"
+			);
+			assert_eq!(
+				count_with(&layout, WidgetFlags::DRAW_SPRITE),
+				GAME_SPRITES + coords,
+				"{overlays:?}"
+			);
+		}
+	}
+
+	#[test]
+	fn trail_keeps_only_its_last_ticks() {
+		let mut trail = SnakeTrail::new(2);
+		trail.push(vec![pos(0, 0), pos(1, 0)]);
+		trail.push(vec![pos(1, 0), pos(2, 0)]);
+		trail.push(vec![pos(2, 0), pos(3, 0)]);
+
+		assert_eq!(trail.age_at(pos(2, 0)), Some(0));
+		assert_eq!(trail.age_at(pos(1, 0)), Some(1));
+		assert_eq!(trail.age_at(pos(0, 0)), None);
+		assert!(trail.color(0).a > trail.color(1).a);
+
+		trail.clear();
+		assert_eq!(trail.age_at(pos(2, 0)), None);
+	}
+
+	#[test]
+	fn empty_trail_remembers_nothing() {
+		let mut trail = SnakeTrail::new(0);
+		trail.push(vec![pos(0, 0)]);
+		assert_eq!(trail.age_at(pos(0, 0)), None);
+	}
+
+	#[test]
+	fn cells_shrink_to_fit_the_board_in_the_available_space() {
+		let config = GameRenderConfig::default();
+		for (board, available, cell_size) in [
+			// fits as is
+			(size(11, 11), size(97, 97), 7),
+			// a wide board is limited by its width, a tall one by its height
+			(size(20, 8), size(97, 97), 4),
+			(size(8, 20), size(97, 60), 3),
+			// cells never grow, nor shrink to nothing
+			(size(2, 2), size(200, 200), 7),
+			(size(300, 4), size(97, 97), 1),
+			// nothing to fit yet
+			(size(11, 11), size(0, 0), 7),
+			(size(0, 0), size(97, 97), 7),
+		] {
+			let fitted = config.fitted(board, available);
+			assert_eq!(fitted.cell_size, cell_size, "{board:?} in {available:?}");
+
+			let board_px = fitted.board_pixel_size(board);
+			if cell_size > 1 && available.w > 0 {
+				assert!(board_px.fits_within(available), "{board:?} in {available:?}");
+			}
+		}
+	}
+
+	#[test]
+	fn fitted_board_is_centered_in_the_available_space() {
+		let config = GameRenderConfig::default();
+		for (board, available) in [
+			(size(20, 8), size(97, 60)),
+			(size(11, 11), size(97, 97)),
+			(size(5, 9), size(90, 40)),
+		] {
+			let board_px = config.fitted(board, available).board_pixel_size(board);
+
+			let mut ui = UiContext::new(available);
+			let area = ui.build_widget(WidgetProps::new(wk!()).with_size(WidgetSize::fill()));
+			let container = ui.build_widget(
 				WidgetProps::new(wk!())
-					.with_size(WidgetSize::hug())
-					.with_anchor_origin(Anchor::CENTER, Anchor::CENTER)
-					.with_layout(WidgetLayout::flex(FlexDirection::Vertical, 4)),
+					.with_size(WidgetSize::fixed(board_px.w, board_px.h))
+					.with_anchor_origin(Anchor::CENTER, Anchor::CENTER),
 			);
-			{
-				let game_over_text = ui.build_widget(WidgetProps::text(wk!(), renderer.text("Game Over! :(")));
-				ui.add_child(column.id(), game_over_text.id());
+			ui.add_child(area.id(), container.id());
+			ui.solve_layout();
+
+			let rect = ui.widget_rect(container.id()).unwrap();
+			assert_eq!(rect.size(), board_px);
+
+			// an odd leftover can't be split evenly, so one side gets the extra pixel
+			let (left, top) = (rect.x as u16, rect.y as u16);
+			let (right, bottom) = (available.w - board_px.w - left, available.h - board_px.h - top);
+			assert!(
+				left.abs_diff(right) <= 1,
+				"{board:?} in {available:?}: {left} left, {right} right"
+			);
+			assert!(
+				top.abs_diff(bottom) <= 1,
+				"{board:?} in {available:?}: {top} top, {bottom} bottom"
+			);
+		}
+	}
 
-				let oh_text =
-					ui.build_widget(WidgetProps::text(wk!(), renderer.text("Oh")).with_mask_and(Some(SNAEK_BLACK)));
+	/// Id of the snaek sheet once registered, for themes drawn from it.
+	fn sheet_id() -> SpritesheetId {
+		let mut renderer = Renderer::new(Bitmap::new(VIEWPORT_SIZE).unwrap(), Bitmap::new(size(1, 1)).unwrap());
+		renderer.register_spritesheet(Bitmap::from_png(IMG_SNAEKSHEET).unwrap())
+	}
 
-				let oh_btn = ui.btn_box(
-					WidgetProps::new(wk!())
-						.with_size(WidgetSize::hug())
-						.with_anchor_origin(Anchor::TOP_CENTER, Anchor::TOP_CENTER)
-						.with_padding(WidgetPadding::hv(4, 2)),
-					WidgetSprite::NineSlice(snaek_sheet_id, snaek_sheet.box_embossed),
-					WidgetSprite::NineSlice(snaek_sheet_id, snaek_sheet.box_carved),
-					oh_text.id(),
-				);
-				ui.add_child(column.id(), oh_btn.id());
+	/// Draws the playfield container of some theme on its own, giving its draw commands.
+	fn drawn_container(theme: &Theme) -> Vec<DrawCommand> {
+		let mut ui = UiContext::new(size(32, 32));
+		let container = ui.build_widget(playfield_container_props(theme, size(28, 21)));
+		ui.solve_layout();
+		assert_eq!(ui.widget_rect(container.id()).unwrap().size(), size(28, 21));
+
+		let mut draw_cmds = Vec::new();
+		ui.draw_widgets(&mut draw_cmds);
+		draw_cmds
+	}
+
+	fn filled_colors(draw_cmds: &[DrawCommand]) -> Vec<Color> {
+		(draw_cmds.iter())
+			.filter_map(|cmd| match *cmd {
+				DrawCommand::Fill { color, .. } => Some(color),
+				_ => None,
+			})
+			.collect()
+	}
+
+	#[test]
+	fn playfield_background_comes_from_the_theme() {
+		let sheet = snake::snaek_sheet();
+		let theme = snaek_theme(sheet_id(), &sheet);
+		assert_eq!(filled_colors(&drawn_container(&theme)), [theme.playfield_background]);
+
+		let neon = Theme {
+			playfield_background: Color::from_hex(0xff0a0014),
+			..snaek_theme(sheet_id(), &sheet)
+		};
+		assert_eq!(filled_colors(&drawn_container(&neon)), [Color::from_hex(0xff0a0014)]);
+	}
+
+	#[test]
+	fn playfield_border_comes_from_the_theme() {
+		let sheet = snake::snaek_sheet();
+		let plain = playfield_container_props(&snaek_theme(sheet_id(), &sheet), size(8, 8));
+		assert!(!plain.flags.has(WidgetFlags::DRAW_BORDER));
+
+		let neon = Theme {
+			playfield_border: Some(Color::from_hex(0xffff00ff)),
+			..snaek_theme(sheet_id(), &sheet)
+		};
+		let bordered = playfield_container_props(&neon, size(8, 8));
+		assert!(bordered
+			.flags
+			.has(WidgetFlags::DRAW_BACKGROUND | WidgetFlags::DRAW_BORDER));
+		assert_eq!(
+			(bordered.border_color, bordered.border_width),
+			(Color::from_hex(0xffff00ff), 1)
+		);
+		assert_eq!(bordered.color, neon.playfield_background);
+	}
+
+	#[test]
+	fn frame_time_splits_into_logic_and_present() {
+		let ms = Duration::from_millis;
+		assert_eq!(split_frame_time(ms(16), ms(10)), (ms(6), ms(10)));
+		assert_eq!(split_frame_time(ms(16), Duration::ZERO), (ms(16), Duration::ZERO));
+		assert_eq!(split_frame_time(ms(16), ms(16)), (Duration::ZERO, ms(16)));
+		// presenting can't take longer than the whole frame
+		assert_eq!(split_frame_time(ms(16), ms(20)), (Duration::ZERO, ms(16)));
+	}
+
+	#[test]
+	fn frame_clock_measures_presenting_apart() {
+		let mut clock = FrameClock::new();
+		clock.begin_present();
+		std::thread::sleep(Duration::from_millis(20));
+		clock.end_present();
+
+		let frame_time = clock.tick();
+		assert!(clock.present_time() >= Duration::from_millis(20));
+		assert_eq!(clock.logic_time() + clock.present_time(), frame_time);
+
+		// presenting is counted anew every frame
+		std::thread::sleep(Duration::from_millis(5));
+		let frame_time = clock.tick();
+		assert_eq!(clock.present_time(), Duration::ZERO);
+		assert_eq!(clock.logic_time(), frame_time);
+	}
+
+	#[test]
+	fn board_sizes_are_clamped_to_playable_bounds() {
+		let max = size(20, 12);
+		assert_eq!(clamp_board_size(size(10, 8), max), size(10, 8));
+		assert_eq!(clamp_board_size(max, max), max);
+		assert_eq!(clamp_board_size(MIN_BOARD_SIZE, max), MIN_BOARD_SIZE);
+
+		// each dimension is clamped on its own
+		assert_eq!(clamp_board_size(size(30, 8), max), size(20, 8));
+		assert_eq!(clamp_board_size(size(2, 30), max), size(5, 12));
+		assert_eq!(clamp_board_size(Size::ZERO, max), MIN_BOARD_SIZE);
+
+		// a board always stays playable, even where it can't be displayed at full size
+		assert_eq!(clamp_board_size(size(10, 10), size(3, 40)), size(5, 10));
+	}
+
+	/// Colors filled by a game drawn with its game over overlay on, in some theme.
+	fn game_over_fills(theme: &Theme) -> Vec<Color> {
+		let mut renderer = Renderer::new(
+			Bitmap::new(VIEWPORT_SIZE).unwrap(),
+			Bitmap::from_png(IMG_ASCII_CHARS).unwrap(),
+		);
+		let sheet_id = renderer.register_spritesheet(Bitmap::from_png(IMG_SNAEKSHEET).unwrap());
+		let sheet = snake::snaek_sheet();
+
+		let snake_game = SnakeGame::new(size(4, 4));
+		let mut ui = UiContext::new(VIEWPORT_SIZE);
+		let container = ui.build_widget(WidgetProps::new(wk!()).with_size(WidgetSize::fill()));
+		draw_snake_game(
+			&snake_game,
+			&mut ui,
+			&renderer,
+			container.id(),
+			sheet_id,
+			&sheet,
+			&GameRenderConfig::default(),
+			&SnakeTrail::new(0),
+			None,
+			RenderFlags::NONE,
+			theme,
+			&mut true,
+		);
+		ui.solve_layout();
+
+		let mut draw_cmds = Vec::new();
+		ui.draw_widgets(&mut draw_cmds);
+		filled_colors(&draw_cmds)
+	}
 
-				if oh_btn.clicked() {
-					*show_game_over = false;
+	#[test]
+	fn overlays_dim_with_the_themes_alpha() {
+		let sheet = snake::snaek_sheet();
+		let theme = snaek_theme(sheet_id(), &sheet);
+		assert_eq!(theme.overlay_background(), Color::from_hex(0x80181425));
+		assert!(game_over_fills(&theme).contains(&Color::from_hex(0x80181425)));
+
+		for dim in [0x00, 0x40, 0xff] {
+			let theme = Theme {
+				overlay_color: Color::from_hex(0x12345678),
+				overlay_dim: dim,
+				..theme
+			};
+			let background = theme.overlay_background();
+			assert_eq!(background.a, dim);
+			assert_eq!(
+				background,
+				Color {
+					a: dim,
+					..Color::from_hex(0x12345678)
 				}
-			}
-			ui.add_child(game_over_overlay.id(), column.id());
+			);
+			assert!(game_over_fills(&theme).contains(&background), "{dim:#x}");
 		}
-		ui.add_child(container_id, game_over_overlay.id());
+	}
+
+	const TONGUE_SIZE: Size = size(3, 3);
+
+	#[test]
+	fn tongues_go_out_to_their_reach_and_back() {
+		let config = GameRenderConfig {
+			tongue_reach: 2,
+			..GameRenderConfig::default()
+		};
+		let extensions: Vec<u16> = [0., 0.25, 0.5, 0.75, 1.]
+			.into_iter()
+			.map(|phase| config.tongue_extension(phase, TONGUE_TIP_LEN))
+			.collect();
+		assert_eq!(extensions, [3, 9, 14, 9, 3]);
+
+		// a tongue that doesn't reach anywhere is still as long as its tip
+		let config = GameRenderConfig {
+			tongue_reach: 0,
+			..config
+		};
+		assert_eq!(config.tongue_extension(0.5, TONGUE_TIP_LEN), 3);
+	}
+
+	#[test]
+	fn tongues_stick_out_the_way_the_head_faces() {
+		let config = GameRenderConfig::default();
+		let slices =
+			|facing| config.tongue_slices(pos(2, 2), facing, 4, TONGUE_SIZE, size(5, 5), WrapConfig::default());
+
+		// the head's on (14, 14), and the tongue is centered on its side
+		let columns = [0, 0, 1, 2];
+		let expected = [
+			(
+				Direction::Up,
+				[pos(16, 13), pos(16, 12), pos(16, 11), pos(16, 10)],
+				Rotate::R270,
+			),
+			(
+				Direction::Right,
+				[pos(21, 16), pos(22, 16), pos(23, 16), pos(24, 16)],
+				Rotate::R0,
+			),
+			(
+				Direction::Down,
+				[pos(16, 21), pos(16, 22), pos(16, 23), pos(16, 24)],
+				Rotate::R90,
+			),
+			(
+				Direction::Left,
+				[pos(13, 16), pos(12, 16), pos(11, 16), pos(10, 16)],
+				Rotate::R180,
+			),
+		];
+		for (facing, positions, rotate) in expected {
+			let expected_slices: Vec<(Pos, u16)> = positions.into_iter().zip(columns).collect();
+			assert_eq!(slices(facing), (expected_slices, rotate), "{facing:?}");
+		}
+	}
+
+	#[test]
+	fn tongues_come_back_on_the_other_side_of_wrap_edges() {
+		let config = GameRenderConfig::default();
+		let positions = |head, facing| {
+			let (slices, _) = config.tongue_slices(head, facing, 4, TONGUE_SIZE, size(5, 5), WrapConfig::default());
+			slices.into_iter().map(|(slice_pos, _)| slice_pos).collect::<Vec<_>>()
+		};
+
+		assert_eq!(
+			positions(pos(4, 2), Direction::Right),
+			[pos(0, 16), pos(1, 16), pos(2, 16), pos(3, 16)]
+		);
+		assert_eq!(
+			positions(pos(0, 2), Direction::Left),
+			[pos(34, 16), pos(33, 16), pos(32, 16), pos(31, 16)]
+		);
+		assert_eq!(
+			positions(pos(3, 0), Direction::Up),
+			[pos(23, 34), pos(23, 33), pos(23, 32), pos(23, 31)]
+		);
+		assert_eq!(
+			positions(pos(3, 4), Direction::Down),
+			[pos(23, 0), pos(23, 1), pos(23, 2), pos(23, 3)]
+		);
+	}
+
+	#[test]
+	fn tongues_stop_at_edges_that_dont_wrap() {
+		let config = GameRenderConfig::default();
+		let only_vertical = WrapConfig {
+			horizontal: false,
+			vertical: true,
+		};
+		let positions = |head, facing, extension| {
+			let (slices, _) = config.tongue_slices(head, facing, extension, TONGUE_SIZE, size(5, 5), only_vertical);
+			slices.into_iter().map(|(slice_pos, _)| slice_pos).collect::<Vec<_>>()
+		};
+
+		assert_eq!(positions(pos(4, 2), Direction::Right, 4), []);
+		assert_eq!(positions(pos(0, 2), Direction::Left, 4), []);
+
+		// the board is 35 pixels wide, so a long tongue from the next cell only has room for 7 slices
+		let on_board: Vec<Pos> = (28..35).map(|x| pos(x, 16)).collect();
+		assert_eq!(positions(pos(3, 2), Direction::Right, 10), on_board);
+
+		// the other axis still wraps
+		assert_eq!(
+			positions(pos(3, 4), Direction::Down, 4),
+			[pos(23, 0), pos(23, 1), pos(23, 2), pos(23, 3)]
+		);
 	}
 }
@@ -3,6 +3,7 @@ use crate::ui::Anchor;
 use self::pos::Pos;
 use self::rect::Rect;
 
+pub mod easing;
 pub mod pos;
 pub mod rect;
 pub mod size;
@@ -0,0 +1,73 @@
+/// How an animation moves from its start to its end over time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[allow(unused)]
+pub enum Easing {
+	/// At the same speed all along.
+	#[default]
+	Linear,
+	/// Starts slow and speeds up.
+	InQuad,
+	/// Starts fast and slows down.
+	OutQuad,
+	/// Starts and ends slow.
+	InOutQuad,
+	/// Starts faster than [`Easing::OutQuad`], and slows down for longer.
+	OutCubic,
+}
+
+impl Easing {
+	/// How far along the animation is, from 0 to 1, when `t` of its time has passed (also from 0 to 1).
+	///
+	/// `t` is clamped, so it's fine to go past the end.
+	pub fn apply(self, t: f32) -> f32 {
+		let t = t.clamp(0., 1.);
+		match self {
+			Easing::Linear => t,
+			Easing::InQuad => t * t,
+			Easing::OutQuad => 1. - (1. - t) * (1. - t),
+			Easing::InOutQuad if t < 0.5 => 2. * t * t,
+			Easing::InOutQuad => 1. - (-2. * t + 2.).powi(2) / 2.,
+			Easing::OutCubic => 1. - (1. - t).powi(3),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const EASINGS: [Easing; 5] = [
+		Easing::Linear,
+		Easing::InQuad,
+		Easing::OutQuad,
+		Easing::InOutQuad,
+		Easing::OutCubic,
+	];
+
+	#[test]
+	fn easings_go_from_start_to_end() {
+		for easing in EASINGS {
+			assert_eq!(easing.apply(0.), 0., "{easing:?}");
+			assert_eq!(easing.apply(1.), 1., "{easing:?}");
+			assert_eq!(easing.apply(-2.), 0., "{easing:?}");
+			assert_eq!(easing.apply(3.), 1., "{easing:?}");
+		}
+	}
+
+	#[test]
+	fn easings_never_go_back() {
+		for easing in EASINGS {
+			let steps: Vec<f32> = (0..=100).map(|i| easing.apply(i as f32 / 100.)).collect();
+			assert!(steps.windows(2).all(|pair| pair[0] <= pair[1]), "{easing:?}");
+		}
+	}
+
+	#[test]
+	fn easings_are_where_they_should_be_halfway() {
+		assert_eq!(Easing::Linear.apply(0.5), 0.5);
+		assert_eq!(Easing::InQuad.apply(0.5), 0.25);
+		assert_eq!(Easing::OutQuad.apply(0.5), 0.75);
+		assert_eq!(Easing::InOutQuad.apply(0.5), 0.5);
+		assert_eq!(Easing::OutCubic.apply(0.5), 0.875);
+	}
+}
@@ -1,5 +1,8 @@
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign};
 
+use super::size::Size;
+use crate::snake::Direction;
+
 /// Position of something on the bitmap, in pixels.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 #[repr(C, align(4))]
@@ -10,6 +13,22 @@ pub struct Pos {
 
 impl Pos {
 	pub const ZERO: Self = Self { x: 0, y: 0 };
+
+	/// Converts this position to a size, clamping negative coordinates to 0.
+	#[inline]
+	#[allow(unused)]
+	pub const fn to_size(self) -> Size {
+		let w = if self.x < 0 { 0 } else { self.x as u16 };
+		let h = if self.y < 0 { 0 } else { self.y as u16 };
+		Size { w, h }
+	}
+
+	/// The position one step away in a direction.
+	#[inline]
+	pub const fn step(self, direction: Direction) -> Pos {
+		let offset = direction.pos_offset();
+		pos(self.x + offset.x, self.y + offset.y)
+	}
 }
 
 #[inline]
@@ -27,6 +46,16 @@ impl Add for Pos {
 	}
 }
 
+impl Add<Size> for Pos {
+	type Output = Pos;
+
+	fn add(mut self, rhs: Size) -> Self::Output {
+		self.x += rhs.w as i16;
+		self.y += rhs.h as i16;
+		self
+	}
+}
+
 impl AddAssign for Pos {
 	fn add_assign(&mut self, rhs: Self) {
 		*self = *self + rhs;
@@ -90,3 +119,41 @@ impl DivAssign<i16> for Pos {
 		*self = *self / rhs;
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::math::size::size;
+
+	#[test]
+	fn positions_convert_to_sizes() {
+		assert_eq!(pos(3, 7).to_size(), size(3, 7));
+		assert_eq!(pos(i16::MAX, 0).to_size(), size(i16::MAX as u16, 0));
+	}
+
+	#[test]
+	fn negative_positions_clamp_to_empty_sizes() {
+		assert_eq!(pos(-3, 7).to_size(), size(0, 7));
+		assert_eq!(pos(3, -7).to_size(), size(3, 0));
+		assert_eq!(pos(i16::MIN, i16::MIN).to_size(), Size::ZERO);
+	}
+
+	#[test]
+	fn adding_a_size_moves_to_its_far_corner() {
+		assert_eq!(pos(2, 3) + size(4, 5), pos(6, 8));
+		assert_eq!(pos(-4, -1) + size(4, 0), pos(0, -1));
+		assert_eq!(pos(2, 3) + Size::ZERO, pos(2, 3));
+	}
+
+	#[test]
+	fn steps_move_one_slot_in_their_direction() {
+		assert_eq!(pos(3, 3).step(Direction::Up), pos(3, 2));
+		assert_eq!(pos(3, 3).step(Direction::Right), pos(4, 3));
+		assert_eq!(pos(3, 3).step(Direction::Down), pos(3, 4));
+		assert_eq!(pos(3, 3).step(Direction::Left), pos(2, 3));
+
+		// it knows nothing about playfields, so it goes past their edges
+		assert_eq!(pos(0, 0).step(Direction::Left), pos(-1, 0));
+		assert_eq!(pos(0, 0).step(Direction::Up), pos(0, -1));
+	}
+}
@@ -1,8 +1,10 @@
+use serde::Serialize;
+
 use super::pos::Pos;
 use super::size::Size;
 
 /// A rectangle with a position and size.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize)]
 #[repr(C, align(8))]
 pub struct Rect {
 	pub x: i16,
@@ -1,5 +1,7 @@
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign};
 
+use super::pos::Pos;
+
 /// Size of a rectangle.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 #[repr(C, align(4))]
@@ -10,6 +12,66 @@ pub struct Size {
 
 impl Size {
 	pub const ZERO: Self = Self { w: 0, h: 0 };
+
+	/// Number of pixels in a rectangle of this size, or `None` if it overflows.
+	#[inline]
+	pub fn area_checked(self) -> Option<usize> {
+		(self.w as usize).checked_mul(self.h as usize)
+	}
+
+	/// Scales this size, or gives `None` if either dimension overflows.
+	#[inline]
+	#[allow(unused)]
+	pub fn checked_mul(self, scale: u16) -> Option<Size> {
+		Some(Size {
+			w: self.w.checked_mul(scale)?,
+			h: self.h.checked_mul(scale)?,
+		})
+	}
+
+	/// Whether a rectangle of this size fits inside one of the other size, in both dimensions.
+	#[inline]
+	#[allow(unused)]
+	pub const fn fits_within(self, other: Size) -> bool {
+		self.w <= other.w && self.h <= other.h
+	}
+
+	/// Component-wise maximum of both sizes.
+	#[inline]
+	#[allow(unused)]
+	pub fn max_component(self, other: Size) -> Size {
+		Size {
+			w: self.w.max(other.w),
+			h: self.h.max(other.h),
+		}
+	}
+
+	/// Component-wise minimum of both sizes.
+	#[inline]
+	#[allow(unused)]
+	pub fn min_component(self, other: Size) -> Size {
+		Size {
+			w: self.w.min(other.w),
+			h: self.h.min(other.h),
+		}
+	}
+
+	/// Converts this size to a position, clamping dimensions that don't fit in a coordinate.
+	#[inline]
+	#[allow(unused)]
+	pub const fn to_pos(self) -> Pos {
+		let x = if self.w > i16::MAX as u16 {
+			i16::MAX
+		} else {
+			self.w as i16
+		};
+		let y = if self.h > i16::MAX as u16 {
+			i16::MAX
+		} else {
+			self.h as i16
+		};
+		Pos { x, y }
+	}
 }
 
 #[inline]
@@ -90,3 +152,59 @@ impl DivAssign<u16> for Size {
 		*self = *self / rhs;
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::math::pos::pos;
+
+	#[test]
+	fn sizes_convert_to_positions() {
+		assert_eq!(size(3, 7).to_pos(), pos(3, 7));
+		assert_eq!(size(3, 7).to_pos().to_size(), size(3, 7));
+	}
+
+	#[test]
+	fn sizes_too_large_for_a_position_clamp() {
+		assert_eq!(size(u16::MAX, 1).to_pos(), pos(i16::MAX, 1));
+		assert_eq!(size(i16::MAX as u16 + 1, u16::MAX).to_pos(), pos(i16::MAX, i16::MAX));
+	}
+
+	#[test]
+	fn area_doesnt_overflow_where_u16_would() {
+		assert_eq!(size(300, 300).area_checked(), Some(90_000));
+		assert_eq!(size(u16::MAX, u16::MAX).area_checked(), Some(65_535 * 65_535));
+		assert_eq!(size(u16::MAX, 0).area_checked(), Some(0));
+	}
+
+	#[test]
+	fn scaling_gives_none_on_overflow() {
+		assert_eq!(size(300, 200).checked_mul(3), Some(size(900, 600)));
+		assert_eq!(size(40_000, 1).checked_mul(2), None);
+		assert_eq!(size(1, 40_000).checked_mul(2), None);
+		assert_eq!(size(u16::MAX, u16::MAX).checked_mul(1), Some(size(u16::MAX, u16::MAX)));
+		assert_eq!(size(u16::MAX, u16::MAX).checked_mul(0), Some(Size::ZERO));
+	}
+
+	#[test]
+	fn sizes_fit_within_sizes_at_least_as_big() {
+		assert!(size(3, 4).fits_within(size(3, 4)));
+		assert!(size(3, 4).fits_within(size(5, 9)));
+		assert!(Size::ZERO.fits_within(Size::ZERO));
+
+		// fitting on one dimension isn't enough
+		assert!(!size(3, 4).fits_within(size(2, 9)));
+		assert!(!size(3, 4).fits_within(size(5, 3)));
+		assert!(!size(3, 4).fits_within(size(2, 3)));
+	}
+
+	#[test]
+	fn component_min_and_max_take_each_dimension_apart() {
+		assert_eq!(size(3, 8).max_component(size(5, 2)), size(5, 8));
+		assert_eq!(size(3, 8).min_component(size(5, 2)), size(3, 2));
+		assert_eq!(size(3, 8).max_component(size(3, 8)), size(3, 8));
+
+		// unlike the lexicographic order
+		assert_eq!(size(3, 8).max(size(5, 2)), size(5, 2));
+	}
+}
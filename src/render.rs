@@ -1,19 +1,31 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
 use std::sync::Arc;
 
-use self::bitmap::Bitmap;
-use self::color::alphacomp::{self, AlphaCompFn};
+use image::error::{ParameterError, ParameterErrorKind};
+use image::{ImageError, ImageResult};
+
+use self::bitmap::{Bitmap, BitmapError};
+use self::color::alphacomp::{self, BlendMode};
 use self::color::Color;
 use self::sprite::{NineSlicePart, NineSlicingSprite, Sprite};
+use super::math::easing::Easing;
 use super::math::pos::{pos, Pos};
 use super::math::rect::Rect;
 use super::math::size::{size, Size};
+use super::snake::{Rng, XorShift32};
 
 pub mod ascii_sheet;
 pub mod bitmap;
+pub mod canvas;
 pub mod color;
 pub mod sprite;
 
 pub use ascii_sheet::{ascii_sheet, AsciiSheet};
+pub use canvas::Canvas;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct SpritesheetId(usize);
@@ -31,7 +43,7 @@ impl FramebufferStack {
 		}
 	}
 
-	pub fn blit_fb_down(&mut self, zindex: usize, acf: AlphaCompFn) {
+	pub fn blit_fb_down(&mut self, zindex: usize, acf: BlendMode) {
 		if zindex >= self.fbs.len() || zindex == 0 {
 			return;
 		}
@@ -44,60 +56,398 @@ impl FramebufferStack {
 		fba.copy_bitmap(fbb, acf);
 	}
 
-	fn push_fbs(&mut self, zindex: usize) {
+	/// Makes sure there's a framebuffer at some z-index, creating the missing ones.
+	fn push_fbs(&mut self, zindex: usize) -> Result<(), BitmapError> {
 		while zindex >= self.fbs.len() {
-			self.fbs.push(Bitmap::new(self.size));
+			self.fbs.push(Bitmap::new(self.size)?);
 		}
+
+		Ok(())
 	}
 
+	/// The framebuffer at some z-index, which has to have been created by [`FramebufferStack::push_fbs`] already.
 	fn fb_mut(&mut self, zindex: usize) -> &mut Bitmap {
-		self.push_fbs(zindex);
 		&mut self.fbs[zindex]
 	}
 
-	fn fb(&mut self, zindex: usize) -> &Bitmap {
-		self.push_fbs(zindex);
+	fn fb(&self, zindex: usize) -> &Bitmap {
 		&self.fbs[zindex]
 	}
 }
 
+/// Statistics about what was drawn during a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RenderStats {
+	/// Number of draw commands executed.
+	pub commands: usize,
+	/// Number of framebuffer pixels written to, after clipping. Overlapping draws are counted once per draw.
+	pub pixels_written: usize,
+	/// Number of times a framebuffer was cleared.
+	pub clears: usize,
+}
+
+/// A full-screen tint that fades out over some frames.
+#[derive(Debug, Clone, Copy)]
+struct Flash {
+	color: Color,
+	frames: u8,
+	frames_left: u8,
+}
+
+impl Flash {
+	/// The flash for the next frame, if it hasn't faded out.
+	fn fade(self) -> Option<Flash> {
+		let frames_left = self.frames_left - 1;
+		(frames_left > 0).then_some(Flash { frames_left, ..self })
+	}
+
+	/// How much the presented frame is tinted, from 0 (not at all) to 1 (fully the flash's color).
+	fn strength(self) -> f32 {
+		self.frames_left as f32 / self.frames as f32
+	}
+}
+
+/// A full-screen shake that dies down over some frames.
+#[derive(Debug, Clone, Copy)]
+struct Shake {
+	magnitude: f32,
+	frames: u8,
+	frame: u8,
+	easing: Easing,
+}
+
+impl Shake {
+	/// The shake for the next frame, if it hasn't died down.
+	fn fade(self) -> Option<Shake> {
+		let frame = self.frame + 1;
+		(frame < self.frames).then_some(Shake { frame, ..self })
+	}
+
+	/// Furthest the presented frame can be moved on this frame, in pixels.
+	fn strength(self) -> f32 {
+		self.magnitude * (1. - self.easing.apply(self.frame as f32 / self.frames as f32))
+	}
+
+	/// A random offset for this frame, no further than its strength on either axis.
+	fn offset(self, rng: &mut impl Rng) -> Pos {
+		let strength = self.strength();
+		let mut random_axis = || {
+			let unit = rng.next_u32() as f32 / u32::MAX as f32 * 2. - 1.;
+			(unit * strength).round() as i16
+		};
+		pos(random_axis(), random_axis())
+	}
+}
+
+/// Copies pixels moved by some offset, repeating the edge pixels where the moved frame doesn't cover.
+fn shift_pixels(out: &mut [u32], pixels: &[u32], size: Size, offset: Pos) {
+	let (w, h) = (size.w as i32, size.h as i32);
+	for y in 0..h {
+		let src_y = (y - offset.y as i32).clamp(0, h - 1);
+		for x in 0..w {
+			let src_x = (x - offset.x as i32).clamp(0, w - 1);
+			out[(y * w + x) as usize] = pixels[(src_y * w + src_x) as usize];
+		}
+	}
+}
+
+/// Copies pixels with a shake offset and a flash applied.
+fn apply_effects(out: &mut [u32], pixels: &[u32], size: Size, flash: Option<(Color, f32)>, offset: Pos) {
+	match offset {
+		Pos::ZERO => out.copy_from_slice(pixels),
+		offset => shift_pixels(out, pixels, size, offset),
+	}
+
+	if let Some((color, strength)) = flash {
+		tint_pixels(out, color, strength);
+	}
+}
+
+/// Moves every pixel's color towards another one by some strength between 0 and 1, keeping its alpha.
+fn tint_pixels(pixels: &mut [u32], color: Color, strength: f32) {
+	let tint = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * strength).round() as u8;
+
+	for pixel in pixels {
+		let from = Color::from_hex(*pixel);
+		let tinted = Color {
+			a: from.a,
+			r: tint(from.r, color.r),
+			g: tint(from.g, color.g),
+			b: tint(from.b, color.b),
+		};
+		*pixel = tinted.to_u32();
+	}
+}
+
 pub struct Renderer {
 	fb_stack: FramebufferStack,
 	ascii_bitmap: Bitmap,
 	ascii_sheet: AsciiSheet,
 	spritesheets: Vec<Bitmap>,
+	/// Ids of every registered spritesheet, in the order they were registered.
+	spritesheet_ids: Vec<SpritesheetId>,
+	/// Spritesheets registered with a name.
+	spritesheet_names: HashMap<Box<str>, SpritesheetId>,
+	last_frame_stats: RenderStats,
+	clear_color: Color,
+	scanline_sprites: bool,
+	/// Plain texts already measured by [`Renderer::text_cached`].
+	text_cache: RefCell<HashMap<Arc<str>, Text>>,
+	flash: Option<Flash>,
+	shake: Option<Shake>,
+	/// Makes shakes random, but the same every time.
+	shake_rng: XorShift32,
+	/// Copy of the first framebuffer with the screen effects applied, presented while any is playing.
+	effects_buffer: Vec<u32>,
 }
 
 impl Renderer {
+	/// Seed of the shakes' randomness, so that they look the same from one run to the next.
+	const SHAKE_SEED: u32 = 0x5eed_5a4e;
+
 	pub fn new(framebuffer: Bitmap, ascii_bitmap: Bitmap) -> Self {
 		Self {
 			fb_stack: FramebufferStack::new(framebuffer),
 			ascii_bitmap,
 			ascii_sheet: ascii_sheet(),
 			spritesheets: Vec::new(),
+			spritesheet_ids: Vec::new(),
+			spritesheet_names: HashMap::new(),
+			last_frame_stats: RenderStats::default(),
+			clear_color: Color::TRANSPARENT,
+			scanline_sprites: false,
+			text_cache: RefCell::default(),
+			flash: None,
+			shake: None,
+			shake_rng: XorShift32::new(Self::SHAKE_SEED),
+			effects_buffer: Vec::new(),
 		}
 	}
 
 	pub fn register_spritesheet(&mut self, sheet: Bitmap) -> SpritesheetId {
 		let id = SpritesheetId(self.spritesheets.len());
 		self.spritesheets.push(sheet);
+		self.spritesheet_ids.push(id);
+		id
+	}
+
+	/// Registers a spritesheet that can be looked up by name with [`Renderer::spritesheet_by_name`].
+	///
+	/// Registering another sheet with the same name makes the name point to the new one. The old sheet stays
+	/// registered, so its id keeps working.
+	pub fn register_spritesheet_named(&mut self, name: &str, sheet: Bitmap) -> SpritesheetId {
+		let id = self.register_spritesheet(sheet);
+		self.spritesheet_names.insert(name.into(), id);
 		id
 	}
 
+	/// Id of the spritesheet last registered with some name.
+	#[allow(unused)]
+	pub fn spritesheet_by_name(&self, name: &str) -> Option<SpritesheetId> {
+		self.spritesheet_names.get(name).copied()
+	}
+
+	/// Ids of every registered spritesheet, in the order they were registered. Ids never change.
+	#[allow(unused)]
+	pub fn spritesheet_ids(&self) -> &[SpritesheetId] {
+		&self.spritesheet_ids
+	}
+
+	/// Re-loads a registered spritesheet from a PNG file on disk.
+	///
+	/// The new sheet must be the same size as the old one, so that the sprite regions pointing into it stay valid.
+	#[allow(unused)]
+	pub fn reload_spritesheet(&mut self, id: SpritesheetId, path: impl AsRef<Path>) -> ImageResult<()> {
+		let sheet = Bitmap::from_png(&fs::read(path)?)?;
+
+		let Some(old_sheet) = self.spritesheets.get_mut(id.0) else {
+			return Err(ImageError::Parameter(ParameterError::from_kind(
+				ParameterErrorKind::Generic(format!("no spritesheet with id {}", id.0)),
+			)));
+		};
+
+		if sheet.size() != old_sheet.size() {
+			return Err(ImageError::Parameter(ParameterError::from_kind(
+				ParameterErrorKind::DimensionMismatch,
+			)));
+		}
+
+		*old_sheet = sheet;
+		Ok(())
+	}
+
+	/// Changes the size of the framebuffer. Its contents are lost.
+	///
+	/// A size of 0 is fine: nothing gets drawn until it's resized again.
+	#[allow(unused)]
+	pub fn resize(&mut self, size: Size) -> Result<(), BitmapError> {
+		self.fb_stack = FramebufferStack::new(Bitmap::new(size)?);
+		Ok(())
+	}
+
+	/// Rect of the framebuffer that gets drawn on. Everything outside of it is clipped.
+	#[allow(unused)]
+	pub fn viewport(&self) -> Rect {
+		Rect::from_pos_size(Pos::ZERO, self.fb_stack.size)
+	}
+
+	#[allow(unused)]
 	pub fn first_framebuffer(&mut self) -> &Bitmap {
 		self.fb_stack.fb(0)
 	}
 
+	/// Copies the last drawn frame into a buffer of `size` pixels, for frontends that own their own buffer.
+	///
+	/// The size has to be the viewport's, and the buffer has to hold exactly that many pixels.
+	///
+	/// Screen effects are applied the way the next [`Renderer::present`] shows them, but they don't move on: only
+	/// presenting makes them fade out, so copying a frame out and presenting it in the same frame shows it once.
+	#[allow(unused)]
+	pub fn render_into(&mut self, out: &mut [u32], size: Size) -> Result<(), BitmapError> {
+		if size != self.fb_stack.size {
+			return Err(BitmapError::SizeMismatch {
+				expected: self.fb_stack.size,
+				actual: size,
+			});
+		}
+
+		let expected = size.w as usize * size.h as usize;
+		if out.len() != expected {
+			return Err(BitmapError::BufferSizeMismatch {
+				expected,
+				actual: out.len(),
+			});
+		}
+
+		let (flash, offset) = self.current_effects();
+		apply_effects(out, self.fb_stack.fb(0).pixels(), size, flash, offset);
+		Ok(())
+	}
+
+	/// Gives the last drawn frame as it should be shown, with the current flash and shake applied.
+	///
+	/// Meant to be called once per presented frame, as it's what makes screen effects fade out.
+	pub fn present(&mut self) -> &[u32] {
+		let (flash, offset) = self.next_effects();
+		if flash.is_none() && offset == Pos::ZERO {
+			return self.fb_stack.fb(0).pixels();
+		}
+
+		let size = self.fb_stack.size;
+		let pixels = self.fb_stack.fb(0).pixels();
+		self.effects_buffer.resize(pixels.len(), 0);
+		apply_effects(&mut self.effects_buffer, pixels, size, flash, offset);
+		&self.effects_buffer
+	}
+
+	/// The flash (color and strength) and shake offset the next presented frame gets, without moving them on.
+	fn current_effects(&self) -> (Option<(Color, f32)>, Pos) {
+		let flash = (self.flash).map(|flash| (flash.color, flash.strength()));
+		// the same offset presenting picks, from a copy of the RNG
+		let offset = (self.shake).map_or(Pos::ZERO, |shake| shake.offset(&mut self.shake_rng.clone()));
+		(flash, offset)
+	}
+
+	/// The flash (color and strength) and shake offset of the frame being presented, moving them on to the next.
+	fn next_effects(&mut self) -> (Option<(Color, f32)>, Pos) {
+		let flash = self.flash.take().map(|flash| {
+			self.flash = flash.fade();
+			(flash.color, flash.strength())
+		});
+
+		let offset = match self.shake.take() {
+			Some(shake) => {
+				self.shake = shake.fade();
+				shake.offset(&mut self.shake_rng)
+			}
+			None => Pos::ZERO,
+		};
+
+		(flash, offset)
+	}
+
+	/// Shakes the presented frames by up to `magnitude` pixels, dying down over some frames along an easing.
+	///
+	/// Only what's presented moves: the framebuffers keep what was drawn on them, and the edges of the frame are
+	/// stretched over what would be left uncovered. A new shake replaces the one playing, and 0 frames stops it.
+	pub fn shake(&mut self, magnitude: f32, frames: u8, easing: Easing) {
+		self.shake = (frames > 0).then_some(Shake {
+			magnitude: magnitude.max(0.),
+			frames,
+			frame: 0,
+			easing,
+		});
+	}
+
+	/// Tints the presented frames towards a color, starting fully tinted and fading out over some frames.
+	///
+	/// Only what's presented changes: the framebuffers keep what was drawn on them.
+	/// A new flash replaces the one playing, and 0 frames stops it.
+	pub fn flash(&mut self, color: Color, frames: u8) {
+		self.flash = (frames > 0).then_some(Flash {
+			color,
+			frames,
+			frames_left: frames,
+		});
+	}
+
 	#[inline]
 	pub fn text<S>(&self, text: S) -> Text
 	where
 		Arc<str>: From<S>,
 	{
-		self.text_inner(Arc::<str>::from(text))
+		let text = Arc::<str>::from(text);
+		let span = TextSpan {
+			start: 0,
+			end: text.len(),
+			color: None,
+		};
+
+		self.text_inner(text, Arc::new([span]))
+	}
+
+	/// Same as [`Renderer::text`], but measures each distinct string only once.
+	///
+	/// Meant for static labels rebuilt every frame: calls with the same string share the same glyph data.
+	pub fn text_cached(&self, text: &str) -> Text {
+		if let Some(cached) = self.text_cache.borrow().get(text) {
+			return cached.clone();
+		}
+
+		let measured = self.text(text);
+		(self.text_cache.borrow_mut()).insert(measured.text.clone(), measured.clone());
+		measured
+	}
+
+	/// Starts building a piece of text out of several spans.
+	#[allow(unused)]
+	pub fn text_builder(&self) -> TextBuilder<'_> {
+		TextBuilder {
+			renderer: self,
+			text: String::new(),
+			spans: Vec::new(),
+		}
+	}
+
+	/// Size of the biggest glyph of the ASCII sheet.
+	///
+	/// All glyphs have the same height, but their width varies.
+	#[allow(unused)]
+	pub fn glyph_size(&self) -> Size {
+		let mut glyph_size = Size::ZERO;
+
+		for c in 0..128 {
+			let c_sprite = ascii_char_to_sprite(c, &self.ascii_sheet);
+			glyph_size.w = glyph_size.w.max(c_sprite.w);
+			glyph_size.h = glyph_size.h.max(c_sprite.h);
+		}
+
+		glyph_size
 	}
 
-	fn text_inner(&self, text: Arc<str>) -> Text {
+	fn text_inner(&self, text: Arc<str>, spans: Arc<[TextSpan]>) -> Text {
 		let mut size = Size::ZERO;
+		let mut glyph_widths = Vec::with_capacity(text.len());
 
 		for &c in text.as_bytes() {
 			let c_sprite = ascii_char_to_sprite(c, &self.ascii_sheet);
@@ -108,19 +458,68 @@ impl Renderer {
 
 			size.w += c_sprite.w;
 			size.h = size.h.max(c_sprite.h);
+			glyph_widths.push(c_sprite.w);
 		}
 
-		Text { text, size }
+		Text {
+			text,
+			size,
+			glyph_widths: glyph_widths.into(),
+			glyph_positions: None,
+			spans,
+		}
 	}
 
-	pub fn draw(&mut self, commands: &[DrawCommand]) {
-		draw(
+	/// Runs draw commands on the framebuffers.
+	///
+	/// Fails if a composite layer needs a framebuffer that can't be allocated.
+	pub fn draw(&mut self, commands: &[DrawCommand]) -> Result<(), BitmapError> {
+		// e.g. a minimized window, there's nothing to draw on
+		if self.fb_stack.size.w == 0 || self.fb_stack.size.h == 0 {
+			self.last_frame_stats = RenderStats::default();
+			return Ok(());
+		}
+
+		self.last_frame_stats = draw(
 			commands,
 			&mut self.fb_stack,
 			&self.spritesheets,
 			&self.ascii_sheet,
 			&self.ascii_bitmap,
-		);
+			self.clear_color,
+			self.scanline_sprites,
+		)?;
+
+		Ok(())
+	}
+
+	/// Gives a surface to draw on the base framebuffer right away, outside of any draw commands.
+	///
+	/// Whatever's drawn goes on top of the last frame, so it should be done after [`Renderer::draw`].
+	#[allow(unused)]
+	pub fn canvas(&mut self) -> Canvas<'_> {
+		Canvas::new(self)
+	}
+
+	/// Sets the color [`DrawCommand::Clear`] fills the base framebuffer with.
+	///
+	/// Composite layers are always cleared to transparent, so they blend correctly.
+	#[allow(unused)]
+	pub fn set_clear_color(&mut self, color: Color) {
+		self.clear_color = color;
+	}
+
+	/// When enabled, consecutive sprite commands are drawn together, one destination row at a time,
+	/// instead of one sprite after the other. The output is the same, but writes are closer together.
+	#[allow(unused)]
+	pub fn set_scanline_sprites(&mut self, enabled: bool) {
+		self.scanline_sprites = enabled;
+	}
+
+	/// Statistics of the last call to [`Renderer::draw`].
+	#[allow(unused)]
+	pub fn last_frame_stats(&self) -> RenderStats {
+		self.last_frame_stats
 	}
 }
 
@@ -129,9 +528,113 @@ impl Renderer {
 pub struct Text {
 	text: Arc<str>,
 	size: Size,
+	glyph_widths: Arc<[u16]>,
+	/// Explicit position of each glyph relative to where the text is drawn.
+	/// When there are none, glyphs are laid out left to right with a 1px gap.
+	glyph_positions: Option<Arc<[Pos]>>,
+	spans: Arc<[TextSpan]>,
+}
+
+/// A span of text drawn with the same color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TextSpan {
+	/// Byte index where the span starts in the text.
+	pub start: usize,
+	/// Byte index where the span ends in the text.
+	pub end: usize,
+	/// Color of the span. If there is none, the current AND mask is used instead.
+	pub color: Option<Color>,
+}
+
+/// Builds a piece of text out of several spans, potentially of different colors.
+pub struct TextBuilder<'a> {
+	renderer: &'a Renderer,
+	text: String,
+	spans: Vec<TextSpan>,
+}
+
+#[allow(unused)]
+impl TextBuilder<'_> {
+	/// Appends a plain span of text.
+	pub fn push(self, text: &str) -> Self {
+		self.push_span(text, None)
+	}
+
+	/// Appends a span of text with a specific color.
+	pub fn push_colored(self, text: &str, color: Color) -> Self {
+		self.push_span(text, Some(color))
+	}
+
+	fn push_span(mut self, text: &str, color: Option<Color>) -> Self {
+		if text.is_empty() {
+			return self;
+		}
+
+		let start = self.text.len();
+		self.text.push_str(text);
+		self.spans.push(TextSpan {
+			start,
+			end: self.text.len(),
+			color,
+		});
+		self
+	}
+
+	pub fn build(self) -> Text {
+		self.renderer.text_inner(Arc::from(self.text), self.spans.into())
+	}
+}
+
+/// A line of wrapped text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextLine {
+	/// Byte range of the line in the text.
+	pub range: Range<usize>,
+	/// Width of the line in pixels.
+	pub width: u16,
 }
 
 impl Text {
+	/// Vertical space between two lines of wrapped text.
+	pub const LINE_GAP: u16 = 1;
+
+	/// Builds a piece of text whose glyphs are each drawn at their own position, relative to where the text is drawn.
+	///
+	/// Characters outside of ASCII are replaced with `?`, like the ASCII sheet would draw them anyway.
+	#[allow(unused)]
+	pub fn from_glyphs(glyphs: Vec<(char, Pos)>) -> Self {
+		let ascii_sheet = ascii_sheet();
+		let mut text = String::with_capacity(glyphs.len());
+		let mut size = Size::ZERO;
+		let mut glyph_widths = Vec::with_capacity(glyphs.len());
+		let mut glyph_positions = Vec::with_capacity(glyphs.len());
+
+		for (c, pos) in glyphs {
+			let c = if c.is_ascii() { c } else { '?' };
+			let c_sprite = ascii_char_to_sprite(c as u8, &ascii_sheet);
+
+			text.push(c);
+			size.w = size.w.max((pos.x.max(0) as u16).saturating_add(c_sprite.w));
+			size.h = size.h.max((pos.y.max(0) as u16).saturating_add(c_sprite.h));
+			glyph_widths.push(c_sprite.w);
+			glyph_positions.push(pos);
+		}
+
+		let span = TextSpan {
+			start: 0,
+			end: text.len(),
+			color: None,
+		};
+
+		Self {
+			text: Arc::from(text),
+			size,
+			glyph_widths: glyph_widths.into(),
+			glyph_positions: Some(glyph_positions.into()),
+			spans: Arc::new([span]),
+		}
+	}
+
 	pub fn text(&self) -> &Arc<str> {
 		&self.text
 	}
@@ -139,6 +642,131 @@ impl Text {
 	pub fn size(&self) -> Size {
 		self.size
 	}
+
+	/// Width of a range of glyphs, including the 1px gaps between them.
+	fn range_width(&self, range: Range<usize>) -> u16 {
+		let glyphs = &self.glyph_widths[range];
+		let gaps = glyphs.len().saturating_sub(1) as u16;
+		glyphs.iter().sum::<u16>() + gaps
+	}
+
+	/// Wraps the text at word boundaries so that each line fits in `max_width`.
+	///
+	/// Words that are too long to fit on their own line are broken wherever they overflow.
+	pub fn wrap(&self, max_width: u16) -> Vec<TextLine> {
+		let bytes = self.text.as_bytes();
+		let mut lines = Vec::new();
+
+		let mut line: Option<Range<usize>> = None;
+		let mut i = 0;
+		while i < bytes.len() {
+			if bytes[i] == b' ' {
+				i += 1;
+				continue;
+			}
+
+			let word_start = i;
+			while i < bytes.len() && bytes[i] != b' ' {
+				i += 1;
+			}
+			let word_end = i;
+
+			match &mut line {
+				Some(range) if self.range_width(range.start..word_end) <= max_width => range.end = word_end,
+				_ => {
+					if let Some(range) = line.take() {
+						lines.push(self.line(range));
+					}
+
+					// hard-break the word for as long as it overflows
+					let mut start = word_start;
+					while self.range_width(start..word_end) > max_width {
+						let mut end = start + 1;
+						while end < word_end && self.range_width(start..end + 1) <= max_width {
+							end += 1;
+						}
+
+						lines.push(self.line(start..end));
+						start = end;
+					}
+
+					if start < word_end {
+						line = Some(start..word_end);
+					}
+				}
+			}
+		}
+
+		if let Some(range) = line {
+			lines.push(self.line(range));
+		}
+
+		lines
+	}
+
+	/// Size of the text once wrapped to fit in `max_width`.
+	pub fn wrapped_size(&self, max_width: u16) -> Size {
+		let lines = self.wrap(max_width);
+		let w = lines.iter().map(|line| line.width).max().unwrap_or(0);
+		size(w, self.lines_height(lines.len()))
+	}
+
+	/// Height of a number of lines of text.
+	pub fn lines_height(&self, line_count: usize) -> u16 {
+		let h = self.size.h;
+		let line_count = u16::try_from(line_count).unwrap_or(u16::MAX);
+		line_count
+			.saturating_mul(h + Self::LINE_GAP)
+			.saturating_sub(Self::LINE_GAP)
+	}
+
+	pub fn spans(&self) -> &[TextSpan] {
+		&self.spans
+	}
+
+	/// Explicit glyph positions, if the text was built from them (see [`Text::from_glyphs`]).
+	pub fn glyph_positions(&self) -> Option<&[Pos]> {
+		self.glyph_positions.as_deref()
+	}
+
+	/// Extracts a range of the text (like a line of wrapped text) as its own piece of text.
+	pub fn slice(&self, range: Range<usize>) -> Text {
+		let spans = (self.spans.iter())
+			.filter(|span| span.start < range.end && range.start < span.end)
+			.map(|span| TextSpan {
+				start: span.start.max(range.start) - range.start,
+				end: span.end.min(range.end) - range.start,
+				color: span.color,
+			})
+			.collect();
+
+		Text {
+			text: Arc::from(&self.text[range.clone()]),
+			size: size(self.range_width(range.clone()), self.size.h),
+			glyph_widths: Arc::from(&self.glyph_widths[range.clone()]),
+			glyph_positions: (self.glyph_positions.as_ref()).map(|positions| Arc::from(&positions[range])),
+			spans,
+		}
+	}
+
+	/// The same text without any span colors, so that it's drawn entirely with the current AND mask.
+	pub fn uncolored(&self) -> Text {
+		let span = TextSpan {
+			start: 0,
+			end: self.text.len(),
+			color: None,
+		};
+
+		Text {
+			spans: Arc::new([span]),
+			..self.clone()
+		}
+	}
+
+	fn line(&self, range: Range<usize>) -> TextLine {
+		let width = self.range_width(range.clone());
+		TextLine { range, width }
+	}
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -150,42 +778,89 @@ pub enum Rotate {
 	R270,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+impl Rotate {
+	/// Size of an area of the given size once rotated.
+	pub fn rotate_size(self, sz: Size) -> Size {
+		match self {
+			Rotate::R0 | Rotate::R180 => sz,
+			Rotate::R90 | Rotate::R270 => size(sz.h, sz.w),
+		}
+	}
+
+	/// How far a sprite of the given size moves when rotated about `pivot` instead of about its center.
+	///
+	/// The pivot is a point of the unrotated sprite, from its top-left corner. Half pixels are rounded down.
+	pub fn pivot_offset(self, sz: Size, pivot: Pos) -> Pos {
+		// in half pixels, so the center is a whole point
+		let dx = 2 * pivot.x as i32 - sz.w as i32;
+		let dy = 2 * pivot.y as i32 - sz.h as i32;
+
+		// where the pivot ends up when rotating about the center (clockwise, like the renderer)
+		let (rx, ry) = match self {
+			Rotate::R0 => (dx, dy),
+			Rotate::R90 => (-dy, dx),
+			Rotate::R180 => (-dx, -dy),
+			Rotate::R270 => (dy, -dx),
+		};
+
+		pos((dx - rx).div_euclid(2) as i16, (dy - ry).div_euclid(2) as i16)
+	}
+}
+
+#[derive(Debug, Clone)]
 pub enum DrawCommand {
+	/// Clears the current framebuffer to the renderer's clear color (see [`Renderer::set_clear_color`]).
 	Clear,
+	/// Clears the current framebuffer to the given color.
+	#[allow(unused)]
+	ClearColor(Color),
 	Fill {
 		rect: Rect,
 		color: Color,
-		acf: AlphaCompFn,
+		acf: BlendMode,
 	},
 	Stroke {
 		rect: Rect,
 		stroke_width: u16,
 		color: Color,
-		acf: AlphaCompFn,
+		acf: BlendMode,
 	},
 	Sprite {
 		pos: Pos,
 		rotate: Rotate,
 		sheet_id: SpritesheetId,
 		sprite: Sprite,
-		acf: AlphaCompFn,
+		acf: BlendMode,
 	},
 	NineSlicingSprite {
 		rect: Rect,
 		sheet_id: SpritesheetId,
 		nss: NineSlicingSprite,
-		acf: AlphaCompFn,
+		acf: BlendMode,
 	},
 	Text {
-		text: Arc<str>,
+		text: Text,
 		pos: Pos,
-		acf: AlphaCompFn,
+		acf: BlendMode,
 	},
 	MaskAnd(Color),
 	MaskOr(Color),
 	BeginComposite,
-	EndComposite(AlphaCompFn),
+	EndComposite(BlendMode),
+}
+
+impl DrawCommand {
+	/// How the command blends what it draws, for the commands that draw with their own blend mode.
+	fn blend_mode(&self) -> Option<BlendMode> {
+		match *self {
+			DrawCommand::Fill { acf, .. }
+			| DrawCommand::Stroke { acf, .. }
+			| DrawCommand::Sprite { acf, .. }
+			| DrawCommand::NineSlicingSprite { acf, .. }
+			| DrawCommand::Text { acf, .. } => Some(acf),
+			_ => None,
+		}
+	}
 }
 
 fn draw(
@@ -194,14 +869,43 @@ fn draw(
 	spritesheets: &[Bitmap],
 	ascii_sheet: &AsciiSheet,
 	ascii_bitmap: &Bitmap,
-) {
+	clear_color: Color,
+	scanline_sprites: bool,
+) -> Result<RenderStats, BitmapError> {
 	let mut mask_and = Color::WHITE;
 	let mut mask_or = Color::TRANSPARENT;
 
+	let mut stats = RenderStats {
+		commands: commands.len(),
+		..Default::default()
+	};
+	let viewport = Rect::from_pos_size(Pos::ZERO, fb_stack.size);
+
+	let mut sprite_batch = Vec::new();
+
 	let mut fb_id = 0;
 	for command in commands {
+		// a blend mode that keeps the destination as it is doesn't draw anything
+		if command.blend_mode().is_some_and(BlendMode::is_noop) {
+			continue;
+		}
+
+		stats.pixels_written += command_area(command, viewport);
+
+		if !sprite_batch.is_empty() && !matches!(command, DrawCommand::Sprite { .. }) {
+			flush_sprite_batch(fb_stack.fb_mut(fb_id), &mut sprite_batch, mask_and, mask_or);
+		}
+
 		match *command {
-			DrawCommand::Clear => (fb_stack.fb_mut(fb_id)).fill(Color::TRANSPARENT, alphacomp::dst),
+			DrawCommand::Clear => {
+				stats.clears += 1;
+				let color = if fb_id == 0 { clear_color } else { Color::TRANSPARENT };
+				(fb_stack.fb_mut(fb_id)).fill(color, alphacomp::SRC);
+			}
+			DrawCommand::ClearColor(color) => {
+				stats.clears += 1;
+				(fb_stack.fb_mut(fb_id)).fill(color, alphacomp::SRC);
+			}
 			DrawCommand::Fill { rect, color, acf } => (fb_stack.fb_mut(fb_id)).fill_area(color, rect, acf),
 			DrawCommand::Stroke {
 				rect,
@@ -213,8 +917,9 @@ fn draw(
 					continue;
 				}
 
+				let stroke_width = stroke_width.min(rect.w).min(rect.h);
 				let hsize = size(rect.w, stroke_width);
-				let vsize = size(stroke_width, rect.h - 2 * stroke_width);
+				let vsize = size(stroke_width, rect.h.saturating_sub(2 * stroke_width));
 				let lry = rect.y + stroke_width as i16;
 
 				let top_pos = rect.pos();
@@ -248,6 +953,17 @@ fn draw(
 					continue;
 				};
 
+				if scanline_sprites {
+					sprite_batch.push(BatchedSprite {
+						bitmap,
+						pos,
+						sprite,
+						rotate,
+						acf,
+					});
+					continue;
+				}
+
 				(fb_stack.fb_mut(fb_id)).copy_and_rotate_bitmap_area(
 					bitmap,
 					pos,
@@ -273,105 +989,24 @@ fn draw(
 					continue;
 				};
 
-				let fb_pos = rect.pos();
-
-				'top_left: {
-					let nssp = nss.slice(NineSlicePart::TopLeft);
-					if nssp.rect.w == 0 || nssp.rect.h == 0 {
-						break 'top_left;
-					}
-
-					let nssp_pos = nssp.rect.pos();
-					let nssp_size = nssp.rect.size();
-					(fb_stack.fb_mut(fb_id))
-						.copy_bitmap_area(bitmap, fb_pos, nssp_pos, nssp_size, acf, mask_and, mask_or);
-				}
-
-				'top_center: {
-					let nssp = nss.slice(NineSlicePart::TopCenter);
-					if nssp.rect.w == 0 || nssp.rect.h == 0 {
-						break 'top_center;
-					}
-
-					let mut x = nss.vl;
-					while x < rect.w.saturating_sub(nssp.rect.w) {
-						let nssp_pos = nssp.rect.pos();
-						let nssp_size = nssp.rect.size();
-						(fb_stack.fb_mut(fb_id)).copy_bitmap_area(
-							bitmap,
-							pos(fb_pos.x + x as i16, fb_pos.y),
-							nssp_pos,
-							size(nssp_size.w.min((rect.w - nssp.rect.w) - x), nssp_size.h),
-							acf,
-							mask_and,
-							mask_or,
-						);
-						x += nssp.rect.w;
+				let regions = nss.regions(rect);
+				for part in NineSlicePart::ALL {
+					let nssp = nss.slice(part);
+					let region = regions[part as usize];
+					if nssp.rect.w == 0 || nssp.rect.h == 0 || region.w == 0 || region.h == 0 {
+						continue;
 					}
-				}
 
-				'top_right: {
-					let nssp = nss.slice(NineSlicePart::TopRight);
-					if nssp.rect.w == 0 || nssp.rect.h == 0 {
-						break 'top_right;
-					}
-
-					let nssp_pos = nssp.rect.pos();
-					let nssp_size = nssp.rect.size();
-					(fb_stack.fb_mut(fb_id)).copy_bitmap_area(
-						bitmap,
-						pos(fb_pos.x + (rect.w - nssp.rect.w) as i16, fb_pos.y),
-						nssp_pos,
-						nssp_size,
-						acf,
-						mask_and,
-						mask_or,
-					);
-				}
-
-				'center_left: {
-					let nssp = nss.slice(NineSlicePart::CenterLeft);
-					if nssp.rect.w == 0 || nssp.rect.h == 0 {
-						break 'center_left;
-					}
-
-					let mut y = nss.ht;
-					while y < rect.h.saturating_sub(nssp.rect.h) {
-						let nssp_pos = nssp.rect.pos();
-						let nssp_size = nssp.rect.size();
-						(fb_stack.fb_mut(fb_id)).copy_bitmap_area(
-							bitmap,
-							pos(fb_pos.x, fb_pos.y + y as i16),
-							nssp_pos,
-							size(nssp_size.w, nssp_size.h.min((rect.h - nssp.rect.h) - y)),
-							acf,
-							mask_and,
-							mask_or,
-						);
-						y += nssp.rect.h;
-					}
-				}
-
-				'center: {
-					let nssp = nss.slice(NineSlicePart::Center);
-					if nssp.rect.w == 0 || nssp.rect.h == 0 {
-						break 'center;
-					}
-
-					let mut y = nss.ht;
-					while y < rect.h.saturating_sub(nssp.rect.h) {
-						let mut x = nss.vl;
-						while x < rect.w.saturating_sub(nssp.rect.w) {
-							let nssp_pos = nssp.rect.pos();
-							let nssp_size = nssp.rect.size();
+					// tile the part over its region, cutting off the last tiles
+					let mut y = 0;
+					while y < region.h {
+						let mut x = 0;
+						while x < region.w {
 							(fb_stack.fb_mut(fb_id)).copy_bitmap_area(
 								bitmap,
-								pos(fb_pos.x + x as i16, fb_pos.y + y as i16),
-								nssp_pos,
-								size(
-									nssp_size.w.min((rect.w - nssp.rect.w) - x),
-									nssp_size.h.min((rect.h - nssp.rect.h) - y),
-								),
+								pos(region.x + x as i16, region.y + y as i16),
+								nssp.rect.pos(),
+								size(nssp.rect.w.min(region.w - x), nssp.rect.h.min(region.h - y)),
 								acf,
 								mask_and,
 								mask_or,
@@ -381,112 +1016,41 @@ fn draw(
 						y += nssp.rect.h;
 					}
 				}
+			}
+			DrawCommand::Text { pos, ref text, acf } => {
+				let origin = pos;
+				let mut pos = pos;
+				let fb = fb_stack.fb_mut(fb_id);
 
-				'center_right: {
-					let nssp = nss.slice(NineSlicePart::CenterRight);
-					if nssp.rect.w == 0 || nssp.rect.h == 0 {
-						break 'center_right;
-					}
-
-					let mut y = nss.ht;
-					while y < rect.h.saturating_sub(nssp.rect.h) {
-						let nssp_pos = nssp.rect.pos();
-						let nssp_size = nssp.rect.size();
-						(fb_stack.fb_mut(fb_id)).copy_bitmap_area(
-							bitmap,
-							pos(fb_pos.x + (rect.w - nssp.rect.w) as i16, fb_pos.y + y as i16),
-							nssp_pos,
-							size(nssp_size.w, nssp_size.h.min((rect.h - nssp.rect.h) - y)),
-							acf,
-							mask_and,
-							mask_or,
-						);
-						y += nssp.rect.h;
-					}
-				}
-
-				'bottom_left: {
-					let nssp = nss.slice(NineSlicePart::BottomLeft);
-					if nssp.rect.w == 0 || nssp.rect.h == 0 {
-						break 'bottom_left;
-					}
-
-					let nssp_pos = nssp.rect.pos();
-					let nssp_size = nssp.rect.size();
-					(fb_stack.fb_mut(fb_id)).copy_bitmap_area(
-						bitmap,
-						pos(fb_pos.x, fb_pos.y + (rect.h - nssp.rect.h) as i16),
-						nssp_pos,
-						nssp_size,
-						acf,
-						mask_and,
-						mask_or,
-					);
-				}
-
-				'bottom_center: {
-					let nssp = nss.slice(NineSlicePart::BottomCenter);
-					if nssp.rect.w == 0 || nssp.rect.h == 0 {
-						break 'bottom_center;
-					}
-
-					let mut x = nss.vl;
-					while x < rect.w.saturating_sub(nssp.rect.w) {
-						let nssp_pos = nssp.rect.pos();
-						let nssp_size = nssp.rect.size();
-						(fb_stack.fb_mut(fb_id)).copy_bitmap_area(
-							bitmap,
-							pos(fb_pos.x + x as i16, fb_pos.y + (rect.h - nssp.rect.h) as i16),
-							nssp_pos,
-							size(nssp_size.w.min((rect.w - nssp.rect.w) - x), nssp_size.h),
+				for span in text.spans() {
+					let mask_and = span.color.unwrap_or(mask_and);
+
+					for (i, &c) in text
+						.text()
+						.as_bytes()
+						.iter()
+						.enumerate()
+						.take(span.end)
+						.skip(span.start)
+					{
+						let c_sprite = ascii_char_to_sprite(c, ascii_sheet);
+						let glyph_pos = match text.glyph_positions() {
+							Some(positions) => origin + positions[i],
+							None => pos,
+						};
+
+						fb.copy_bitmap_area(
+							ascii_bitmap,
+							glyph_pos,
+							c_sprite.pos(),
+							c_sprite.size(),
 							acf,
 							mask_and,
 							mask_or,
 						);
-						x += nssp.rect.w;
+						pos.x += c_sprite.w as i16 + 1;
 					}
 				}
-
-				'bottom_right: {
-					let nssp = nss.slice(NineSlicePart::BottomRight);
-					if nssp.rect.w == 0 || nssp.rect.h == 0 {
-						break 'bottom_right;
-					}
-
-					let nssp_pos = nssp.rect.pos();
-					let nssp_size = nssp.rect.size();
-					(fb_stack.fb_mut(fb_id)).copy_bitmap_area(
-						bitmap,
-						pos(
-							fb_pos.x + (rect.w - nssp.rect.w) as i16,
-							fb_pos.y + (rect.h - nssp.rect.h) as i16,
-						),
-						nssp_pos,
-						nssp_size,
-						acf,
-						mask_and,
-						mask_or,
-					);
-				}
-			}
-			DrawCommand::Text { pos, ref text, acf } => {
-				let mut pos = pos;
-				let fb = fb_stack.fb_mut(fb_id);
-
-				for &c in text.as_bytes() {
-					let c_sprite = ascii_char_to_sprite(c, ascii_sheet);
-
-					fb.copy_bitmap_area(
-						ascii_bitmap,
-						pos,
-						c_sprite.pos(),
-						c_sprite.size(),
-						acf,
-						mask_and,
-						mask_or,
-					);
-					pos.x += c_sprite.w as i16 + 1;
-				}
 			}
 			DrawCommand::MaskAnd(color) => {
 				mask_and = color;
@@ -496,13 +1060,114 @@ fn draw(
 			}
 			DrawCommand::BeginComposite => {
 				fb_id += 1;
+				fb_stack.push_fbs(fb_id)?;
 			}
 			DrawCommand::EndComposite(acf) => {
-				fb_stack.blit_fb_down(fb_id, acf);
+				if !acf.is_noop() {
+					fb_stack.blit_fb_down(fb_id, acf);
+				}
 				fb_id -= 1;
 			}
 		}
 	}
+
+	if !sprite_batch.is_empty() {
+		flush_sprite_batch(fb_stack.fb_mut(fb_id), &mut sprite_batch, mask_and, mask_or);
+	}
+
+	Ok(stats)
+}
+
+/// A sprite command waiting to be drawn with the rest of its batch.
+struct BatchedSprite<'a> {
+	bitmap: &'a Bitmap,
+	pos: Pos,
+	sprite: Sprite,
+	rotate: Rotate,
+	acf: BlendMode,
+}
+
+/// Draws a batch of sprites one framebuffer row at a time.
+///
+/// Every pixel still gets composited in submission order, so overlapping sprites look the same as when
+/// drawn one by one.
+fn flush_sprite_batch(fb: &mut Bitmap, batch: &mut Vec<BatchedSprite>, mask_and: Color, mask_or: Color) {
+	let top = |s: &BatchedSprite| s.pos.y as i32;
+	let bottom = |s: &BatchedSprite| s.pos.y as i32 + s.rotate.rotate_size(s.sprite.size()).h as i32;
+
+	// bucket the sprites by the first row they touch (the sort is stable, so submission order is kept)
+	let mut by_top: Vec<usize> = (0..batch.len()).collect();
+	by_top.sort_by_key(|&i| top(&batch[i]));
+
+	let start_y = batch.iter().map(top).min().unwrap_or(0).max(0);
+	let end_y = batch.iter().map(bottom).max().unwrap_or(0).min(fb.size().h as i32);
+
+	// sprites covering the current row, sorted by submission order
+	let mut active: Vec<usize> = Vec::new();
+	let mut next = 0;
+	for y in start_y..end_y {
+		while next < by_top.len() && top(&batch[by_top[next]]) <= y {
+			let i = by_top[next];
+			let at = active.binary_search(&i).unwrap_or_else(|at| at);
+			active.insert(at, i);
+			next += 1;
+		}
+		active.retain(|&i| bottom(&batch[i]) > y);
+
+		for &i in &active {
+			let s = &batch[i];
+			fb.copy_and_rotate_bitmap_row(
+				s.bitmap,
+				s.pos,
+				s.sprite.rect.pos(),
+				s.sprite.rect.size(),
+				s.acf,
+				mask_and,
+				mask_or,
+				s.rotate,
+				(y - top(s)) as u16,
+			);
+		}
+	}
+
+	batch.clear();
+}
+
+/// Number of pixels of the viewport a draw command covers.
+fn command_area(command: &DrawCommand, viewport: Rect) -> usize {
+	let rect = match *command {
+		DrawCommand::Clear | DrawCommand::ClearColor(_) => viewport,
+		DrawCommand::Fill { rect, .. } => rect,
+		DrawCommand::Stroke { rect, stroke_width, .. } => {
+			// the stroke is the rect minus its inside
+			let sw = stroke_width.min(rect.w).min(rect.h);
+			let inner = Rect::from_xywh(
+				rect.x + sw as i16,
+				rect.y + sw as i16,
+				rect.w.saturating_sub(2 * sw),
+				rect.h.saturating_sub(2 * sw),
+			);
+			return clipped_area(rect, viewport) - clipped_area(inner, viewport);
+		}
+		DrawCommand::Sprite {
+			pos, rotate, sprite, ..
+		} => Rect::from_pos_size(pos, rotate.rotate_size(sprite.rect.size())),
+		DrawCommand::NineSlicingSprite { rect, .. } => rect,
+		DrawCommand::Text { ref text, pos, .. } => Rect::from_pos_size(pos, text.size()),
+		DrawCommand::MaskAnd(_) | DrawCommand::MaskOr(_) | DrawCommand::BeginComposite => return 0,
+		DrawCommand::EndComposite(_) => viewport,
+	};
+
+	clipped_area(rect, viewport)
+}
+
+fn clipped_area(rect: Rect, viewport: Rect) -> usize {
+	let x0 = (rect.x as i32).max(viewport.x as i32);
+	let y0 = (rect.y as i32).max(viewport.y as i32);
+	let x1 = (rect.x as i32 + rect.w as i32).min(viewport.x as i32 + viewport.w as i32);
+	let y1 = (rect.y as i32 + rect.h as i32).min(viewport.y as i32 + viewport.h as i32);
+
+	(x1 - x0).max(0) as usize * (y1 - y0).max(0) as usize
 }
 
 fn ascii_char_to_sprite(c: u8, ascii_sheet: &AsciiSheet) -> Sprite {
@@ -601,3 +1266,796 @@ fn ascii_char_to_sprite(c: u8, ascii_sheet: &AsciiSheet) -> Sprite {
 		_ => ascii_sheet.question_mark,
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const IMG_ASCII_CHARS: &[u8] = include_bytes!("../assets/ascii-chars.png");
+
+	fn renderer() -> Renderer {
+		let framebuffer = Bitmap::new(size(16, 16)).unwrap();
+		Renderer::new(framebuffer, Bitmap::from_png(IMG_ASCII_CHARS).unwrap())
+	}
+
+	fn wrapped_lines(text: &Text, max_width: u16) -> Vec<String> {
+		let lines = text.wrap(max_width);
+		lines
+			.into_iter()
+			.map(|line| text.text()[line.range].to_owned())
+			.collect()
+	}
+
+	/// Draws some commands on a new renderer, to compare what they look like.
+	fn drawn(commands: &[DrawCommand]) -> Vec<u32> {
+		let mut renderer = renderer();
+		renderer.resize(size(64, 16)).unwrap();
+		renderer.draw(commands).unwrap();
+		renderer.first_framebuffer().pixels().to_vec()
+	}
+
+	#[test]
+	fn single_plain_span_is_the_same_as_plain_text() {
+		let renderer = renderer();
+		let plain = renderer.text("Score 42");
+		let built = renderer.text_builder().push("Score 42").build();
+		assert_eq!(built, plain);
+
+		let draw_text = |text: Text| {
+			drawn(&[
+				DrawCommand::MaskAnd(Color::from_hex(0xffff0000)),
+				DrawCommand::Text {
+					text,
+					pos: pos(1, 1),
+					acf: alphacomp::OVER,
+				},
+			])
+		};
+		let drawn_plain = draw_text(plain);
+		assert!(drawn_plain.iter().any(|&px| px != 0), "the text wasn't drawn at all");
+		assert_eq!(draw_text(built), drawn_plain);
+	}
+
+	#[test]
+	fn spans_add_up_to_the_whole_text() {
+		let renderer = renderer();
+		let red = Color::from_hex(0xffff0000);
+		let text = (renderer.text_builder())
+			.push("Score ")
+			.push("")
+			.push_colored("+5", red)
+			.build();
+
+		assert_eq!(text.text().as_ref(), "Score +5");
+		assert_eq!(text.size(), renderer.text("Score +5").size());
+		assert_eq!(
+			text.spans(),
+			[
+				TextSpan {
+					start: 0,
+					end: 6,
+					color: None
+				},
+				TextSpan {
+					start: 6,
+					end: 8,
+					color: Some(red)
+				},
+			]
+		);
+
+		let score_w = renderer.text("Score ").size().w;
+		let delta_w = renderer.text("+5").size().w;
+		assert_eq!(text.size().w, score_w + 1 + delta_w);
+	}
+
+	#[test]
+	fn glyph_size_fits_every_glyph() {
+		let renderer = renderer();
+		let glyph_size = renderer.glyph_size();
+		for c in 0..128u8 {
+			let text = renderer.text(String::from(c as char));
+			assert!(text.size().w <= glyph_size.w && text.size().h <= glyph_size.h);
+		}
+		assert_eq!(renderer.text("M").size().h, glyph_size.h);
+	}
+
+	const OPAQUE: u32 = 0xffffffff;
+
+	#[test]
+	fn viewport_is_the_framebuffer() {
+		let mut renderer = renderer();
+		assert_eq!(renderer.viewport(), Rect::from_xywh(0, 0, 16, 16));
+		renderer.resize(size(8, 4)).unwrap();
+		assert_eq!(renderer.viewport(), Rect::from_xywh(0, 0, 8, 4));
+	}
+
+	#[test]
+	fn sprites_off_the_edges_are_clipped() {
+		let cases = [
+			// partly off each edge
+			pos(-2, 2),
+			pos(6, 2),
+			pos(2, -2),
+			pos(2, 6),
+			pos(-2, -2),
+			pos(6, 6),
+			// fully off each edge
+			pos(-4, 2),
+			pos(8, 2),
+			pos(2, -4),
+			pos(2, 8),
+			pos(i16::MIN, i16::MIN),
+			pos(i16::MAX, i16::MAX),
+		];
+
+		for sprite_pos in cases {
+			let mut renderer = renderer();
+			renderer.resize(size(8, 8)).unwrap();
+			let sheet_id = renderer.register_spritesheet(Bitmap::from_buffer(vec![OPAQUE; 16], size(4, 4)).unwrap());
+			renderer
+				.draw(&[DrawCommand::Sprite {
+					pos: sprite_pos,
+					rotate: Rotate::R0,
+					sheet_id,
+					sprite: Sprite::new(Rect::from_xywh(0, 0, 4, 4)),
+					acf: alphacomp::OVER,
+				}])
+				.unwrap();
+
+			let fb = renderer.first_framebuffer();
+			for y in 0..8 {
+				for x in 0..8 {
+					let covered = (sprite_pos.x as i32..sprite_pos.x as i32 + 4).contains(&x)
+						&& (sprite_pos.y as i32..sprite_pos.y as i32 + 4).contains(&y);
+					let expected = if covered { OPAQUE } else { 0 };
+					assert_eq!(
+						fb.get(pos(x as i16, y as i16)),
+						Some(expected),
+						"{sprite_pos:?} at {x},{y}"
+					);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn everything_off_the_edges_is_clipped() {
+		let mut renderer = renderer();
+		renderer.resize(size(8, 8)).unwrap();
+		let text = renderer.text("clipped");
+		let off_rects = [
+			Rect::from_xywh(-20, -20, 10, 10),
+			Rect::from_xywh(8, 0, 10, 10),
+			Rect::from_xywh(0, 8, 4, 4),
+		];
+
+		let mut commands = Vec::new();
+		for rect in off_rects {
+			let color = Color::from_hex(OPAQUE);
+			commands.push(DrawCommand::Fill {
+				rect,
+				color,
+				acf: alphacomp::OVER,
+			});
+			commands.push(DrawCommand::Stroke {
+				rect,
+				stroke_width: 2,
+				color,
+				acf: alphacomp::OVER,
+			});
+			commands.push(DrawCommand::Text {
+				text: text.clone(),
+				pos: rect.pos(),
+				acf: alphacomp::OVER,
+			});
+		}
+		renderer.draw(&commands).unwrap();
+
+		assert!(renderer.first_framebuffer().pixels().iter().all(|&px| px == 0));
+	}
+
+	#[test]
+	fn sentence_wraps_at_word_boundaries() {
+		let renderer = renderer();
+		let text = renderer.text("the quick brown fox");
+		let max_width = (renderer.text("the quick").size().w).max(renderer.text("brown fox").size().w);
+
+		assert_eq!(wrapped_lines(&text, max_width), ["the quick", "brown fox"]);
+		assert_eq!(wrapped_lines(&text, text.size().w), ["the quick brown fox"]);
+		assert_eq!(text.wrapped_size(max_width).h, text.lines_height(2));
+	}
+
+	#[test]
+	fn overlong_words_are_hard_broken() {
+		let renderer = renderer();
+		let text = renderer.text("aaaaaaaa bb");
+		let max_width = renderer.text("aaa").size().w;
+
+		assert_eq!(wrapped_lines(&text, max_width), ["aaa", "aaa", "aa", "bb"]);
+		for line in text.wrap(max_width) {
+			assert!(line.width <= max_width);
+		}
+	}
+
+	/// Writes a PNG of a single color to a temporary file, giving its path.
+	fn temp_png(name: &str, w: u32, h: u32, rgba: [u8; 4]) -> std::path::PathBuf {
+		let path = std::env::temp_dir().join(format!("snaek-{name}-{}.png", std::process::id()));
+		image::RgbaImage::from_pixel(w, h, image::Rgba(rgba))
+			.save(&path)
+			.unwrap();
+		path
+	}
+
+	#[test]
+	fn reloading_a_spritesheet_swaps_its_bitmap() {
+		let mut renderer = renderer();
+		let id = renderer.register_spritesheet(Bitmap::from_buffer(vec![0xff00ff00; 4], size(2, 2)).unwrap());
+
+		let path = temp_png("reload", 2, 2, [255, 0, 0, 255]);
+		let reloaded = renderer.reload_spritesheet(id, &path);
+		fs::remove_file(&path).unwrap();
+
+		reloaded.unwrap();
+		assert_eq!(renderer.spritesheets[id.0].pixels(), [0xffff0000; 4]);
+	}
+
+	#[test]
+	fn reloading_a_spritesheet_of_another_size_fails() {
+		let mut renderer = renderer();
+		let id = renderer.register_spritesheet(Bitmap::from_buffer(vec![0xff00ff00; 4], size(2, 2)).unwrap());
+
+		let path = temp_png("reload-resized", 3, 2, [255, 0, 0, 255]);
+		let reloaded = renderer.reload_spritesheet(id, &path);
+		let unknown = renderer.reload_spritesheet(SpritesheetId(id.0 + 1), &path);
+		fs::remove_file(&path).unwrap();
+
+		assert!(matches!(reloaded, Err(ImageError::Parameter(_))), "{reloaded:?}");
+		assert!(matches!(unknown, Err(ImageError::Parameter(_))), "{unknown:?}");
+		assert_eq!(renderer.spritesheets[id.0].pixels(), [0xff00ff00; 4]);
+	}
+
+	#[test]
+	fn reloading_a_missing_file_fails() {
+		let mut renderer = renderer();
+		let id = renderer.register_spritesheet(Bitmap::new(size(2, 2)).unwrap());
+
+		let missing = std::env::temp_dir().join("snaek-reload-missing.png");
+		assert!(matches!(
+			renderer.reload_spritesheet(id, missing),
+			Err(ImageError::IoError(_))
+		));
+	}
+
+	#[test]
+	fn stats_count_commands_and_clipped_pixels() {
+		let mut renderer = renderer();
+		let color = Color::from_hex(0xffff0000);
+		renderer
+			.draw(&[
+				DrawCommand::Clear,
+				DrawCommand::MaskAnd(Color::WHITE),
+				DrawCommand::Fill {
+					rect: Rect::from_xywh(2, 2, 4, 3),
+					color,
+					acf: alphacomp::OVER,
+				},
+				DrawCommand::Stroke {
+					rect: Rect::from_xywh(0, 0, 6, 6),
+					stroke_width: 1,
+					color,
+					acf: alphacomp::OVER,
+				},
+				// only its top-left 2x2 corner is on the framebuffer
+				DrawCommand::Fill {
+					rect: Rect::from_xywh(14, 14, 4, 4),
+					color,
+					acf: alphacomp::OVER,
+				},
+			])
+			.unwrap();
+
+		assert_eq!(
+			renderer.last_frame_stats(),
+			RenderStats {
+				commands: 5,
+				pixels_written: 16 * 16 + 4 * 3 + (6 * 6 - 4 * 4) + 2 * 2,
+				clears: 1,
+			}
+		);
+	}
+
+	#[test]
+	fn stats_skip_commands_that_draw_nothing() {
+		let mut renderer = renderer();
+		let fill = |acf| DrawCommand::Fill {
+			rect: Rect::from_xywh(0, 0, 4, 4),
+			color: Color::WHITE,
+			acf,
+		};
+		renderer.draw(&[fill(alphacomp::DST), fill(alphacomp::OVER)]).unwrap();
+		assert_eq!(renderer.last_frame_stats().commands, 2);
+		assert_eq!(renderer.last_frame_stats().pixels_written, 16);
+
+		renderer.resize(Size::ZERO).unwrap();
+		renderer.draw(&[DrawCommand::Clear]).unwrap();
+		assert_eq!(renderer.last_frame_stats(), RenderStats::default());
+	}
+
+	/// Draws a fill and a sprite with some blend mode over a half-drawn framebuffer, giving its pixels.
+	fn composited(acf: BlendMode) -> Vec<u32> {
+		let mut renderer = renderer();
+		let sprite_sheet = Bitmap::from_buffer((0..16).map(|i| 0x80402010 | i).collect(), size(4, 4)).unwrap();
+		let sheet_id = renderer.register_spritesheet(sprite_sheet);
+
+		renderer
+			.draw(&[
+				DrawCommand::ClearColor(Color::from_hex(0xff203040)),
+				DrawCommand::Fill {
+					rect: Rect::from_xywh(0, 0, 8, 8),
+					color: Color::from_hex(0x80ff0000),
+					acf: alphacomp::OVER,
+				},
+				DrawCommand::Fill {
+					rect: Rect::from_xywh(4, 4, 8, 8),
+					color: Color::from_hex(0xc000ff00),
+					acf,
+				},
+				DrawCommand::Sprite {
+					pos: pos(6, 2),
+					rotate: Rotate::R90,
+					sheet_id,
+					sprite: Sprite::new(Rect::from_xywh(0, 0, 4, 4)),
+					acf,
+				},
+			])
+			.unwrap();
+		renderer.first_framebuffer().pixels().to_vec()
+	}
+
+	#[test]
+	fn dst_leaves_the_framebuffer_unchanged() {
+		let mut renderer = renderer();
+		renderer
+			.draw(&[
+				DrawCommand::ClearColor(Color::from_hex(0xff203040)),
+				DrawCommand::Fill {
+					rect: Rect::from_xywh(0, 0, 8, 8),
+					color: Color::from_hex(0x80ff0000),
+					acf: alphacomp::OVER,
+				},
+			])
+			.unwrap();
+		let before = renderer.first_framebuffer().pixels().to_vec();
+
+		assert_eq!(composited(alphacomp::DST), before);
+	}
+
+	#[test]
+	fn blend_mode_shortcuts_keep_the_output_identical() {
+		assert_eq!(composited(alphacomp::DST), composited(BlendMode::new(alphacomp::dst)));
+		assert_eq!(composited(alphacomp::SRC), composited(BlendMode::new(alphacomp::src)));
+		assert_ne!(composited(alphacomp::SRC), composited(alphacomp::DST));
+	}
+
+	#[test]
+	fn clear_color_fills_the_whole_framebuffer() {
+		let over_a_fill = |color| {
+			drawn(&[
+				DrawCommand::Fill {
+					rect: Rect::from_xywh(2, 2, 8, 8),
+					color: Color::from_hex(0xffff0000),
+					acf: alphacomp::OVER,
+				},
+				DrawCommand::ClearColor(Color::from_hex(color)),
+			])
+		};
+
+		let pixels = over_a_fill(0xff203040);
+		assert_eq!(pixels.len(), 64 * 16);
+		assert!(pixels.iter().all(|&px| px == 0xff203040));
+
+		// the color replaces what was there rather than being blended over it
+		assert!(over_a_fill(0x80203040).iter().all(|&px| px == 0x80203040));
+	}
+
+	#[test]
+	fn clear_uses_the_renderers_clear_color() {
+		assert!(drawn(&[DrawCommand::Clear]).iter().all(|&px| px == 0));
+
+		let mut renderer = renderer();
+		renderer.set_clear_color(Color::from_hex(0xff102030));
+		renderer.draw(&[DrawCommand::Clear]).unwrap();
+		assert!(renderer.first_framebuffer().pixels().iter().all(|&px| px == 0xff102030));
+
+		// a clear to a specific color leaves the renderer's one as it was
+		renderer.draw(&[DrawCommand::ClearColor(Color::WHITE)]).unwrap();
+		renderer.draw(&[DrawCommand::Clear]).unwrap();
+		assert!(renderer.first_framebuffer().pixels().iter().all(|&px| px == 0xff102030));
+	}
+
+	/// Draws a scene twice, with and without scanline-ordered sprites, giving both frames.
+	fn drawn_both_ways(fb_size: Size, scene: impl Fn(SpritesheetId) -> Vec<DrawCommand>) -> [Vec<u32>; 2] {
+		[false, true].map(|scanline| {
+			let mut renderer = renderer();
+			renderer.resize(fb_size).unwrap();
+			renderer.set_scanline_sprites(scanline);
+
+			// every pixel differs, so any misplaced one shows up
+			let sheet = Bitmap::from_buffer(
+				(0..16 * 16).map(|i| 0x80000000 | (i * 0x010203)).collect(),
+				size(16, 16),
+			);
+			let sheet_id = renderer.register_spritesheet(sheet.unwrap());
+			renderer.draw(&scene(sheet_id)).unwrap();
+			renderer.first_framebuffer().pixels().to_vec()
+		})
+	}
+
+	const ROTATIONS: [Rotate; 4] = [Rotate::R0, Rotate::R90, Rotate::R180, Rotate::R270];
+
+	#[test]
+	fn scanline_sprites_draw_a_full_grid_the_same() {
+		// the playfield: 11x11 cells of 8x8, some of them going off the edges
+		let scene = |sheet_id| {
+			let mut commands = vec![DrawCommand::ClearColor(Color::from_hex(0xff203040))];
+			for (i, (x, y)) in (0..11).flat_map(|y| (0..11).map(move |x| (x, y))).enumerate() {
+				commands.push(DrawCommand::Sprite {
+					pos: pos(x * 8 - 3, y * 8 - 3),
+					rotate: ROTATIONS[i % 4],
+					sheet_id,
+					sprite: Sprite::new(Rect::from_xywh((i % 2 * 8) as i16, (i / 2 % 2 * 8) as i16, 8, 8)),
+					acf: alphacomp::OVER,
+				});
+			}
+			commands
+		};
+
+		let [default, scanline] = drawn_both_ways(size(82, 82), scene);
+		assert_eq!(scanline, default);
+	}
+
+	#[test]
+	fn overlapping_scanline_sprites_keep_their_order() {
+		let scene = |sheet_id| {
+			let mut commands = vec![DrawCommand::Clear];
+			for (i, (x, y)) in [(0, 6), (3, 0), (5, 4), (1, 2), (4, 5), (2, 2)].into_iter().enumerate() {
+				commands.push(DrawCommand::Sprite {
+					pos: pos(x, y),
+					rotate: ROTATIONS[i % 4],
+					sheet_id,
+					sprite: Sprite::new(Rect::from_xywh(i as i16, 0, 6, 7)),
+					acf: alphacomp::OVER,
+				});
+			}
+
+			// a fill in the middle of the sprites is drawn over the ones before it, and under the ones after it
+			commands.insert(
+				3,
+				DrawCommand::Fill {
+					rect: Rect::from_xywh(2, 3, 6, 4),
+					color: Color::from_hex(0x80ff0000),
+					acf: alphacomp::OVER,
+				},
+			);
+			commands
+		};
+
+		let [default, scanline] = drawn_both_ways(size(16, 16), scene);
+		assert_eq!(scanline, default);
+	}
+
+	fn text_at(text: Text, pos: Pos) -> DrawCommand {
+		DrawCommand::Text {
+			text,
+			pos,
+			acf: alphacomp::OVER,
+		}
+	}
+
+	#[test]
+	fn glyphs_are_drawn_at_their_own_positions() {
+		let renderer = renderer();
+		// a wave, with a glyph going up past the text's origin
+		let glyphs = vec![
+			('w', pos(0, 2)),
+			('a', pos(6, 0)),
+			('v', pos(12, -1)),
+			('y', pos(24, 3)),
+		];
+		let wavy = Text::from_glyphs(glyphs.clone());
+		assert_eq!(&wavy.text()[..], "wavy");
+
+		let one_by_one: Vec<DrawCommand> = (glyphs.iter())
+			.map(|&(c, offset)| text_at(renderer.text(c.to_string()), pos(10, 4) + offset))
+			.collect();
+		assert_eq!(drawn(&[text_at(wavy, pos(10, 4))]), drawn(&one_by_one));
+	}
+
+	#[test]
+	fn plain_text_is_laid_out_glyph_after_glyph() {
+		let renderer = renderer();
+		let plain = renderer.text("snaek!");
+		assert_eq!(plain.glyph_positions(), None);
+
+		let mut x = 0;
+		let glyphs: Vec<(char, Pos)> = (plain.text().chars())
+			.map(|c| {
+				let glyph = (c, pos(x, 0));
+				x += renderer.text(c.to_string()).size().w as i16 + 1;
+				glyph
+			})
+			.collect();
+		let laid_out = Text::from_glyphs(glyphs);
+
+		assert_eq!(laid_out.size(), plain.size());
+		assert_eq!(
+			drawn(&[text_at(laid_out, pos(3, 3))]),
+			drawn(&[text_at(plain, pos(3, 3))])
+		);
+	}
+
+	#[test]
+	fn glyphs_outside_ascii_become_question_marks() {
+		let renderer = renderer();
+		let text = Text::from_glyphs(vec![('é', pos(0, 0)), ('?', pos(8, 0))]);
+		assert_eq!(&text.text()[..], "??");
+		assert_eq!(text.glyph_positions(), Some(&[pos(0, 0), pos(8, 0)][..]));
+		assert_eq!(text.size().w, 8 + renderer.text("?").size().w);
+	}
+
+	/// A 16x16 renderer that drew a red square on blue.
+	fn squared_renderer() -> Renderer {
+		let mut renderer = renderer();
+		renderer
+			.draw(&[
+				DrawCommand::ClearColor(Color::from_hex(0xff0000ff)),
+				DrawCommand::Fill {
+					rect: Rect::from_xywh(4, 4, 4, 4),
+					color: Color::from_hex(0xffff0000),
+					acf: alphacomp::OVER,
+				},
+			])
+			.unwrap();
+		renderer
+	}
+
+	#[test]
+	fn rendering_into_a_buffer_copies_the_last_frame() {
+		let mut renderer = squared_renderer();
+		let mut out = vec![0; 16 * 16];
+		renderer.render_into(&mut out, size(16, 16)).unwrap();
+
+		let at = |x: usize, y: usize| out[y * 16 + x];
+		assert_eq!(at(0, 0), 0xff0000ff);
+		assert_eq!(at(4, 4), 0xffff0000);
+		assert_eq!(at(7, 7), 0xffff0000);
+		assert_eq!(at(8, 7), 0xff0000ff);
+		assert_eq!(out, renderer.first_framebuffer().pixels());
+	}
+
+	#[test]
+	fn rendering_into_a_buffer_applies_the_screen_effects() {
+		let mut presented = squared_renderer();
+		let mut rendered = squared_renderer();
+		for renderer in [&mut presented, &mut rendered] {
+			renderer.flash(Color::WHITE, 4);
+		}
+
+		let mut out = vec![0; 16 * 16];
+		rendered.render_into(&mut out, size(16, 16)).unwrap();
+		assert_eq!(out, presented.present());
+		assert_ne!(out, rendered.first_framebuffer().pixels());
+	}
+
+	#[test]
+	fn rendering_into_a_buffer_and_presenting_moves_effects_on_once() {
+		let mut presented = squared_renderer();
+		let mut both = squared_renderer();
+		for renderer in [&mut presented, &mut both] {
+			renderer.flash(Color::WHITE, 4);
+			renderer.shake(3., 6, Easing::Linear);
+		}
+
+		let mut out = vec![0; 16 * 16];
+		for frame in 0..8 {
+			both.render_into(&mut out, size(16, 16)).unwrap();
+			let expected = presented.present().to_vec();
+			assert_eq!(both.present(), expected, "frame {frame}");
+			assert_eq!(out, expected, "frame {frame}");
+		}
+	}
+
+	#[test]
+	fn rendering_into_a_mismatched_buffer_fails() {
+		let mut renderer = squared_renderer();
+
+		let mut out = vec![0; 16 * 16];
+		assert_eq!(
+			renderer.render_into(&mut out, size(8, 32)),
+			Err(BitmapError::SizeMismatch {
+				expected: size(16, 16),
+				actual: size(8, 32),
+			})
+		);
+
+		let mut out = vec![0; 16 * 15];
+		assert_eq!(
+			renderer.render_into(&mut out, size(16, 16)),
+			Err(BitmapError::BufferSizeMismatch {
+				expected: 256,
+				actual: 240,
+			})
+		);
+		assert!(out.iter().all(|&px| px == 0));
+	}
+
+	#[test]
+	fn cached_text_is_shared_between_calls() {
+		let renderer = renderer();
+		let first = renderer.text_cached("Snaek");
+		let second = renderer.text_cached("Snaek");
+
+		assert_eq!(first, renderer.text("Snaek"));
+		assert_eq!(second, first);
+		assert!(Arc::ptr_eq(&second.text, &first.text));
+		assert!(Arc::ptr_eq(&second.glyph_widths, &first.glyph_widths));
+		assert_eq!(renderer.text_cache.borrow().len(), 1);
+	}
+
+	#[test]
+	fn other_strings_get_their_own_cached_text() {
+		let renderer = renderer();
+		let score = renderer.text_cached("Score");
+		let time = renderer.text_cached("Time");
+
+		assert_ne!(time, score);
+		assert_eq!(time, renderer.text("Time"));
+		assert!(!Arc::ptr_eq(&time.text, &score.text));
+		assert_eq!(renderer.text_cache.borrow().len(), 2);
+
+		// plain text is never cached
+		renderer.text("Length");
+		assert_eq!(renderer.text_cache.borrow().len(), 2);
+	}
+
+	#[test]
+	fn flashes_tint_the_presented_frame_and_fade_out() {
+		let mut renderer = squared_renderer();
+		let drawn = renderer.first_framebuffer().pixels().to_vec();
+		renderer.flash(Color::WHITE, 4);
+
+		// the blue background goes to white, then fades back to blue
+		for expected in [0xffffffff, 0xffbfbfff, 0xff8080ff, 0xff4040ff, 0xff0000ff, 0xff0000ff] {
+			assert_eq!(renderer.present()[0], expected);
+		}
+		assert_eq!(renderer.first_framebuffer().pixels(), drawn);
+	}
+
+	#[test]
+	fn flashes_keep_the_pixels_alpha() {
+		let mut renderer = squared_renderer();
+		renderer
+			.draw(&[DrawCommand::ClearColor(Color::from_hex(0x40000000))])
+			.unwrap();
+		renderer.flash(Color::from_hex(0xff204060), 2);
+		assert_eq!(renderer.present()[0], 0x40204060);
+		assert_eq!(renderer.present()[0], 0x40102030);
+		assert_eq!(renderer.present()[0], 0x40000000);
+	}
+
+	#[test]
+	fn flashes_of_zero_frames_stop_the_one_playing() {
+		let mut renderer = squared_renderer();
+		renderer.flash(Color::WHITE, 10);
+		renderer.present();
+		renderer.flash(Color::WHITE, 0);
+		let drawn = renderer.first_framebuffer().pixels().to_vec();
+		assert_eq!(renderer.present(), drawn);
+	}
+
+	#[test]
+	fn shakes_stay_within_their_strength_and_die_down() {
+		for easing in [Easing::Linear, Easing::InQuad, Easing::OutCubic] {
+			let mut renderer = squared_renderer();
+			renderer.shake(3., 8, easing);
+
+			let mut moved = 0;
+			for frame in 0..8 {
+				let strength = 3. * (1. - easing.apply(frame as f32 / 8.));
+				let (_, offset) = renderer.next_effects();
+				assert!(
+					offset.x.abs() as f32 <= strength + 0.5 && offset.y.abs() as f32 <= strength + 0.5,
+					"{easing:?} moved by {offset:?} on frame {frame}, with a strength of {strength}"
+				);
+				moved += (offset != Pos::ZERO) as u32;
+			}
+			assert!(moved > 0, "{easing:?} never moved");
+
+			for _ in 0..4 {
+				assert_eq!(renderer.next_effects().1, Pos::ZERO, "{easing:?}");
+			}
+		}
+	}
+
+	#[test]
+	fn shakes_get_weaker_every_frame() {
+		let shake = |frame| Shake {
+			magnitude: 4.,
+			frames: 4,
+			frame,
+			easing: Easing::Linear,
+		};
+		let strengths: Vec<f32> = (0..4).map(|frame| shake(frame).strength()).collect();
+		assert_eq!(strengths, [4., 3., 2., 1.]);
+		assert!(shake(3).fade().is_none());
+	}
+
+	#[test]
+	fn shaken_frames_leave_no_border_uncovered() {
+		let mut renderer = squared_renderer();
+		let drawn = renderer.first_framebuffer().pixels().to_vec();
+		renderer.shake(6., 10, Easing::Linear);
+		for _ in 0..10 {
+			assert!(renderer.present().iter().all(|&px| px != 0));
+		}
+		assert_eq!(renderer.first_framebuffer().pixels(), drawn);
+
+		let mut out = [0; 6];
+		shift_pixels(&mut out, &[1, 2, 3, 4, 5, 6], size(3, 2), pos(1, -1));
+		assert_eq!(out, [4, 4, 5, 4, 4, 5]);
+	}
+
+	/// A 1x1 spritesheet of a single color.
+	fn plain_sheet(color: u32) -> Bitmap {
+		Bitmap::from_buffer(vec![color], size(1, 1)).unwrap()
+	}
+
+	/// Color of the only pixel of a sheet, as drawn at the origin.
+	fn drawn_sheet_color(renderer: &mut Renderer, sheet_id: SpritesheetId) -> u32 {
+		renderer
+			.draw(&[
+				DrawCommand::Clear,
+				DrawCommand::Sprite {
+					pos: Pos::ZERO,
+					rotate: Rotate::R0,
+					sheet_id,
+					sprite: Sprite::new(Rect::from_xywh(0, 0, 1, 1)),
+					acf: alphacomp::OVER,
+				},
+			])
+			.unwrap();
+		renderer.first_framebuffer().get(Pos::ZERO).unwrap()
+	}
+
+	#[test]
+	fn named_spritesheets_can_be_looked_up() {
+		let mut renderer = renderer();
+		let red = renderer.register_spritesheet_named("red", plain_sheet(0xffff0000));
+		let unnamed = renderer.register_spritesheet(plain_sheet(0xff00ff00));
+		let blue = renderer.register_spritesheet_named("blue", plain_sheet(0xff0000ff));
+
+		assert_eq!(renderer.spritesheet_by_name("red"), Some(red));
+		assert_eq!(renderer.spritesheet_by_name("blue"), Some(blue));
+		assert_eq!(renderer.spritesheet_by_name("green"), None);
+		assert_eq!(renderer.spritesheet_ids(), [red, unnamed, blue]);
+		assert_eq!(drawn_sheet_color(&mut renderer, blue), 0xff0000ff);
+	}
+
+	#[test]
+	fn spritesheet_ids_stay_the_same() {
+		let mut renderer = renderer();
+		let old_red = renderer.register_spritesheet_named("red", plain_sheet(0xffff0000));
+		let new_red = renderer.register_spritesheet_named("red", plain_sheet(0xffcc0000));
+		assert_ne!(old_red, new_red);
+		assert_eq!(renderer.spritesheet_by_name("red"), Some(new_red));
+
+		// the old sheet is still there, under the same id
+		for _ in 0..3 {
+			renderer.register_spritesheet(plain_sheet(0xff00ff00));
+		}
+		assert_eq!(&renderer.spritesheet_ids()[..2], [old_red, new_red]);
+		assert_eq!(drawn_sheet_color(&mut renderer, old_red), 0xffff0000);
+		assert_eq!(drawn_sheet_color(&mut renderer, new_red), 0xffcc0000);
+	}
+}
@@ -1,9 +1,64 @@
-use super::color::alphacomp::AlphaCompFn;
+use std::error::Error;
+use std::fmt;
+
+use image::error::{LimitError, LimitErrorKind};
+use image::{ImageError, ImageFormat, ImageResult};
+
+use super::color::alphacomp::BlendMode;
 use super::color::Color;
 use super::{Pos, Rect, Rotate, Size};
 use crate::math;
 use crate::math::pos::pos;
 
+/// Error when creating a bitmap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitmapError {
+	/// The bitmap has too many pixels to be allocated.
+	SizeOverflow(Size),
+	/// The buffer doesn't have as many pixels as the bitmap's size requires.
+	BufferSizeMismatch { expected: usize, actual: usize },
+	/// The bitmap doesn't have the size it's expected to have.
+	SizeMismatch { expected: Size, actual: Size },
+}
+
+impl fmt::Display for BitmapError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::SizeOverflow(size) => write!(f, "bitmap of size {}x{} is too big", size.w, size.h),
+			Self::BufferSizeMismatch { expected, actual } => {
+				write!(f, "bitmap buffer has {actual} pixels instead of {expected}")
+			}
+			Self::SizeMismatch { expected, actual } => write!(
+				f,
+				"bitmap is {}x{} instead of {}x{}",
+				actual.w, actual.h, expected.w, expected.h
+			),
+		}
+	}
+}
+
+impl Error for BitmapError {}
+
+impl From<BitmapError> for ImageError {
+	fn from(e: BitmapError) -> Self {
+		ImageError::Limits(LimitError::from_kind(match e {
+			BitmapError::SizeOverflow(_) => LimitErrorKind::InsufficientMemory,
+			BitmapError::BufferSizeMismatch { .. } | BitmapError::SizeMismatch { .. } => LimitErrorKind::DimensionError,
+		}))
+	}
+}
+
+/// How pixels are sampled when a bitmap is scaled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[allow(unused)]
+pub enum ScaleMode {
+	/// Takes the closest pixel, which keeps the pixel-art look.
+	#[default]
+	Nearest,
+	/// Blends the 4 closest pixels, for smooth results like blurry shadows.
+	Bilinear,
+}
+
 /// RGBA bitmap.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(C)]
@@ -13,16 +68,46 @@ pub struct Bitmap {
 }
 
 impl Bitmap {
-	pub fn from_buffer(buffer: Vec<u32>, size: Size) -> Self {
-		Self { buffer, size }
+	pub fn from_buffer(buffer: Vec<u32>, size: Size) -> Result<Self, BitmapError> {
+		let area = size.area_checked().ok_or(BitmapError::SizeOverflow(size))?;
+		if buffer.len() != area {
+			return Err(BitmapError::BufferSizeMismatch {
+				expected: area,
+				actual: buffer.len(),
+			});
+		}
+
+		Ok(Self { buffer, size })
+	}
+
+	/// Loads a PNG from memory into a raw ARGB8 bitmap.
+	pub fn from_png(png: &[u8]) -> ImageResult<Self> {
+		let img = image::load_from_memory_with_format(png, ImageFormat::Png)?;
+
+		let (Ok(w), Ok(h)) = (u16::try_from(img.width()), u16::try_from(img.height())) else {
+			return Err(ImageError::Limits(LimitError::from_kind(
+				LimitErrorKind::DimensionError,
+			)));
+		};
+
+		let buffer = (img.into_rgba8().pixels())
+			.map(|pixel| {
+				let [r, g, b, a] = pixel.0;
+				u32::from_le_bytes([b, g, r, a])
+			})
+			.collect::<Vec<u32>>();
+
+		Ok(Self::from_buffer(buffer, Size { w, h })?)
 	}
 
 	#[inline]
-	pub fn new(size: Size) -> Self {
-		Self {
-			buffer: vec![0; size.w as usize * size.h as usize],
+	pub fn new(size: Size) -> Result<Self, BitmapError> {
+		let area = size.area_checked().ok_or(BitmapError::SizeOverflow(size))?;
+
+		Ok(Self {
+			buffer: vec![0; area],
 			size,
-		}
+		})
 	}
 
 	#[inline]
@@ -30,6 +115,27 @@ impl Bitmap {
 		&self.buffer
 	}
 
+	/// Gives the pixel at a position, or `None` if it's outside the bitmap.
+	#[inline]
+	pub fn get(&self, pos: Pos) -> Option<u32> {
+		self.checked_index(pos).map(|i| self.buffer[i])
+	}
+
+	/// Gives the pixel at a position (mutable), or `None` if it's outside the bitmap.
+	#[inline]
+	pub fn get_mut(&mut self, pos: Pos) -> Option<&mut u32> {
+		self.checked_index(pos).map(|i| &mut self.buffer[i])
+	}
+
+	/// Sets the pixel at a position. Does nothing if it's outside the bitmap.
+	#[inline]
+	#[allow(unused)]
+	pub fn set(&mut self, pos: Pos, px: u32) {
+		if let Some(this_px) = self.get_mut(pos) {
+			*this_px = px;
+		}
+	}
+
 	fn line_indices(&self, pos: Pos, width: u16) -> (usize, usize) {
 		let start_x = (self.size.w as usize).min(pos.x as usize);
 		let end_x = (self.size.w as usize).min(width as usize + start_x);
@@ -64,9 +170,56 @@ impl Bitmap {
 		self.size
 	}
 
-	pub fn copy_bitmap(&mut self, other: &Bitmap, acf: AlphaCompFn) {
+	/// Bounding box of all the pixels that differ between this bitmap and another one of the same size.
+	///
+	/// Gives `None` if they're identical.
+	#[allow(unused)]
+	pub fn diff_rect(&self, other: &Bitmap) -> Result<Option<Rect>, BitmapError> {
+		if self.size != other.size {
+			return Err(BitmapError::SizeMismatch {
+				expected: self.size,
+				actual: other.size,
+			});
+		}
+
+		let (mut min_x, mut min_y) = (usize::MAX, usize::MAX);
+		let (mut max_x, mut max_y) = (0, 0);
+		// an empty bitmap has no lines, but chunks can't be empty
+		let width = (self.size.w as usize).max(1);
+
+		for (y, (line, other_line)) in self.buffer.chunks(width).zip(other.buffer.chunks(width)).enumerate() {
+			let Some(first) = line.iter().zip(other_line).position(|(px, other_px)| px != other_px) else {
+				continue;
+			};
+			let last = line
+				.iter()
+				.zip(other_line)
+				.rposition(|(px, other_px)| px != other_px)
+				.unwrap_or(first);
+
+			min_x = min_x.min(first);
+			max_x = max_x.max(last);
+			min_y = min_y.min(y);
+			max_y = y;
+		}
+
+		Ok((min_y != usize::MAX).then(|| {
+			Rect::from_xywh(
+				min_x as i16,
+				min_y as i16,
+				(max_x - min_x + 1) as u16,
+				(max_y - min_y + 1) as u16,
+			)
+		}))
+	}
+
+	pub fn copy_bitmap(&mut self, other: &Bitmap, acf: BlendMode) {
+		if acf.is_noop() {
+			return;
+		}
+
 		for (px, other_px) in self.buffer.iter_mut().zip(other.buffer.iter()) {
-			*px = (acf)(Color::from_hex(*other_px), Color::from_hex(*px)).to_u32();
+			*px = acf.apply(Color::from_hex(*other_px), Color::from_hex(*px)).to_u32();
 		}
 	}
 
@@ -77,27 +230,43 @@ impl Bitmap {
 		this_pos: Pos,
 		other_pos: Pos,
 		size: Size,
-		acf: AlphaCompFn,
+		acf: BlendMode,
 		mask_and: Color,
 		mask_or: Color,
 	) {
-		let this_cropped_rect = self.crop_rect(Rect::from_pos_size(this_pos, size));
-		let this_pos = this_cropped_rect.pos();
-
-		if (this_pos.x as i32) >= (self.size.w as i32) || (this_pos.y as i32) >= (self.size.h as i32) {
+		// crop against this bitmap, then shift the other area by however much got cropped
+		let this_rect = Rect::from_pos_size(this_pos, size);
+		let this_cropped_rect = self.crop_rect(this_rect);
+		let other_rect = Rect::from_pos_size(
+			other_pos + (this_cropped_rect.pos() - this_rect.pos()),
+			this_cropped_rect.size(),
+		);
+
+		// crop against the other bitmap, and shift this area back accordingly
+		let other_cropped_rect = other.crop_rect(other_rect);
+		let this_pos = this_cropped_rect.pos() + (other_cropped_rect.pos() - other_rect.pos());
+		let other_pos = other_cropped_rect.pos();
+		let size = other_cropped_rect.size();
+
+		if size.w == 0 || size.h == 0 || acf.is_noop() {
 			return;
 		}
 
-		if (this_pos.x as i32) + (size.w as i32) < 0 || (this_pos.y as i32) + (size.h as i32) < 0 {
-			return;
-		}
+		let ignores_dst = acf.ignores_dst();
 
-		for y in 0..(size.h as i32).min(self.size.h as i32 - this_pos.y as i32) as i16 {
+		for y in 0..size.h as i16 {
 			let this_line = self.line_mut(pos(this_pos.x, this_pos.y + y), size.w);
 			let other_line = other.line(pos(other_pos.x, other_pos.y + y), size.w);
+
+			if ignores_dst && mask_and == Color::WHITE && mask_or == Color::TRANSPARENT {
+				// straight copy
+				this_line.copy_from_slice(other_line);
+				continue;
+			}
+
 			for (this_px, other_px) in this_line.iter_mut().zip(other_line.iter()) {
 				let other_color = (Color::from_hex(*other_px) & mask_and) | mask_or;
-				let c = (acf)(other_color, Color::from_hex(*this_px));
+				let c = acf.apply(other_color, Color::from_hex(*this_px));
 				*this_px = c.to_u32();
 			}
 		}
@@ -110,91 +279,193 @@ impl Bitmap {
 		this_pos: Pos,
 		other_pos: Pos,
 		size: Size,
-		acf: AlphaCompFn,
+		acf: BlendMode,
 		mask_and: Color,
 		mask_or: Color,
 		rotate: Rotate,
 	) {
-		let rot_size = match rotate {
-			Rotate::R0 | Rotate::R180 => math::size::size(size.w, size.h),
-			Rotate::R90 | Rotate::R270 => math::size::size(size.h, size.w),
-		};
+		if acf.is_noop() {
+			return;
+		}
 
-		let this_cropped_rect = self.crop_rect(Rect::from_pos_size(this_pos, rot_size));
-		let this_pos = this_cropped_rect.pos();
+		let rot_size = rotate.rotate_size(size);
+		for ry in 0..rot_size.h {
+			self.copy_and_rotate_bitmap_row(other, this_pos, other_pos, size, acf, mask_and, mask_or, rotate, ry);
+		}
+	}
 
-		if (this_pos.x as i32) >= (self.size.w as i32) || (this_pos.y as i32) >= (self.size.h as i32) {
+	/// Copies a single destination row (relative to `this_pos`) of a rotated area.
+	///
+	/// Drawing every row in `0..rotated height` is equivalent to [`Bitmap::copy_and_rotate_bitmap_area`].
+	#[allow(clippy::too_many_arguments)]
+	pub fn copy_and_rotate_bitmap_row(
+		&mut self,
+		other: &Bitmap,
+		this_pos: Pos,
+		other_pos: Pos,
+		size: Size,
+		acf: BlendMode,
+		mask_and: Color,
+		mask_or: Color,
+		rotate: Rotate,
+		ry: u16,
+	) {
+		let rot_size = rotate.rotate_size(size);
+		let this_y = this_pos.y as i32 + ry as i32;
+		if ry >= rot_size.h || this_y < 0 || this_y >= self.size.h as i32 {
 			return;
 		}
 
-		if (this_pos.x as i32) + (rot_size.w as i32) < 0 || (this_pos.y as i32) + (rot_size.h as i32) < 0 {
+		let ry = ry as i16;
+		for rx in 0..rot_size.w as i16 {
+			// inverse of the rotation, from destination to source coordinates
+			let (x, y) = match rotate {
+				Rotate::R0 => (rx, ry),
+				Rotate::R90 => (ry, size.h as i16 - 1 - rx),
+				Rotate::R180 => (size.w as i16 - 1 - rx, size.h as i16 - 1 - ry),
+				Rotate::R270 => (size.w as i16 - 1 - ry, rx),
+			};
+
+			let dpos = math::pos::pos(x, y);
+			let rpos = math::pos::pos(rx, ry);
+
+			let Some(other_px) = other.get(other_pos + dpos) else {
+				continue;
+			};
+			let other_color = (Color::from_hex(other_px) & mask_and) | mask_or;
+
+			if let Some(this_px) = self.get_mut(this_pos + rpos) {
+				*this_px = acf.apply(other_color, Color::from_hex(*this_px)).to_u32();
+			}
+		}
+	}
+
+	/// Copies an area of another bitmap into an area of this one, scaling it to fit.
+	#[allow(unused)]
+	pub fn blit_scaled(&mut self, other: &Bitmap, this_rect: Rect, other_rect: Rect, acf: BlendMode, mode: ScaleMode) {
+		let other_rect = other.crop_rect(other_rect);
+		let cropped_rect = self.crop_rect(this_rect);
+		if other_rect.w == 0 || other_rect.h == 0 || cropped_rect.w == 0 || cropped_rect.h == 0 {
 			return;
 		}
 
-		for y in 0..(size.h as i32).min(self.size.h as i32 - this_pos.y as i32) as i16 {
-			for x in 0..(size.w as i32).min(self.size.w as i32 - this_pos.x as i32) as i16 {
-				let (rx, ry) = match rotate {
-					Rotate::R0 => (x, y),
-					Rotate::R90 => (size.h as i16 - 1 - y, x),
-					Rotate::R180 => (size.w as i16 - 1 - x, size.h as i16 - 1 - y),
-					Rotate::R270 => (y, size.w as i16 - 1 - x),
-				};
+		let scale_x = other_rect.w as f32 / this_rect.w as f32;
+		let scale_y = other_rect.h as f32 / this_rect.h as f32;
 
-				let dpos = math::pos::pos(x, y);
-				let rpos = math::pos::pos(rx, ry);
+		for y in cropped_rect.y..cropped_rect.y + cropped_rect.h as i16 {
+			for x in cropped_rect.x..cropped_rect.x + cropped_rect.w as i16 {
+				// position of this pixel's center in the other area
+				let u = (x - this_rect.x) as f32 * scale_x + scale_x / 2.;
+				let v = (y - this_rect.y) as f32 * scale_y + scale_y / 2.;
 
-				let other_idx = other.index(other_pos + dpos);
-				let other_px = other.buffer[other_idx];
-				let other_color = (Color::from_hex(other_px) & mask_and) | mask_or;
+				let other_color = match mode {
+					ScaleMode::Nearest => other.sample_clamped(other_rect, u as i16, v as i16),
+					ScaleMode::Bilinear => other.sample_bilinear(other_rect, u - 0.5, v - 0.5),
+				};
 
-				let other_idx = self.index(this_pos + rpos);
-				let this_px = &mut self.buffer[other_idx];
-				*this_px = (acf)(other_color, Color::from_hex(*this_px)).to_u32();
+				let this_px = &mut self.buffer[(y as usize) * self.size.w as usize + x as usize];
+				*this_px = acf.apply(other_color, Color::from_hex(*this_px)).to_u32();
 			}
 		}
 	}
 
-	pub fn fill(&mut self, color: Color, acf: AlphaCompFn) {
+	/// Pixel at a position relative to an area, clamped to that area.
+	fn sample_clamped(&self, area: Rect, x: i16, y: i16) -> Color {
+		let x = area.x + x.clamp(0, area.w as i16 - 1);
+		let y = area.y + y.clamp(0, area.h as i16 - 1);
+		Color::from_hex(self.buffer[self.index(pos(x, y))])
+	}
+
+	/// Blends the 4 pixels around a position relative to an area.
+	///
+	/// Blending is done with premultiplied alpha, so that transparent pixels don't bleed their color into halos.
+	fn sample_bilinear(&self, area: Rect, u: f32, v: f32) -> Color {
+		let (x0, y0) = (u.floor(), v.floor());
+		let (tx, ty) = (u - x0, v - y0);
+		let (x0, y0) = (x0 as i16, y0 as i16);
+
+		let mut premul = [0f32; 4];
+		let samples = [
+			(x0, y0, (1. - tx) * (1. - ty)),
+			(x0 + 1, y0, tx * (1. - ty)),
+			(x0, y0 + 1, (1. - tx) * ty),
+			(x0 + 1, y0 + 1, tx * ty),
+		];
+		for (x, y, weight) in samples {
+			let c = self.sample_clamped(area, x, y);
+			let a = c.a as f32 / 255.;
+			premul[0] += a * weight;
+			premul[1] += c.r as f32 * a * weight;
+			premul[2] += c.g as f32 * a * weight;
+			premul[3] += c.b as f32 * a * weight;
+		}
+
+		let [a, r, g, b] = premul;
+		if a <= 0. {
+			return Color::TRANSPARENT;
+		}
+
+		Color {
+			a: (a * 255.).round() as u8,
+			r: (r / a).round() as u8,
+			g: (g / a).round() as u8,
+			b: (b / a).round() as u8,
+		}
+	}
+
+	pub fn fill(&mut self, color: Color, acf: BlendMode) {
+		if acf.is_noop() {
+			return;
+		}
+
+		if acf.ignores_dst() {
+			self.buffer.fill(acf.apply(color, Color::TRANSPARENT).to_u32());
+			return;
+		}
+
 		for px in &mut self.buffer {
-			*px = (acf)(color, Color::from_hex(*px)).to_u32();
+			*px = acf.apply(color, Color::from_hex(*px)).to_u32();
 		}
 	}
 
-	pub fn fill_area(&mut self, color: Color, rect: Rect, acf: AlphaCompFn) {
+	pub fn fill_area(&mut self, color: Color, rect: Rect, acf: BlendMode) {
 		let rect = self.crop_rect(rect);
-		if rect.w == 0 || rect.h == 0 {
+		if rect.w == 0 || rect.h == 0 || acf.is_noop() {
+			return;
+		}
+
+		if acf.ignores_dst() {
+			let filled = acf.apply(color, Color::TRANSPARENT).to_u32();
+			for y in 0..rect.h as i16 {
+				self.line_mut(pos(rect.x, rect.y + y), rect.w).fill(filled);
+			}
 			return;
 		}
 
 		for y in 0..rect.h as i16 {
 			for px in self.line_mut(pos(rect.x, rect.y + y), rect.w) {
-				*px = (acf)(color, Color::from_hex(*px)).to_u32();
+				*px = acf.apply(color, Color::from_hex(*px)).to_u32();
 			}
 		}
 	}
 
-	fn crop_rect(&self, mut rect: Rect) -> Rect {
-		if rect.x < 0 {
-			rect.w = rect.w.saturating_add_signed(rect.x);
-			rect.x = 0;
-		}
-
-		if rect.y < 0 {
-			rect.h = rect.h.saturating_add_signed(rect.y);
-			rect.y = 0;
-		}
+	/// Crops a rect so that it fits inside the bitmap.
+	fn crop_rect(&self, rect: Rect) -> Rect {
+		let (w, h) = (self.size.w as i32, self.size.h as i32);
 
-		let difference = (rect.x as i32 + rect.w as i32 - self.size.w as i32) as i16;
-		if difference > 0 {
-			rect.w = rect.w.saturating_add_signed(-difference);
-		}
+		let x0 = (rect.x as i32).clamp(0, w);
+		let y0 = (rect.y as i32).clamp(0, h);
+		let x1 = (rect.x as i32 + rect.w as i32).clamp(0, w);
+		let y1 = (rect.y as i32 + rect.h as i32).clamp(0, h);
 
-		let difference = (rect.y as i32 + rect.h as i32 - self.size.h as i32) as i16;
-		if difference > 0 {
-			rect.h = rect.h.saturating_add_signed(-difference);
-		}
+		Rect::from_xywh(x0 as i16, y0 as i16, (x1 - x0).max(0) as u16, (y1 - y0).max(0) as u16)
+	}
 
-		rect
+	/// Converts a position to the index of a pixel on the bitmap, if it's inside of it.
+	fn checked_index(&self, pos: Pos) -> Option<usize> {
+		let inside =
+			pos.x >= 0 && pos.y >= 0 && (pos.x as i32) < (self.size.w as i32) && (pos.y as i32) < (self.size.h as i32);
+		inside.then(|| pos.y as usize * self.size.w as usize + pos.x as usize)
 	}
 
 	/// Converts a position to the index of a pixel on the bitmap.
@@ -206,3 +477,176 @@ impl Bitmap {
 		pos.y as usize * self.size.w as usize + pos.x as usize
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::math::size::size;
+	use crate::render::color::alphacomp;
+
+	#[test]
+	fn pixels_in_bounds_can_be_read_and_written() {
+		let mut bitmap = Bitmap::new(size(3, 2)).unwrap();
+		bitmap.set(pos(2, 1), 0xff00ff00);
+		*bitmap.get_mut(pos(0, 1)).unwrap() = 0xffff0000;
+
+		assert_eq!(bitmap.get(pos(2, 1)), Some(0xff00ff00));
+		assert_eq!(bitmap.get(pos(0, 1)), Some(0xffff0000));
+		assert_eq!(bitmap.get(pos(0, 0)), Some(0));
+		assert_eq!(bitmap.pixels(), [0, 0, 0, 0xffff0000, 0, 0xff00ff00]);
+	}
+
+	#[test]
+	fn pixels_out_of_bounds_are_none() {
+		let mut bitmap = Bitmap::new(size(3, 2)).unwrap();
+		for p in [pos(3, 0), pos(0, 2), pos(-1, 0), pos(0, -1), pos(i16::MIN, i16::MAX)] {
+			assert_eq!(bitmap.get(p), None, "{p:?}");
+			assert_eq!(bitmap.get_mut(p), None, "{p:?}");
+
+			bitmap.set(p, 0xffffffff);
+			assert!(bitmap.pixels().iter().all(|&px| px == 0), "{p:?}");
+		}
+	}
+
+	#[test]
+	fn buffers_need_as_many_pixels_as_the_size() {
+		let bitmap = Bitmap::from_buffer(vec![1; 300 * 300], size(300, 300)).unwrap();
+		assert_eq!(bitmap.pixels().len(), 90_000);
+
+		assert_eq!(
+			Bitmap::from_buffer(vec![0; 4], size(u16::MAX, u16::MAX)),
+			Err(BitmapError::BufferSizeMismatch {
+				expected: 65_535 * 65_535,
+				actual: 4
+			})
+		);
+		// what the area would be if it wrapped around in a u16
+		assert_eq!(
+			Bitmap::from_buffer(vec![0; 90_000 % 65_536], size(300, 300)),
+			Err(BitmapError::BufferSizeMismatch {
+				expected: 90_000,
+				actual: 90_000 % 65_536
+			})
+		);
+	}
+
+	#[test]
+	fn bitmap_errors_become_image_limit_errors() {
+		let errors = [
+			BitmapError::SizeOverflow(size(u16::MAX, u16::MAX)),
+			BitmapError::BufferSizeMismatch { expected: 4, actual: 2 },
+			BitmapError::SizeMismatch {
+				expected: size(2, 2),
+				actual: size(1, 4),
+			},
+		];
+		for e in errors {
+			assert!(matches!(ImageError::from(e), ImageError::Limits(_)), "{e}");
+		}
+	}
+
+	/// A 2x2 bitmap scaled up to 4x4 with the given mode.
+	fn scaled_2x(pixels: [u32; 4], mode: ScaleMode) -> Bitmap {
+		let src = Bitmap::from_buffer(pixels.to_vec(), size(2, 2)).unwrap();
+		let mut dst = Bitmap::new(size(4, 4)).unwrap();
+		dst.blit_scaled(
+			&src,
+			Rect::from_xywh(0, 0, 4, 4),
+			Rect::from_xywh(0, 0, 2, 2),
+			alphacomp::SRC,
+			mode,
+		);
+		dst
+	}
+
+	const QUAD: [u32; 4] = [0xffff0000, 0xff00ff00, 0xff0000ff, 0xffffffff];
+
+	#[test]
+	fn nearest_scaling_duplicates_pixels() {
+		let [a, b, c, d] = QUAD;
+		#[rustfmt::skip]
+		let expected = [
+			a, a, b, b,
+			a, a, b, b,
+			c, c, d, d,
+			c, c, d, d,
+		];
+		assert_eq!(scaled_2x(QUAD, ScaleMode::Nearest).pixels(), expected);
+		assert_eq!(ScaleMode::default(), ScaleMode::Nearest);
+	}
+
+	#[test]
+	fn bilinear_scaling_keeps_the_corners_and_blends_between_them() {
+		let scaled = scaled_2x(QUAD, ScaleMode::Bilinear);
+		let nearest = scaled_2x(QUAD, ScaleMode::Nearest);
+
+		// the outermost pixels only see their own corner, since sampling is clamped to the area
+		for corner in [pos(0, 0), pos(3, 0), pos(0, 3), pos(3, 3)] {
+			assert_eq!(scaled.get(corner), nearest.get(corner), "{corner:?}");
+		}
+
+		// between red and green, a quarter of the way in
+		let between = Color::from_hex(scaled.get(pos(1, 0)).unwrap());
+		assert_eq!((between.a, between.r, between.g, between.b), (255, 191, 64, 0));
+	}
+
+	#[test]
+	fn bilinear_scaling_doesnt_bleed_transparent_colors() {
+		// opaque red next to fully transparent green
+		let scaled = scaled_2x([0xffff0000, 0x0000ff00, 0xffff0000, 0x0000ff00], ScaleMode::Bilinear);
+
+		let edge = Color::from_hex(scaled.get(pos(2, 0)).unwrap());
+		assert_eq!((edge.a, edge.r, edge.g, edge.b), (64, 255, 0, 0));
+		assert_eq!(scaled.get(pos(3, 0)), Some(Color::TRANSPARENT.to_u32()));
+	}
+
+	/// A 6x5 bitmap with a different color on every pixel.
+	fn gradient() -> Bitmap {
+		Bitmap::from_buffer((0..30).map(|i| 0xff000000 | i).collect(), size(6, 5)).unwrap()
+	}
+
+	#[test]
+	fn identical_bitmaps_have_no_diff() {
+		assert_eq!(gradient().diff_rect(&gradient()), Ok(None));
+	}
+
+	#[test]
+	fn a_single_pixel_diffs_to_itself() {
+		for p in [pos(0, 0), pos(5, 4), pos(3, 1), pos(5, 0)] {
+			let mut other = gradient();
+			other.set(p, 0);
+			assert_eq!(
+				gradient().diff_rect(&other),
+				Ok(Some(Rect::from_xywh(p.x, p.y, 1, 1))),
+				"{p:?}"
+			);
+		}
+	}
+
+	#[test]
+	fn scattered_pixels_diff_to_their_bounding_box() {
+		let mut other = gradient();
+		other.set(pos(4, 1), 0);
+		other.set(pos(1, 2), 0);
+		other.set(pos(2, 3), 0);
+		assert_eq!(gradient().diff_rect(&other), Ok(Some(Rect::from_xywh(1, 1, 4, 3))));
+		// it doesn't matter which one is compared against which
+		assert_eq!(other.diff_rect(&gradient()), Ok(Some(Rect::from_xywh(1, 1, 4, 3))));
+
+		other.set(pos(0, 4), 0);
+		other.set(pos(5, 0), 0);
+		assert_eq!(gradient().diff_rect(&other), Ok(Some(Rect::from_xywh(0, 0, 6, 5))));
+	}
+
+	#[test]
+	fn bitmaps_of_different_sizes_cant_be_diffed() {
+		let other = Bitmap::new(size(5, 6)).unwrap();
+		assert_eq!(
+			gradient().diff_rect(&other),
+			Err(BitmapError::SizeMismatch {
+				expected: size(6, 5),
+				actual: size(5, 6),
+			})
+		);
+	}
+}
@@ -0,0 +1,222 @@
+use std::slice;
+
+use super::color::alphacomp::{self, BlendMode};
+use super::color::Color;
+use super::sprite::Sprite;
+use super::{draw, DrawCommand, Renderer, Rotate, SpritesheetId, Text};
+use crate::math::pos::{pos, Pos};
+use crate::math::rect::Rect;
+
+/// Draws straight into the renderer's base framebuffer, for custom HUD elements that don't need widgets.
+///
+/// Everything is drawn right away on top of what's already there, clipped to the viewport.
+/// It stays until the framebuffer gets cleared, usually at the start of the next frame's [`Renderer::draw`].
+#[allow(unused)]
+pub struct Canvas<'a> {
+	renderer: &'a mut Renderer,
+}
+
+#[allow(unused)]
+impl<'a> Canvas<'a> {
+	pub(super) fn new(renderer: &'a mut Renderer) -> Self {
+		Self { renderer }
+	}
+
+	pub fn fill_rect(&mut self, rect: Rect, color: Color, acf: BlendMode) {
+		self.renderer.fb_stack.fb_mut(0).fill_area(color, rect, acf);
+	}
+
+	/// Draws a one pixel wide line between two points, both included.
+	pub fn draw_line(&mut self, from: Pos, to: Pos, color: Color, acf: BlendMode) {
+		let fb = self.renderer.fb_stack.fb_mut(0);
+
+		// Bresenham, in i32 so that long lines don't overflow
+		let (mut x, mut y) = (from.x as i32, from.y as i32);
+		let (dx, dy) = ((to.x as i32 - x).abs(), -(to.y as i32 - y).abs());
+		let (sx, sy) = ((to.x as i32 - x).signum(), (to.y as i32 - y).signum());
+		let mut err = dx + dy;
+
+		loop {
+			if let Some(px) = fb.get_mut(pos(x as i16, y as i16)) {
+				*px = acf.apply(color, Color::from_hex(*px)).to_u32();
+			}
+
+			if x == to.x as i32 && y == to.y as i32 {
+				break;
+			}
+
+			let e2 = 2 * err;
+			if e2 >= dy {
+				err += dy;
+				x += sx;
+			}
+			if e2 <= dx {
+				err += dx;
+				y += sy;
+			}
+		}
+	}
+
+	/// Draws a sprite from a registered spritesheet, unrotated.
+	pub fn blit(&mut self, pos: Pos, sheet_id: SpritesheetId, sprite: Sprite, acf: BlendMode) {
+		self.command(DrawCommand::Sprite {
+			pos,
+			rotate: Rotate::R0,
+			sheet_id,
+			sprite,
+			acf,
+		});
+	}
+
+	/// Draws some text in a color. Spans that have their own color keep it.
+	pub fn text(&mut self, pos: Pos, text: &Text, color: Color) {
+		let commands = [
+			DrawCommand::MaskAnd(color),
+			DrawCommand::Text {
+				text: text.clone(),
+				pos,
+				acf: alphacomp::OVER,
+			},
+		];
+		self.commands(&commands);
+	}
+
+	fn command(&mut self, command: DrawCommand) {
+		self.commands(slice::from_ref(&command));
+	}
+
+	/// Runs draw commands on the base framebuffer, without touching the renderer's frame stats.
+	fn commands(&mut self, commands: &[DrawCommand]) {
+		let renderer = &mut *self.renderer;
+		let drawn = draw(
+			commands,
+			&mut renderer.fb_stack,
+			&renderer.spritesheets,
+			&renderer.ascii_sheet,
+			&renderer.ascii_bitmap,
+			renderer.clear_color,
+			false,
+		);
+
+		// the canvas never begins a composite, so no framebuffer ever needs to be allocated
+		debug_assert!(drawn.is_ok(), "The canvas allocated a framebuffer");
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::math::size::size;
+	use crate::render::bitmap::Bitmap;
+
+	const IMG_ASCII_CHARS: &[u8] = include_bytes!("../../assets/ascii-chars.png");
+	const RED: u32 = 0xffff0000;
+
+	fn renderer() -> Renderer {
+		let framebuffer = Bitmap::new(size(8, 8)).unwrap();
+		Renderer::new(framebuffer, Bitmap::from_png(IMG_ASCII_CHARS).unwrap())
+	}
+
+	/// Positions of every pixel of the framebuffer that was drawn on.
+	fn drawn_pixels(renderer: &mut Renderer) -> Vec<Pos> {
+		let fb = renderer.first_framebuffer();
+		(0..fb.size().h as i16)
+			.flat_map(|y| (0..fb.size().w as i16).map(move |x| pos(x, y)))
+			.filter(|&p| fb.get(p) != Some(0))
+			.collect()
+	}
+
+	#[test]
+	fn filled_rects_are_clipped_to_the_viewport() {
+		let mut renderer = renderer();
+		let mut canvas = renderer.canvas();
+		canvas.fill_rect(Rect::from_xywh(1, 2, 2, 1), Color::from_hex(RED), alphacomp::OVER);
+		assert_eq!(drawn_pixels(&mut renderer), [pos(1, 2), pos(2, 2)]);
+
+		let mut canvas = renderer.canvas();
+		canvas.fill_rect(Rect::from_xywh(-3, 6, 5, 5), Color::from_hex(RED), alphacomp::OVER);
+		assert_eq!(
+			drawn_pixels(&mut renderer),
+			[pos(1, 2), pos(2, 2), pos(0, 6), pos(1, 6), pos(0, 7), pos(1, 7)]
+		);
+	}
+
+	#[test]
+	fn lines_include_both_ends() {
+		let mut renderer = renderer();
+		let mut canvas = renderer.canvas();
+		canvas.draw_line(pos(3, 1), pos(0, 1), Color::from_hex(RED), alphacomp::OVER);
+		assert_eq!(
+			drawn_pixels(&mut renderer),
+			[pos(0, 1), pos(1, 1), pos(2, 1), pos(3, 1)]
+		);
+
+		let mut renderer = self::renderer();
+		let mut canvas = renderer.canvas();
+		canvas.draw_line(pos(1, 1), pos(3, 3), Color::from_hex(RED), alphacomp::OVER);
+		assert_eq!(drawn_pixels(&mut renderer), [pos(1, 1), pos(2, 2), pos(3, 3)]);
+
+		let mut renderer = self::renderer();
+		let mut canvas = renderer.canvas();
+		canvas.draw_line(pos(2, 5), pos(2, 5), Color::from_hex(RED), alphacomp::OVER);
+		assert_eq!(drawn_pixels(&mut renderer), [pos(2, 5)]);
+	}
+
+	#[test]
+	fn lines_are_clipped_to_the_viewport() {
+		let mut renderer = renderer();
+		let mut canvas = renderer.canvas();
+		canvas.draw_line(pos(-5, 4), pos(i16::MAX, 4), Color::from_hex(RED), alphacomp::OVER);
+		assert_eq!(
+			drawn_pixels(&mut renderer),
+			(0..8).map(|x| pos(x, 4)).collect::<Vec<_>>()
+		);
+
+		// a line that's entirely outside draws nothing
+		let mut renderer = self::renderer();
+		let mut canvas = renderer.canvas();
+		canvas.draw_line(pos(-4, -1), pos(12, -9), Color::from_hex(RED), alphacomp::OVER);
+		assert_eq!(drawn_pixels(&mut renderer), []);
+	}
+
+	#[test]
+	fn blits_and_text_draw_like_their_commands() {
+		let sheet = Bitmap::from_buffer(vec![RED; 9], size(3, 3)).unwrap();
+		let sprite = Sprite::new(Rect::from_xywh(0, 0, 3, 3));
+
+		let mut drawn = renderer();
+		let sheet_id = drawn.register_spritesheet(sheet.clone());
+		let text = drawn.text("hi");
+		let mut canvas = drawn.canvas();
+		canvas.blit(pos(6, -1), sheet_id, sprite, alphacomp::OVER);
+		canvas.text(pos(0, 3), &text, Color::from_hex(0xff00ff00));
+
+		let mut commanded = renderer();
+		let sheet_id = commanded.register_spritesheet(sheet);
+		commanded
+			.draw(&[
+				DrawCommand::Sprite {
+					pos: pos(6, -1),
+					rotate: Rotate::R0,
+					sheet_id,
+					sprite,
+					acf: alphacomp::OVER,
+				},
+				DrawCommand::MaskAnd(Color::from_hex(0xff00ff00)),
+				DrawCommand::Text {
+					text,
+					pos: pos(0, 3),
+					acf: alphacomp::OVER,
+				},
+			])
+			.unwrap();
+
+		assert!(drawn.first_framebuffer().pixels().contains(&0xff00ff00));
+		assert_eq!(drawn.first_framebuffer().get(pos(7, 1)), Some(RED));
+		assert_eq!(drawn.first_framebuffer().get(pos(5, 1)), Some(0));
+		assert_eq!(
+			drawn.first_framebuffer().pixels(),
+			commanded.first_framebuffer().pixels()
+		);
+	}
+}
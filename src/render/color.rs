@@ -28,6 +28,30 @@ impl Color {
 		}
 	}
 
+	/// Creates an opaque color from a hue (in degrees), saturation and value (both between 0 and 1).
+	pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Self {
+		let hue = hue.rem_euclid(360.) / 60.;
+		let chroma = value * saturation;
+		let x = chroma * (1. - (hue % 2. - 1.).abs());
+		let m = value - chroma;
+
+		let (r, g, b) = match hue as u8 {
+			0 => (chroma, x, 0.),
+			1 => (x, chroma, 0.),
+			2 => (0., chroma, x),
+			3 => (0., x, chroma),
+			4 => (x, 0., chroma),
+			_ => (chroma, 0., x),
+		};
+
+		Self {
+			a: 0xff,
+			r: ((r + m) * 255.).round() as u8,
+			g: ((g + m) * 255.).round() as u8,
+			b: ((b + m) * 255.).round() as u8,
+		}
+	}
+
 	#[inline]
 	pub const fn to_u32(self) -> u32 {
 		// We're doing math here to not assume big- or little-endian
@@ -185,8 +209,63 @@ pub mod alphacomp {
 
 	use super::Color;
 
-	/// An alpha composition function.
-	pub type AlphaCompFn = fn(Color, Color) -> Color;
+	/// An alpha composition function, along with what it does with B so the renderer can skip work it doesn't need.
+	#[derive(Debug, Clone, Copy)]
+	pub struct BlendMode {
+		f: fn(Color, Color) -> Color,
+		ignores_dst: bool,
+		is_noop: bool,
+	}
+
+	impl BlendMode {
+		/// A blend mode for a custom function, which is assumed to depend on B.
+		#[allow(unused)]
+		pub const fn new(f: fn(Color, Color) -> Color) -> Self {
+			Self {
+				f,
+				ignores_dst: false,
+				is_noop: false,
+			}
+		}
+
+		/// Composites A onto B.
+		#[inline]
+		pub fn apply(self, pixa: Color, pixb: Color) -> Color {
+			(self.f)(pixa, pixb)
+		}
+
+		/// Whether the result doesn't depend on B, so B doesn't need to be read at all.
+		#[inline]
+		pub const fn ignores_dst(self) -> bool {
+			self.ignores_dst
+		}
+
+		/// Whether the result is always B, so drawing with it doesn't need to happen at all.
+		#[inline]
+		pub const fn is_noop(self) -> bool {
+			self.is_noop
+		}
+	}
+
+	/// `A over B`.
+	pub const OVER: BlendMode = BlendMode::new(over);
+	/// `A + B`.
+	pub const ADD: BlendMode = BlendMode::new(add);
+	/// `A xor B`.
+	pub const XOR: BlendMode = BlendMode::new(xor);
+	/// A, replacing B.
+	pub const SRC: BlendMode = BlendMode {
+		f: src,
+		ignores_dst: true,
+		is_noop: false,
+	};
+	/// B, leaving it as it is.
+	#[allow(unused)]
+	pub const DST: BlendMode = BlendMode {
+		f: dst,
+		ignores_dst: false,
+		is_noop: true,
+	};
 
 	/// Computes `A over B`.
 	#[inline]
@@ -212,7 +291,66 @@ pub mod alphacomp {
 
 	/// Computes A.
 	#[inline]
-	pub fn dst(pixa: Color, _pixb: Color) -> Color {
+	pub fn src(pixa: Color, _pixb: Color) -> Color {
 		pixa
 	}
+
+	/// Computes B.
+	#[inline]
+	pub fn dst(_pixa: Color, pixb: Color) -> Color {
+		pixb
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::alphacomp::{self, BlendMode};
+	use super::Color;
+
+	const COLORS: [u32; 5] = [0x00000000, 0xffffffff, 0x80ff0000, 0xff00ff80, 0x4012_3456];
+
+	fn colors() -> impl Iterator<Item = Color> {
+		COLORS.into_iter().map(Color::from_hex)
+	}
+
+	#[test]
+	fn only_dst_is_a_noop() {
+		assert!(alphacomp::DST.is_noop());
+		for mode in [alphacomp::OVER, alphacomp::ADD, alphacomp::XOR, alphacomp::SRC] {
+			assert!(!mode.is_noop(), "{mode:?}");
+		}
+		assert!(!BlendMode::new(alphacomp::dst).is_noop());
+	}
+
+	#[test]
+	fn only_src_ignores_dst() {
+		assert!(alphacomp::SRC.ignores_dst());
+		for mode in [alphacomp::OVER, alphacomp::ADD, alphacomp::XOR, alphacomp::DST] {
+			assert!(!mode.ignores_dst(), "{mode:?}");
+		}
+		assert!(!BlendMode::new(alphacomp::src).ignores_dst());
+	}
+
+	#[test]
+	fn metadata_agrees_with_what_the_modes_compute() {
+		let modes = [
+			alphacomp::OVER,
+			alphacomp::ADD,
+			alphacomp::XOR,
+			alphacomp::SRC,
+			alphacomp::DST,
+		];
+		for mode in modes {
+			for a in colors() {
+				for b in colors() {
+					if mode.is_noop() {
+						assert_eq!(mode.apply(a, b), b, "{mode:?}");
+					}
+					if mode.ignores_dst() {
+						assert_eq!(mode.apply(a, b), mode.apply(a, Color::TRANSPARENT), "{mode:?}");
+					}
+				}
+			}
+		}
+	}
 }
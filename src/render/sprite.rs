@@ -43,6 +43,21 @@ pub enum NineSlicePart {
 	BottomRight,
 }
 
+impl NineSlicePart {
+	/// All parts, in the order of [`NineSlicingSprite::regions`].
+	pub const ALL: [Self; 9] = [
+		Self::TopLeft,
+		Self::TopCenter,
+		Self::TopRight,
+		Self::CenterLeft,
+		Self::Center,
+		Self::CenterRight,
+		Self::BottomLeft,
+		Self::BottomCenter,
+		Self::BottomRight,
+	];
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct NineSlicingSprite {
 	pub sprite: Sprite,
@@ -69,71 +84,227 @@ impl NineSlicingSprite {
 		}
 	}
 
+	/// Where each part gets drawn when the sprite is stretched over some target rect, indexed by [`NineSlicePart`].
+	///
+	/// Corners keep their size, while the edges and center stretch to fill the rest. The regions always tile the target
+	/// exactly: if it's too small for the corners, the left and top ones get priority.
+	///
+	/// Bars past the edge of the sprite, or in the wrong order, just make the parts they'd bound empty.
+	pub fn regions(&self, target: Rect) -> [Rect; 9] {
+		let (vl, vr, ht, hb) = self.bars();
+		let left = vl.min(target.w);
+		let right = (self.sprite.w - vr).min(target.w - left);
+		let center_w = target.w - left - right;
+
+		let top = ht.min(target.h);
+		let bottom = (self.sprite.h - hb).min(target.h - top);
+		let center_h = target.h - top - bottom;
+
+		let columns = [
+			(target.x, left),
+			(target.x + left as i16, center_w),
+			(target.x + (left + center_w) as i16, right),
+		];
+		let rows = [
+			(target.y, top),
+			(target.y + top as i16, center_h),
+			(target.y + (top + center_h) as i16, bottom),
+		];
+
+		std::array::from_fn(|i| {
+			let (x, w) = columns[i % 3];
+			let (y, h) = rows[i / 3];
+			Rect::from_xywh(x, y, w, h)
+		})
+	}
+
+	/// The bars, clamped to the sprite so none of the parts they bound can overflow it.
+	fn bars(&self) -> (u16, u16, u16, u16) {
+		let (w, h) = (self.sprite.w, self.sprite.h);
+		(self.vl.min(w), self.vr.min(w), self.ht.min(h), self.hb.min(h))
+	}
+
+	/// The part of the sprite that gets drawn for a slice. Misplaced bars make it empty rather than overflowing.
 	pub fn slice(&self, part: NineSlicePart) -> Sprite {
+		let (vl, vr, ht, hb) = self.bars();
 		match part {
 			NineSlicePart::TopLeft => {
 				let x = self.sprite.x;
 				let y = self.sprite.y;
-				let w = self.vl;
-				let h = self.ht;
+				let w = vl;
+				let h = ht;
 				Sprite::new(Rect::from_xywh(x, y, w, h))
 			}
 			NineSlicePart::TopCenter => {
-				let x = self.sprite.x + self.vl as i16;
+				let x = self.sprite.x + vl as i16;
 				let y = self.sprite.y;
-				let w = self.vr - self.vl;
-				let h = self.ht;
+				let w = vr.saturating_sub(vl);
+				let h = ht;
 				Sprite::new(Rect::from_xywh(x, y, w, h))
 			}
 			NineSlicePart::TopRight => {
-				let x = self.sprite.x + self.vr as i16;
+				let x = self.sprite.x + vr as i16;
 				let y = self.sprite.y;
-				let w = self.sprite.w - self.vr;
-				let h = self.ht;
+				let w = self.sprite.w - vr;
+				let h = ht;
 				Sprite::new(Rect::from_xywh(x, y, w, h))
 			}
 			NineSlicePart::CenterLeft => {
 				let x = self.sprite.x;
-				let y = self.sprite.y + self.ht as i16;
-				let w = self.vl;
-				let h = self.hb - self.ht;
+				let y = self.sprite.y + ht as i16;
+				let w = vl;
+				let h = hb.saturating_sub(ht);
 				Sprite::new(Rect::from_xywh(x, y, w, h))
 			}
 			NineSlicePart::Center => {
-				let x = self.sprite.x + self.vl as i16;
-				let y = self.sprite.y + self.ht as i16;
-				let w = self.vr - self.vl;
-				let h = self.hb - self.ht;
+				let x = self.sprite.x + vl as i16;
+				let y = self.sprite.y + ht as i16;
+				let w = vr.saturating_sub(vl);
+				let h = hb.saturating_sub(ht);
 				Sprite::new(Rect::from_xywh(x, y, w, h))
 			}
 			NineSlicePart::CenterRight => {
-				let x = self.sprite.x + self.vr as i16;
-				let y = self.sprite.y + self.ht as i16;
-				let w = self.sprite.w - self.vr;
-				let h = self.hb - self.ht;
+				let x = self.sprite.x + vr as i16;
+				let y = self.sprite.y + ht as i16;
+				let w = self.sprite.w - vr;
+				let h = hb.saturating_sub(ht);
 				Sprite::new(Rect::from_xywh(x, y, w, h))
 			}
 			NineSlicePart::BottomLeft => {
 				let x = self.sprite.x;
-				let y = self.sprite.y + self.hb as i16;
-				let w = self.vl;
-				let h = self.sprite.h - self.hb;
+				let y = self.sprite.y + hb as i16;
+				let w = vl;
+				let h = self.sprite.h - hb;
 				Sprite::new(Rect::from_xywh(x, y, w, h))
 			}
 			NineSlicePart::BottomCenter => {
-				let x = self.sprite.x + self.vl as i16;
-				let y = self.sprite.y + self.hb as i16;
-				let w = self.vr - self.vl;
-				let h = self.sprite.h - self.hb;
+				let x = self.sprite.x + vl as i16;
+				let y = self.sprite.y + hb as i16;
+				let w = vr.saturating_sub(vl);
+				let h = self.sprite.h - hb;
 				Sprite::new(Rect::from_xywh(x, y, w, h))
 			}
 			NineSlicePart::BottomRight => {
-				let x = self.sprite.x + self.vr as i16;
-				let y = self.sprite.y + self.hb as i16;
-				let w = self.sprite.w - self.vr;
-				let h = self.sprite.h - self.hb;
+				let x = self.sprite.x + vr as i16;
+				let y = self.sprite.y + hb as i16;
+				let w = self.sprite.w - vr;
+				let h = self.sprite.h - hb;
 				Sprite::new(Rect::from_xywh(x, y, w, h))
 			}
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A 9x9 sprite with 3 pixel borders, like the boxes of the snaeksheet.
+	fn boxed() -> NineSlicingSprite {
+		NineSlicingSprite::new(Rect::from_xywh(10, 20, 9, 9), 3, 6, 3, 6)
+	}
+
+	/// How many of the regions cover each pixel of the target, row by row.
+	fn coverage(regions: &[Rect; 9], target: Rect) -> Vec<Vec<u8>> {
+		let mut coverage = vec![vec![0; target.w as usize]; target.h as usize];
+		for region in regions {
+			for y in region.y..region.y + region.h as i16 {
+				for x in region.x..region.x + region.w as i16 {
+					coverage[(y - target.y) as usize][(x - target.x) as usize] += 1;
+				}
+			}
+		}
+		coverage
+	}
+
+	fn assert_tiles(sprite: &NineSlicingSprite, target: Rect) {
+		let regions = sprite.regions(target);
+		for region in regions {
+			assert!(
+				region.x >= target.x
+					&& region.y >= target.y
+					&& region.x + region.w as i16 <= target.x + target.w as i16
+					&& region.y + region.h as i16 <= target.y + target.h as i16,
+				"{region:?} sticks out of {target:?}"
+			);
+		}
+
+		let coverage = coverage(&regions, target);
+		assert!(
+			coverage.iter().flatten().all(|&n| n == 1),
+			"{regions:?} don't tile {target:?}: {coverage:?}"
+		);
+	}
+
+	#[test]
+	fn regions_tile_the_target_exactly() {
+		for (w, h) in [
+			(9, 9),
+			(6, 6),
+			(7, 13),
+			(40, 12),
+			(100, 3),
+			(2, 50),
+			(1, 1),
+			(0, 0),
+			(0, 8),
+		] {
+			assert_tiles(&boxed(), Rect::from_xywh(-4, 7, w, h));
+		}
+	}
+
+	#[test]
+	fn corners_keep_their_size_and_edges_stretch() {
+		let regions = boxed().regions(Rect::from_xywh(0, 0, 20, 10));
+		let by_part = |part: NineSlicePart| regions[part as usize];
+
+		assert_eq!(by_part(NineSlicePart::TopLeft), Rect::from_xywh(0, 0, 3, 3));
+		assert_eq!(by_part(NineSlicePart::TopCenter), Rect::from_xywh(3, 0, 14, 3));
+		assert_eq!(by_part(NineSlicePart::BottomRight), Rect::from_xywh(17, 7, 3, 3));
+		assert_eq!(by_part(NineSlicePart::Center), Rect::from_xywh(3, 3, 14, 4));
+	}
+
+	#[test]
+	fn a_target_smaller_than_the_corners_favors_the_top_left() {
+		let regions = boxed().regions(Rect::from_xywh(0, 0, 4, 2));
+
+		assert_eq!(regions[NineSlicePart::TopLeft as usize], Rect::from_xywh(0, 0, 3, 2));
+		assert_eq!(regions[NineSlicePart::TopRight as usize], Rect::from_xywh(3, 0, 1, 2));
+		assert_eq!(regions[NineSlicePart::Center as usize].w, 0);
+		assert_eq!(regions[NineSlicePart::BottomLeft as usize].h, 0);
+		assert_tiles(&boxed(), Rect::from_xywh(0, 0, 4, 2));
+	}
+
+	#[test]
+	fn degenerate_bars_still_tile_the_target() {
+		let sprites = [
+			// no borders at all, everything is center
+			NineSlicingSprite::new(Rect::from_xywh(0, 0, 9, 9), 0, 9, 0, 9),
+			// bars past the edge of the sprite
+			NineSlicingSprite::new(Rect::from_xywh(0, 0, 9, 9), 12, 20, 15, 30),
+			// bars in the wrong order
+			NineSlicingSprite::new(Rect::from_xywh(0, 0, 9, 9), 6, 3, 7, 2),
+			// an empty sprite
+			NineSlicingSprite::new(Rect::ZERO, 0, 0, 0, 0),
+		];
+		for sprite in sprites {
+			for (w, h) in [(9, 9), (5, 20), (30, 2), (0, 0)] {
+				assert_tiles(&sprite, Rect::from_xywh(1, 1, w, h));
+			}
+		}
+	}
+
+	#[test]
+	fn misplaced_bars_slice_nothing_out_of_bounds() {
+		let sprite = NineSlicingSprite::new(Rect::from_xywh(10, 20, 9, 9), 6, 3, 12, 2);
+		for part in NineSlicePart::ALL {
+			let slice = sprite.slice(part);
+			let empty = slice.w == 0 || slice.h == 0;
+			let inside =
+				slice.x >= 10 && slice.x + slice.w as i16 <= 19 && slice.y >= 20 && slice.y + slice.h as i16 <= 29;
+			assert!(empty || inside, "{part:?} slices {slice:?} out of the sprite");
+		}
+		assert_eq!(sprite.slice(NineSlicePart::TopCenter).w, 0);
+		assert_eq!(sprite.slice(NineSlicePart::CenterLeft).h, 0);
+	}
+}
@@ -1,18 +1,74 @@
+pub mod replay;
 pub mod snaeksheet;
 
+use std::error::Error;
+use std::fmt;
 use std::time::{Duration, Instant};
 
-use rand::rngs::ThreadRng;
-use rand::Rng;
+pub use replay::{Ghost, Replay, ReplayRecorder};
 pub use snaeksheet::{snaek_sheet, SnaekSheet};
 
 use crate::math::pos::{pos, Pos};
-use crate::math::size::Size;
+use crate::math::size::{size, Size};
+use crate::render::Rotate;
 
-fn rand_pos(rng: &mut ThreadRng, size: Size) -> Pos {
-	let x = rng.gen_range(0..size.w as i16);
-	let y = rng.gen_range(0..size.h as i16);
-	pos(x, y)
+/// A source of random numbers for the game.
+pub trait Rng {
+	fn next_u32(&mut self) -> u32;
+}
+
+/// The game's built-in RNG, a simple xorshift.
+#[derive(Debug, Clone)]
+pub struct XorShift32(u32);
+
+impl XorShift32 {
+	pub fn new(seed: u32) -> Self {
+		// a xorshift stays stuck at 0 forever
+		match seed {
+			0 => Self(0x9e37_79b9),
+			seed => Self(seed),
+		}
+	}
+
+	pub fn from_entropy() -> Self {
+		Self::new(rand::random())
+	}
+}
+
+impl Rng for XorShift32 {
+	fn next_u32(&mut self) -> u32 {
+		let mut x = self.0;
+		x ^= x << 13;
+		x ^= x >> 17;
+		x ^= x << 5;
+		self.0 = x;
+		x
+	}
+}
+
+/// Turns a calendar date `(year, month, day)` into a seed, so everyone playing on the same day gets the same game.
+///
+/// The seed only depends on the date's numbers, never on the machine it runs on.
+pub fn daily_seed(date: (i32, u32, u32)) -> u64 {
+	let (year, month, day) = date;
+
+	// FNV-1a over the little-endian bytes of the date
+	let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+	let bytes = (year.to_le_bytes().into_iter())
+		.chain(month.to_le_bytes())
+		.chain(day.to_le_bytes());
+	for b in bytes {
+		hash ^= b as u64;
+		hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+	}
+
+	hash
+}
+
+fn rand_pos(rng: &mut impl Rng, size: Size) -> Pos {
+	let x = rng.next_u32() % size.w as u32;
+	let y = rng.next_u32() % size.h as u32;
+	pos(x as i16, y as i16)
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -50,327 +106,1744 @@ pub enum Banana {
 	Cyan = 3,
 }
 
-pub struct SnakeGame {
-	thread_rng: ThreadRng,
-	size: Size,
-	playfield: Box<[Slot]>,
-	snake_head: Pos,
-	snake_tail: Pos,
-	bananas_eaten: u32,
-	direction: Direction,
-	ate_banana: bool,
-	is_dead: bool,
+/// Why the game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameOverReason {
+	/// The snake ran into itself.
+	HitSelf,
+	/// The snake ran into another snake, or into its head.
+	HitSnake,
+	/// The snake ran into the edge of the playfield, on an axis that doesn't wrap around.
+	HitWall,
+	/// The snake filled the whole playfield, so there's nowhere left for bananas.
+	Won,
+}
 
-	start: Instant,
-	duration: Duration,
+/// How the snake speeds up as the game goes on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpeedRamp {
+	/// How much the speed multiplier grows for every banana eaten.
+	pub per_banana: f32,
+	/// Highest the speed multiplier can get.
+	pub max_multiplier: f32,
 }
 
-impl SnakeGame {
-	pub fn new(playfield_size: Size) -> Self {
-		let thread_rng = rand::thread_rng();
+/// Which edges of the playfield the snake wraps around, instead of dying when going through them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WrapConfig {
+	/// Going through the left or right edge makes the snake come out of the other one.
+	pub horizontal: bool,
+	/// Going through the top or bottom edge makes the snake come out of the other one.
+	pub vertical: bool,
+}
 
-		let playfield = vec![Slot::default(); playfield_size.w as usize * playfield_size.h as usize].into_boxed_slice();
-		let snake_head = pos((playfield_size.w / 2) as i16, (playfield_size.h / 2) as i16);
-		let snake_tail = snake_head - pos(-1, 0);
+impl Default for WrapConfig {
+	fn default() -> Self {
+		Self {
+			horizontal: true,
+			vertical: true,
+		}
+	}
+}
 
-		let mut game = Self {
-			thread_rng,
-			size: playfield_size,
-			playfield,
-			snake_head,
-			snake_tail,
-			bananas_eaten: 0,
-			direction: Direction::Right,
-			ate_banana: false,
-			is_dead: false,
+/// How the score of a snake is counted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreConfig {
+	/// Points for every banana eaten.
+	pub per_banana: u32,
+	/// Points for every full second the run lasted.
+	pub per_second: u32,
+	/// Points for every slot the snake takes up.
+	pub per_length: u32,
+	/// Longest time between two bananas for the second one to continue the combo, going by the run's duration.
+	pub combo_window: Duration,
+	/// Highest a combo can multiply the points of a banana by.
+	pub max_combo: u32,
+}
 
-			start: Instant::now(),
-			duration: Duration::default(),
-		};
+impl Default for ScoreConfig {
+	/// The score is the number of bananas eaten.
+	fn default() -> Self {
+		Self {
+			per_banana: 1,
+			per_second: 0,
+			per_length: 0,
+			combo_window: Duration::ZERO,
+			max_combo: 1,
+		}
+	}
+}
 
-		game.restart();
-		game
+/// Something that happened during a game update.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(unused)]
+pub enum GameEvent {
+	/// The snake ate a banana at some position.
+	BananaEaten(Banana, Pos),
+	/// The snake turned to a new direction.
+	Turned(Direction),
+	/// The game ended, either because every snake died or because they won.
+	Died(GameOverReason),
+	/// A player's snake died. The game goes on as long as another snake is alive.
+	SnakeDied(usize, GameOverReason),
+	/// The snake grew to a new length.
+	Grew(usize),
+	/// The death animation finished playing after the snake died.
+	DeathAnimationEnded,
+}
+
+/// Error when placing a banana by hand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(unused)]
+pub enum SpawnError {
+	/// The position is outside of the playfield.
+	OutOfBounds(Pos),
+	/// A snake is in the way.
+	Occupied(Pos),
+}
+
+impl fmt::Display for SpawnError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::OutOfBounds(p) => write!(f, "({}, {}) is outside of the playfield", p.x, p.y),
+			Self::Occupied(p) => write!(f, "a snake is in the way at ({}, {})", p.x, p.y),
+		}
 	}
+}
 
-	pub fn change_direction(&mut self, direction: Direction) {
-		let opposite = self.direction.opposite();
+impl Error for SpawnError {}
 
-		if direction == opposite {
-			return;
+/// Something wrong with the state of a game, found by [`SnakeGame::validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntegrityError {
+	/// A snake's head isn't where the snake says it is.
+	MissingHead { player: usize, pos: Pos },
+	/// A snake's body doesn't lead from one slot to the next.
+	Discontinuous { player: usize, pos: Pos },
+	/// A snake's body doesn't end with its tail where the snake says it is.
+	MissingTail { player: usize, pos: Pos },
+	/// A slot is part of a snake more than once, or part of two snakes.
+	Overlap(Pos),
+	/// A slot has part of a snake, but isn't part of any snake's body.
+	StraySnake(Pos),
+	/// A banana is on a slot taken by a snake.
+	BananaOnSnake(Pos),
+}
+
+impl fmt::Display for IntegrityError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::MissingHead { player, pos: p } => {
+				write!(f, "player {player}'s head isn't at ({}, {})", p.x, p.y)
+			}
+			Self::Discontinuous { player, pos: p } => {
+				write!(f, "player {player}'s body breaks off at ({}, {})", p.x, p.y)
+			}
+			Self::MissingTail { player, pos: p } => {
+				write!(
+					f,
+					"player {player}'s body doesn't end with its tail at ({}, {})",
+					p.x, p.y
+				)
+			}
+			Self::Overlap(p) => write!(f, "the snakes overlap at ({}, {})", p.x, p.y),
+			Self::StraySnake(p) => write!(f, "({}, {}) has part of a snake that isn't in any body", p.x, p.y),
+			Self::BananaOnSnake(p) => write!(f, "a banana is under a snake at ({}, {})", p.x, p.y),
 		}
+	}
+}
+
+impl Error for IntegrityError {}
+
+/// How a game restarts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestartKind {
+	/// A whole new run: the score and time start over.
+	Fresh,
+	/// The same run goes on after a death: the snakes are put back at the start, but keep their score and the
+	/// time keeps counting from where it was.
+	#[allow(unused)]
+	Continue,
+}
+
+/// One of the snakes on the playfield.
+#[derive(Debug, Clone)]
+pub struct Snake {
+	head: Pos,
+	tail: Pos,
+	len: usize,
+	bananas_eaten: u32,
+	/// Yellow, red and cyan bananas eaten.
+	bananas_by_color: [u32; 3],
+	/// Points from the bananas eaten, combos included.
+	banana_points: u32,
+	/// Number of bananas eaten in a row quickly enough, the last one included.
+	combo: u32,
+	/// Duration of the run when the snake last ate a banana.
+	last_eaten_at: Option<Duration>,
+	direction: Direction,
+	ate_banana: bool,
+	is_dead: bool,
+	/// Updates left before the snake dies, if it's about to die.
+	death_grace_left: Option<u8>,
+	/// Tick count when the direction last changed, if the snake hasn't moved that way yet.
+	direction_queued_at: Option<u64>,
+	/// Ticks between the last turn being queued and the snake actually turning.
+	last_input_latency: u8,
+}
 
-		self.direction = direction;
+#[allow(unused)]
+impl Snake {
+	pub fn head(&self) -> Pos {
+		self.head
 	}
 
-	pub fn update_duration(&mut self) {
-		if self.is_dead {
-			return;
-		}
+	pub fn tail(&self) -> Pos {
+		self.tail
+	}
 
-		self.duration = self.start.elapsed();
+	/// Number of slots the snake takes up.
+	pub fn length(&self) -> usize {
+		self.len
 	}
 
-	pub fn update(&mut self) {
-		if self.is_dead {
-			return;
-		}
+	pub fn bananas_eaten(&self) -> u32 {
+		self.bananas_eaten
+	}
 
-		self.ate_banana = false;
-		self.playfield[self.slot_index(self.snake_head)].set_direction_next(self.direction);
+	/// Yellow, red and cyan bananas eaten, in that order.
+	pub fn bananas_by_color(&self) -> [u32; 3] {
+		self.bananas_by_color
+	}
 
-		let next_head = self.next_at(self.snake_head);
+	pub fn direction(&self) -> Direction {
+		self.direction
+	}
 
-		let next_slot = self.playfield[self.slot_index(next_head)];
-		if next_slot.banana().is_some() {
-			// banana eating logic
-			self.ate_banana = true;
+	pub fn is_dead(&self) -> bool {
+		self.is_dead
+	}
 
-			// snake collision!
-			// Since the tail stays in place, any snake part will make the snake die.
-			if next_slot.has_snake() {
-				self.is_dead = true;
-				return;
-			}
+	/// Number of bananas eaten in a row quickly enough, the last one included.
+	pub fn combo(&self) -> u32 {
+		self.combo
+	}
+}
 
-			// push head
-			let curr_slot = &mut self.playfield[self.slot_index(self.snake_head)];
-			curr_slot.set_direction_next(self.direction);
-			curr_slot.set_snake_tail();
+/// Where a snake is about to go this update.
+#[derive(Clone, Copy)]
+struct SnakeMove {
+	turned: bool,
+	next_head: Pos,
+	next_slot: Slot,
+}
 
-			self.snake_head = self.wrap_pos(next_head);
-			let next_slot = &mut self.playfield[self.slot_index(next_head)];
-			next_slot.set_direction_prev(self.direction.opposite());
-			next_slot.set_snake_head();
+pub struct SnakeGame<R: Rng = XorShift32> {
+	rng: R,
+	size: Size,
+	playfield: Box<[Slot]>,
+	snakes: Vec<Snake>,
+	/// Direction the snakes face when the game (re)starts.
+	initial_direction: Direction,
+	/// Whether every snake is dead.
+	is_dead: bool,
+	is_won: bool,
+	wrap: WrapConfig,
+	/// Relative chances of spawning a yellow, red or cyan banana.
+	banana_weights: [u32; 3],
+	score_config: ScoreConfig,
+	/// Number of updates a snake can spend about to die before actually dying.
+	death_grace: u8,
+	/// Shortest a snake can get by shrinking, at least 1.
+	min_length: usize,
+	/// Number of updates the death animation lasts.
+	death_animation_ticks: u16,
+	/// Updates since the last snake died, while they're all dead.
+	death_animation_tick: Option<u16>,
+	/// Bananas that couldn't be placed because there was no free slot, placed as soon as there is one.
+	missing_bananas: usize,
+
+	/// When the duration was last updated.
+	last_duration_update: Instant,
+	duration: Duration,
+	/// Base speed of the snakes, in ticks per second.
+	tps: f32,
+	speed_ramp: Option<SpeedRamp>,
+	/// Time accumulated towards the next tick.
+	tick_time: Duration,
+	/// Number of updates spent with a snake alive since the game (re)started.
+	ticks: u64,
+
+	/// Longest time a single duration update can add, so the timer doesn't jump when the process is suspended.
+	max_frame_delta: Duration,
+
+	/// Called whenever a snake eats a banana.
+	on_eat: Vec<Box<dyn FnMut(Banana, Pos)>>,
+	/// Called whenever a snake dies.
+	on_death: Vec<Box<dyn FnMut(GameOverReason)>>,
+}
 
-			// eat banana
-			next_slot.set_banana(None);
-			self.bananas_eaten += 1;
+/// Clones the whole state of the game, RNG included, so that the clone plays out exactly like the original would.
+///
+/// Callbacks can't be cloned, so the clone starts without any.
+impl<R: Rng + Clone> Clone for SnakeGame<R> {
+	fn clone(&self) -> Self {
+		Self {
+			rng: self.rng.clone(),
+			size: self.size,
+			playfield: self.playfield.clone(),
+			snakes: self.snakes.clone(),
+			initial_direction: self.initial_direction,
+			is_dead: self.is_dead,
+			is_won: self.is_won,
+			wrap: self.wrap,
+			banana_weights: self.banana_weights,
+			score_config: self.score_config,
+			death_grace: self.death_grace,
+			min_length: self.min_length,
+			death_animation_ticks: self.death_animation_ticks,
+			death_animation_tick: self.death_animation_tick,
+			missing_bananas: self.missing_bananas,
+
+			last_duration_update: self.last_duration_update,
+			duration: self.duration,
+			tps: self.tps,
+			speed_ramp: self.speed_ramp,
+			tick_time: self.tick_time,
+			ticks: self.ticks,
+
+			max_frame_delta: self.max_frame_delta,
+
+			on_eat: Vec::new(),
+			on_death: Vec::new(),
+		}
+	}
+}
 
-			self.place_banana();
-		} else {
-			// snake be snakin
+/// How fast a [`SnakeGame::bench_run`] went.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(unused)]
+pub struct BenchRun {
+	/// Number of updates played.
+	pub ticks: u32,
+	/// Time it took to play them.
+	pub elapsed: Duration,
+}
 
-			// snake collision!
-			// Here it's fine if it's just the tail, since we're popping it right after.
-			if next_slot.has_snake_head() {
-				self.is_dead = true;
-				return;
-			}
+#[allow(unused)]
+impl BenchRun {
+	/// Updates played per second. A run too short to be timed counts as having taken a nanosecond.
+	pub fn ticks_per_second(&self) -> f64 {
+		self.ticks as f64 / self.elapsed.max(Duration::from_nanos(1)).as_secs_f64()
+	}
+}
+
+impl SnakeGame {
+	/// Size of the playfield of [`SnakeGame::bench_run`].
+	const BENCH_SIZE: Size = size(16, 16);
 
-			// pop tail
-			let next_tail = self.next_at(self.snake_tail);
+	pub fn new(playfield_size: Size) -> Self {
+		Self::with_rng(playfield_size, XorShift32::from_entropy())
+	}
 
-			let curr_slot = &mut self.playfield[self.slot_index(self.snake_tail)];
-			curr_slot.remove_snake();
+	/// Creates a game whose bananas always spawn the same way for the same seed.
+	pub fn with_seed(playfield_size: Size, seed: u64) -> Self {
+		let seed = (seed ^ (seed >> 32)) as u32;
+		Self::with_rng(playfield_size, XorShift32::new(seed))
+	}
 
-			self.snake_tail = self.wrap_pos(next_tail);
-			let next_slot = &mut self.playfield[self.slot_index(next_tail)];
-			next_slot.remove_snake();
-			next_slot.set_snake_tail();
+	/// Plays some updates of a game without rendering anything, and measures how fast the game logic ran.
+	///
+	/// The snake heads for the closest banana, and the game restarts as soon as it's over, so every update does
+	/// the work of a running game. The same seed always plays out the same way.
+	#[allow(unused)]
+	pub fn bench_run(seed: u64, ticks: u32) -> BenchRun {
+		let mut game = Self::with_seed(Self::BENCH_SIZE, seed);
+		let mut events = Vec::new();
+
+		let start = Instant::now();
+		for _ in 0..ticks {
+			if game.is_dead || game.is_won {
+				game.restart(RestartKind::Fresh);
+			}
 
-			// push head
-			let curr_slot = &mut self.playfield[self.slot_index(self.snake_head)];
-			curr_slot.set_direction_next(self.direction);
-			curr_slot.set_snake_tail();
+			if let Some(direction) = game.banana_bearing(game.snake().head) {
+				game.change_direction(0, direction);
+			}
+
+			events.clear();
+			game.update(&mut events);
+		}
 
-			self.snake_head = self.wrap_pos(next_head);
-			let next_slot = &mut self.playfield[self.slot_index(next_head)];
-			next_slot.set_direction_prev(self.direction.opposite());
-			next_slot.set_snake_head();
+		BenchRun {
+			ticks,
+			elapsed: start.elapsed(),
 		}
 	}
+}
 
-	pub fn restart(&mut self) {
-		self.playfield.fill(Slot::default());
+impl<R: Rng> SnakeGame<R> {
+	/// Most snakes a game can have, as far as slots can tell them apart.
+	pub const MAX_PLAYERS: usize = 8;
 
-		self.snake_head = pos(self.size.w as i16 / 2, self.size.h as i16 / 2);
-		self.snake_tail = pos(self.snake_head.x - 1, self.snake_head.y);
+	/// Creates a single-snake game that uses a specific RNG to place bananas.
+	pub fn with_rng(playfield_size: Size, rng: R) -> Self {
+		Self::with_players(playfield_size, 1, rng)
+	}
 
-		let head_slot = &mut self.playfield[self.slot_index(self.snake_head)];
-		head_slot.set_direction_prev(Direction::Left);
-		head_slot.set_direction_next(self.direction);
-		head_slot.set_snake_head();
+	/// Creates a game with several snakes, that uses a specific RNG to place bananas.
+	///
+	/// The snakes start next to each other, all facing the same direction.
+	///
+	/// # Panics
+	///
+	/// Panics if there are no players, more than [`SnakeGame::MAX_PLAYERS`], or more than the playfield has rows
+	/// (or columns) to put them on.
+	#[allow(unused)]
+	pub fn with_players(playfield_size: Size, players: usize, rng: R) -> Self {
+		assert!(
+			(1..=Self::MAX_PLAYERS).contains(&players),
+			"A game needs between 1 and {} players, not {players}",
+			Self::MAX_PLAYERS
+		);
+		assert!(
+			players <= playfield_size.w.min(playfield_size.h) as usize,
+			"{players} snakes don't fit next to each other on a {}x{} playfield",
+			playfield_size.w,
+			playfield_size.h
+		);
 
-		let tail_slot = &mut self.playfield[self.slot_index(self.snake_tail)];
-		tail_slot.set_direction_next(Direction::Right);
-		tail_slot.set_snake_tail();
+		let playfield = vec![Slot::default(); playfield_size.w as usize * playfield_size.h as usize].into_boxed_slice();
+		let snake = Snake {
+			head: Pos::ZERO,
+			tail: Pos::ZERO,
+			len: 2,
+			bananas_eaten: 0,
+			bananas_by_color: [0; 3],
+			banana_points: 0,
+			combo: 0,
+			last_eaten_at: None,
+			direction: Direction::Right,
+			ate_banana: false,
+			is_dead: false,
+			death_grace_left: None,
+			direction_queued_at: None,
+			last_input_latency: 0,
+		};
 
-		self.bananas_eaten = 0;
-		self.direction = Direction::Right;
-		self.is_dead = false;
+		let mut game = Self {
+			rng,
+			size: playfield_size,
+			playfield,
+			snakes: vec![snake; players],
+			initial_direction: Direction::Right,
+			is_dead: false,
+			is_won: false,
+			wrap: WrapConfig::default(),
+			banana_weights: [90, 9, 1],
+			score_config: ScoreConfig::default(),
+			death_grace: 0,
+			min_length: 1,
+			death_animation_ticks: 6,
+			death_animation_tick: None,
+			missing_bananas: 0,
+
+			last_duration_update: Instant::now(),
+			duration: Duration::default(),
+			max_frame_delta: Duration::from_millis(250),
+			tps: 3.,
+			speed_ramp: None,
+			tick_time: Duration::ZERO,
+			ticks: 0,
+
+			on_eat: Vec::new(),
+			on_death: Vec::new(),
+		};
 
-		self.place_banana();
-		self.start = Instant::now();
-		self.duration = Duration::default();
+		game.restart(RestartKind::Fresh);
+		game
 	}
 
-	fn place_banana(&mut self) {
-		loop {
-			let banana_pos = rand_pos(&mut self.thread_rng, self.size);
-			let slot = &mut self.playfield[self.slot_index(banana_pos)];
-			if slot.has_snake() {
-				continue;
-			}
+	/// Turns a player's snake towards a direction, which it takes on the next update.
+	///
+	/// # Panics
+	///
+	/// Panics if there's no such player.
+	pub fn change_direction(&mut self, player: usize, direction: Direction) {
+		// The snake can't go back to the slot it came from.
+		// This is checked against the head rather than the current direction, because the direction can change
+		// several times before the snake actually moves.
+		let came_from = self.slot_at(self.snakes[player].head).direction_prev();
+
+		if direction == came_from {
+			return;
+		}
 
-			let banana = match self.thread_rng.gen_range(0..100) {
-				0 => Banana::Cyan,
-				1..=9 => Banana::Red,
-				_ => Banana::Yellow,
-			};
-			slot.set_banana(Some(banana));
-			break;
+		let ticks = self.ticks;
+		let snake = &mut self.snakes[player];
+		if direction != snake.direction {
+			snake.direction_queued_at = Some(ticks);
 		}
-	}
 
-	pub fn size(&self) -> Size {
-		self.size
+		snake.direction = direction;
 	}
 
-	pub fn slot_at(&self, pos: Pos) -> Slot {
-		self.playfield[self.slot_index(pos)]
+	/// Sets how many updates a snake can spend about to die before actually dying.
+	///
+	/// During that time, the snake stays in place, and turning to a direction where it doesn't die saves it.
+	#[allow(unused)]
+	pub fn set_death_grace(&mut self, frames: u8) {
+		self.death_grace = frames;
 	}
 
-	pub fn snake_head(&self) -> Pos {
-		self.snake_head
+	/// Registers a callback that gets called with the banana and where it was whenever a snake eats one.
+	///
+	/// Callbacks only get copies of what happened, so they can't change the game in the middle of an update.
+	#[allow(unused)]
+	pub fn on_eat(&mut self, callback: Box<dyn FnMut(Banana, Pos)>) {
+		self.on_eat.push(callback);
 	}
 
-	pub fn ate_banana(&self) -> bool {
-		self.ate_banana
+	/// Registers a callback that gets called with the reason whenever a snake dies.
+	#[allow(unused)]
+	pub fn on_death(&mut self, callback: Box<dyn FnMut(GameOverReason)>) {
+		self.on_death.push(callback);
 	}
 
-	pub fn is_dead(&self) -> bool {
-		self.is_dead
+	/// Sets the shortest a snake can get by shrinking, which is 1 by default.
+	///
+	/// A snake always has at least its head, so 0 is raised to 1.
+	#[allow(unused)]
+	pub fn set_min_length(&mut self, min_length: usize) {
+		self.min_length = min_length.max(1);
 	}
 
-	pub fn bananas_eaten(&self) -> u32 {
-		self.bananas_eaten
+	/// Sets how many updates the death animation lasts before the game is over for good.
+	#[allow(unused)]
+	pub fn set_death_animation_ticks(&mut self, ticks: u16) {
+		self.death_animation_ticks = ticks;
 	}
 
-	pub fn direction(&self) -> Direction {
-		self.direction
+	/// Sets the direction the snakes face when the game restarts.
+	///
+	/// # Panics
+	///
+	/// Panics if the playfield is too thin along that direction for a snake's body to fit behind its head,
+	/// or if it can't fit all the snakes next to each other across that direction.
+	#[allow(unused)]
+	pub fn set_initial_direction(&mut self, direction: Direction) {
+		let (axis_len, across_len) = match direction {
+			Direction::Up | Direction::Down => (self.size.h, self.size.w),
+			Direction::Left | Direction::Right => (self.size.w, self.size.h),
+		};
+		assert!(
+			axis_len >= 2,
+			"The snake doesn't fit on the playfield facing {direction:?}"
+		);
+		assert!(
+			self.snakes.len() <= across_len as usize,
+			"{} snakes don't fit next to each other facing {direction:?}",
+			self.snakes.len()
+		);
+
+		self.initial_direction = direction;
 	}
 
-	pub fn duration(&self) -> Duration {
-		self.duration
+	/// Sets the relative chances of spawning each color of banana.
+	///
+	/// For example, weights of `(3, 1, 0)` spawn a yellow banana 3 times out of 4, and never a cyan one.
+	/// If all weights are 0, only yellow bananas spawn.
+	#[allow(unused)]
+	pub fn set_banana_weights(&mut self, yellow: u32, red: u32, cyan: u32) {
+		self.banana_weights = [yellow, red, cyan];
 	}
 
-	#[inline]
-	fn wrap_pos(&self, p: Pos) -> Pos {
-		let w = self.size.w as i16;
-		let h = self.size.h as i16;
+	/// Sets how the score is counted. Points already scored from bananas are kept as they were.
+	#[allow(unused)]
+	pub fn set_score_config(&mut self, score_config: ScoreConfig) {
+		self.score_config = score_config;
+	}
 
-		let x = if p.x < 0 { -(-p.x % w) + w } else { p.x % w };
-		let y = if p.y < 0 { -(-p.y % h) + h } else { p.y % h };
+	/// Sets which edges of the playfield the snakes wrap around.
+	#[allow(unused)]
+	pub fn set_wrap(&mut self, wrap: WrapConfig) {
+		self.wrap = wrap;
+	}
 
-		pos(x, y)
+	/// Sets the base speed of the snakes, in ticks per second.
+	#[allow(unused)]
+	pub fn set_speed(&mut self, tps: f32) {
+		self.tps = tps.max(f32::EPSILON);
 	}
 
-	#[inline]
-	fn slot_index(&self, pos: Pos) -> usize {
-		let pos = self.wrap_pos(pos);
-		pos.y as usize * self.size.w as usize + pos.x as usize
+	/// Sets how the snakes speed up from their base speed as they eat bananas, if at all.
+	#[allow(unused)]
+	pub fn set_speed_ramp(&mut self, speed_ramp: Option<SpeedRamp>) {
+		self.speed_ramp = speed_ramp;
 	}
 
-	#[inline]
-	fn next_at(&self, pos: Pos) -> Pos {
-		pos + self.playfield[self.slot_index(pos)].direction_next().pos_offset()
+	/// Base speed of the snakes, in ticks per second.
+	#[allow(unused)]
+	pub fn speed(&self) -> f32 {
+		self.tps
 	}
-}
 
-/// A slot on the playfield.
-///
-/// # Anatomy of a slot type
-///
-/// ```ignore
-/// xx xx   xx xx
-/// || ||   || ||
-/// || ||   || ++---- direction enum (prev)
-/// || ||   ++------- direction enum (next)
-/// || ||
-/// || ++------------ snake enum
-/// ++--------------- banana enum
-/// ```
-///
-/// Direction enum:
-/// ```ignore
-/// +------------------------+
-/// | up   (00) | right (01) |
-/// | down (10) | left  (11) |
-/// +------------------------+
-/// ```
-///
-/// Snake enum:
-/// ```ignore
-/// +-----------------------+
-/// | none (00) | head (01) |
-/// | tail (10) | body (11) |
-/// +-----------------------+
-/// ```
-///
-/// Banana enum:
-/// ```ignore
-/// +-------------------------+
-/// | none (00) | yellow (01) |
-/// | red  (10) | cyan   (11) |
-/// +-------------------------+
-/// ```
-#[derive(Clone, Copy, PartialEq, Eq, Default)]
-#[repr(transparent)]
-pub struct Slot(u8);
+	/// Current speed of the snakes, in ticks per second, with the speed ramp applied.
+	///
+	/// The ramp goes by the bananas eaten by all snakes together.
+	pub fn current_tps(&self) -> f32 {
+		let bananas_eaten: u32 = self.snakes.iter().map(|snake| snake.bananas_eaten).sum();
+		let multiplier = match self.speed_ramp {
+			Some(ramp) => (1. + ramp.per_banana * bananas_eaten as f32).min(ramp.max_multiplier),
+			None => 1.,
+		};
 
-impl Slot {
-	#[inline]
-	pub fn direction_prev(&self) -> Direction {
-		match self.0 & 0b0000_0011 {
-			0b0000_0000 => Direction::Up,
-			0b0000_0001 => Direction::Right,
-			0b0000_0010 => Direction::Down,
-			0b0000_0011 => Direction::Left,
-			_ => unreachable!(),
-		}
+		self.tps * multiplier
 	}
 
-	#[inline]
-	pub fn set_direction_prev(&mut self, direction: Direction) {
-		self.0 &= 0b1111_1100;
-		self.0 |= match direction {
-			Direction::Up => 0b0000_0000,
-			Direction::Right => 0b0000_0001,
-			Direction::Down => 0b0000_0010,
-			Direction::Left => 0b0000_0011,
-		}
+	/// How far the game is towards its next update, from 0 (it just updated) to 1 (it's about to), going by the time
+	/// fed to [`SnakeGame::advance`].
+	///
+	/// Meant for animations that play out between two updates.
+	pub fn animation_phase(&self) -> f32 {
+		let tick_duration = 1. / self.current_tps();
+		(self.tick_time.as_secs_f32() / tick_duration).clamp(0., 1.)
 	}
 
-	#[inline]
-	pub fn direction_next(&self) -> Direction {
-		match self.0 & 0b0000_1100 {
-			0b0000_0000 => Direction::Up,
-			0b0000_0100 => Direction::Right,
-			0b0000_1000 => Direction::Down,
-			0b0000_1100 => Direction::Left,
-			_ => unreachable!(),
+	/// Advances the game by some time, updating it once per tick that's due at the current speed.
+	///
+	/// Returns how many times the game was updated.
+	pub fn advance(&mut self, dt: Duration, events: &mut Vec<GameEvent>) -> u32 {
+		// same as the timer, a suspended process shouldn't make the snake zoom off
+		self.tick_time += dt.min(self.max_frame_delta);
+
+		let mut ticks = 0;
+		loop {
+			let tick_duration = Duration::from_secs_f32(1. / self.current_tps());
+			if self.tick_time < tick_duration {
+				break;
+			}
+
+			self.tick_time -= tick_duration;
+			self.update(events);
+			ticks += 1;
 		}
+
+		ticks
 	}
 
-	#[inline]
-	pub fn set_direction_next(&mut self, direction: Direction) {
-		self.0 &= 0b1111_0011;
-		self.0 |= match direction {
-			Direction::Up => 0b0000_0000,
-			Direction::Right => 0b0000_0100,
-			Direction::Down => 0b0000_1000,
-			Direction::Left => 0b0000_1100,
+	/// Updates the game `n` times in a row, no matter the speed, to quickly get to a later state when debugging.
+	pub fn step_n(&mut self, n: u32, events: &mut Vec<GameEvent>) {
+		for _ in 0..n {
+			self.update(events);
 		}
 	}
 
-	#[inline]
-	pub fn has_snake_head(&self) -> bool {
-		self.0 & 0b0001_0000 > 0
+	/// Sets the longest time a single call to [`SnakeGame::update_duration`] can add to the game's duration.
+	#[allow(unused)]
+	pub fn set_max_frame_delta(&mut self, max_frame_delta: Duration) {
+		self.max_frame_delta = max_frame_delta;
 	}
 
-	#[inline]
-	pub fn set_snake_head(&mut self) {
-		self.0 |= 0b0001_0000;
+	pub fn update_duration(&mut self) {
+		let now = Instant::now();
+		let delta = now - self.last_duration_update;
+		self.last_duration_update = now;
+
+		self.advance_duration(delta);
+	}
+
+	/// Adds some time to the game's duration, capped to the max frame delta.
+	fn advance_duration(&mut self, delta: Duration) {
+		if self.is_dead || self.is_won {
+			return;
+		}
+
+		self.duration += delta.min(self.max_frame_delta);
+	}
+
+	/// Moves every snake that's alive forward by one slot, pushing anything that happened into `events`.
+	///
+	/// Snakes are resolved together, so the outcome doesn't depend on the order of the players:
+	/// two heads going into the same slot both die, and a tail only gets out of the way if its snake moves on
+	/// without growing.
+	///
+	/// In debug builds, this panics if the update leaves the game in a state that fails [`SnakeGame::validate`].
+	pub fn update(&mut self, events: &mut Vec<GameEvent>) {
+		self.update_snakes(events);
+
+		if cfg!(debug_assertions) {
+			if let Err(e) = self.validate() {
+				panic!("The game got into an invalid state: {e}");
+			}
+		}
+	}
+
+	fn update_snakes(&mut self, events: &mut Vec<GameEvent>) {
+		if let Some(tick) = self.death_animation_tick {
+			if tick < self.death_animation_ticks {
+				self.death_animation_tick = Some(tick + 1);
+				if tick + 1 == self.death_animation_ticks {
+					events.push(GameEvent::DeathAnimationEnded);
+				}
+			}
+		}
+
+		if self.is_dead || self.is_won {
+			return;
+		}
+
+		self.ticks += 1;
+
+		// where every snake that's still alive is going
+		let mut moves = Vec::with_capacity(self.snakes.len());
+		for player in 0..self.snakes.len() {
+			let Snake { head, direction, .. } = self.snakes[player];
+			if self.snakes[player].is_dead {
+				moves.push(None);
+				continue;
+			}
+
+			let head_index = self.slot_index(head);
+			let head_slot = &mut self.playfield[head_index];
+			let turned = head_slot.direction_prev() != direction.opposite();
+			head_slot.set_direction_next(direction);
+			// change_direction never turns a snake back, and body parts rely on it to be drawn
+			assert_ne!(
+				head_slot.direction_next(),
+				head_slot.direction_prev(),
+				"The snake's head is going back where it came from"
+			);
+
+			let next_head = self.next_at(head);
+			moves.push(Some(SnakeMove {
+				turned,
+				next_head,
+				next_slot: self.playfield[self.slot_index(next_head)],
+			}));
+		}
+
+		// snake collision!
+		// A snake that runs into something stays in place, so its tail doesn't get out of the way anymore, which
+		// can make another snake run into it. This goes on until no other snake has to stop.
+		let mut collisions: Vec<Option<GameOverReason>> = vec![None; moves.len()];
+		loop {
+			let mut changed = false;
+			for player in 0..moves.len() {
+				if moves[player].is_none() || collisions[player].is_some() {
+					continue;
+				}
+
+				collisions[player] = self.collision(player, &moves, &collisions);
+				changed |= collisions[player].is_some();
+			}
+
+			if !changed {
+				break;
+			}
+		}
+
+		let mut last_death = None;
+		for (player, collision) in collisions.iter().enumerate() {
+			let Some(mv) = moves[player] else {
+				continue;
+			};
+
+			let snake = &mut self.snakes[player];
+			// the snake doesn't eat what's on the other side of a wall
+			snake.ate_banana = collision.is_none() && mv.next_slot.banana().is_some();
+
+			let Some(reason) = *collision else {
+				snake.death_grace_left = None;
+				continue;
+			};
+
+			match snake.death_grace_left {
+				// don't die just yet, there's still time to turn away
+				None if self.death_grace > 0 => snake.death_grace_left = Some(self.death_grace),
+				Some(left) if left > 1 => snake.death_grace_left = Some(left - 1),
+				_ => {
+					snake.death_grace_left = None;
+					snake.is_dead = true;
+					last_death = Some(reason);
+					events.push(GameEvent::SnakeDied(player, reason));
+					for callback in &mut self.on_death {
+						callback(reason);
+					}
+				}
+			}
+		}
+
+		// snake be snakin
+		// Tails are popped before any head moves in, so a head can follow right behind another snake's tail.
+		let moving = |player: usize| moves[player].filter(|_| collisions[player].is_none());
+		for player in 0..moves.len() {
+			let Some(mv) = moving(player) else {
+				continue;
+			};
+
+			let ticks = self.ticks;
+			let snake = &mut self.snakes[player];
+			if let Some(queued_at) = snake.direction_queued_at.take() {
+				if mv.turned {
+					snake.last_input_latency = (ticks - queued_at).min(u8::MAX as u64) as u8;
+				}
+			}
+
+			if mv.turned {
+				events.push(GameEvent::Turned(snake.direction));
+			}
+
+			if mv.next_slot.banana().is_none() {
+				self.pop_tail(player);
+			}
+		}
+
+		let mut bananas_eaten = 0;
+		for player in 0..moves.len() {
+			let Some(mv) = moving(player) else {
+				continue;
+			};
+
+			// push head
+			// A single-slot snake that doesn't grow leaves nothing behind, as its only slot was popped as its tail.
+			let Snake {
+				head, direction, len, ..
+			} = self.snakes[player];
+			let next_head = self.wrap_pos(mv.next_head);
+			let stays_single = len == 1 && mv.next_slot.banana().is_none();
+			if stays_single {
+				self.snakes[player].tail = next_head;
+			} else {
+				let curr_index = self.slot_index(head);
+				let curr_slot = &mut self.playfield[curr_index];
+				// the head becomes a body part, or the new tail if it's the only slot left
+				if self.snakes[player].tail == head {
+					curr_slot.remove_snake();
+					curr_slot.set_player(player);
+				}
+				curr_slot.set_direction_next(direction);
+				curr_slot.set_snake_tail();
+			}
+
+			self.snakes[player].head = next_head;
+			let next_index = self.slot_index(next_head);
+			let next_slot = &mut self.playfield[next_index];
+			debug_assert!(!next_slot.has_snake(), "The snake's head moved into another snake");
+			next_slot.set_direction_prev(direction.opposite());
+			match stays_single {
+				true => next_slot.set_snake_single(),
+				false => next_slot.set_snake_head(),
+			}
+			next_slot.set_player(player);
+
+			// eat banana
+			// The head only enters one slot per update, so at most one banana is eaten and the snake grows exactly
+			// once. The new banana is placed after the heads moved in, so it can't respawn on a slot just eaten.
+			if let Some(banana) = next_slot.banana() {
+				next_slot.set_banana(None);
+
+				events.push(GameEvent::BananaEaten(banana, next_head));
+				for callback in &mut self.on_eat {
+					callback(banana, next_head);
+				}
+
+				let snake = &mut self.snakes[player];
+				snake.bananas_eaten += 1;
+				snake.bananas_by_color[banana as usize - 1] += 1;
+
+				let combo_goes_on = (snake.last_eaten_at)
+					.is_some_and(|at| self.duration.saturating_sub(at) <= self.score_config.combo_window);
+				snake.combo = match combo_goes_on {
+					true => (snake.combo + 1).min(self.score_config.max_combo.max(1)),
+					false => 1,
+				};
+				snake.last_eaten_at = Some(self.duration);
+				let points = self.score_config.per_banana.saturating_mul(snake.combo);
+				snake.banana_points = snake.banana_points.saturating_add(points);
+				snake.len += 1;
+				events.push(GameEvent::Grew(snake.len));
+				bananas_eaten += 1;
+			}
+		}
+
+		let total_len: usize = self.snakes.iter().map(|snake| snake.len).sum();
+		if bananas_eaten > 0 && total_len == self.playfield.len() {
+			self.is_won = true;
+			events.push(GameEvent::Died(GameOverReason::Won));
+		} else {
+			self.missing_bananas += bananas_eaten;
+		}
+
+		// every snake moved, so there may be room for bananas that didn't fit before
+		while self.missing_bananas > 0 && self.place_banana().is_some() {
+			self.missing_bananas -= 1;
+		}
+
+		if let Some(reason) = last_death.filter(|_| self.snakes.iter().all(|snake| snake.is_dead)) {
+			self.is_dead = true;
+			events.push(GameEvent::Died(reason));
+
+			self.death_animation_tick = Some(0);
+			if self.death_animation_ticks == 0 {
+				events.push(GameEvent::DeathAnimationEnded);
+			}
+		}
+	}
+
+	/// What a snake runs into by making its move, if anything.
+	fn collision(
+		&self,
+		player: usize,
+		moves: &[Option<SnakeMove>],
+		collisions: &[Option<GameOverReason>],
+	) -> Option<GameOverReason> {
+		let mv = moves[player]?;
+		if self.hits_wall(mv.next_head) {
+			return Some(GameOverReason::HitWall);
+		}
+
+		// heads going into the same slot run into each other, whatever else happens to them
+		let target = self.wrap_pos(mv.next_head);
+		let head_on = (moves.iter().enumerate())
+			.any(|(other, other_mv)| other != player && other_mv.is_some_and(|m| self.wrap_pos(m.next_head) == target));
+		if head_on {
+			return Some(GameOverReason::HitSnake);
+		}
+
+		let slot = mv.next_slot;
+		if !slot.has_snake() {
+			return None;
+		}
+
+		// A tail gets out of the way if its snake moves on without growing, since it's popped right after.
+		// When eating a banana, the tail stays in place, so any snake part is deadly.
+		// A single-slot snake is its own tail, so it gets out of the way just the same.
+		let owner = slot.player();
+		let tail_vacates =
+			(moves[owner]).is_some_and(|owner_mv| owner_mv.next_slot.banana().is_none() && collisions[owner].is_none());
+		let is_tail = matches!(slot.snake_role(), Some(SnakeRole::Tail | SnakeRole::SingleCell));
+		if is_tail && tail_vacates {
+			return None;
+		}
+
+		match owner == player {
+			true => Some(GameOverReason::HitSelf),
+			false => Some(GameOverReason::HitSnake),
+		}
+	}
+
+	/// Removes the last slot of a snake, making the one before it the new tail.
+	///
+	/// When that's the head, the snake is left with a single slot. When the snake already only has one, its slot is
+	/// left empty, so its head has to be pushed right after.
+	///
+	/// This doesn't update the snake's length.
+	fn pop_tail(&mut self, player: usize) {
+		let Snake { head, tail, .. } = self.snakes[player];
+
+		let curr_index = self.slot_index(tail);
+		self.playfield[curr_index].remove_snake();
+		if tail == head {
+			return;
+		}
+
+		let next_tail = self.wrap_pos(self.next_at(tail));
+		self.snakes[player].tail = next_tail;
+		let next_index = self.slot_index(next_tail);
+		let next_slot = &mut self.playfield[next_index];
+		debug_assert!(
+			next_slot.has_snake() && next_slot.player() == player,
+			"The snake's tail got cut off from its body"
+		);
+		next_slot.remove_snake();
+		match next_tail == head {
+			true => next_slot.set_snake_single(),
+			false => next_slot.set_snake_tail(),
+		}
+		next_slot.set_player(player);
+	}
+
+	/// Shrinks a player's snake by cutting up to `by` slots off its tail, without going below its minimum length.
+	///
+	/// Returns how many slots were actually cut off, which is 0 when the snake is already as short as it can be.
+	#[allow(unused)]
+	pub fn shrink(&mut self, player: usize, by: usize) -> usize {
+		let by = by.min(self.snakes[player].len.saturating_sub(self.min_length));
+		for _ in 0..by {
+			self.pop_tail(player);
+		}
+
+		self.snakes[player].len -= by;
+		by
+	}
+
+	/// Changes the size of the playfield, and restarts the game on it from scratch.
+	///
+	/// # Panics
+	///
+	/// Panics if the snakes don't fit next to each other on the new playfield.
+	pub fn resize(&mut self, playfield_size: Size) {
+		let players = self.snakes.len();
+		assert!(
+			players <= playfield_size.w.min(playfield_size.h) as usize,
+			"{players} snakes don't fit next to each other on a {}x{} playfield",
+			playfield_size.w,
+			playfield_size.h
+		);
+
+		self.size = playfield_size;
+		self.playfield =
+			vec![Slot::default(); playfield_size.w as usize * playfield_size.h as usize].into_boxed_slice();
+		self.restart(RestartKind::Fresh);
+	}
+
+	pub fn restart(&mut self, kind: RestartKind) {
+		self.playfield.fill(Slot::default());
+
+		// the snakes are spread evenly across the direction they face, with their body right behind their head
+		let direction = self.initial_direction;
+		let players = self.snakes.len();
+		for player in 0..players {
+			let head = match direction {
+				Direction::Left | Direction::Right => pos(
+					self.size.w as i16 / 2,
+					((player + 1) * self.size.h as usize / (players + 1)) as i16,
+				),
+				Direction::Up | Direction::Down => pos(
+					((player + 1) * self.size.w as usize / (players + 1)) as i16,
+					self.size.h as i16 / 2,
+				),
+			};
+			let tail = self.wrap_pos(head.step(direction.opposite()));
+
+			let head_index = self.slot_index(head);
+			let head_slot = &mut self.playfield[head_index];
+			head_slot.set_direction_prev(direction.opposite());
+			head_slot.set_direction_next(direction);
+			head_slot.set_snake_head();
+			head_slot.set_player(player);
+
+			let tail_index = self.slot_index(tail);
+			let tail_slot = &mut self.playfield[tail_index];
+			tail_slot.set_direction_next(direction);
+			tail_slot.set_snake_tail();
+			tail_slot.set_player(player);
+
+			self.snakes[player] = Snake {
+				head,
+				tail,
+				len: 2,
+				bananas_eaten: match kind {
+					RestartKind::Fresh => 0,
+					RestartKind::Continue => self.snakes[player].bananas_eaten,
+				},
+				bananas_by_color: match kind {
+					RestartKind::Fresh => [0; 3],
+					RestartKind::Continue => self.snakes[player].bananas_by_color,
+				},
+				banana_points: match kind {
+					RestartKind::Fresh => 0,
+					RestartKind::Continue => self.snakes[player].banana_points,
+				},
+				combo: 0,
+				last_eaten_at: None,
+				direction,
+				ate_banana: false,
+				is_dead: false,
+				death_grace_left: None,
+				direction_queued_at: None,
+				last_input_latency: 0,
+			};
+		}
+
+		self.is_dead = false;
+		self.is_won = false;
+		self.death_animation_tick = None;
+
+		self.missing_bananas = match self.place_banana() {
+			Some(_) => 0,
+			None => 1,
+		};
+		self.last_duration_update = Instant::now();
+		self.tick_time = Duration::ZERO;
+		if kind == RestartKind::Fresh {
+			self.duration = Duration::default();
+			self.ticks = 0;
+		}
+	}
+
+	/// Puts a banana on a free slot of the playfield, for scripted scenarios that can't rely on the RNG.
+	///
+	/// The playfield keeps as many bananas as it had: if there was one somewhere else, it's moved there.
+	#[allow(unused)]
+	pub fn set_banana(&mut self, banana_pos: Pos, banana: Banana) -> Result<(), SpawnError> {
+		let in_bounds =
+			(0..self.size.w as i16).contains(&banana_pos.x) && (0..self.size.h as i16).contains(&banana_pos.y);
+		if !in_bounds {
+			return Err(SpawnError::OutOfBounds(banana_pos));
+		}
+
+		let index = self.slot_index(banana_pos);
+		if self.playfield[index].has_snake() {
+			return Err(SpawnError::Occupied(banana_pos));
+		}
+
+		if self.playfield[index].banana().is_none() {
+			match self.playfield.iter_mut().find(|slot| slot.banana().is_some()) {
+				Some(old) => old.set_banana(None),
+				// it's one of the bananas that didn't fit
+				None => self.missing_bananas = self.missing_bananas.saturating_sub(1),
+			}
+		}
+
+		self.playfield[index].set_banana(Some(banana));
+		Ok(())
+	}
+
+	/// Puts a random banana on a random free slot, giving where it went, or `None` if there's no free slot.
+	fn place_banana(&mut self) -> Option<Pos> {
+		// there must be a free slot, or this would never end
+		let is_free = |slot: &Slot| !slot.has_snake() && slot.banana().is_none();
+		if !self.playfield.iter().any(is_free) {
+			return None;
+		}
+
+		loop {
+			let banana_pos = rand_pos(&mut self.rng, self.size);
+			let slot = &mut self.playfield[self.slot_index(banana_pos)];
+			if slot.has_snake() || slot.banana().is_some() {
+				continue;
+			}
+
+			let [yellow, red, cyan] = self.banana_weights;
+			let total = yellow.saturating_add(red).saturating_add(cyan);
+
+			let roll = self.rng.next_u32().checked_rem(total).unwrap_or(0);
+			let banana = if roll < yellow || total == 0 {
+				Banana::Yellow
+			} else if roll < yellow.saturating_add(red) {
+				Banana::Red
+			} else {
+				Banana::Cyan
+			};
+			slot.set_banana(Some(banana));
+			return Some(banana_pos);
+		}
+	}
+
+	/// Checks that the playfield agrees with the snakes, to catch bugs that corrupt the game's state.
+	///
+	/// Every snake's body has to go slot by slot from its head to its tail, as long as the snake is, without
+	/// crossing edges that don't wrap. No slot can be part of two bodies, every snake slot has to be part of one,
+	/// and no banana can be under a snake.
+	pub fn validate(&self) -> Result<(), IntegrityError> {
+		let mut visited = vec![false; self.playfield.len()];
+
+		for (player, snake) in self.snakes.iter().enumerate() {
+			let mut pos = snake.head;
+			for i in 0..snake.len {
+				let index = self.slot_index(pos);
+				let slot = self.playfield[index];
+				if visited[index] {
+					return Err(IntegrityError::Overlap(pos));
+				}
+				visited[index] = true;
+
+				let role = slot.snake_role().filter(|_| slot.player() == player);
+				let is_tail = i + 1 == snake.len;
+				match (i, role) {
+					(0, Some(SnakeRole::SingleCell)) if is_tail && pos == snake.tail => {}
+					(0, Some(SnakeRole::Head)) if !is_tail => {}
+					(0, _) => return Err(IntegrityError::MissingHead { player, pos }),
+					(_, Some(SnakeRole::Tail)) if is_tail && pos == snake.tail => {}
+					(_, _) if is_tail => return Err(IntegrityError::MissingTail { player, pos }),
+					(_, Some(SnakeRole::Body)) => {}
+					(_, _) => return Err(IntegrityError::Discontinuous { player, pos }),
+				}
+
+				if is_tail {
+					break;
+				}
+
+				let prev = pos.step(slot.direction_prev());
+				if self.hits_wall(prev) || self.slot_at(prev).direction_next() != slot.direction_prev().opposite() {
+					return Err(IntegrityError::Discontinuous { player, pos });
+				}
+				pos = self.wrap_pos(prev);
+			}
+		}
+
+		for (index, slot) in self.playfield.iter().enumerate() {
+			if !slot.has_snake() {
+				continue;
+			}
+
+			let pos = pos(
+				(index % self.size.w as usize) as i16,
+				(index / self.size.w as usize) as i16,
+			);
+			if !visited[index] {
+				return Err(IntegrityError::StraySnake(pos));
+			}
+			if slot.banana().is_some() {
+				return Err(IntegrityError::BananaOnSnake(pos));
+			}
+		}
+
+		Ok(())
+	}
+
+	/// A fingerprint of the game's state, to cheaply check whether two games are in sync.
+	///
+	/// It covers the playfield (snakes and bananas), directions, what each snake ate, the points it got for it and
+	/// its score, and every setting that changes how the game plays out or is scored. It doesn't cover the RNG, nor
+	/// timing other than through the time bonus of the scores.
+	/// The algorithm (FNV-1a) is stable, so it can be compared across runs and machines.
+	#[allow(unused)]
+	pub fn checksum(&self) -> u64 {
+		const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+		const FNV_PRIME: u64 = 0x100000001b3;
+
+		let mut hash = FNV_OFFSET;
+		let mut write = |bytes: &[u8]| {
+			for &byte in bytes {
+				hash ^= byte as u64;
+				hash = hash.wrapping_mul(FNV_PRIME);
+			}
+		};
+
+		write(&self.size.w.to_le_bytes());
+		write(&self.size.h.to_le_bytes());
+		for slot in self.playfield.iter() {
+			write(&slot.0.to_le_bytes());
+		}
+
+		for (player, snake) in self.snakes.iter().enumerate() {
+			write(&snake.head.x.to_le_bytes());
+			write(&snake.head.y.to_le_bytes());
+			write(&snake.tail.x.to_le_bytes());
+			write(&snake.tail.y.to_le_bytes());
+			write(&(snake.len as u64).to_le_bytes());
+			write(&snake.bananas_eaten.to_le_bytes());
+			for eaten in snake.bananas_by_color {
+				write(&eaten.to_le_bytes());
+			}
+			write(&snake.banana_points.to_le_bytes());
+			write(&self.score_of(player).to_le_bytes());
+			write(&snake.combo.to_le_bytes());
+			write(&[snake.direction as u8, snake.is_dead as u8]);
+		}
+
+		// configuration
+		write(&[self.wrap.horizontal as u8, self.wrap.vertical as u8]);
+		write(&[self.initial_direction as u8]);
+		for weight in self.banana_weights {
+			write(&weight.to_le_bytes());
+		}
+		let ScoreConfig {
+			per_banana,
+			per_second,
+			per_length,
+			combo_window,
+			max_combo,
+		} = self.score_config;
+		for points in [per_banana, per_second, per_length, max_combo] {
+			write(&points.to_le_bytes());
+		}
+		write(&combo_window.as_nanos().to_le_bytes());
+		write(&(self.min_length as u64).to_le_bytes());
+		write(&self.death_animation_ticks.to_le_bytes());
+		write(&self.tps.to_bits().to_le_bytes());
+		match self.speed_ramp {
+			Some(ramp) => {
+				write(&[1]);
+				write(&ramp.per_banana.to_bits().to_le_bytes());
+				write(&ramp.max_multiplier.to_bits().to_le_bytes());
+			}
+			None => write(&[0]),
+		}
+
+		write(&[self.death_grace]);
+		for snake in &self.snakes {
+			let grace_left = snake.death_grace_left;
+			write(&[grace_left.is_some() as u8, grace_left.unwrap_or(0)]);
+		}
+
+		hash
+	}
+
+	#[allow(unused)]
+	pub fn wrap(&self) -> WrapConfig {
+		self.wrap
+	}
+
+	pub fn size(&self) -> Size {
+		self.size
+	}
+
+	/// Positions of all the slots that aren't empty, and what's in them.
+	#[allow(unused)]
+	pub fn occupied_cells(&self) -> impl Iterator<Item = (Pos, CellKind)> + '_ {
+		let w = self.size.w as usize;
+		(self.playfield.iter().enumerate()).filter_map(move |(i, slot)| {
+			let kind = match (slot.snake_role(), slot.banana()) {
+				(Some(role), _) => CellKind::Snake(role),
+				(None, Some(banana)) => CellKind::Banana(banana),
+				(None, None) => return None,
+			};
+
+			Some((pos((i % w) as i16, (i / w) as i16), kind))
+		})
+	}
+
+	/// Describes what every slot of the playfield looks like, independently of how it's drawn.
+	pub fn render_model(&self) -> RenderModel {
+		let mut cells = Vec::new();
+
+		for y in 0..self.size.h as i16 {
+			for x in 0..self.size.w as i16 {
+				let slot_pos = pos(x, y);
+				let slot = self.slot_at(slot_pos);
+
+				if let Some(banana) = slot.banana() {
+					cells.push((slot_pos, CellVisual::Banana(banana)));
+				}
+
+				if let Some(visual) = slot.snake_visual() {
+					// before the first move, heads already face where they're about to go
+					let visual = match visual {
+						CellVisual::SnakeHead(_) if self.ticks == 0 => {
+							CellVisual::snake_head(self.snakes[slot.player()].direction)
+						}
+						CellVisual::SnakeSingle(_) if self.ticks == 0 => {
+							CellVisual::snake_single(self.snakes[slot.player()].direction)
+						}
+						visual => visual,
+					};
+					cells.push((slot_pos, visual));
+				}
+			}
+		}
+
+		RenderModel { size: self.size, cells }
+	}
+
+	pub fn slot_at(&self, pos: Pos) -> Slot {
+		self.playfield[self.slot_index(pos)]
+	}
+
+	/// The slot one step away from a position in some direction.
+	///
+	/// Past an edge that wraps around this is the slot on the other side, and past one that doesn't it's `None`.
+	#[allow(unused)]
+	pub fn slot_in_direction(&self, from: Pos, direction: Direction) -> Option<&Slot> {
+		let to = from.step(direction);
+		match self.hits_wall(to) {
+			true => None,
+			false => Some(&self.playfield[self.slot_index(to)]),
+		}
+	}
+
+	/// All the snakes, indexed by player.
+	#[allow(unused)]
+	pub fn snakes(&self) -> &[Snake] {
+		&self.snakes
+	}
+
+	/// The first player's snake, which is the only one in a single-snake game.
+	fn snake(&self) -> &Snake {
+		&self.snakes[0]
+	}
+
+	/// Number of bananas the first player's snake ate in a row quickly enough, the last one included.
+	///
+	/// It's 0 once it's been too long since the last banana for the next one to continue the combo.
+	#[allow(unused)]
+	pub fn current_combo(&self) -> u32 {
+		let snake = self.snake();
+		match snake.last_eaten_at {
+			Some(at) if self.duration.saturating_sub(at) <= self.score_config.combo_window => snake.combo,
+			_ => 0,
+		}
+	}
+
+	pub fn snake_head(&self) -> Pos {
+		self.snake().head
+	}
+
+	/// Number of slots the snake takes up.
+	#[allow(unused)]
+	pub fn snake_len(&self) -> usize {
+		self.snake().len
+	}
+
+	/// Positions of every slot of the snake, from its head to its tail.
+	///
+	/// Each position is one step away from the one before it, wrapping around the edges of the playfield.
+	#[allow(unused)]
+	pub fn body_segments(&self) -> Vec<Pos> {
+		self.recent_positions(self.snake().len)
+	}
+
+	/// Positions of the last `n` slots the snake's head went through, most recent first.
+	///
+	/// These are the snake's own slots, starting from its head, so there are never more than its length.
+	pub fn recent_positions(&self, n: usize) -> Vec<Pos> {
+		let n = n.min(self.snake().len);
+		let mut positions = Vec::with_capacity(n);
+
+		let mut pos = self.snake().head;
+		for _ in 0..n {
+			positions.push(pos);
+			pos = self.wrap_pos(pos.step(self.slot_at(pos).direction_prev()));
+		}
+
+		positions
+	}
+
+	pub fn ate_banana(&self) -> bool {
+		self.snake().ate_banana
+	}
+
+	/// Whether every snake is dead.
+	#[allow(unused)]
+	pub fn is_dead(&self) -> bool {
+		self.is_dead
+	}
+
+	/// How far along the death animation is, from 0 to 1, if every snake is dead.
+	///
+	/// It stays at 1 once the animation is over, until the game restarts.
+	pub fn death_animation_progress(&self) -> Option<f32> {
+		let tick = self.death_animation_tick?;
+		if self.death_animation_ticks == 0 {
+			return Some(1.);
+		}
+
+		Some(tick as f32 / self.death_animation_ticks as f32)
+	}
+
+	/// Number of ticks the game went through since the run started, to timestamp what happens during it.
+	///
+	/// Only ticks where the snakes are alive count. A fresh restart sets it back to 0, continuing a run doesn't.
+	#[allow(unused)]
+	pub fn tick_count(&self) -> u64 {
+		self.ticks
+	}
+
+	/// How many ticks it took for the snake's last turn to happen after [`SnakeGame::change_direction`] asked for it.
+	///
+	/// A turn asked for between two ticks happens on the next one, which is a latency of 1.
+	/// Ticks spent about to die (see [`SnakeGame::set_death_grace`]) count too, since the snake doesn't move then.
+	#[allow(unused)]
+	pub fn last_input_latency_ticks(&self) -> u8 {
+		self.snake().last_input_latency
+	}
+
+	/// Whether the snakes filled the whole playfield.
+	pub fn is_won(&self) -> bool {
+		self.is_won
+	}
+
+	/// Number of bananas that should be on the playfield but aren't, because there was no free slot for them.
+	///
+	/// They're placed as soon as there's room again.
+	#[allow(unused)]
+	pub fn missing_bananas(&self) -> usize {
+		self.missing_bananas
+	}
+
+	/// Whether the snake is about to die, unless it turns away in time.
+	pub fn is_dying(&self) -> bool {
+		self.snake().death_grace_left.is_some()
+	}
+
+	#[allow(unused)]
+	pub fn bananas_eaten(&self) -> u32 {
+		self.snake().bananas_eaten
+	}
+
+	/// Score of the first player's snake, as counted by the score config.
+	pub fn score(&self) -> u32 {
+		self.score_of(0)
+	}
+
+	/// Score of a player's snake, as counted by the score config.
+	///
+	/// The time bonus is counted from the game's duration, so every snake gets the same one.
+	pub fn score_of(&self, player: usize) -> u32 {
+		let config = &self.score_config;
+		let snake = &self.snakes[player];
+
+		let time_bonus = config.per_second.saturating_mul(self.duration.as_secs() as u32);
+		let length_bonus = config.per_length.saturating_mul(snake.len as u32);
+		snake
+			.banana_points
+			.saturating_add(time_bonus)
+			.saturating_add(length_bonus)
+	}
+
+	/// Yellow, red and cyan bananas the snake ate, in that order.
+	pub fn bananas_by_color(&self) -> [u32; 3] {
+		self.snake().bananas_by_color
+	}
+
+	pub fn direction(&self) -> Direction {
+		self.snake().direction
+	}
+
+	/// Direction the snake's head is drawn facing.
+	///
+	/// It's the way it last moved, except before its first move, where it's the way it's about to go.
+	pub fn head_facing(&self) -> Direction {
+		match self.ticks {
+			0 => self.snake().direction,
+			_ => self.slot_at(self.snake().head).direction_prev().opposite(),
+		}
+	}
+
+	pub fn duration(&self) -> Duration {
+		self.duration
+	}
+
+	/// Direction to go from `from` to get to the closest banana, the short way around through edges that wrap.
+	///
+	/// When the banana is as far horizontally as vertically, the horizontal direction wins.
+	/// Gives `None` if there's no banana, or if `from` is on the closest one.
+	#[allow(unused)]
+	pub fn banana_bearing(&self, from: Pos) -> Option<Direction> {
+		let axis_delta = |from: i16, to: i16, len: u16, wraps: bool| {
+			let delta = to - from;
+			match wraps && delta.abs() > len as i16 / 2 {
+				true => delta - delta.signum() * len as i16,
+				false => delta,
+			}
+		};
+
+		let mut closest: Option<Pos> = None;
+		for y in 0..self.size.h as i16 {
+			for x in 0..self.size.w as i16 {
+				if self.slot_at(pos(x, y)).banana().is_none() {
+					continue;
+				}
+
+				let delta = pos(
+					axis_delta(from.x, x, self.size.w, self.wrap.horizontal),
+					axis_delta(from.y, y, self.size.h, self.wrap.vertical),
+				);
+				let distance = |d: Pos| d.x.abs() + d.y.abs();
+				if closest.is_none_or(|closest| distance(delta) < distance(closest)) {
+					closest = Some(delta);
+				}
+			}
+		}
+
+		let delta = closest?;
+		match delta.x.abs() >= delta.y.abs() {
+			_ if delta == Pos::ZERO => None,
+			true if delta.x > 0 => Some(Direction::Right),
+			true => Some(Direction::Left),
+			false if delta.y > 0 => Some(Direction::Down),
+			false => Some(Direction::Up),
+		}
+	}
+
+	#[inline]
+	fn wrap_pos(&self, p: Pos) -> Pos {
+		let w = self.size.w as i16;
+		let h = self.size.h as i16;
+
+		pos(p.x.rem_euclid(w), p.y.rem_euclid(h))
+	}
+
+	/// Whether a position is past an edge of the playfield that doesn't wrap around.
+	fn hits_wall(&self, p: Pos) -> bool {
+		let out_x = p.x < 0 || p.x >= self.size.w as i16;
+		let out_y = p.y < 0 || p.y >= self.size.h as i16;
+
+		(out_x && !self.wrap.horizontal) || (out_y && !self.wrap.vertical)
+	}
+
+	#[inline]
+	fn slot_index(&self, pos: Pos) -> usize {
+		let pos = self.wrap_pos(pos);
+		pos.y as usize * self.size.w as usize + pos.x as usize
+	}
+
+	#[inline]
+	fn next_at(&self, pos: Pos) -> Pos {
+		pos.step(self.playfield[self.slot_index(pos)].direction_next())
+	}
+}
+
+/// What occupies a slot of the playfield.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(unused)]
+pub enum CellKind {
+	Snake(SnakeRole),
+	Banana(Banana),
+}
+
+/// What the playfield looks like, as a list of visuals to draw on each slot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderModel {
+	/// Size of the playfield, in slots.
+	pub size: Size,
+	/// Visuals of the slots that aren't empty, from top-left to bottom-right.
+	/// A slot can have several visuals, in the order they should be drawn.
+	pub cells: Vec<(Pos, CellVisual)>,
+}
+
+/// How something on a slot looks.
+///
+/// Snake sprites are rotated from their orientation in the spritesheet:
+/// heads, single-slot snakes and straight parts face right, corners go from the right to the bottom, and tails
+/// connect upwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellVisual {
+	SnakeHead(Rotate),
+	/// A snake that's only one slot long, so both its head and its tail.
+	SnakeSingle(Rotate),
+	SnakeStraight(Rotate),
+	SnakeCorner(Rotate),
+	SnakeTail(Rotate),
+	Banana(Banana),
+}
+
+impl CellVisual {
+	/// A snake head facing some direction.
+	pub const fn snake_head(facing: Direction) -> Self {
+		CellVisual::SnakeHead(facing_rotate(facing))
+	}
+
+	/// A single-slot snake facing some direction.
+	pub const fn snake_single(facing: Direction) -> Self {
+		CellVisual::SnakeSingle(facing_rotate(facing))
+	}
+}
+
+/// Rotation of a sprite that faces right in the spritesheet, to face some direction.
+const fn facing_rotate(facing: Direction) -> Rotate {
+	match facing {
+		Direction::Up => Rotate::R270,
+		Direction::Right => Rotate::R0,
+		Direction::Down => Rotate::R90,
+		Direction::Left => Rotate::R180,
+	}
+}
+
+/// Part of the snake that occupies a slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnakeRole {
+	Head,
+	Tail,
+	Body,
+	/// The snake is only one slot long, so this is both its head and its tail.
+	SingleCell,
+}
+
+/// A slot on the playfield.
+///
+/// # Anatomy of a slot type
+///
+/// ```ignore
+/// xxx x   xx xx   xx xx
+/// ||| |   || ||   || ||
+/// ||| |   || ||   || ++---- direction enum (prev)
+/// ||| |   || ||   ++------- direction enum (next)
+/// ||| |   || ||
+/// ||| |   || ++------------ snake enum
+/// ||| |   ++--------------- banana enum
+/// ||| |
+/// ||| +-------------------- single-cell snake flag
+/// +++---------------------- player index
+/// ```
+///
+/// Direction enum:
+/// ```ignore
+/// +------------------------+
+/// | up   (00) | right (01) |
+/// | down (10) | left  (11) |
+/// +------------------------+
+/// ```
+///
+/// Snake enum:
+/// ```ignore
+/// +-----------------------+
+/// | none (00) | head (01) |
+/// | tail (10) | body (11) |
+/// +-----------------------+
+/// ```
+///
+/// The single-cell snake flag is set on top of the body variant when the snake is only one slot long,
+/// meaning its head is also its tail.
+///
+/// Banana enum:
+/// ```ignore
+/// +-------------------------+
+/// | none (00) | yellow (01) |
+/// | red  (10) | cyan   (11) |
+/// +-------------------------+
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+#[repr(transparent)]
+pub struct Slot(u16);
+
+impl Slot {
+	#[inline]
+	pub fn direction_prev(&self) -> Direction {
+		match self.0 & 0b0000_0011 {
+			0b0000_0000 => Direction::Up,
+			0b0000_0001 => Direction::Right,
+			0b0000_0010 => Direction::Down,
+			0b0000_0011 => Direction::Left,
+			_ => unreachable!(),
+		}
+	}
+
+	#[inline]
+	pub fn set_direction_prev(&mut self, direction: Direction) {
+		self.0 &= !0b0000_0011;
+		self.0 |= match direction {
+			Direction::Up => 0b0000_0000,
+			Direction::Right => 0b0000_0001,
+			Direction::Down => 0b0000_0010,
+			Direction::Left => 0b0000_0011,
+		}
+	}
+
+	#[inline]
+	pub fn direction_next(&self) -> Direction {
+		match self.0 & 0b0000_1100 {
+			0b0000_0000 => Direction::Up,
+			0b0000_0100 => Direction::Right,
+			0b0000_1000 => Direction::Down,
+			0b0000_1100 => Direction::Left,
+			_ => unreachable!(),
+		}
+	}
+
+	#[inline]
+	pub fn set_direction_next(&mut self, direction: Direction) {
+		self.0 &= !0b0000_1100;
+		self.0 |= match direction {
+			Direction::Up => 0b0000_0000,
+			Direction::Right => 0b0000_0100,
+			Direction::Down => 0b0000_1000,
+			Direction::Left => 0b0000_1100,
+		}
+	}
+
+	/// Whether the head bit is set, which is the case for both the head and body parts (but not the tail).
+	#[inline]
+	pub fn has_snake_head(&self) -> bool {
+		self.0 & 0b0001_0000 > 0
+	}
+
+	#[inline]
+	pub fn set_snake_head(&mut self) {
+		self.0 |= 0b0001_0000;
 	}
 
 	#[inline]
@@ -378,39 +1851,2049 @@ impl Slot {
 		self.0 & 0b0010_0000 > 0
 	}
 
-	#[inline]
-	pub fn set_snake_tail(&mut self) {
-		self.0 |= 0b0010_0000;
+	#[inline]
+	pub fn set_snake_tail(&mut self) {
+		self.0 |= 0b0010_0000;
+	}
+
+	#[inline]
+	pub fn has_snake(&self) -> bool {
+		self.0 & 0b0011_0000 > 0
+	}
+
+	/// Marks the slot as the only slot of the snake, which is both its head and its tail.
+	#[inline]
+	pub fn set_snake_single(&mut self) {
+		self.0 |= 0b0001_0011_0000;
+	}
+
+	/// What the snake part in this slot looks like, if there is one.
+	///
+	/// It only depends on the directions stored in the slot, never on where its neighbors are, so a part next to
+	/// a wrapping edge looks the same as anywhere else even though the slot it connects to is on the other side.
+	pub fn snake_visual(&self) -> Option<CellVisual> {
+		use Direction as D;
+
+		let visual = match self.snake_role()? {
+			SnakeRole::Body if self.direction_next() == self.direction_prev().opposite() => {
+				CellVisual::SnakeStraight(facing_rotate(self.direction_next()))
+			}
+			SnakeRole::Body => {
+				let rotate = match (self.direction_next(), self.direction_prev()) {
+					(D::Up, D::Right) | (D::Right, D::Up) => Rotate::R270,
+					(D::Right, D::Down) | (D::Down, D::Right) => Rotate::R0,
+					(D::Down, D::Left) | (D::Left, D::Down) => Rotate::R90,
+					(D::Left, D::Up) | (D::Up, D::Left) => Rotate::R180,
+					// update makes sure a snake never goes back where it came from
+					(next, _) => unreachable!("Body part going back where it came from, to the {next:?}"),
+				};
+				CellVisual::SnakeCorner(rotate)
+			}
+			SnakeRole::Head => CellVisual::snake_head(self.direction_prev().opposite()),
+			SnakeRole::Tail => {
+				let rotate = match self.direction_next() {
+					D::Up => Rotate::R0,
+					D::Right => Rotate::R90,
+					D::Down => Rotate::R180,
+					D::Left => Rotate::R270,
+				};
+				CellVisual::SnakeTail(rotate)
+			}
+			// like a head, it faces away from where it came from, as its next direction is only set when it moves
+			SnakeRole::SingleCell => CellVisual::snake_single(self.direction_prev().opposite()),
+		};
+
+		Some(visual)
+	}
+
+	/// Which part of the snake is in this slot, if any.
+	#[inline]
+	pub fn snake_role(&self) -> Option<SnakeRole> {
+		if self.0 & 0b0001_0000_0000 > 0 {
+			return Some(SnakeRole::SingleCell);
+		}
+
+		match (self.has_snake_head(), self.has_snake_tail()) {
+			(true, true) => Some(SnakeRole::Body),
+			(true, false) => Some(SnakeRole::Head),
+			(false, true) => Some(SnakeRole::Tail),
+			(false, false) => None,
+		}
+	}
+
+	pub fn remove_snake(&mut self) {
+		self.0 &= !0b1111_0011_0000;
+	}
+
+	/// Which player's snake is in this slot. Only meaningful when there is a snake in it.
+	#[inline]
+	pub fn player(&self) -> usize {
+		((self.0 & 0b1110_0000_0000) >> 9) as usize
+	}
+
+	#[inline]
+	pub fn set_player(&mut self, player: usize) {
+		debug_assert!(player < 8, "Slots can only tell 8 players apart");
+		self.0 &= !0b1110_0000_0000;
+		self.0 |= ((player as u16) << 9) & 0b1110_0000_0000;
+	}
+
+	#[inline]
+	pub fn banana(&self) -> Option<Banana> {
+		match self.0 & 0b1100_0000 {
+			0b0000_0000 => None,
+			0b0100_0000 => Some(Banana::Yellow),
+			0b1000_0000 => Some(Banana::Red),
+			0b1100_0000 => Some(Banana::Cyan),
+			_ => unreachable!(),
+		}
+	}
+
+	#[inline]
+	pub fn set_banana(&mut self, banana: Option<Banana>) {
+		self.0 &= !0b1100_0000;
+		self.0 |= match banana {
+			None => 0b0000_0000,
+			Some(Banana::Yellow) => 0b0100_0000,
+			Some(Banana::Red) => 0b1000_0000,
+			Some(Banana::Cyan) => 0b1100_0000,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::cell::RefCell;
+	use std::rc::Rc;
+
+	use super::*;
+
+	/// A fresh game on a 5x5 playfield, its snake going right from the middle with its head on (2, 2).
+	fn game() -> SnakeGame {
+		SnakeGame::with_seed(size(5, 5), 1)
+	}
+
+	fn update(game: &mut SnakeGame) -> Vec<GameEvent> {
+		let mut events = Vec::new();
+		game.update(&mut events);
+		events
+	}
+
+	#[test]
+	fn eating_a_banana_emits_it() {
+		let mut game = game();
+		game.set_banana(pos(3, 2), Banana::Red).unwrap();
+
+		let events = update(&mut game);
+		assert!(
+			events.contains(&GameEvent::BananaEaten(Banana::Red, pos(3, 2))),
+			"{events:?}"
+		);
+		assert!(events.contains(&GameEvent::Grew(3)), "{events:?}");
+	}
+
+	#[test]
+	fn moving_around_emits_nothing_but_turns() {
+		let mut game = game();
+		game.set_banana(pos(0, 0), Banana::Yellow).unwrap();
+
+		assert_eq!(update(&mut game), []);
+		game.change_direction(0, Direction::Down);
+		assert_eq!(update(&mut game), [GameEvent::Turned(Direction::Down)]);
+	}
+
+	const DIRECTIONS: [Direction; 4] = [Direction::Up, Direction::Right, Direction::Down, Direction::Left];
+
+	/// A body slot going from `prev` to `next`.
+	fn body_slot(prev: Direction, next: Direction) -> Slot {
+		let mut slot = Slot::default();
+		slot.set_snake_head();
+		slot.set_snake_tail();
+		slot.set_direction_prev(prev);
+		slot.set_direction_next(next);
+		slot
+	}
+
+	/// Where a side of a sprite ends up once the sprite is drawn rotated clockwise.
+	fn rotated_side(side: Direction, rotate: Rotate) -> Direction {
+		let quarter_turns = match rotate {
+			Rotate::R0 => 0,
+			Rotate::R90 => 1,
+			Rotate::R180 => 2,
+			Rotate::R270 => 3,
+		};
+		DIRECTIONS[(side as usize + quarter_turns) % 4]
+	}
+
+	#[test]
+	fn corners_are_rotated_to_connect_both_sides() {
+		// unrotated, the corner sprite connects its right and bottom sides
+		let corner_sides = [Direction::Right, Direction::Down];
+
+		let mut corners = 0;
+		for prev in DIRECTIONS {
+			for next in DIRECTIONS {
+				if next == prev || next == prev.opposite() {
+					continue;
+				}
+
+				let Some(CellVisual::SnakeCorner(rotate)) = body_slot(prev, next).snake_visual() else {
+					panic!("going from {prev:?} to {next:?} isn't drawn as a corner");
+				};
+
+				let mut sides = corner_sides.map(|side| rotated_side(side, rotate));
+				sides.sort_by_key(|&side| side as u8);
+				let mut expected = [prev, next];
+				expected.sort_by_key(|&side| side as u8);
+				assert_eq!(sides, expected, "going from {prev:?} to {next:?}");
+				corners += 1;
+			}
+		}
+
+		assert_eq!(corners, 8);
+	}
+
+	#[test]
+	fn straight_parts_face_where_they_go() {
+		for next in DIRECTIONS {
+			let visual = body_slot(next.opposite(), next).snake_visual();
+			assert_eq!(visual, Some(CellVisual::SnakeStraight(facing_rotate(next))));
+		}
+	}
+
+	#[test]
+	#[should_panic(expected = "Body part going back where it came from")]
+	fn body_going_back_where_it_came_from_is_a_bug() {
+		body_slot(Direction::Left, Direction::Left).snake_visual();
+	}
+
+	#[test]
+	#[should_panic(expected = "The snake's head is going back where it came from")]
+	fn snakes_never_move_back_where_they_came_from() {
+		let mut game = game();
+		game.snakes[0].direction = Direction::Left;
+		update(&mut game);
+	}
+
+	#[test]
+	fn slots_know_their_snake_role() {
+		let mut game = game();
+		let role_at = |game: &SnakeGame, x, y| game.slot_at(pos(x, y)).snake_role();
+		assert_eq!(role_at(&game, 2, 2), Some(SnakeRole::Head));
+		assert_eq!(role_at(&game, 1, 2), Some(SnakeRole::Tail));
+		assert_eq!(role_at(&game, 0, 2), None);
+
+		game.set_banana(pos(3, 2), Banana::Yellow).unwrap();
+		update(&mut game);
+		assert_eq!(role_at(&game, 3, 2), Some(SnakeRole::Head));
+		assert_eq!(role_at(&game, 2, 2), Some(SnakeRole::Body));
+		assert_eq!(role_at(&game, 1, 2), Some(SnakeRole::Tail));
+
+		game.shrink(0, 2);
+		assert_eq!(role_at(&game, 3, 2), Some(SnakeRole::SingleCell));
+		assert_eq!(role_at(&game, 2, 2), None);
+		assert_eq!(role_at(&game, 1, 2), None);
+	}
+
+	/// A game with walls all around, its snake a couple of updates away from the right one and `grace` updates to
+	/// turn away from it.
+	fn walled_game(grace: u8) -> SnakeGame {
+		let mut game = game();
+		game.set_wrap(WrapConfig {
+			horizontal: false,
+			vertical: false,
+		});
+		game.set_death_grace(grace);
+		game.set_banana(pos(0, 0), Banana::Yellow).unwrap();
+
+		update(&mut game);
+		update(&mut game);
+		assert_eq!(game.snake_head(), pos(4, 2));
+		game
+	}
+
+	#[test]
+	fn turning_within_the_grace_survives() {
+		let mut game = walled_game(2);
+
+		assert_eq!(update(&mut game), []);
+		assert!(game.is_dying());
+		assert_eq!(game.snake_head(), pos(4, 2));
+
+		game.change_direction(0, Direction::Down);
+		update(&mut game);
+		assert!(!game.is_dying() && !game.is_dead());
+		assert_eq!(game.snake_head(), pos(4, 3));
+	}
+
+	#[test]
+	fn not_turning_dies_once_the_grace_is_over() {
+		let mut game = walled_game(2);
+
+		for _ in 0..2 {
+			assert_eq!(update(&mut game), []);
+			assert!(game.is_dying() && !game.is_dead());
+		}
+
+		let events = update(&mut game);
+		assert!(
+			events.contains(&GameEvent::SnakeDied(0, GameOverReason::HitWall)),
+			"{events:?}"
+		);
+		assert!(game.is_dead() && !game.is_dying());
+	}
+
+	#[test]
+	fn no_grace_dies_right_away() {
+		let mut game = walled_game(0);
+
+		update(&mut game);
+		assert!(game.is_dead());
+	}
+
+	/// An RNG that plays back a fixed list of numbers, over and over.
+	struct ScriptedRng {
+		values: Vec<u32>,
+		next: usize,
+	}
+
+	impl ScriptedRng {
+		fn new(values: &[u32]) -> Self {
+			Self {
+				values: values.to_vec(),
+				next: 0,
+			}
+		}
+	}
+
+	impl Rng for ScriptedRng {
+		fn next_u32(&mut self) -> u32 {
+			let value = self.values[self.next % self.values.len()];
+			self.next += 1;
+			value
+		}
+	}
+
+	/// Where every banana of a game is, and which one it is.
+	fn bananas<R: Rng>(game: &SnakeGame<R>) -> Vec<(Pos, Banana)> {
+		let Size { w, h } = game.size();
+		(0..h as i16)
+			.flat_map(|y| (0..w as i16).map(move |x| pos(x, y)))
+			.filter_map(|p| game.slot_at(p).banana().map(|banana| (p, banana)))
+			.collect()
+	}
+
+	#[test]
+	fn scripted_rng_places_the_banana_where_predicted() {
+		// (2, 2) is the snake's head, so it's rolled again, then the weights give a red banana
+		let game = SnakeGame::with_rng(size(5, 5), ScriptedRng::new(&[2, 2, 7, 4, 95]));
+		assert_eq!(bananas(&game), [(pos(2, 4), Banana::Red)]);
+
+		let game = SnakeGame::with_rng(size(5, 5), ScriptedRng::new(&[0, 3, 99]));
+		assert_eq!(bananas(&game), [(pos(0, 3), Banana::Cyan)]);
+	}
+
+	#[test]
+	fn scripted_rng_places_the_next_banana_once_one_is_eaten() {
+		let mut game = SnakeGame::with_rng(size(5, 5), ScriptedRng::new(&[3, 2, 0, 1, 4, 10]));
+		assert_eq!(bananas(&game), [(pos(3, 2), Banana::Yellow)]);
+
+		let mut events = Vec::new();
+		game.update(&mut events);
+		assert_eq!(bananas(&game), [(pos(1, 4), Banana::Yellow)]);
+	}
+
+	#[test]
+	fn recent_positions_go_from_the_head_up_to_the_length() {
+		let mut game = game();
+		assert_eq!(game.recent_positions(5), [pos(2, 2), pos(1, 2)]);
+
+		game.set_banana(pos(3, 2), Banana::Yellow).unwrap();
+		update(&mut game);
+		game.set_banana(pos(0, 0), Banana::Yellow).unwrap();
+		game.change_direction(0, Direction::Down);
+		update(&mut game);
+
+		assert_eq!(game.recent_positions(10), [pos(3, 3), pos(3, 2), pos(2, 2)]);
+		assert_eq!(game.recent_positions(2), [pos(3, 3), pos(3, 2)]);
+		assert_eq!(game.recent_positions(0), []);
+		assert_eq!(game.body_segments(), game.recent_positions(game.snake_len()));
+	}
+
+	#[test]
+	fn recent_positions_wrap_around_the_edges() {
+		let mut game = game();
+		game.set_banana(pos(0, 0), Banana::Yellow).unwrap();
+		for _ in 0..3 {
+			update(&mut game);
+		}
+
+		assert_eq!(game.snake_head(), pos(0, 2));
+		assert_eq!(game.recent_positions(2), [pos(0, 2), pos(4, 2)]);
+	}
+
+	/// Plays a game wrapping only some edges, its snake going one way until it goes through an edge or dies.
+	fn go_through_an_edge(wrap: WrapConfig, direction: Direction) -> SnakeGame {
+		let mut game = game();
+		game.set_wrap(wrap);
+		game.set_banana(pos(0, 0), Banana::Yellow).unwrap();
+		game.change_direction(0, direction);
+
+		for _ in 0..3 {
+			update(&mut game);
+		}
+		game
+	}
+
+	#[test]
+	fn horizontal_wrap_only_wraps_left_and_right() {
+		let wrap = WrapConfig {
+			horizontal: true,
+			vertical: false,
+		};
+
+		let mut left = game();
+		left.set_wrap(wrap);
+		left.set_banana(pos(0, 0), Banana::Yellow).unwrap();
+		left.change_direction(0, Direction::Down);
+		update(&mut left);
+		left.change_direction(0, Direction::Left);
+		for _ in 0..3 {
+			update(&mut left);
+		}
+		assert!(!left.is_dead());
+		assert_eq!(left.snake_head(), pos(4, 3));
+
+		let right = go_through_an_edge(wrap, Direction::Right);
+		assert!(!right.is_dead());
+		assert_eq!(right.snake_head(), pos(0, 2));
+
+		let up = go_through_an_edge(wrap, Direction::Up);
+		assert!(up.is_dead());
+		assert_eq!(up.snake_head(), pos(2, 0));
+	}
+
+	#[test]
+	fn vertical_wrap_only_wraps_top_and_bottom() {
+		let wrap = WrapConfig {
+			horizontal: false,
+			vertical: true,
+		};
+
+		let game = go_through_an_edge(wrap, Direction::Up);
+		assert!(!game.is_dead());
+		assert_eq!(game.snake_head(), pos(2, 4));
+
+		let game = go_through_an_edge(wrap, Direction::Down);
+		assert!(!game.is_dead());
+		assert_eq!(game.snake_head(), pos(2, 0));
+
+		let game = go_through_an_edge(wrap, Direction::Right);
+		assert!(game.is_dead());
+		assert_eq!(game.snake_head(), pos(4, 2));
+	}
+
+	#[test]
+	fn equal_games_have_equal_checksums() {
+		let mut a = SnakeGame::with_seed(size(8, 8), 7);
+		let mut b = SnakeGame::with_seed(size(8, 8), 7);
+		assert_eq!(a.checksum(), b.checksum());
+
+		for _ in 0..10 {
+			update(&mut a);
+			update(&mut b);
+			assert_eq!(a.checksum(), b.checksum());
+		}
+		assert_eq!(a.clone().checksum(), a.checksum());
+	}
+
+	#[test]
+	fn moving_a_single_segment_changes_the_checksum() {
+		let game = game();
+		let mut moved = game.clone();
+
+		// the tail goes from behind the head to under it
+		let tail = moved.slot_index(pos(1, 2));
+		let below = moved.slot_index(pos(2, 3));
+		moved.playfield.swap(tail, below);
+		moved.snakes[0].tail = pos(2, 3);
+		assert_ne!(moved.checksum(), game.checksum());
+	}
+
+	#[test]
+	fn scores_and_settings_change_the_checksum() {
+		let game = game();
+		let checksum = game.checksum();
+		let changed = |change: fn(&mut SnakeGame)| {
+			let mut game = game.clone();
+			change(&mut game);
+			game.checksum()
+		};
+
+		assert_ne!(changed(|game| game.snakes[0].banana_points += 1), checksum);
+		assert_ne!(changed(|game| game.snakes[0].bananas_eaten += 1), checksum);
+		assert_ne!(
+			changed(|game| game.set_score_config(ScoreConfig {
+				per_banana: game.score_config.per_banana + 1,
+				..game.score_config
+			})),
+			checksum
+		);
+		assert_ne!(
+			changed(|game| game.set_wrap(WrapConfig {
+				horizontal: false,
+				vertical: true
+			})),
+			checksum
+		);
+		assert_ne!(changed(|game| game.set_death_grace(1)), checksum);
+		assert_ne!(changed(|game| game.set_speed(4.)), checksum);
+		assert_ne!(changed(|game| game.set_banana_weights(1, 1, 1)), checksum);
+		assert_eq!(changed(|game| game.set_speed(game.speed())), checksum);
+	}
+
+	#[test]
+	fn runs_diverge_after_a_single_different_input() {
+		let mut a = SnakeGame::with_seed(size(8, 8), 7);
+		let mut b = SnakeGame::with_seed(size(8, 8), 7);
+		for _ in 0..2 {
+			update(&mut a);
+			update(&mut b);
+		}
+		assert_eq!(a.checksum(), b.checksum());
+
+		b.change_direction(0, Direction::Up);
+		assert_ne!(a.checksum(), b.checksum());
+		for _ in 0..3 {
+			update(&mut a);
+			update(&mut b);
+			assert_ne!(a.checksum(), b.checksum());
+		}
+	}
+
+	#[test]
+	fn eating_on_consecutive_ticks_grows_once_per_banana() {
+		let mut game = game();
+		let per_banana = game.score_config.per_banana;
+
+		game.set_banana(pos(3, 2), Banana::Yellow).unwrap();
+		let events = update(&mut game);
+		assert_eq!(events.iter().filter(|e| matches!(e, GameEvent::Grew(_))).count(), 1);
+		assert_eq!((game.snake_len(), game.bananas_eaten()), (3, 1));
+		assert_eq!(game.snakes()[0].banana_points, per_banana);
+
+		game.set_banana(pos(4, 2), Banana::Yellow).unwrap();
+		let events = update(&mut game);
+		assert_eq!(events.iter().filter(|e| matches!(e, GameEvent::Grew(_))).count(), 1);
+		assert_eq!((game.snake_len(), game.bananas_eaten()), (4, 2));
+		assert_eq!(game.snakes()[0].banana_points, 2 * per_banana);
+
+		update(&mut game);
+		assert_eq!((game.snake_len(), game.bananas_eaten()), (4, 2));
+	}
+
+	#[test]
+	fn bananas_never_respawn_on_the_slot_just_eaten() {
+		// the first banana goes on (3, 2), and the RNG tries to put the next one there again right after
+		let mut game = SnakeGame::with_rng(size(5, 5), ScriptedRng::new(&[3, 2, 0, 3, 2, 0, 0, 0]));
+		assert_eq!(bananas(&game), [(pos(3, 2), Banana::Yellow)]);
+
+		let mut events = Vec::new();
+		game.update(&mut events);
+		assert_eq!(game.snake_head(), pos(3, 2));
+		assert_eq!(bananas(&game), [(pos(0, 0), Banana::Yellow)]);
+	}
+
+	#[test]
+	fn restarting_faces_the_initial_direction() {
+		for direction in DIRECTIONS {
+			let mut game = game();
+			game.set_initial_direction(direction);
+			game.restart(RestartKind::Fresh);
+
+			let snake = &game.snakes()[0];
+			assert_eq!(snake.direction(), direction);
+			assert_eq!(snake.head(), pos(2, 2));
+			assert_eq!(snake.tail(), pos(2, 2).step(direction.opposite()), "{direction:?}");
+			assert_eq!(game.validate(), Ok(()), "{direction:?}");
+
+			game.set_banana(pos(0, 0), Banana::Yellow).unwrap();
+			update(&mut game);
+			assert_eq!(game.snake_head(), pos(2, 2).step(direction), "{direction:?}");
+		}
+	}
+
+	#[test]
+	fn initial_direction_waits_for_a_restart() {
+		let mut game = game();
+		game.set_initial_direction(Direction::Up);
+		assert_eq!(game.snakes()[0].direction(), Direction::Right);
+		assert_eq!(game.snakes()[0].tail(), pos(1, 2));
+	}
+
+	#[test]
+	#[should_panic(expected = "doesn't fit on the playfield facing Down")]
+	fn initial_direction_needs_room_for_the_body() {
+		let mut game = SnakeGame::with_seed(size(5, 1), 1);
+		game.set_initial_direction(Direction::Down);
+	}
+
+	#[test]
+	fn filling_the_playfield_wins() {
+		// the only free slot is right in front of the snake, so that's where the banana goes
+		let mut game = SnakeGame::with_seed(size(3, 1), 1);
+		assert_eq!(bananas(&game).len(), 1);
+		assert_eq!(bananas(&game)[0].0, pos(2, 0));
+		assert!(!game.is_won());
+
+		let events = update(&mut game);
+		assert!(game.is_won() && !game.is_dead());
+		assert!(events.contains(&GameEvent::Died(GameOverReason::Won)), "{events:?}");
+		assert_eq!(game.snake_len(), 3);
+		assert_eq!(bananas(&game), []);
+		assert_eq!(game.missing_bananas(), 0);
+
+		// nothing moves anymore
+		let checksum = game.checksum();
+		assert_eq!(update(&mut game), []);
+		assert_eq!(game.checksum(), checksum);
+	}
+
+	#[test]
+	fn filling_a_bigger_playfield_wins() {
+		let mut game = SnakeGame::with_seed(size(2, 2), 1);
+		// going around in a circle can't miss the banana
+		for direction in [Direction::Down, Direction::Left, Direction::Up, Direction::Right]
+			.into_iter()
+			.cycle()
+			.take(8)
+		{
+			if game.is_won() {
+				break;
+			}
+			assert!(!game.is_dead());
+			game.change_direction(0, direction);
+			update(&mut game);
+		}
+		assert!(game.is_won());
+
+		assert_eq!(game.snake_len(), 4);
+		assert_eq!(bananas(&game), []);
+	}
+
+	/// Color of the banana placed by a restart with some weights, when the RNG rolls `roll` for its color.
+	fn weighted_banana(weights: [u32; 3], roll: u32) -> Banana {
+		let mut game = SnakeGame::with_rng(size(5, 5), ScriptedRng::new(&[0, 0, roll]));
+		let [yellow, red, cyan] = weights;
+		game.set_banana_weights(yellow, red, cyan);
+		game.restart(RestartKind::Fresh);
+
+		let bananas = bananas(&game);
+		assert_eq!(bananas.len(), 1);
+		bananas[0].1
+	}
+
+	#[test]
+	fn weights_pick_colors_by_their_share_of_the_roll() {
+		// out of 6, rolls 0 to 2 are yellow, 3 and 4 are red, and 5 is cyan
+		let weights = [3, 2, 1];
+		let colors = (0..12).map(|roll| weighted_banana(weights, roll)).collect::<Vec<_>>();
+		let expected = [
+			Banana::Yellow,
+			Banana::Yellow,
+			Banana::Yellow,
+			Banana::Red,
+			Banana::Red,
+			Banana::Cyan,
+		];
+		assert_eq!(colors, [expected, expected].concat());
+	}
+
+	#[test]
+	fn only_weighted_colors_spawn() {
+		for roll in [0, 1, 2, 99, u32::MAX] {
+			assert_eq!(weighted_banana([1, 0, 0], roll), Banana::Yellow);
+			assert_eq!(weighted_banana([0, 1, 0], roll), Banana::Red);
+			assert_eq!(weighted_banana([0, 0, 7], roll), Banana::Cyan);
+			assert_eq!(weighted_banana([0, 0, 0], roll), Banana::Yellow);
+		}
+	}
+
+	#[test]
+	fn a_huge_frame_delta_only_advances_by_the_cap() {
+		let mut game = game();
+		game.set_max_frame_delta(Duration::from_millis(100));
+
+		// e.g. the process was suspended for an hour between two frames
+		game.last_duration_update = Instant::now() - Duration::from_secs(3600);
+		game.update_duration();
+		assert_eq!(game.duration(), Duration::from_millis(100));
+
+		game.advance_duration(Duration::from_millis(30));
+		assert_eq!(game.duration(), Duration::from_millis(130));
+	}
+
+	#[test]
+	fn a_huge_frame_delta_only_plays_the_ticks_of_the_cap() {
+		let mut game = game();
+		game.set_banana(pos(0, 0), Banana::Yellow).unwrap();
+		game.set_speed(10.);
+		game.set_max_frame_delta(Duration::from_millis(250));
+
+		let mut events = Vec::new();
+		assert_eq!(game.advance(Duration::from_secs(3600), &mut events), 2);
+		assert_eq!(game.tick_count(), 2);
 	}
 
-	#[inline]
-	pub fn has_snake(&self) -> bool {
-		self.0 & 0b0011_0000 > 0
+	#[test]
+	fn the_timer_stops_once_the_game_is_over() {
+		let mut game = walled_game(0);
+		update(&mut game);
+		assert!(game.is_dead());
+
+		let duration = game.duration();
+		game.advance_duration(Duration::from_millis(100));
+		assert_eq!(game.duration(), duration);
 	}
 
-	pub fn remove_snake(&mut self) {
-		self.0 &= 0b1100_1111;
+	#[test]
+	fn base_speed_alone_sets_the_tick_cadence() {
+		let mut game = game();
+		game.set_banana(pos(0, 0), Banana::Yellow).unwrap();
+		game.set_speed(4.);
+		assert_eq!(game.current_tps(), 4.);
+
+		// a tick every 250ms, with frames every 100ms
+		let mut events = Vec::new();
+		let ticks: Vec<u32> = (0..10)
+			.map(|_| game.advance(Duration::from_millis(100), &mut events))
+			.collect();
+		assert_eq!(ticks, [0, 0, 1, 0, 1, 0, 0, 1, 0, 1]);
+		assert_eq!(game.tick_count(), 4);
 	}
 
-	#[inline]
-	pub fn banana(&self) -> Option<Banana> {
-		match self.0 & 0b1100_0000 {
-			0b0000_0000 => None,
-			0b0100_0000 => Some(Banana::Yellow),
-			0b1000_0000 => Some(Banana::Red),
-			0b1100_0000 => Some(Banana::Cyan),
-			_ => unreachable!(),
+	#[test]
+	fn speed_ramp_multiplies_the_base_speed() {
+		let mut game = game();
+		game.set_speed(4.);
+		game.set_speed_ramp(Some(SpeedRamp {
+			per_banana: 0.5,
+			max_multiplier: 2.,
+		}));
+		assert_eq!(game.current_tps(), 4.);
+
+		for (x, tps) in [(3, 6.), (4, 8.), (0, 8.)] {
+			game.set_banana(pos(x, 2), Banana::Yellow).unwrap();
+			update(&mut game);
+			assert_eq!(game.current_tps(), tps);
 		}
+		assert_eq!(game.speed(), 4.);
+
+		game.set_speed_ramp(None);
+		assert_eq!(game.current_tps(), 4.);
 	}
 
-	#[inline]
-	pub fn set_banana(&mut self, banana: Option<Banana>) {
-		self.0 &= 0b0011_1111;
-		self.0 |= match banana {
-			None => 0b0000_0000,
-			Some(Banana::Yellow) => 0b0100_0000,
-			Some(Banana::Red) => 0b1000_0000,
-			Some(Banana::Cyan) => 0b1100_0000,
+	#[test]
+	fn occupied_cells_are_only_the_snake_and_the_banana() {
+		let mut game = SnakeGame::with_seed(size(40, 30), 3);
+		game.set_banana(pos(35, 2), Banana::Cyan).unwrap();
+
+		let head = game.snake_head();
+		let tail = game.snakes()[0].tail();
+		assert_eq!(
+			game.occupied_cells().collect::<Vec<_>>(),
+			[
+				(pos(35, 2), CellKind::Banana(Banana::Cyan)),
+				(tail, CellKind::Snake(SnakeRole::Tail)),
+				(head, CellKind::Snake(SnakeRole::Head)),
+			]
+		);
+	}
+
+	#[test]
+	fn occupied_cells_follow_the_snake_as_it_grows() {
+		let mut game = game();
+		game.set_banana(pos(3, 2), Banana::Yellow).unwrap();
+		update(&mut game);
+		game.set_banana(pos(0, 0), Banana::Red).unwrap();
+
+		let mut cells = game.occupied_cells().collect::<Vec<_>>();
+		cells.sort_by_key(|&(p, _)| (p.x, p.y));
+		assert_eq!(
+			cells,
+			[
+				(pos(0, 0), CellKind::Banana(Banana::Red)),
+				(pos(1, 2), CellKind::Snake(SnakeRole::Tail)),
+				(pos(2, 2), CellKind::Snake(SnakeRole::Body)),
+				(pos(3, 2), CellKind::Snake(SnakeRole::Head)),
+			]
+		);
+		assert_eq!(game.occupied_cells().count(), game.snake_len() + 1);
+	}
+
+	/// A game whose snake curled up on the 2x2 square at (2, 2), its head at (2, 3) right below its tail.
+	fn curled_game() -> SnakeGame {
+		let mut game = game();
+		for (direction, banana) in [
+			(Direction::Right, Some(pos(3, 2))),
+			(Direction::Down, Some(pos(3, 3))),
+			(Direction::Left, None),
+		] {
+			game.set_banana(banana.unwrap_or(pos(0, 0)), Banana::Yellow).unwrap();
+			game.change_direction(0, direction);
+			update(&mut game);
+		}
+
+		assert_eq!(game.body_segments(), [pos(2, 3), pos(3, 3), pos(3, 2), pos(2, 2)]);
+		game
+	}
+
+	#[test]
+	fn turning_into_the_vacating_tail_is_safe() {
+		let mut game = curled_game();
+		game.change_direction(0, Direction::Up);
+
+		let events = update(&mut game);
+		assert!(!game.is_dead(), "{events:?}");
+		assert_eq!(game.body_segments(), [pos(2, 2), pos(2, 3), pos(3, 3), pos(3, 2)]);
+
+		// and it can go round in circles forever
+		for direction in [Direction::Right, Direction::Down, Direction::Left, Direction::Up].repeat(3) {
+			game.change_direction(0, direction);
+			update(&mut game);
+			assert!(!game.is_dead());
+		}
+	}
+
+	#[test]
+	fn turning_into_the_body_before_the_tail_is_deadly() {
+		// same as the curled up game, but one slot longer so its tail's still behind the square
+		let mut game = game();
+		for (direction, banana) in [
+			(Direction::Right, pos(3, 2)),
+			(Direction::Down, pos(3, 3)),
+			(Direction::Left, pos(2, 3)),
+		] {
+			game.set_banana(banana, Banana::Yellow).unwrap();
+			game.change_direction(0, direction);
+			update(&mut game);
+		}
+		assert_eq!(game.snakes()[0].tail(), pos(1, 2));
+
+		game.set_banana(pos(0, 0), Banana::Yellow).unwrap();
+		game.change_direction(0, Direction::Up);
+		let events = update(&mut game);
+		assert!(events.contains(&GameEvent::Died(GameOverReason::HitSelf)), "{events:?}");
+	}
+
+	/// Two snakes on a 5x3 playfield with the first one shrunk to a single slot, so that the second one can turn into
+	/// its tail: the first one's on (2, 1), the second one's head right below it.
+	///
+	/// A snake can't eat a banana on its own tail, so growing only keeps a tail in place for other snakes.
+	fn tailgating_game() -> SnakeGame {
+		let mut game = SnakeGame::with_players(size(5, 3), 2, XorShift32::new(1));
+		game.shrink(0, 1);
+		assert_eq!(game.snakes()[0].head(), pos(2, 1));
+		assert_eq!(game.slot_at(pos(2, 1)).snake_role(), Some(SnakeRole::SingleCell));
+		assert_eq!(game.snakes()[1].head(), pos(2, 2));
+
+		game.change_direction(1, Direction::Up);
+		game
+	}
+
+	#[test]
+	fn going_into_a_tail_that_moves_on_is_safe() {
+		let mut game = tailgating_game();
+		game.set_banana(pos(0, 0), Banana::Yellow).unwrap();
+
+		update(&mut game);
+		assert!(game.snakes().iter().all(|snake| !snake.is_dead()));
+		assert_eq!(game.snakes()[0].head(), pos(3, 1));
+		assert_eq!(game.snakes()[1].head(), pos(2, 1));
+	}
+
+	#[test]
+	fn going_into_a_tail_that_stays_to_grow_is_deadly() {
+		let mut game = tailgating_game();
+		game.set_banana(pos(3, 1), Banana::Yellow).unwrap();
+
+		update(&mut game);
+		assert_eq!(game.snakes()[0].length(), 2);
+		assert!(!game.snakes()[0].is_dead());
+		assert!(game.snakes()[1].is_dead());
+	}
+
+	#[test]
+	fn death_animation_advances_every_tick_while_dead() {
+		let mut game = walled_game(0);
+		game.set_death_animation_ticks(4);
+		assert_eq!(game.death_animation_progress(), None);
+
+		let events = update(&mut game);
+		assert!(events.contains(&GameEvent::Died(GameOverReason::HitWall)), "{events:?}");
+		assert_eq!(game.death_animation_progress(), Some(0.));
+
+		for progress in [0.25, 0.5, 0.75] {
+			assert_eq!(update(&mut game), []);
+			assert!(game.is_dead());
+			assert_eq!(game.death_animation_progress(), Some(progress));
+		}
+
+		assert_eq!(update(&mut game), [GameEvent::DeathAnimationEnded]);
+		assert_eq!(game.death_animation_progress(), Some(1.));
+
+		// it's over, so it stays there
+		assert_eq!(update(&mut game), []);
+		assert!(game.is_dead());
+		assert_eq!(game.death_animation_progress(), Some(1.));
+	}
+
+	#[test]
+	fn no_death_animation_ends_as_soon_as_the_snake_dies() {
+		let mut game = walled_game(0);
+		game.set_death_animation_ticks(0);
+
+		let events = update(&mut game);
+		assert_eq!(
+			events,
+			[
+				GameEvent::SnakeDied(0, GameOverReason::HitWall),
+				GameEvent::Died(GameOverReason::HitWall),
+				GameEvent::DeathAnimationEnded
+			]
+		);
+		assert_eq!(game.death_animation_progress(), Some(1.));
+	}
+
+	#[test]
+	fn restarting_cancels_the_death_animation() {
+		for kind in [RestartKind::Fresh, RestartKind::Continue] {
+			let mut game = walled_game(0);
+			update(&mut game);
+			update(&mut game);
+			assert!(game.death_animation_progress().is_some());
+
+			game.restart(kind);
+			assert!(!game.is_dead());
+			assert_eq!(game.death_animation_progress(), None);
+
+			// and the next death starts it over
+			game.set_wrap(WrapConfig {
+				horizontal: false,
+				vertical: false,
+			});
+			for _ in 0..3 {
+				update(&mut game);
+			}
+			assert_eq!(game.death_animation_progress(), Some(0.), "{kind:?}");
+		}
+	}
+
+	/// Everything a callback got called with, in order.
+	type Calls<T> = Rc<RefCell<Vec<T>>>;
+
+	/// A callback pushing what it's called with, and what it pushed to.
+	fn recorder<T: 'static>() -> (Calls<T>, Box<dyn FnMut(T)>) {
+		let calls = Rc::new(RefCell::new(Vec::new()));
+		let pushed_to = Rc::clone(&calls);
+		(calls, Box::new(move |call| pushed_to.borrow_mut().push(call)))
+	}
+
+	#[test]
+	fn eat_callbacks_see_the_banana_eaten() {
+		let mut game = game();
+		let (first, mut record_first) = recorder();
+		let (second, mut record_second) = recorder();
+		game.on_eat(Box::new(move |banana, banana_pos| record_first((banana, banana_pos))));
+		game.on_eat(Box::new(move |banana, banana_pos| record_second((banana, banana_pos))));
+
+		game.set_banana(pos(0, 0), Banana::Yellow).unwrap();
+		update(&mut game);
+		assert_eq!(*first.borrow(), []);
+
+		game.set_banana(pos(4, 2), Banana::Cyan).unwrap();
+		update(&mut game);
+		assert_eq!(*first.borrow(), [(Banana::Cyan, pos(4, 2))]);
+		assert_eq!(*second.borrow(), *first.borrow());
+	}
+
+	#[test]
+	fn death_callbacks_see_why_the_snake_died() {
+		let mut game = walled_game(0);
+		let (deaths, record) = recorder();
+		game.on_death(record);
+
+		update(&mut game);
+		assert_eq!(*deaths.borrow(), [GameOverReason::HitWall]);
+
+		// the game's already over, so there's no death to report anymore
+		update(&mut game);
+		assert_eq!(deaths.borrow().len(), 1);
+	}
+
+	#[test]
+	fn cloned_games_dont_call_the_callbacks() {
+		let mut game = game();
+		let (eaten, mut record) = recorder();
+		game.on_eat(Box::new(move |banana, _| record(banana)));
+
+		let mut clone = game.clone();
+		clone.set_banana(pos(3, 2), Banana::Red).unwrap();
+		update(&mut clone);
+		assert_eq!(*eaten.borrow(), []);
+	}
+
+	#[test]
+	fn render_model_follows_a_scripted_game() {
+		let mut game = game();
+		game.set_banana(pos(0, 0), Banana::Red).unwrap();
+		let model = game.render_model();
+		assert_eq!(model.size, size(5, 5));
+		assert_eq!(
+			model.cells,
+			[
+				(pos(0, 0), CellVisual::Banana(Banana::Red)),
+				(pos(1, 2), CellVisual::SnakeTail(Rotate::R90)),
+				(pos(2, 2), CellVisual::SnakeHead(Rotate::R0)),
+			]
+		);
+
+		game.set_banana(pos(3, 2), Banana::Cyan).unwrap();
+		update(&mut game);
+		game.set_banana(pos(0, 4), Banana::Yellow).unwrap();
+		game.change_direction(0, Direction::Down);
+		update(&mut game);
+
+		assert_eq!(
+			game.render_model().cells,
+			[
+				(pos(2, 2), CellVisual::SnakeTail(Rotate::R90)),
+				(pos(3, 2), CellVisual::SnakeCorner(Rotate::R90)),
+				(pos(3, 3), CellVisual::SnakeHead(Rotate::R90)),
+				(pos(0, 4), CellVisual::Banana(Banana::Yellow)),
+			]
+		);
+	}
+
+	#[test]
+	fn render_model_heads_face_the_next_move_before_the_first_one() {
+		let mut game = game();
+		game.set_banana(pos(0, 0), Banana::Yellow).unwrap();
+		let head_visual = |game: &SnakeGame| {
+			let model = game.render_model();
+			let (_, visual) = model.cells.into_iter().find(|&(p, _)| p == game.snake_head()).unwrap();
+			visual
+		};
+
+		game.change_direction(0, Direction::Up);
+		assert_eq!(head_visual(&game), CellVisual::snake_head(Direction::Up));
+
+		// once it moved, the head faces where it went, even if it's about to turn
+		update(&mut game);
+		game.change_direction(0, Direction::Left);
+		assert_eq!(head_visual(&game), CellVisual::snake_head(Direction::Up));
+	}
+
+	#[test]
+	fn render_model_tails_connect_to_the_rest_of_the_snake() {
+		for direction in DIRECTIONS {
+			let mut game = game();
+			game.set_initial_direction(direction);
+			game.restart(RestartKind::Fresh);
+			game.set_banana(pos(0, 0), Banana::Yellow).unwrap();
+
+			let model = game.render_model();
+			let tail = game.snakes()[0].tail();
+			let Some(&(_, CellVisual::SnakeTail(rotate))) = model.cells.iter().find(|&&(p, _)| p == tail) else {
+				panic!("no tail on {tail:?}: {model:?}");
+			};
+			assert_eq!(rotated_side(Direction::Up, rotate), direction);
+		}
+	}
+
+	#[test]
+	fn the_same_date_gives_the_same_seed() {
+		assert_eq!(daily_seed((2026, 10, 14)), daily_seed((2026, 10, 14)));
+
+		// pinned, so that it can't change from one build or machine to another
+		assert_eq!(daily_seed((2026, 10, 14)), 0x2b25_3545_9e6c_ca1a);
+		assert_eq!(daily_seed((2000, 1, 1)), 0xc531_105b_b130_33a8);
+
+		let board = |seed| SnakeGame::with_seed(size(11, 11), seed).checksum();
+		assert_eq!(board(daily_seed((2026, 10, 14))), board(daily_seed((2026, 10, 14))));
+	}
+
+	#[test]
+	fn adjacent_dates_give_different_seeds() {
+		let date = (2026, 10, 14);
+		let neighbours = [
+			(2026, 10, 13),
+			(2026, 10, 15),
+			(2026, 9, 14),
+			(2026, 11, 14),
+			(2025, 10, 14),
+			(2027, 10, 14),
+			// the same numbers in another order
+			(2026, 14, 10),
+		];
+		for neighbour in neighbours {
+			assert_ne!(daily_seed(date), daily_seed(neighbour), "{neighbour:?}");
+		}
+
+		// a whole year of days, crossing into the next one
+		let mut seeds: Vec<u64> = (1..=12)
+			.flat_map(|month| (1..=31).map(move |day| daily_seed((2026, month, day))))
+			.chain([daily_seed((2027, 1, 1))])
+			.collect();
+		let days = seeds.len();
+		seeds.sort_unstable();
+		seeds.dedup();
+		assert_eq!(seeds.len(), days);
+	}
+
+	/// A game whose snake ate its way to 6 slots long, turning once and going through an edge.
+	fn long_game() -> SnakeGame {
+		let mut game = game();
+		for (direction, banana) in [
+			(Direction::Right, pos(3, 2)),
+			(Direction::Right, pos(4, 2)),
+			(Direction::Right, pos(0, 2)),
+			(Direction::Down, pos(0, 3)),
+		] {
+			game.set_banana(banana, Banana::Yellow).unwrap();
+			game.change_direction(0, direction);
+			update(&mut game);
+		}
+		game.set_banana(pos(4, 4), Banana::Yellow).unwrap();
+
+		assert_eq!(game.snake_len(), 6);
+		assert_valid_chain(&game);
+		game
+	}
+
+	/// Checks that the first snake's slots go from its head to its tail one step at a time, with nothing left over.
+	fn assert_valid_chain(game: &SnakeGame) {
+		let snake = &game.snakes()[0];
+		let segments = game.body_segments();
+		assert_eq!(segments.len(), snake.length());
+		assert_eq!(segments.first(), Some(&snake.head()));
+		assert_eq!(segments.last(), Some(&snake.tail()));
+
+		let snake_slots = (game.playfield.iter()).filter(|slot| slot.has_snake()).count();
+		assert_eq!(snake_slots, snake.length(), "some snake slots aren't part of the chain");
+
+		for (i, &segment) in segments.iter().enumerate() {
+			let slot = game.slot_at(segment);
+			let expected_role = match i {
+				_ if segments.len() == 1 => SnakeRole::SingleCell,
+				0 => SnakeRole::Head,
+				_ if i == segments.len() - 1 => SnakeRole::Tail,
+				_ => SnakeRole::Body,
+			};
+			assert_eq!(slot.snake_role(), Some(expected_role), "{segment:?}");
+
+			if let Some(&behind) = segments.get(i + 1) {
+				let next = game.wrap_pos(behind.step(game.slot_at(behind).direction_next()));
+				assert_eq!(next, segment, "{behind:?} doesn't lead to {segment:?}");
+			}
+		}
+	}
+
+	#[test]
+	fn shrinking_stops_at_the_minimum_length() {
+		let mut game = long_game();
+		game.set_min_length(3);
+
+		for (cut, len) in [(1, 5), (1, 4), (1, 3), (0, 3), (0, 3)] {
+			assert_eq!(game.shrink(0, 1), cut);
+			assert_eq!(game.snake_len(), len);
+			assert_valid_chain(&game);
+		}
+		assert_eq!(game.shrink(0, 100), 0);
+		assert_eq!(game.snake_len(), 3);
+
+		// and it keeps going as a normal snake afterwards
+		for _ in 0..4 {
+			update(&mut game);
+			assert!(!game.is_dead());
+			assert_valid_chain(&game);
+		}
+	}
+
+	#[test]
+	fn shrinking_a_player_leaves_the_others_alone() {
+		let mut game = two_player_game(5);
+		game.set_banana(pos(3, 3), Banana::Yellow).unwrap();
+		update(&mut game);
+		assert_eq!(game.snakes()[1].length(), 3);
+		let snake = |game: &SnakeGame| {
+			let Snake { head, tail, len, .. } = game.snakes()[0];
+			(head, tail, len, game.slot_at(head).0, game.slot_at(tail).0)
+		};
+		let first = snake(&game);
+
+		game.set_min_length(2);
+		assert_eq!(game.shrink(1, 5), 1);
+		assert_eq!(game.shrink(1, 1), 0);
+		assert_eq!(game.snakes()[1].length(), 2);
+		assert_eq!(game.validate(), Ok(()));
+
+		assert_eq!(snake(&game), first);
+	}
+
+	#[test]
+	fn shrinking_a_lot_at_once_leaves_just_the_minimum() {
+		let mut game = long_game();
+		game.set_min_length(2);
+		assert_eq!(game.shrink(0, 100), 4);
+		assert_eq!(game.snake_len(), 2);
+		assert_valid_chain(&game);
+
+		// a minimum of 0 would leave no snake at all, so it's 1 instead
+		let mut game = long_game();
+		game.set_min_length(0);
+		assert_eq!(game.shrink(0, usize::MAX), 5);
+		assert_eq!(game.snake_len(), 1);
+		assert_valid_chain(&game);
+
+		for _ in 0..3 {
+			update(&mut game);
+			assert_valid_chain(&game);
+		}
+	}
+
+	#[test]
+	fn raising_the_minimum_doesnt_grow_the_snake() {
+		let mut game = long_game();
+		game.set_min_length(10);
+		assert_eq!(game.shrink(0, 1), 0);
+		assert_eq!(game.snake_len(), 6);
+		assert_valid_chain(&game);
+	}
+
+	#[test]
+	fn a_turn_between_ticks_has_a_latency_of_one() {
+		let mut game = game();
+		game.set_banana(pos(0, 0), Banana::Yellow).unwrap();
+		assert_eq!(game.last_input_latency_ticks(), 0);
+
+		update(&mut game);
+		game.change_direction(0, Direction::Down);
+		assert_eq!(game.last_input_latency_ticks(), 0);
+		assert_eq!(update(&mut game), [GameEvent::Turned(Direction::Down)]);
+		assert_eq!(game.last_input_latency_ticks(), 1);
+
+		// going straight on keeps the last turn's latency
+		update(&mut game);
+		assert_eq!(game.last_input_latency_ticks(), 1);
+	}
+
+	#[test]
+	fn only_turns_that_happen_are_measured() {
+		let mut game = game();
+		game.set_banana(pos(0, 0), Banana::Yellow).unwrap();
+
+		// changing its mind several times before the tick still turns on it
+		game.change_direction(0, Direction::Down);
+		game.change_direction(0, Direction::Up);
+		assert_eq!(update(&mut game), [GameEvent::Turned(Direction::Up)]);
+		assert_eq!(game.last_input_latency_ticks(), 1);
+
+		// a turn that's taken back before the tick doesn't happen, so nothing changes
+		game.restart(RestartKind::Continue);
+		assert_eq!(game.last_input_latency_ticks(), 0);
+		game.change_direction(0, Direction::Down);
+		game.change_direction(0, Direction::Right);
+		assert_eq!(update(&mut game), []);
+		assert_eq!(game.last_input_latency_ticks(), 0);
+
+		// nor does asking for the direction it's already going
+		game.change_direction(0, Direction::Right);
+		update(&mut game);
+		assert_eq!(game.last_input_latency_ticks(), 0);
+	}
+
+	/// A two-player game on a 5-wide playfield, both snakes going right with their heads on the middle column, and
+	/// the banana out of their way.
+	fn two_player_game(h: u16) -> SnakeGame {
+		let mut game = SnakeGame::with_players(size(5, h), 2, XorShift32::new(1));
+		game.set_banana(pos(0, 0), Banana::Yellow).unwrap();
+		game
+	}
+
+	#[test]
+	fn snakes_going_into_the_same_slot_both_die() {
+		let mut game = two_player_game(5);
+		assert_eq!(game.snakes()[0].head(), pos(2, 1));
+		assert_eq!(game.snakes()[1].head(), pos(2, 3));
+
+		game.change_direction(0, Direction::Down);
+		game.change_direction(1, Direction::Up);
+		let events = update(&mut game);
+		assert!(
+			events.contains(&GameEvent::SnakeDied(0, GameOverReason::HitSnake)),
+			"{events:?}"
+		);
+		assert!(
+			events.contains(&GameEvent::SnakeDied(1, GameOverReason::HitSnake)),
+			"{events:?}"
+		);
+		assert!(
+			events.contains(&GameEvent::Died(GameOverReason::HitSnake)),
+			"{events:?}"
+		);
+		assert!(game.is_dead());
+	}
+
+	#[test]
+	fn snakes_swapping_heads_both_die() {
+		// the heads are right next to each other, and go through one another
+		let mut game = two_player_game(3);
+		assert_eq!(game.snakes()[1].head(), pos(2, 2));
+
+		game.change_direction(0, Direction::Down);
+		game.change_direction(1, Direction::Up);
+		update(&mut game);
+		assert!(game.snakes().iter().all(|snake| snake.is_dead()));
+	}
+
+	#[test]
+	fn running_into_another_snake_only_kills_the_one_running() {
+		let mut game = two_player_game(3);
+
+		// the second snake's head goes into the body the first one leaves behind
+		game.change_direction(1, Direction::Up);
+		let events = update(&mut game);
+		assert_eq!(events, [GameEvent::SnakeDied(1, GameOverReason::HitSnake)]);
+		assert!(!game.is_dead());
+
+		update(&mut game);
+		assert_eq!(game.snakes()[0].head(), pos(4, 1));
+		assert!(!game.snakes()[0].is_dead());
+	}
+
+	#[test]
+	fn snakes_eat_and_grow_on_their_own() {
+		let mut game = two_player_game(5);
+
+		game.set_banana(pos(3, 1), Banana::Red).unwrap();
+		update(&mut game);
+		let [first, second] = [&game.snakes()[0], &game.snakes()[1]];
+		assert_eq!((first.length(), first.bananas_by_color()), (3, [0, 1, 0]));
+		assert_eq!((second.length(), second.bananas_eaten()), (2, 0));
+
+		game.set_banana(pos(4, 3), Banana::Cyan).unwrap();
+		update(&mut game);
+		let [first, second] = [&game.snakes()[0], &game.snakes()[1]];
+		assert_eq!((first.length(), first.bananas_eaten()), (3, 1));
+		assert_eq!((second.length(), second.bananas_by_color()), (3, [0, 0, 1]));
+
+		// and every slot knows whose snake is on it
+		for (player, snake) in game.snakes().iter().enumerate() {
+			assert_eq!(game.slot_at(snake.head()).player(), player);
+			assert_eq!(game.slot_at(snake.tail()).player(), player);
+		}
+	}
+
+	/// A 9x9 game with its banana somewhere, wrapping on some edges. Its snake is on (3, 4) and (4, 4).
+	fn bearing_game(banana_pos: Pos, horizontal: bool, vertical: bool) -> SnakeGame {
+		let mut game = SnakeGame::with_seed(size(9, 9), 1);
+		game.set_wrap(WrapConfig { horizontal, vertical });
+		game.set_banana(banana_pos, Banana::Yellow).unwrap();
+		game
+	}
+
+	#[test]
+	fn bearing_goes_through_the_seam_when_its_shorter() {
+		// 2 slots away through the left edge, instead of 7 to the right
+		let game = bearing_game(pos(8, 1), true, true);
+		assert_eq!(game.banana_bearing(pos(1, 1)), Some(Direction::Left));
+		assert_eq!(game.banana_bearing(pos(0, 1)), Some(Direction::Left));
+		// and the other way around
+		let game = bearing_game(pos(0, 1), true, true);
+		assert_eq!(game.banana_bearing(pos(7, 1)), Some(Direction::Right));
+
+		// same through the top and bottom edges
+		let game = bearing_game(pos(2, 8), true, true);
+		assert_eq!(game.banana_bearing(pos(2, 0)), Some(Direction::Up));
+		let game = bearing_game(pos(2, 0), true, true);
+		assert_eq!(game.banana_bearing(pos(2, 7)), Some(Direction::Down));
+	}
+
+	#[test]
+	fn bearing_goes_the_long_way_when_the_edge_doesnt_wrap() {
+		let game = bearing_game(pos(8, 1), false, true);
+		assert_eq!(game.banana_bearing(pos(1, 1)), Some(Direction::Right));
+
+		let game = bearing_game(pos(2, 8), true, false);
+		assert_eq!(game.banana_bearing(pos(2, 0)), Some(Direction::Down));
+	}
+
+	#[test]
+	fn bearing_picks_the_longest_axis_first() {
+		// 3 left through the seam and 2 down
+		let game = bearing_game(pos(7, 3), true, true);
+		assert_eq!(game.banana_bearing(pos(1, 1)), Some(Direction::Left));
+		// 1 right and 2 up through the seam
+		let game = bearing_game(pos(2, 8), true, true);
+		assert_eq!(game.banana_bearing(pos(1, 1)), Some(Direction::Up));
+
+		// on a tie, horizontal wins
+		let game = bearing_game(pos(8, 8), true, true);
+		assert_eq!(game.banana_bearing(pos(1, 1)), Some(Direction::Left));
+	}
+
+	#[test]
+	fn bearing_from_the_banana_is_none() {
+		let game = bearing_game(pos(8, 1), true, true);
+		assert_eq!(game.banana_bearing(pos(8, 1)), None);
+	}
+
+	/// A game that ate a red banana, played for a while, then died hitting a wall.
+	fn died_after_eating() -> SnakeGame {
+		let mut game = game();
+		game.set_wrap(WrapConfig {
+			horizontal: false,
+			vertical: false,
+		});
+		game.set_banana(pos(3, 2), Banana::Red).unwrap();
+		game.advance_duration(Duration::from_millis(10));
+		while !game.is_dead() {
+			update(&mut game);
+		}
+		assert_eq!(game.bananas_eaten(), 1);
+		game
+	}
+
+	#[test]
+	fn fresh_restart_zeroes_score_and_time() {
+		let mut game = died_after_eating();
+		assert!(game.score() > 0);
+
+		game.restart(RestartKind::Fresh);
+		assert!(!game.is_dead());
+		assert_eq!(game.score(), 0);
+		assert_eq!(game.bananas_eaten(), 0);
+		assert_eq!(game.tick_count(), 0);
+		assert_eq!(game.duration(), Duration::ZERO);
+	}
+
+	#[test]
+	fn continue_restart_keeps_score_and_time() {
+		let mut game = died_after_eating();
+		let (score, ticks, duration) = (game.score(), game.tick_count(), game.duration());
+		assert!(duration > Duration::ZERO);
+
+		game.restart(RestartKind::Continue);
+		assert!(!game.is_dead());
+		assert_eq!(game.score(), score);
+		assert_eq!(game.bananas_eaten(), 1);
+		assert_eq!(game.tick_count(), ticks);
+		assert_eq!(game.duration(), duration);
+
+		// but the snake is back at the start, as short as it began
+		assert_eq!(game.snake().head(), pos(2, 2));
+		assert_eq!(game.snake().len, 2);
+	}
+
+	#[test]
+	fn heads_face_where_theyre_about_to_go_before_the_first_move() {
+		for initial in DIRECTIONS {
+			for turn in DIRECTIONS.into_iter().filter(|&turn| turn != initial.opposite()) {
+				let mut game = game();
+				game.set_initial_direction(initial);
+				game.restart(RestartKind::Fresh);
+				assert_eq!(game.head_facing(), initial);
+
+				game.change_direction(0, turn);
+				assert_eq!(game.head_facing(), turn, "{initial:?} turning {turn:?}");
+			}
+		}
+	}
+
+	#[test]
+	fn head_sprites_are_rotated_to_face_their_direction() {
+		assert_eq!(
+			CellVisual::snake_head(Direction::Right),
+			CellVisual::SnakeHead(Rotate::R0)
+		);
+		assert_eq!(
+			CellVisual::snake_head(Direction::Down),
+			CellVisual::SnakeHead(Rotate::R90)
+		);
+		assert_eq!(
+			CellVisual::snake_head(Direction::Left),
+			CellVisual::SnakeHead(Rotate::R180)
+		);
+		assert_eq!(
+			CellVisual::snake_head(Direction::Up),
+			CellVisual::SnakeHead(Rotate::R270)
+		);
+	}
+
+	#[test]
+	fn bananas_can_be_put_on_free_slots() {
+		let mut game = game();
+		assert_eq!(game.set_banana(pos(4, 0), Banana::Red), Ok(()));
+		assert_eq!(bananas(&game), [(pos(4, 0), Banana::Red)]);
+
+		// the banana that was there moves, and can be put back on its own slot with another color
+		assert_eq!(game.set_banana(pos(0, 3), Banana::Yellow), Ok(()));
+		assert_eq!(game.set_banana(pos(0, 3), Banana::Cyan), Ok(()));
+		assert_eq!(bananas(&game), [(pos(0, 3), Banana::Cyan)]);
+	}
+
+	#[test]
+	fn bananas_cant_be_put_on_snakes_or_outside() {
+		let mut game = game();
+		game.set_banana(pos(4, 0), Banana::Red).unwrap();
+
+		assert_eq!(
+			game.set_banana(pos(2, 2), Banana::Yellow),
+			Err(SpawnError::Occupied(pos(2, 2)))
+		);
+		assert_eq!(
+			game.set_banana(pos(1, 2), Banana::Yellow),
+			Err(SpawnError::Occupied(pos(1, 2)))
+		);
+		for p in [pos(5, 0), pos(0, 5), pos(-1, 2)] {
+			assert_eq!(game.set_banana(p, Banana::Yellow), Err(SpawnError::OutOfBounds(p)));
+		}
+
+		// the banana stays where it was
+		assert_eq!(bananas(&game), [(pos(4, 0), Banana::Red)]);
+	}
+
+	/// Ten points per banana, doubling for a banana eaten within half a second of the one before it.
+	const COMBO_SCORE: ScoreConfig = ScoreConfig {
+		per_banana: 10,
+		per_second: 0,
+		per_length: 0,
+		combo_window: Duration::from_millis(500),
+		max_combo: 2,
+	};
+
+	/// Puts a banana right in front of the snake, and eats it.
+	fn eat_next_banana(game: &mut SnakeGame) {
+		let next_head = game.wrap_pos(game.snake_head().step(game.direction()));
+		game.set_banana(next_head, Banana::Yellow).unwrap();
+		update(game);
+	}
+
+	/// Score after eating bananas on the slots right in front of the snake, with some time between each of them.
+	fn score_eating(config: ScoreConfig, bananas: usize, time_between: Duration) -> u32 {
+		let mut game = game();
+		game.set_score_config(config);
+		game.set_max_frame_delta(Duration::MAX);
+		for i in 0..bananas {
+			if i > 0 {
+				game.advance_duration(time_between);
+			}
+			eat_next_banana(&mut game);
+		}
+		assert_eq!(game.bananas_eaten(), bananas as u32);
+		game.score()
+	}
+
+	#[test]
+	fn quick_bananas_score_a_combo() {
+		assert_eq!(score_eating(COMBO_SCORE, 2, Duration::from_millis(100)), 10 + 20);
+		assert_eq!(score_eating(COMBO_SCORE, 2, Duration::from_millis(500)), 10 + 20);
+		// up to the max combo
+		assert_eq!(score_eating(COMBO_SCORE, 3, Duration::from_millis(100)), 10 + 20 + 20);
+	}
+
+	#[test]
+	fn slow_bananas_score_no_combo() {
+		assert_eq!(score_eating(COMBO_SCORE, 2, Duration::from_millis(501)), 10 + 10);
+		assert_eq!(score_eating(COMBO_SCORE, 3, Duration::from_secs(2)), 10 + 10 + 10);
+	}
+
+	#[test]
+	fn default_score_is_the_bananas_eaten() {
+		assert_eq!(score_eating(ScoreConfig::default(), 3, Duration::ZERO), 3);
+		assert_eq!(score_eating(ScoreConfig::default(), 2, Duration::from_secs(5)), 2);
+	}
+
+	#[test]
+	fn score_counts_time_and_length() {
+		let mut game = game();
+		game.set_score_config(ScoreConfig {
+			per_banana: 0,
+			per_second: 3,
+			per_length: 5,
+			..ScoreConfig::default()
+		});
+		game.set_max_frame_delta(Duration::MAX);
+		assert_eq!(game.score(), 2 * 5);
+
+		// only full seconds count
+		game.advance_duration(Duration::from_millis(2500));
+		assert_eq!(game.score(), 2 * 3 + 2 * 5);
+	}
+
+	#[test]
+	fn stepping_a_clone_leaves_the_original_alone() {
+		let mut game = game();
+		game.set_banana(pos(3, 2), Banana::Yellow).unwrap();
+		let checksum = game.checksum();
+
+		let mut clone = game.clone();
+		assert_eq!(clone.checksum(), checksum);
+		clone.change_direction(0, Direction::Down);
+		update(&mut clone);
+		update(&mut clone);
+		assert_eq!(clone.snake_head(), pos(2, 4));
+		assert_eq!(game.checksum(), checksum);
+
+		// and the other way around
+		update(&mut game);
+		assert_eq!(game.bananas_eaten(), 1);
+		assert_eq!(clone.bananas_eaten(), 0);
+		assert_eq!(clone.snake_head(), pos(2, 4));
+	}
+
+	#[test]
+	fn clones_get_a_copy_of_the_rng() {
+		let mut game = game();
+		game.set_banana(pos(3, 2), Banana::Yellow).unwrap();
+		let mut clone = game.clone();
+
+		// eating places the next banana with the RNG, so both place the same one on the same slot
+		update(&mut game);
+		update(&mut clone);
+		assert_eq!(bananas(&game).len(), 1);
+		assert_eq!(bananas(&game), bananas(&clone));
+		assert_eq!(game.checksum(), clone.checksum());
+	}
+
+	#[test]
+	fn tick_count_goes_up_once_per_update() {
+		let mut game = game();
+		game.set_banana(pos(0, 0), Banana::Yellow).unwrap();
+		assert_eq!(game.tick_count(), 0);
+		for ticks in 1..=4 {
+			update(&mut game);
+			assert_eq!(game.tick_count(), ticks);
+		}
+
+		game.restart(RestartKind::Fresh);
+		assert_eq!(game.tick_count(), 0);
+		update(&mut game);
+		assert_eq!(game.tick_count(), 1);
+	}
+
+	#[test]
+	fn tick_count_stops_once_dead() {
+		let mut game = died_after_eating();
+		let ticks = game.tick_count();
+		assert!(ticks > 0);
+		update(&mut game);
+		update(&mut game);
+		assert_eq!(game.tick_count(), ticks);
+	}
+
+	#[test]
+	fn single_slot_snakes_get_their_own_visual() {
+		let mut game = game();
+		game.set_banana(pos(0, 0), Banana::Yellow).unwrap();
+		assert_eq!(game.shrink(0, 1), 1);
+		assert_eq!(game.slot_at(pos(2, 2)).snake_role(), Some(SnakeRole::SingleCell));
+
+		let snake_cells = |game: &SnakeGame| -> Vec<(Pos, CellVisual)> {
+			let model = game.render_model();
+			(model.cells.into_iter())
+				.filter(|(_, visual)| !matches!(visual, CellVisual::Banana(_)))
+				.collect()
+		};
+		assert_eq!(
+			snake_cells(&game),
+			[(pos(2, 2), CellVisual::snake_single(Direction::Right))]
+		);
+
+		// it stays a single slot as it moves around
+		game.change_direction(0, Direction::Down);
+		update(&mut game);
+		assert_eq!(game.slot_at(pos(2, 3)).snake_role(), Some(SnakeRole::SingleCell));
+		assert_eq!(
+			snake_cells(&game),
+			[(pos(2, 3), CellVisual::snake_single(Direction::Down))]
+		);
+	}
+
+	#[test]
+	fn full_playfields_go_without_a_banana() {
+		// the snake takes up the whole playfield, so there's nowhere to put a banana
+		let mut game = SnakeGame::with_seed(size(2, 1), 1);
+		assert_eq!(bananas(&game), []);
+		assert_eq!(game.missing_bananas(), 1);
+		assert!(!game
+			.render_model()
+			.cells
+			.iter()
+			.any(|(_, visual)| matches!(visual, CellVisual::Banana(_))));
+
+		// it can still go around, following its tail
+		for _ in 0..3 {
+			assert_eq!(update(&mut game), []);
+			assert!(!game.is_dead() && !game.is_won());
+			assert_eq!(bananas(&game), []);
+		}
+	}
+
+	#[test]
+	fn missing_bananas_are_placed_once_theres_room() {
+		let mut game = SnakeGame::with_seed(size(2, 1), 1);
+		game.shrink(0, 1);
+		assert_eq!(bananas(&game), []);
+
+		update(&mut game);
+		assert_eq!(game.missing_bananas(), 0);
+		assert_eq!(bananas(&game).len(), 1);
+		assert_ne!(bananas(&game)[0].0, game.snake_head());
+	}
+
+	#[test]
+	fn stepping_n_times_is_the_same_as_updating_n_times() {
+		let mut stepped = SnakeGame::with_seed(size(9, 9), 7);
+		let mut updated = stepped.clone();
+		stepped.change_direction(0, Direction::Down);
+		updated.change_direction(0, Direction::Down);
+
+		let mut stepped_events = Vec::new();
+		stepped.step_n(5, &mut stepped_events);
+		let mut updated_events = Vec::new();
+		for _ in 0..5 {
+			updated.update(&mut updated_events);
+		}
+
+		assert_eq!(stepped.tick_count(), 5);
+		assert_eq!(stepped.checksum(), updated.checksum());
+		assert_eq!(stepped_events, updated_events);
+
+		stepped.step_n(0, &mut stepped_events);
+		assert_eq!(stepped.tick_count(), 5);
+	}
+
+	#[test]
+	fn resizing_restarts_on_the_new_playfield() {
+		let mut game = game();
+		game.set_banana(pos(3, 2), Banana::Yellow).unwrap();
+		update(&mut game);
+		assert_eq!(game.bananas_eaten(), 1);
+
+		game.resize(size(8, 6));
+		assert_eq!(game.size(), size(8, 6));
+		assert_eq!(game.bananas_eaten(), 0);
+		assert_eq!(game.snake_head(), pos(4, 3));
+		assert_eq!(game.validate(), Ok(()));
+
+		// and restarts keep the new size
+		game.restart(RestartKind::Fresh);
+		assert_eq!(game.size(), size(8, 6));
+		assert_eq!(bananas(&game).len(), 1);
+	}
+
+	#[test]
+	fn bananas_within_the_combo_window_build_the_combo() {
+		let mut game = game();
+		game.set_score_config(ScoreConfig {
+			max_combo: 5,
+			..COMBO_SCORE
+		});
+		assert_eq!(game.current_combo(), 0);
+
+		for combo in 1..=3 {
+			eat_next_banana(&mut game);
+			assert_eq!(game.current_combo(), combo);
+			game.advance_duration(Duration::from_millis(200));
+		}
+		assert_eq!(game.snake().combo(), 3);
+	}
+
+	#[test]
+	fn bananas_after_the_combo_window_restart_the_combo() {
+		let mut game = game();
+		game.set_score_config(COMBO_SCORE);
+		game.set_max_frame_delta(Duration::MAX);
+		eat_next_banana(&mut game);
+		game.advance_duration(Duration::from_millis(200));
+		eat_next_banana(&mut game);
+		assert_eq!(game.current_combo(), 2);
+
+		// once the window passed, the combo's gone
+		game.advance_duration(Duration::from_millis(500));
+		assert_eq!(game.current_combo(), 2);
+		game.advance_duration(Duration::from_millis(1));
+		assert_eq!(game.current_combo(), 0);
+		assert_eq!(game.snake().combo(), 2);
+
+		eat_next_banana(&mut game);
+		assert_eq!(game.current_combo(), 1);
+	}
+	#[test]
+	fn bench_runs_complete_at_a_sane_speed() {
+		let run = SnakeGame::bench_run(1, 10_000);
+		assert_eq!(run.ticks, 10_000);
+		let tps = run.ticks_per_second();
+		assert!(tps.is_finite() && tps > 0., "{run:?} gave {tps} ticks per second");
+
+		assert_eq!(SnakeGame::bench_run(1, 0).ticks_per_second(), 0.);
+	}
+
+	#[test]
+	fn bench_run_durations_never_divide_by_zero() {
+		let run = BenchRun {
+			ticks: 10,
+			elapsed: Duration::ZERO,
+		};
+		assert!(run.ticks_per_second().is_finite());
+	}
+
+	#[test]
+	fn body_segments_go_from_head_to_tail_one_step_at_a_time() {
+		let game = long_game();
+		let segments = game.body_segments();
+		assert_eq!(
+			segments,
+			[pos(0, 3), pos(0, 2), pos(4, 2), pos(3, 2), pos(2, 2), pos(1, 2)]
+		);
+		assert_eq!(segments.len(), game.snake_len());
+
+		// including through the edge, between (0, 2) and (4, 2)
+		for pair in segments.windows(2) {
+			let adjacent = DIRECTIONS
+				.iter()
+				.any(|&direction| game.wrap_pos(pair[0].step(direction)) == pair[1]);
+			assert!(adjacent, "{:?} and {:?} aren't next to each other", pair[0], pair[1]);
+		}
+	}
+
+	#[test]
+	fn slots_in_a_direction_wrap_around_like_the_game() {
+		let mut game = game();
+		let is_slot_at = |game: &SnakeGame, slot: Option<&Slot>, p: Pos| {
+			slot.is_some_and(|slot| std::ptr::eq(slot, &game.playfield[game.slot_index(p)]))
+		};
+
+		// the snake's head is in the middle, with its tail on its left
+		assert!(is_slot_at(
+			&game,
+			game.slot_in_direction(pos(2, 2), Direction::Up),
+			pos(2, 1)
+		));
+		let tail = game.slot_in_direction(pos(2, 2), Direction::Left);
+		assert_eq!(tail.and_then(Slot::snake_role), Some(SnakeRole::Tail));
+
+		assert!(is_slot_at(
+			&game,
+			game.slot_in_direction(pos(0, 3), Direction::Left),
+			pos(4, 3)
+		));
+		assert!(is_slot_at(
+			&game,
+			game.slot_in_direction(pos(1, 4), Direction::Down),
+			pos(1, 0)
+		));
+
+		game.set_wrap(WrapConfig {
+			horizontal: false,
+			vertical: true,
+		});
+		assert!(game.slot_in_direction(pos(0, 3), Direction::Left).is_none());
+		assert!(game.slot_in_direction(pos(4, 0), Direction::Right).is_none());
+		assert!(is_slot_at(
+			&game,
+			game.slot_in_direction(pos(1, 4), Direction::Down),
+			pos(1, 0)
+		));
+		assert!(is_slot_at(
+			&game,
+			game.slot_in_direction(pos(0, 3), Direction::Right),
+			pos(1, 3)
+		));
+	}
+
+	/// Changes a slot of a game by hand, behind its back.
+	fn corrupt(game: &mut SnakeGame, p: Pos, change: impl FnOnce(&mut Slot)) {
+		let index = game.slot_index(p);
+		change(&mut game.playfield[index]);
+	}
+
+	#[test]
+	fn games_played_normally_are_valid() {
+		assert_eq!(game().validate(), Ok(()));
+		assert_eq!(long_game().validate(), Ok(()));
+		assert_eq!(two_player_game(5).validate(), Ok(()));
+
+		let mut game = SnakeGame::with_seed(size(9, 9), 3);
+		for i in 0..200 {
+			if game.is_dead() || game.is_won() {
+				game.restart(RestartKind::Fresh);
+			}
+			if let Some(direction) = game.banana_bearing(game.snake_head()) {
+				game.change_direction(0, direction);
+			}
+			update(&mut game);
+			assert_eq!(game.validate(), Ok(()), "after {i} updates");
+		}
+	}
+
+	#[test]
+	fn snakes_not_where_they_say_they_are_are_invalid() {
+		let mut game = long_game();
+		game.snakes[0].head = pos(2, 4);
+		assert_eq!(
+			game.validate(),
+			Err(IntegrityError::MissingHead {
+				player: 0,
+				pos: pos(2, 4)
+			})
+		);
+
+		let mut game = long_game();
+		game.snakes[0].tail = pos(3, 3);
+		assert_eq!(
+			game.validate(),
+			Err(IntegrityError::MissingTail {
+				player: 0,
+				pos: pos(1, 2)
+			})
+		);
+	}
+
+	#[test]
+	fn broken_bodies_are_invalid() {
+		// (3, 2) leads up, where there's nothing
+		let mut game = long_game();
+		corrupt(&mut game, pos(3, 2), |slot| slot.set_direction_prev(Direction::Up));
+		assert_eq!(
+			game.validate(),
+			Err(IntegrityError::Discontinuous {
+				player: 0,
+				pos: pos(3, 2)
+			})
+		);
+
+		// a body part where there should be the tail
+		let mut game = long_game();
+		corrupt(&mut game, pos(1, 2), |slot| slot.set_snake_head());
+		assert_eq!(
+			game.validate(),
+			Err(IntegrityError::MissingTail {
+				player: 0,
+				pos: pos(1, 2)
+			})
+		);
+	}
+
+	#[test]
+	fn snakes_going_through_themselves_are_invalid() {
+		// a snake taking up a whole row, with its tail turned into a body part that leads back to its head
+		let mut game = game();
+		for banana in [pos(3, 2), pos(4, 2), pos(0, 2)] {
+			game.set_banana(banana, Banana::Yellow).unwrap();
+			update(&mut game);
 		}
+		game.set_banana(pos(0, 0), Banana::Yellow).unwrap();
+		assert_eq!(game.validate(), Ok(()));
+
+		corrupt(&mut game, pos(1, 2), |slot| {
+			slot.set_snake_head();
+			slot.set_direction_prev(Direction::Left);
+		});
+		corrupt(&mut game, pos(0, 2), |slot| slot.set_direction_next(Direction::Right));
+		game.snakes[0].len += 1;
+		assert_eq!(game.validate(), Err(IntegrityError::Overlap(pos(0, 2))));
+	}
+
+	#[test]
+	fn stray_snake_parts_and_buried_bananas_are_invalid() {
+		let mut game = long_game();
+		corrupt(&mut game, pos(3, 4), |slot| slot.set_snake_head());
+		assert_eq!(game.validate(), Err(IntegrityError::StraySnake(pos(3, 4))));
+
+		let mut game = long_game();
+		corrupt(&mut game, pos(3, 2), |slot| slot.set_banana(Some(Banana::Red)));
+		assert_eq!(game.validate(), Err(IntegrityError::BananaOnSnake(pos(3, 2))));
+	}
+
+	#[test]
+	#[cfg(debug_assertions)]
+	#[should_panic(expected = "The game got into an invalid state")]
+	fn updates_catch_invalid_states_in_debug_builds() {
+		let mut game = long_game();
+		corrupt(&mut game, pos(3, 2), |slot| slot.set_banana(Some(Banana::Red)));
+		update(&mut game);
+	}
+
+	#[test]
+	fn animation_phase_goes_from_one_update_to_the_next() {
+		let mut game = game();
+		game.set_banana(pos(0, 0), Banana::Yellow).unwrap();
+		let tick = Duration::from_secs_f32(1. / game.current_tps());
+		let mut events = Vec::new();
+		assert_eq!(game.animation_phase(), 0.);
+
+		assert_eq!(game.advance(tick / 4, &mut events), 0);
+		assert!(
+			(game.animation_phase() - 0.25).abs() < 0.01,
+			"{}",
+			game.animation_phase()
+		);
+		assert_eq!(game.advance(tick / 2, &mut events), 0);
+		assert!(
+			(game.animation_phase() - 0.75).abs() < 0.01,
+			"{}",
+			game.animation_phase()
+		);
+
+		// updating starts it over
+		assert_eq!(game.advance(tick / 2, &mut events), 1);
+		assert!(
+			(game.animation_phase() - 0.25).abs() < 0.01,
+			"{}",
+			game.animation_phase()
+		);
+	}
+
+	#[test]
+	fn parts_on_either_side_of_a_side_seam_connect_across_it() {
+		let game = long_game();
+		let cells = game.render_model().cells;
+		let visual = |p| cells.iter().find(|&&(q, _)| q == p).map(|&(_, visual)| visual);
+
+		// going right off the right edge, and turning down right after coming back on the left one
+		assert_eq!(
+			visual(pos(4, 2)),
+			Some(CellVisual::SnakeStraight(facing_rotate(Direction::Right)))
+		);
+		assert_eq!(visual(pos(0, 2)), Some(CellVisual::SnakeCorner(Rotate::R90)));
+		assert_eq!(visual(pos(0, 3)), Some(CellVisual::snake_head(Direction::Down)));
+	}
+
+	#[test]
+	fn parts_on_either_side_of_a_top_seam_connect_across_it() {
+		let mut game = game();
+		game.set_banana(pos(2, 1), Banana::Yellow).unwrap();
+		game.change_direction(0, Direction::Up);
+		update(&mut game);
+		game.set_banana(pos(2, 0), Banana::Yellow).unwrap();
+		update(&mut game);
+		game.set_banana(pos(4, 4), Banana::Yellow).unwrap();
+		update(&mut game);
+		assert_eq!(game.body_segments(), [pos(2, 4), pos(2, 0), pos(2, 1), pos(2, 2)]);
+
+		let straight = CellVisual::SnakeStraight(facing_rotate(Direction::Up));
+		assert_eq!(
+			game.render_model().cells,
+			[
+				(pos(2, 0), straight),
+				(pos(2, 1), straight),
+				(pos(2, 2), CellVisual::SnakeTail(Rotate::R0)),
+				(pos(2, 4), CellVisual::snake_head(Direction::Up)),
+				(pos(4, 4), CellVisual::Banana(Banana::Yellow)),
+			]
+		);
 	}
 }
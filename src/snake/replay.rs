@@ -0,0 +1,234 @@
+use crate::snake::{Direction, GameEvent, RenderModel, SnakeGame};
+
+/// A turn asked for during a recorded run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayInput {
+	/// Tick count of the game when the turn was asked for, so it's taken on the update after it.
+	pub tick: u64,
+	pub player: usize,
+	pub direction: Direction,
+}
+
+/// A recorded run, as the game it started from and every turn asked for during it.
+///
+/// The game is deterministic once its RNG is set, so playing the same turns on the same ticks always gets to the
+/// same run.
+#[derive(Clone)]
+pub struct Replay {
+	start: SnakeGame,
+	inputs: Vec<ReplayInput>,
+	/// Number of ticks the run lasted.
+	ticks: u64,
+	score: u32,
+}
+
+impl Replay {
+	/// Score the run ended with.
+	pub fn score(&self) -> u32 {
+		self.score
+	}
+
+	/// Number of ticks the run lasted.
+	#[allow(unused)]
+	pub fn ticks(&self) -> u64 {
+		self.ticks
+	}
+
+	#[allow(unused)]
+	pub fn inputs(&self) -> &[ReplayInput] {
+		&self.inputs
+	}
+
+	/// Whether the run could be raced against in `game`, which needs the same playfield.
+	pub fn fits(&self, game: &SnakeGame) -> bool {
+		self.start.size() == game.size()
+	}
+}
+
+/// Records a run as it's played, to make a [`Replay`] out of it.
+pub struct ReplayRecorder {
+	start: SnakeGame,
+	inputs: Vec<ReplayInput>,
+}
+
+impl ReplayRecorder {
+	/// Starts recording a run of the game from how it is right now, usually right after it (re)started.
+	pub fn new(game: &SnakeGame) -> Self {
+		Self {
+			start: game.clone(),
+			inputs: Vec::new(),
+		}
+	}
+
+	/// Turns a player's snake with [`SnakeGame::change_direction`], recording the turn if it changed anything.
+	pub fn change_direction(&mut self, game: &mut SnakeGame, player: usize, direction: Direction) {
+		let before = game.snakes()[player].direction();
+		game.change_direction(player, direction);
+
+		if game.snakes()[player].direction() != before {
+			self.inputs.push(ReplayInput {
+				tick: game.tick_count(),
+				player,
+				direction,
+			});
+		}
+	}
+
+	/// The run recorded so far, ending at where `game` is now.
+	pub fn finish(&self, game: &SnakeGame) -> Replay {
+		Replay {
+			start: self.start.clone(),
+			inputs: self.inputs.clone(),
+			ticks: game.tick_count(),
+			score: game.score(),
+		}
+	}
+}
+
+/// A replay playing back on its own game, to show it next to a live one.
+///
+/// It's a separate game, so it never gets in the way of the live snakes.
+pub struct Ghost {
+	game: SnakeGame,
+	inputs: Vec<ReplayInput>,
+	/// Index of the next input to play.
+	next_input: usize,
+	events: Vec<GameEvent>,
+}
+
+impl Ghost {
+	pub fn new(replay: &Replay) -> Self {
+		Self {
+			game: replay.start.clone(),
+			inputs: replay.inputs.clone(),
+			next_input: 0,
+			events: Vec::new(),
+		}
+	}
+
+	/// Plays the replay until its game reaches some tick count, or until its run is over.
+	///
+	/// It can't go back, so a tick count it's already past does nothing.
+	pub fn sync_to(&mut self, tick: u64) {
+		while self.game.tick_count() < tick && !self.game.is_dead() && !self.game.is_won() {
+			let now = self.game.tick_count();
+			while let Some(input) = self.inputs.get(self.next_input).filter(|input| input.tick <= now) {
+				self.game.change_direction(input.player, input.direction);
+				self.next_input += 1;
+			}
+
+			self.events.clear();
+			self.game.update(&mut self.events);
+		}
+	}
+
+	/// The game the replay is playing on.
+	#[allow(unused)]
+	pub fn game(&self) -> &SnakeGame {
+		&self.game
+	}
+
+	pub fn render_model(&self) -> RenderModel {
+		self.game.render_model()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::math::pos::Pos;
+	use crate::math::size::size;
+
+	/// Records a run that steers towards bananas, giving its replay and the snake's body after every tick.
+	fn recorded_run(ticks: u64) -> (Replay, Vec<Vec<Pos>>) {
+		let mut game = SnakeGame::with_seed(size(9, 9), 5);
+		let mut recorder = ReplayRecorder::new(&game);
+		let mut bodies = vec![game.body_segments()];
+		let mut events = Vec::new();
+
+		while game.tick_count() < ticks && !game.is_dead() {
+			if let Some(direction) = game.banana_bearing(game.snake_head()) {
+				recorder.change_direction(&mut game, 0, direction);
+			}
+			game.update(&mut events);
+			bodies.push(game.body_segments());
+		}
+
+		assert!(game.bananas_eaten() > 0, "the run never ate");
+		(recorder.finish(&game), bodies)
+	}
+
+	#[test]
+	fn ghosts_are_where_the_run_was_at_every_tick() {
+		let (replay, bodies) = recorded_run(60);
+		assert_eq!(replay.ticks(), bodies.len() as u64 - 1);
+		assert!(!replay.inputs().is_empty());
+
+		let mut ghost = Ghost::new(&replay);
+		for (tick, body) in bodies.iter().enumerate() {
+			ghost.sync_to(tick as u64);
+			assert_eq!(ghost.game().tick_count(), tick as u64);
+			assert_eq!(&ghost.game().body_segments(), body, "tick {tick}");
+		}
+		assert_eq!(ghost.game().score(), replay.score());
+	}
+
+	#[test]
+	fn ghosts_can_skip_ahead_but_not_go_back() {
+		let (replay, bodies) = recorded_run(40);
+
+		let mut ghost = Ghost::new(&replay);
+		ghost.sync_to(25);
+		assert_eq!(ghost.game().body_segments(), bodies[25]);
+		ghost.sync_to(10);
+		assert_eq!(ghost.game().tick_count(), 25);
+	}
+
+	#[test]
+	fn only_turns_that_change_something_are_recorded() {
+		let mut game = SnakeGame::with_seed(size(9, 9), 5);
+		let mut recorder = ReplayRecorder::new(&game);
+
+		// going the same way, or back where it came from
+		recorder.change_direction(&mut game, 0, Direction::Right);
+		recorder.change_direction(&mut game, 0, Direction::Left);
+		assert_eq!(recorder.finish(&game).inputs(), []);
+
+		recorder.change_direction(&mut game, 0, Direction::Up);
+		assert_eq!(
+			recorder.finish(&game).inputs(),
+			[ReplayInput {
+				tick: 0,
+				player: 0,
+				direction: Direction::Up,
+			}]
+		);
+	}
+
+	#[test]
+	fn replays_only_fit_games_of_the_same_size() {
+		let (replay, _) = recorded_run(10);
+		assert!(replay.fits(&SnakeGame::with_seed(size(9, 9), 99)));
+		assert!(!replay.fits(&SnakeGame::with_seed(size(9, 8), 5)));
+	}
+
+	#[test]
+	fn ghosts_never_get_in_the_way_of_the_live_game() {
+		let (replay, bodies) = recorded_run(40);
+		let mut ghost = Ghost::new(&replay);
+
+		// the live game starts on the same cells as the ghost, and goes its own way
+		let mut live = SnakeGame::with_seed(size(9, 9), 5);
+		let mut alone = live.clone();
+		let mut events = Vec::new();
+		for tick in 1..=replay.ticks() {
+			live.update(&mut events);
+			alone.update(&mut events);
+			ghost.sync_to(tick);
+
+			assert_eq!(live.body_segments(), alone.body_segments());
+			assert_eq!(live.is_dead(), alone.is_dead());
+			assert_eq!(ghost.game().body_segments(), bodies[tick as usize]);
+		}
+	}
+}
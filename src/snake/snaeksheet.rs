@@ -1,7 +1,13 @@
+use std::error::Error;
+use std::fmt;
+
 use crate::math::rect::Rect;
+use crate::math::size::Size;
 use crate::render::sprite::{NineSlicingSprite, Sprite};
+use crate::render::Rotate;
+use crate::snake::{Banana, CellVisual};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[repr(C)]
 pub struct SnaekSheet {
 	/// Mouse cursor
@@ -15,6 +21,8 @@ pub struct SnaekSheet {
 	pub snake_gay: Sprite,
 	/// End of the snake
 	pub snake_end: Sprite,
+	/// Snake that's only one slot long, curled up on itself
+	pub snake_single: Sprite,
 	/// Tongue of the snake
 	pub snake_tongue: Sprite,
 
@@ -82,6 +90,7 @@ pub fn snaek_sheet() -> SnaekSheet {
 		snake_straight:     Sprite::new(Rect::from_xywh(  7,   0,  7,  7)),
 		snake_gay:          Sprite::new(Rect::from_xywh(  0,   0,  7,  7)),
 		snake_end:          Sprite::new(Rect::from_xywh(  0,   7,  7,  7)),
+		snake_single:       Sprite::new(Rect::from_xywh( 76,   0,  7,  7)),
 		snake_tongue:       Sprite::new(Rect::from_xywh( 21,   2,  3,  3)),
 
 		banana_yellow:      Sprite::new(Rect::from_xywh(  7,   7,  7,  7)),
@@ -136,3 +145,459 @@ pub fn snaek_sheet() -> SnaekSheet {
 		                    Sprite::new(Rect::from_xywh( 68,  14,  8, 14)) ],
 	}
 }
+
+/// One of the single sprites of a [`SnaekSheet`], to pick sprites from data rather than by field.
+///
+/// Digits and nine-slices aren't in it: digits are already indexed through their arrays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[allow(unused)]
+pub enum SpriteName {
+	Cursor,
+	SnakeHead,
+	SnakeStraight,
+	SnakeGay,
+	SnakeEnd,
+	SnakeSingle,
+	SnakeTongue,
+	BananaYellow,
+	BananaRed,
+	BananaCyan,
+	CarvedSepLine,
+	SnaekIcon,
+	IconMinimize,
+	IconClose,
+	IconPlay,
+	IconDebug,
+	IconRestart,
+	NumBang,
+	NumColon,
+	BignumPlaceholder,
+}
+
+#[allow(unused)]
+impl SpriteName {
+	/// Every sprite name, in the sheet's order.
+	pub const ALL: [SpriteName; 20] = [
+		SpriteName::Cursor,
+		SpriteName::SnakeHead,
+		SpriteName::SnakeStraight,
+		SpriteName::SnakeGay,
+		SpriteName::SnakeEnd,
+		SpriteName::SnakeSingle,
+		SpriteName::SnakeTongue,
+		SpriteName::BananaYellow,
+		SpriteName::BananaRed,
+		SpriteName::BananaCyan,
+		SpriteName::CarvedSepLine,
+		SpriteName::SnaekIcon,
+		SpriteName::IconMinimize,
+		SpriteName::IconClose,
+		SpriteName::IconPlay,
+		SpriteName::IconDebug,
+		SpriteName::IconRestart,
+		SpriteName::NumBang,
+		SpriteName::NumColon,
+		SpriteName::BignumPlaceholder,
+	];
+
+	/// Name of the sprite's region in atlas definitions (see [`SnaekSheet::from_atlas`]).
+	pub const fn name(self) -> &'static str {
+		match self {
+			SpriteName::Cursor => "cursor",
+			SpriteName::SnakeHead => "snake_head",
+			SpriteName::SnakeStraight => "snake_straight",
+			SpriteName::SnakeGay => "snake_gay",
+			SpriteName::SnakeEnd => "snake_end",
+			SpriteName::SnakeSingle => "snake_single",
+			SpriteName::SnakeTongue => "snake_tongue",
+			SpriteName::BananaYellow => "banana_yellow",
+			SpriteName::BananaRed => "banana_red",
+			SpriteName::BananaCyan => "banana_cyan",
+			SpriteName::CarvedSepLine => "carved_sep_line",
+			SpriteName::SnaekIcon => "snaek_icon",
+			SpriteName::IconMinimize => "icon_minimize",
+			SpriteName::IconClose => "icon_close",
+			SpriteName::IconPlay => "icon_play",
+			SpriteName::IconDebug => "icon_debug",
+			SpriteName::IconRestart => "icon_restart",
+			SpriteName::NumBang => "num_bang",
+			SpriteName::NumColon => "num_colon",
+			SpriteName::BignumPlaceholder => "bignum_placeholder",
+		}
+	}
+}
+
+/// Error when loading a spritesheet atlas definition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AtlasError {
+	/// A line couldn't be parsed as `name = [numbers...]`.
+	Syntax { line: usize },
+	/// There's no region with that name in the sheet.
+	UnknownRegion { line: usize, name: String },
+	/// The region was given the wrong amount of numbers (4 for sprites, 8 for nine-slices).
+	WrongArity { line: usize, name: String },
+	/// The region doesn't fit inside the spritesheet, or its nine-slice bars don't fit inside the region.
+	OutOfBounds { name: String },
+}
+
+impl fmt::Display for AtlasError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Syntax { line } => write!(f, "line {line}: expected `name = [numbers...]`"),
+			Self::UnknownRegion { line, name } => write!(f, "line {line}: unknown region `{name}`"),
+			Self::WrongArity { line, name } => {
+				write!(f, "line {line}: wrong amount of numbers for region `{name}`")
+			}
+			Self::OutOfBounds { name } => write!(f, "region `{name}` doesn't fit inside the spritesheet"),
+		}
+	}
+}
+
+impl Error for AtlasError {}
+
+/// A named region of the sheet, as found in atlas definitions.
+enum Region<'a> {
+	Sprite(&'a mut Sprite),
+	NineSlice(&'a mut NineSlicingSprite),
+}
+
+impl SnaekSheet {
+	/// Sprite and rotation to draw a cell visual with.
+	pub fn cell_sprite(&self, visual: CellVisual) -> (Sprite, Rotate) {
+		match visual {
+			CellVisual::SnakeHead(rotate) => (self.snake_head, rotate),
+			CellVisual::SnakeSingle(rotate) => (self.snake_single, rotate),
+			CellVisual::SnakeStraight(rotate) => (self.snake_straight, rotate),
+			CellVisual::SnakeCorner(rotate) => (self.snake_gay, rotate),
+			CellVisual::SnakeTail(rotate) => (self.snake_end, rotate),
+			CellVisual::Banana(Banana::Yellow) => (self.banana_yellow, Rotate::R0),
+			CellVisual::Banana(Banana::Red) => (self.banana_red, Rotate::R0),
+			CellVisual::Banana(Banana::Cyan) => (self.banana_cyan, Rotate::R0),
+		}
+	}
+
+	/// The sprite with some name.
+	#[allow(unused)]
+	pub fn get(&self, name: SpriteName) -> Sprite {
+		match name {
+			SpriteName::Cursor => self.cursor,
+			SpriteName::SnakeHead => self.snake_head,
+			SpriteName::SnakeStraight => self.snake_straight,
+			SpriteName::SnakeGay => self.snake_gay,
+			SpriteName::SnakeEnd => self.snake_end,
+			SpriteName::SnakeSingle => self.snake_single,
+			SpriteName::SnakeTongue => self.snake_tongue,
+			SpriteName::BananaYellow => self.banana_yellow,
+			SpriteName::BananaRed => self.banana_red,
+			SpriteName::BananaCyan => self.banana_cyan,
+			SpriteName::CarvedSepLine => self.carved_sep_line,
+			SpriteName::SnaekIcon => self.snaek_icon,
+			SpriteName::IconMinimize => self.icon_minimize,
+			SpriteName::IconClose => self.icon_close,
+			SpriteName::IconPlay => self.icon_play,
+			SpriteName::IconDebug => self.icon_debug,
+			SpriteName::IconRestart => self.icon_restart,
+			SpriteName::NumBang => self.num_bang,
+			SpriteName::NumColon => self.num_colon,
+			SpriteName::BignumPlaceholder => self.bignum_placeholder,
+		}
+	}
+
+	/// Loads the sprite regions from an atlas definition, on top of the default [`snaek_sheet`].
+	///
+	/// The definition is a small subset of TOML: one `name = [x, y, w, h]` line per sprite, and
+	/// `name = [x, y, w, h, vl, vr, ht, hb]` per nine-slice. Array sprites are named like `nums.3`.
+	/// Regions that aren't mentioned keep their default rects.
+	pub fn from_atlas(src: &str, sheet_size: Size) -> Result<Self, AtlasError> {
+		let mut sheet = snaek_sheet();
+
+		for (i, line) in src.lines().enumerate() {
+			let line_nr = i + 1;
+
+			let line = line.split('#').next().unwrap_or_default().trim();
+			if line.is_empty() {
+				continue;
+			}
+
+			let (name, nums) = parse_atlas_line(line).ok_or(AtlasError::Syntax { line: line_nr })?;
+
+			let mut found = false;
+			let mut arity_ok = true;
+			sheet.for_each_region(|region_name, region| {
+				if region_name != name {
+					return;
+				}
+
+				found = true;
+				match (region, nums.as_slice()) {
+					(Region::Sprite(sprite), &[x, y, w, h]) => {
+						*sprite = Sprite::new(Rect::from_xywh(x as i16, y as i16, w, h));
+					}
+					(Region::NineSlice(nss), &[x, y, w, h, vl, vr, ht, hb]) => {
+						*nss = NineSlicingSprite::new(Rect::from_xywh(x as i16, y as i16, w, h), vl, vr, ht, hb);
+					}
+					_ => arity_ok = false,
+				}
+			});
+
+			if !found {
+				return Err(AtlasError::UnknownRegion {
+					line: line_nr,
+					name: name.to_owned(),
+				});
+			}
+			if !arity_ok {
+				return Err(AtlasError::WrongArity {
+					line: line_nr,
+					name: name.to_owned(),
+				});
+			}
+		}
+
+		let mut out_of_bounds = None;
+		sheet.for_each_region(|name, region| {
+			if out_of_bounds.is_some() {
+				return;
+			}
+
+			let fits = match region {
+				Region::Sprite(sprite) => rect_fits(sprite.rect, sheet_size),
+				Region::NineSlice(nss) => {
+					let rect = nss.sprite.rect;
+					rect_fits(rect, sheet_size)
+						&& (nss.vl <= nss.vr && nss.vr <= rect.w)
+						&& (nss.ht <= nss.hb && nss.hb <= rect.h)
+				}
+			};
+
+			if !fits {
+				out_of_bounds = Some(name.to_owned());
+			}
+		});
+
+		match out_of_bounds {
+			Some(name) => Err(AtlasError::OutOfBounds { name }),
+			None => Ok(sheet),
+		}
+	}
+
+	/// Calls `f` on every named region of the sheet.
+	fn for_each_region(&mut self, mut f: impl FnMut(&str, Region)) {
+		f("cursor", Region::Sprite(&mut self.cursor));
+
+		f("snake_head", Region::Sprite(&mut self.snake_head));
+		f("snake_straight", Region::Sprite(&mut self.snake_straight));
+		f("snake_gay", Region::Sprite(&mut self.snake_gay));
+		f("snake_end", Region::Sprite(&mut self.snake_end));
+		f("snake_single", Region::Sprite(&mut self.snake_single));
+		f("snake_tongue", Region::Sprite(&mut self.snake_tongue));
+
+		f("banana_yellow", Region::Sprite(&mut self.banana_yellow));
+		f("banana_red", Region::Sprite(&mut self.banana_red));
+		f("banana_cyan", Region::Sprite(&mut self.banana_cyan));
+
+		f("box_playfield", Region::NineSlice(&mut self.box_playfield));
+		f("box_big_carved", Region::NineSlice(&mut self.box_big_carved));
+		f("box_num_display", Region::NineSlice(&mut self.box_num_display));
+		f("box_text_input", Region::NineSlice(&mut self.box_text_input));
+		f("box_embossed", Region::NineSlice(&mut self.box_embossed));
+		f("box_carved", Region::NineSlice(&mut self.box_carved));
+		f("box_green", Region::NineSlice(&mut self.box_green));
+		f("box_red", Region::NineSlice(&mut self.box_red));
+
+		f("carved_sep_line", Region::Sprite(&mut self.carved_sep_line));
+
+		f("snaek_icon", Region::Sprite(&mut self.snaek_icon));
+
+		f("icon_minimize", Region::Sprite(&mut self.icon_minimize));
+		f("icon_close", Region::Sprite(&mut self.icon_close));
+
+		f("icon_play", Region::Sprite(&mut self.icon_play));
+		f("icon_debug", Region::Sprite(&mut self.icon_debug));
+		f("icon_restart", Region::Sprite(&mut self.icon_restart));
+
+		f("num_bang", Region::Sprite(&mut self.num_bang));
+		f("num_colon", Region::Sprite(&mut self.num_colon));
+		for (i, num) in self.nums.iter_mut().enumerate() {
+			f(&format!("nums.{i}"), Region::Sprite(num));
+		}
+
+		f("bignum_placeholder", Region::Sprite(&mut self.bignum_placeholder));
+		for (i, bignum) in self.bignums.iter_mut().enumerate() {
+			f(&format!("bignums.{i}"), Region::Sprite(bignum));
+		}
+	}
+}
+
+/// Parses a `name = [numbers...]` line.
+fn parse_atlas_line(line: &str) -> Option<(&str, Vec<u16>)> {
+	let (name, value) = line.split_once('=')?;
+	let value = value.trim().strip_prefix('[')?.strip_suffix(']')?;
+
+	let nums = (value.split(','))
+		.map(str::trim)
+		// allow a trailing comma
+		.filter(|num| !num.is_empty())
+		.map(|num| num.parse::<u16>().ok())
+		.collect::<Option<Vec<u16>>>()?;
+
+	Some((name.trim(), nums))
+}
+
+fn rect_fits(rect: Rect, sheet_size: Size) -> bool {
+	rect.x >= 0
+		&& rect.y >= 0
+		&& rect.x as u32 + rect.w as u32 <= sheet_size.w as u32
+		&& rect.y as u32 + rect.h as u32 <= sheet_size.h as u32
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::math::size::size;
+	use crate::snake::Direction;
+
+	const SHEET_SIZE: Size = size(83, 28);
+
+	#[test]
+	fn small_atlas_moves_only_its_regions() {
+		let atlas = "
+# moved around
+snake_head = [0, 21, 7, 7]
+nums.3 = [40, 1, 3, 5,]
+
+box_red = [30, 20, 6, 6, 2, 4, 1, 5]  # a bigger one
+";
+		let sheet = SnaekSheet::from_atlas(atlas, SHEET_SIZE).unwrap();
+		let default = snaek_sheet();
+
+		assert_eq!(sheet.snake_head.rect, Rect::from_xywh(0, 21, 7, 7));
+		assert_eq!(sheet.nums[3].rect, Rect::from_xywh(40, 1, 3, 5));
+		assert_eq!(
+			sheet.box_red,
+			NineSlicingSprite::new(Rect::from_xywh(30, 20, 6, 6), 2, 4, 1, 5)
+		);
+
+		assert_eq!(sheet.nums[2], default.nums[2]);
+		assert_eq!(sheet.box_green, default.box_green);
+		for name in SpriteName::ALL
+			.into_iter()
+			.filter(|&name| name != SpriteName::SnakeHead)
+		{
+			assert_eq!(sheet.get(name), default.get(name), "{name:?}");
+		}
+	}
+
+	#[test]
+	fn bundled_atlas_is_the_default_sheet() {
+		let mut sheet = SnaekSheet::from_atlas(include_str!("../../assets/snaeksheet.toml"), SHEET_SIZE).unwrap();
+		let mut default = snaek_sheet();
+
+		let mut regions = Vec::new();
+		sheet.for_each_region(|name, region| regions.push((name.to_owned(), region_rects(region))));
+		let mut default_regions = Vec::new();
+		default.for_each_region(|name, region| default_regions.push((name.to_owned(), region_rects(region))));
+		assert_eq!(regions, default_regions);
+	}
+
+	/// The rect of a region, and its nine-slice bars if it has any.
+	fn region_rects(region: Region) -> (Rect, Option<[u16; 4]>) {
+		match region {
+			Region::Sprite(sprite) => (sprite.rect, None),
+			Region::NineSlice(nss) => (nss.sprite.rect, Some([nss.vl, nss.vr, nss.ht, nss.hb])),
+		}
+	}
+
+	#[test]
+	fn broken_atlases_say_where() {
+		let error = |src: &str| SnaekSheet::from_atlas(src, SHEET_SIZE).unwrap_err();
+
+		assert_eq!(
+			error("cursor = [1, 2, 3, 4]\ncursor [1, 2]"),
+			AtlasError::Syntax { line: 2 }
+		);
+		assert_eq!(error("cursor = [1, 2, -3, 4]"), AtlasError::Syntax { line: 1 });
+		assert_eq!(
+			error("\n\nsnake_tail = [0, 0, 7, 7]"),
+			AtlasError::UnknownRegion {
+				line: 3,
+				name: "snake_tail".into()
+			}
+		);
+		assert_eq!(
+			error("box_red = [0, 0, 7, 7]"),
+			AtlasError::WrongArity {
+				line: 1,
+				name: "box_red".into()
+			}
+		);
+	}
+
+	#[test]
+	fn regions_must_fit_in_the_sheet() {
+		let error = |src: &str| SnaekSheet::from_atlas(src, SHEET_SIZE).unwrap_err();
+
+		assert_eq!(
+			error("cursor = [80, 0, 4, 6]"),
+			AtlasError::OutOfBounds { name: "cursor".into() }
+		);
+		assert_eq!(
+			error("bignums.9 = [0, 20, 8, 14]"),
+			AtlasError::OutOfBounds {
+				name: "bignums.9".into()
+			}
+		);
+		assert_eq!(
+			error("box_red = [0, 0, 3, 3, 2, 1, 1, 2]"),
+			AtlasError::OutOfBounds { name: "box_red".into() }
+		);
+		assert!(SnaekSheet::from_atlas("cursor = [79, 22, 4, 6]", SHEET_SIZE).is_ok());
+	}
+
+	#[test]
+	fn single_slot_snakes_use_their_own_sprite() {
+		let sheet = snaek_sheet();
+		assert_ne!(sheet.snake_single, sheet.snake_head);
+		assert_eq!(
+			sheet.cell_sprite(CellVisual::snake_single(Direction::Down)),
+			(sheet.snake_single, Rotate::R90)
+		);
+		assert_eq!(
+			sheet.cell_sprite(CellVisual::snake_head(Direction::Down)),
+			(sheet.snake_head, Rotate::R90)
+		);
+	}
+
+	#[test]
+	fn every_sprite_name_has_its_own_region_in_the_sheet() {
+		let sheet = snaek_sheet();
+
+		let mut rects = Vec::new();
+		for name in SpriteName::ALL {
+			let rect = sheet.get(name).rect;
+			assert!(rect.w > 0 && rect.h > 0, "{name:?} is empty");
+			let in_bounds = rect.x >= 0
+				&& rect.y >= 0
+				&& rect.x as u16 + rect.w <= SHEET_SIZE.w
+				&& rect.y as u16 + rect.h <= SHEET_SIZE.h;
+			assert!(in_bounds, "{name:?} is outside the sheet");
+			assert!(!rects.contains(&rect), "{name:?} shares {rect:?}");
+			rects.push(rect);
+		}
+	}
+
+	#[test]
+	fn sprite_names_match_the_atlas_regions() {
+		let mut names: Vec<&str> = SpriteName::ALL.iter().map(|name| name.name()).collect();
+		names.sort_unstable();
+		names.dedup();
+		assert_eq!(names.len(), SpriteName::ALL.len());
+
+		// moving a region by its name moves the sprite of that name
+		for name in SpriteName::ALL {
+			let atlas = format!("{} = [1, 2, 3, 4]", name.name());
+			let sheet = SnaekSheet::from_atlas(&atlas, SHEET_SIZE).unwrap();
+			assert_eq!(sheet.get(name).rect, Rect::from_xywh(1, 2, 3, 4), "{name:?}");
+		}
+	}
+}
@@ -4,10 +4,13 @@ use std::hash::{DefaultHasher, Hash, Hasher};
 use std::num::NonZeroUsize;
 use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, DerefMut};
 
-use crate::math::pos::Pos;
+use minifb::{Key, Scale};
+use serde::Serialize;
+
+use crate::math::pos::{pos, Pos};
 use crate::math::rect::Rect;
 use crate::math::size::Size;
-use crate::render::color::alphacomp::AlphaCompFn;
+use crate::render::color::alphacomp::BlendMode;
 use crate::render::color::{alphacomp, Color};
 use crate::render::sprite::{NineSlicingSprite, Sprite};
 use crate::render::{DrawCommand, Rotate, SpritesheetId, Text};
@@ -24,10 +27,23 @@ pub struct WidgetId(NonZeroUsize);
 /// A key that uniquely identifies a widget.
 ///
 /// It contains
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize)]
 #[repr(C)]
 pub struct WidgetKey(u64);
 
+impl WidgetKey {
+	/// A stable color unique-ish to this key, to tell widgets apart when debugging.
+	pub fn debug_color(&self) -> Color {
+		// the key is already a hash, but similar keys should still get very different hues
+		let mut h = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+		h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+		h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+		h ^= h >> 31;
+
+		Color::from_hsv((h % 360) as f32, 0.65, 1.)
+	}
+}
+
 pub struct WidgetKeyHasher<H: Hasher = DefaultHasher>(H);
 
 impl<H: Hasher> WidgetKeyHasher<H> {
@@ -74,7 +90,7 @@ macro_rules! wk {
 pub struct Widget {
 	// tree links
 	// It's side-stepping-the-borrow-checker time!
-	// note: `next` and `prev` get reused as an intrusive free list when the widget is "freed".
+	// note: `next` gets reused as an intrusive free list when the widget is "freed".
 	parent: Option<WidgetId>,
 	prev: Option<WidgetId>,
 	next: Option<WidgetId>,
@@ -94,6 +110,15 @@ pub struct Widget {
 	hovered: bool,
 	pressed: bool,
 	clicked: bool,
+	right_pressed: bool,
+	right_clicked: bool,
+	middle_pressed: bool,
+	middle_clicked: bool,
+	/// Where the cursor was when the widget started being dragged.
+	drag_origin: Option<Pos>,
+	drag_delta: Pos,
+	/// Dragged widget that just got dropped on this one.
+	dropped: Option<WidgetId>,
 
 	// Layout state calculated each frame
 	solved_rect: Rect,
@@ -106,6 +131,12 @@ pub struct WidgetReaction {
 	hovered: bool,
 	pressed: bool,
 	clicked: bool,
+	right_pressed: bool,
+	right_clicked: bool,
+	middle_pressed: bool,
+	middle_clicked: bool,
+	drag_delta: Pos,
+	dropped: Option<WidgetId>,
 }
 
 impl WidgetReaction {
@@ -128,6 +159,44 @@ impl WidgetReaction {
 	pub const fn clicked(&self) -> bool {
 		self.clicked
 	}
+
+	#[inline]
+	#[allow(unused)]
+	pub const fn right_pressed(&self) -> bool {
+		self.right_pressed
+	}
+
+	#[inline]
+	#[allow(unused)]
+	pub const fn right_clicked(&self) -> bool {
+		self.right_clicked
+	}
+
+	#[inline]
+	#[allow(unused)]
+	pub const fn middle_pressed(&self) -> bool {
+		self.middle_pressed
+	}
+
+	#[inline]
+	#[allow(unused)]
+	pub const fn middle_clicked(&self) -> bool {
+		self.middle_clicked
+	}
+
+	/// How far the cursor moved since the widget started being dragged.
+	#[inline]
+	#[allow(unused)]
+	pub const fn drag_delta(&self) -> Pos {
+		self.drag_delta
+	}
+
+	/// Dragged widget that just got dropped on this one, if any.
+	#[inline]
+	#[allow(unused)]
+	pub const fn dropped(&self) -> Option<WidgetId> {
+		self.dropped
+	}
 }
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -151,6 +220,30 @@ impl Anchor {
 	pub const BOTTOM_RIGHT:  Self = Self { x: 1.0, y: 1.0 };
 }
 
+/// Horizontal alignment of the lines of a text widget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[allow(unused)]
+pub enum HAlign {
+	#[default]
+	Left,
+	Center,
+	Right,
+}
+
+/// How a widget's border is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[allow(unused)]
+pub enum BorderStyle {
+	#[default]
+	Solid,
+	/// Dashes of some length, separated by gaps of the same length.
+	Dashed(u16),
+	/// Single pixels, separated by single-pixel gaps.
+	Dotted,
+	/// Two solid lines, with a one-pixel gap between them.
+	Double,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 #[repr(C, u16, align(4))]
 pub enum WidgetDim {
@@ -223,7 +316,7 @@ impl WidgetPadding {
 	}
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize)]
 #[repr(transparent)]
 pub struct WidgetFlags(u32);
 
@@ -239,6 +332,8 @@ impl WidgetFlags {
 	pub const DRAW_BORDER:     Self = Self(1 << 5);
 	pub const DRAW_BACKGROUND: Self = Self(1 << 6);
 	pub const DRAW_SPRITE:     Self = Self(1 << 7);
+	pub const DRAGGABLE:       Self = Self(1 << 8);
+	pub const DROP_TARGET:     Self = Self(1 << 9);
 }
 
 impl WidgetFlags {
@@ -280,6 +375,24 @@ pub enum FlexDirection {
 	#[default]
 	Horizontal,
 	Vertical,
+	/// Horizontal, with the last child on the left.
+	#[allow(unused)]
+	HorizontalReverse,
+	/// Vertical, with the last child at the top.
+	#[allow(unused)]
+	VerticalReverse,
+}
+
+impl FlexDirection {
+	#[inline]
+	pub const fn is_horizontal(self) -> bool {
+		matches!(self, Self::Horizontal | Self::HorizontalReverse)
+	}
+
+	#[inline]
+	pub const fn is_reverse(self) -> bool {
+		matches!(self, Self::HorizontalReverse | Self::VerticalReverse)
+	}
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
@@ -289,38 +402,78 @@ pub enum WidgetLayout {
 	Flex {
 		direction: FlexDirection,
 		gap: i16,
+		/// Whether children that overflow a line go on a new one, instead of going past the widget's edge.
+		wrap: bool,
 	},
 }
 
 impl WidgetLayout {
 	#[inline]
 	pub fn flex(direction: FlexDirection, gap: i16) -> Self {
-		Self::Flex { direction, gap }
+		Self::Flex {
+			direction,
+			gap,
+			wrap: false,
+		}
+	}
+
+	/// Same flex layout, but wrapping its children onto several lines. It also separates lines by the gap.
+	///
+	/// Does nothing on a stacked layout.
+	#[inline]
+	#[allow(unused)]
+	pub fn with_wrap(self, wrap: bool) -> Self {
+		match self {
+			Self::Flex { direction, gap, .. } => Self::Flex { direction, gap, wrap },
+			Self::Stacked => Self::Stacked,
+		}
 	}
 }
 
+/// Name of a sprite that gets resolved when drawing, so the sprite it points to can be swapped (e.g. by a theme).
+///
+/// Unlike [`crate::snake::snaeksheet::SpriteName`], which only names sprites of the snaek sheet, any name can be
+/// bound here, to any spritesheet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NamedSpriteKey(pub &'static str);
+
 #[derive(Debug, Clone)]
 pub enum WidgetSprite {
 	Simple(SpritesheetId, Sprite),
 	NineSlice(SpritesheetId, NineSlicingSprite),
+	/// A sprite bound to a name with [`UiContext::bind_sprite`].
+	#[allow(unused)]
+	Named(NamedSpriteKey),
 }
 
 /// Userland widget properties
 #[derive(Debug, Clone, Default)]
 pub struct WidgetProps {
 	pub key: WidgetKey,
+	/// Name shown for the widget in layout dumps, to tell which one it is.
+	pub debug_name: Option<&'static str>,
 
 	// feature combinations
 	pub flags: WidgetFlags,
 	pub color: Color,
 	pub text: Option<Text>,
+	pub text_align: HAlign,
+	/// Offset and color of a copy of the text drawn underneath it.
+	pub text_shadow: Option<(Pos, Color)>,
+	/// Color of a 1px outline drawn around the text's glyphs.
+	pub text_outline: Option<Color>,
 	pub border_color: Color,
 	pub border_width: u16,
+	pub border_style: BorderStyle,
+	pub corner_radius: u16,
+	pub shortcut: Option<Key>,
 	pub mask_and: Option<Color>,
 	pub mask_or: Option<Color>,
-	pub acf: Option<AlphaCompFn>,
+	pub acf: Option<BlendMode>,
 	pub sprite: Option<WidgetSprite>,
 	pub rotate: Rotate,
+	/// Point of the (unrotated) sprite it's rotated about, from its top-left corner. It's its center by default.
+	pub rotate_pivot: Option<Pos>,
 
 	// declarative layout data
 	pub anchor: Anchor,
@@ -339,6 +492,12 @@ impl WidgetProps {
 		Self { key, ..Self::default() }
 	}
 
+	#[inline]
+	pub const fn with_debug_name(mut self, debug_name: &'static str) -> Self {
+		self.debug_name = Some(debug_name);
+		self
+	}
+
 	#[inline]
 	pub const fn with_flags(mut self, flags: WidgetFlags) -> Self {
 		self.flags = flags;
@@ -357,6 +516,24 @@ impl WidgetProps {
 		self
 	}
 
+	#[inline]
+	pub const fn with_text_align(mut self, text_align: HAlign) -> Self {
+		self.text_align = text_align;
+		self
+	}
+
+	#[inline]
+	pub const fn with_text_shadow(mut self, offset: Pos, color: Color) -> Self {
+		self.text_shadow = Some((offset, color));
+		self
+	}
+
+	#[inline]
+	pub const fn with_text_outline(mut self, color: Color) -> Self {
+		self.text_outline = Some(color);
+		self
+	}
+
 	#[inline]
 	pub const fn with_border_color(mut self, border_color: Color) -> Self {
 		self.border_color = border_color;
@@ -369,12 +546,48 @@ impl WidgetProps {
 		self
 	}
 
+	#[inline]
+	pub const fn with_border_style(mut self, border_style: BorderStyle) -> Self {
+		self.border_style = border_style;
+		self
+	}
+
+	/// Rounds the corners of the background, and of the border if it's solid.
+	#[inline]
+	pub const fn with_corner_radius(mut self, corner_radius: u16) -> Self {
+		self.corner_radius = corner_radius;
+		self
+	}
+
+	/// Makes the widget also get clicked when a key is pressed.
+	#[inline]
+	pub const fn with_shortcut(mut self, shortcut: Key) -> Self {
+		self.shortcut = Some(shortcut);
+		self
+	}
+
 	#[inline]
 	pub const fn with_mask_and(mut self, mask_and: Option<Color>) -> Self {
 		self.mask_and = mask_and;
 		self
 	}
 
+	/// Makes the widget's pixels translucent, by masking their alpha with `opacity` on top of its mask.
+	///
+	/// The alpha is ANDed like the rest of the mask, so it's exact for opaque pixels but not for translucent ones.
+	#[inline]
+	pub const fn with_opacity(mut self, opacity: u8) -> Self {
+		let mask_and = match self.mask_and {
+			Some(mask_and) => mask_and,
+			None => Color::WHITE,
+		};
+		self.mask_and = Some(Color {
+			a: mask_and.a & opacity,
+			..mask_and
+		});
+		self
+	}
+
 	#[inline]
 	pub const fn with_mask_or(mut self, mask_or: Option<Color>) -> Self {
 		self.mask_or = mask_or;
@@ -382,7 +595,7 @@ impl WidgetProps {
 	}
 
 	#[inline]
-	pub const fn with_acf(mut self, acf: Option<AlphaCompFn>) -> Self {
+	pub const fn with_acf(mut self, acf: Option<BlendMode>) -> Self {
 		self.acf = acf;
 		self
 	}
@@ -399,6 +612,12 @@ impl WidgetProps {
 		self
 	}
 
+	#[inline]
+	pub const fn with_rotate_pivot(mut self, pivot: Pos) -> Self {
+		self.rotate_pivot = Some(pivot);
+		self
+	}
+
 	#[inline]
 	pub const fn with_anchor_origin(mut self, anchor: Anchor, origin: Anchor) -> Self {
 		self.anchor = anchor;
@@ -446,6 +665,21 @@ pub struct UiContext {
 
 	viewport_size: Size,
 	current_frame: u64,
+
+	/// Outline every widget with its key's debug color.
+	debug_outlines: bool,
+
+	/// Widget being dragged around, if any.
+	dragging: Option<WidgetId>,
+
+	/// Keys that just got pressed this frame, for widget shortcuts.
+	pressed_keys: Vec<Key>,
+
+	/// What named sprites point to.
+	named_sprites: HashMap<NamedSpriteKey, WidgetSprite>,
+
+	/// Number of draw commands pushed by the last [`UiContext::draw_widgets_into`].
+	last_draw_cmd_count: usize,
 }
 
 impl UiContext {
@@ -466,26 +700,24 @@ impl UiContext {
 		}
 	}
 
+	/// Changes the size of the viewport the UI is laid out in. A size of 0 lays everything out as empty.
+	#[allow(unused)]
+	pub fn set_viewport(&mut self, viewport_size: Size) {
+		self.viewport_size = viewport_size;
+	}
+
+	#[allow(unused)]
+	pub fn set_debug_outlines(&mut self, debug_outlines: bool) {
+		self.debug_outlines = debug_outlines;
+	}
+
 	pub fn build_widget(&mut self, props: WidgetProps) -> WidgetReaction {
 		match self.keys.get(&props.key) {
 			Some(&id) => {
 				let mut widget = self.widget_mut(id);
 
-				if widget.freed {
-					if let Some(prev) = widget.prev {
-						let mut prev = self.widget_mut(prev);
-						prev.next = widget.next;
-						widget.prev = None;
-					}
-
-					if let Some(next) = widget.next {
-						let mut next = self.widget_mut(next);
-						next.prev = widget.prev;
-						widget.next = None;
-					}
-				}
-
 				let widget = widget.deref_mut();
+				widget.last_frame_touched = self.current_frame;
 				widget.parent = None;
 				widget.prev = None;
 				widget.next = None;
@@ -499,12 +731,17 @@ impl UiContext {
 					hovered: widget.hovered,
 					pressed: widget.pressed,
 					clicked: widget.clicked,
+					right_pressed: widget.right_pressed,
+					right_clicked: widget.right_clicked,
+					middle_pressed: widget.middle_pressed,
+					middle_clicked: widget.middle_clicked,
+					drag_delta: widget.drag_delta,
+					dropped: widget.dropped,
 				}
 			}
 			None => {
-				let id = Self::id_from_index(self.widgets.len());
-				self.keys.insert(props.key, id);
-				self.widgets.push(RefCell::new(Widget {
+				let key = props.key;
+				let widget = Widget {
 					parent: None,
 					prev: None,
 					next: None,
@@ -520,16 +757,44 @@ impl UiContext {
 					hovered: false,
 					pressed: false,
 					clicked: false,
+					right_pressed: false,
+					right_clicked: false,
+					middle_pressed: false,
+					middle_clicked: false,
+					drag_origin: None,
+					drag_delta: Pos::ZERO,
+					dropped: None,
 
 					solved_rect: Rect::ZERO,
 					solved_min_size: Size::ZERO,
-				}));
+				};
+
+				// freed nodes get reused before allocating new ones
+				let id = match self.first_freed {
+					Some(id) => {
+						let next_freed = self.widget(id).next;
+						self.first_freed = next_freed;
+						*self.widget_mut(id) = widget;
+						id
+					}
+					None => {
+						self.widgets.push(RefCell::new(widget));
+						Self::id_from_index(self.widgets.len() - 1)
+					}
+				};
+				self.keys.insert(key, id);
 
 				WidgetReaction {
 					id,
 					hovered: false,
 					pressed: false,
 					clicked: false,
+					right_pressed: false,
+					right_clicked: false,
+					middle_pressed: false,
+					middle_clicked: false,
+					drag_delta: Pos::ZERO,
+					dropped: None,
 				}
 			}
 		}
@@ -553,34 +818,46 @@ impl UiContext {
 		self.widget_mut(child_id).parent = Some(wid);
 	}
 
-	fn free_untouched_widgets_rec(&mut self, wid: WidgetId) {
-		let (last_frame_touched, child) = {
-			let w = self.widget(wid);
-			(w.last_frame_touched, w.first_child)
-		};
-
-		if last_frame_touched != self.current_frame {
-			// free widget
+	/// Frees every widget that wasn't built since the last call, and starts a new frame.
+	///
+	/// Freed widgets lose their key and their state, and their nodes go to a free list to be reused by the next
+	/// widgets with new keys, so a UI that builds about the same widgets every frame stops allocating nodes.
+	pub fn free_untouched_widgets(&mut self) {
+		for index in 0..self.widgets.len() {
+			let wid = Self::id_from_index(index);
 
-			{
+			let key = {
 				let mut w = self.widget_mut(wid);
+				if w.freed || w.last_frame_touched == self.current_frame {
+					continue;
+				}
+
 				w.freed = true;
+				w.parent = None;
 				w.prev = None;
 				w.next = self.first_freed;
-			}
+				w.first_child = None;
+				w.last_child = None;
+				w.children_count = 0;
+				w.props.key
+			};
 
+			self.keys.remove(&key);
 			self.first_freed = Some(wid);
-		}
 
-		let mut child = child;
-		while let Some(ch) = child {
-			self.free_untouched_widgets_rec(ch);
-			child = self.widget(ch).next;
+			// the node can go to another widget, which isn't the one being dragged
+			if self.dragging == Some(wid) {
+				self.dragging = None;
+			}
 		}
+
+		self.current_frame += 1;
 	}
 
-	pub fn free_untouched_widgets(&mut self) {
-		self.free_untouched_widgets_rec(Self::ROOT_WIDGET);
+	/// Number of widget nodes allocated, both the ones in use and the freed ones waiting to be reused.
+	#[allow(unused)]
+	pub fn pooled_capacity(&self) -> usize {
+		self.widgets.len()
 	}
 
 	fn draw_widgets_rec(&mut self, draw_cmds: &mut Vec<DrawCommand>, wid: WidgetId) {
@@ -588,7 +865,7 @@ impl UiContext {
 			let widget = self.widget(wid);
 			let props = &widget.props;
 
-			let acf = props.acf.unwrap_or(alphacomp::over);
+			let acf = props.acf.unwrap_or(alphacomp::OVER);
 
 			let mut solved_rect = widget.solved_rect;
 			solved_rect.x += widget.props.draw_offset.x;
@@ -603,17 +880,20 @@ impl UiContext {
 			}
 
 			if props.flags.has(WidgetFlags::DRAW_SPRITE) {
-				match props.sprite {
-					Some(WidgetSprite::Simple(sheet_id, sprite)) => {
+				match props.sprite.as_ref().and_then(|sprite| self.resolve_sprite(sprite)) {
+					Some(&WidgetSprite::Simple(sheet_id, sprite)) => {
+						let pivot_offset = (props.rotate_pivot)
+							.map_or(Pos::ZERO, |pivot| props.rotate.pivot_offset(sprite.rect.size(), pivot));
+
 						draw_cmds.push(DrawCommand::Sprite {
-							pos: solved_rect.pos(),
+							pos: solved_rect.pos() + pivot_offset,
 							rotate: widget.props.rotate,
 							sheet_id,
 							sprite,
 							acf,
 						});
 					}
-					Some(WidgetSprite::NineSlice(sheet_id, nss)) => {
+					Some(&WidgetSprite::NineSlice(sheet_id, nss)) => {
 						draw_cmds.push(DrawCommand::NineSlicingSprite {
 							rect: solved_rect,
 							sheet_id,
@@ -621,34 +901,73 @@ impl UiContext {
 							acf,
 						});
 					}
-					None => {}
+					Some(WidgetSprite::Named(_)) | None => {}
 				}
 			}
 
 			if props.flags.has(WidgetFlags::DRAW_BACKGROUND) {
-				draw_cmds.push(DrawCommand::Fill {
-					rect: solved_rect,
-					color: props.color,
-					acf,
-				});
+				push_rounded_fill(draw_cmds, solved_rect, props.corner_radius, props.color, acf);
 			}
 
 			if props.flags.has(WidgetFlags::DRAW_BORDER) {
-				draw_cmds.push(DrawCommand::Stroke {
-					rect: solved_rect,
-					color: props.border_color,
-					stroke_width: 1,
-					acf,
-				});
+				match props.border_style {
+					BorderStyle::Solid if props.corner_radius > 0 => {
+						push_rounded_border(draw_cmds, solved_rect, props.corner_radius, props.border_color, acf);
+					}
+					style => push_border(draw_cmds, solved_rect, props.border_color, style, acf),
+				}
 			}
 
 			if props.flags.has(WidgetFlags::DRAW_TEXT) {
 				if let Some(text) = &widget.props.text {
-					draw_cmds.push(DrawCommand::Text {
-						text: text.text().clone(),
-						pos: solved_rect.pos(),
-						acf,
-					});
+					let mut lines = Vec::new();
+					match props.size.w {
+						WidgetDim::Hug => lines.push((text.clone(), solved_rect.pos())),
+						WidgetDim::Fixed(_) | WidgetDim::Fill => {
+							let mut line_pos = solved_rect.pos();
+							for line in text.wrap(solved_rect.w) {
+								let leftover_w = solved_rect.w.saturating_sub(line.width) as i16;
+								let offset_x = match props.text_align {
+									HAlign::Left => 0,
+									HAlign::Center => leftover_w / 2,
+									HAlign::Right => leftover_w,
+								};
+
+								lines.push((text.slice(line.range), pos(line_pos.x + offset_x, line_pos.y)));
+								line_pos.y += (text.size().h + Text::LINE_GAP) as i16;
+							}
+						}
+					}
+
+					// the shadow goes under the outline, which goes under the text itself
+					let mut underlays = Vec::new();
+					if let Some((offset, color)) = props.text_shadow {
+						underlays.push((offset, color));
+					}
+					if let Some(color) = props.text_outline {
+						for (x, y) in [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)] {
+							underlays.push((pos(x, y), color));
+						}
+					}
+
+					for &(offset, color) in &underlays {
+						draw_cmds.push(DrawCommand::MaskAnd(color));
+						for (line, line_pos) in &lines {
+							draw_cmds.push(DrawCommand::Text {
+								text: line.uncolored(),
+								pos: *line_pos + offset,
+								acf,
+							});
+						}
+					}
+
+					if !underlays.is_empty() {
+						draw_cmds.push(DrawCommand::MaskAnd(props.mask_and.unwrap_or(Color::WHITE)));
+					}
+
+					for (text, pos) in lines {
+						draw_cmds.push(DrawCommand::Text { text, pos, acf });
+					}
 				}
 			}
 
@@ -659,6 +978,15 @@ impl UiContext {
 			if props.mask_or.is_some() {
 				draw_cmds.push(DrawCommand::MaskOr(Color::TRANSPARENT));
 			}
+
+			if self.debug_outlines {
+				draw_cmds.push(DrawCommand::Stroke {
+					rect: solved_rect,
+					color: props.key.debug_color(),
+					stroke_width: 1,
+					acf: alphacomp::OVER,
+				});
+			}
 		}
 
 		let mut child = self.widget(wid).first_child;
@@ -672,16 +1000,34 @@ impl UiContext {
 		draw_cmds.push(DrawCommand::BeginComposite);
 		draw_cmds.push(DrawCommand::Clear);
 		self.draw_widgets_rec(draw_cmds, Self::ROOT_WIDGET);
-		draw_cmds.push(DrawCommand::EndComposite(alphacomp::over));
+		draw_cmds.push(DrawCommand::EndComposite(alphacomp::OVER));
 	}
 
-	fn react_rec(&mut self, mouse: &Mouse, wid: WidgetId) -> bool {
+	/// Same as [`UiContext::draw_widgets`], but reserves room for the whole frame first.
+	///
+	/// Keep the same buffer around and clear it every frame instead of making a new one: once it grew to fit a
+	/// scene, drawing that scene again doesn't reallocate it.
+	pub fn draw_widgets_into(&mut self, buf: &mut Vec<DrawCommand>) {
+		// a background, a border and a sprite or text per widget, plus the composite layer around them
+		let estimate = self.widgets.len() * 3 + 3;
+		buf.reserve(estimate.max(self.last_draw_cmd_count));
+
+		let start = buf.len();
+		self.draw_widgets(buf);
+		self.last_draw_cmd_count = buf.len() - start;
+	}
+
+	fn react_rec(&mut self, mouse: &Mouse, cursor: Pos, inside: bool, wid: WidgetId) -> bool {
 		{
 			let mut widget = self.widget_mut(wid);
 			if widget.props.flags.has(WidgetFlags::DISABLED) {
 				widget.hovered = false;
 				widget.pressed = false;
 				widget.clicked = false;
+				widget.right_pressed = false;
+				widget.right_clicked = false;
+				widget.middle_pressed = false;
+				widget.middle_clicked = false;
 				return false;
 			}
 		}
@@ -689,7 +1035,7 @@ impl UiContext {
 		let mut any_child_hovered = false;
 		let mut child = self.widget(wid).first_child;
 		while let Some(ch) = child {
-			any_child_hovered |= self.react_rec(mouse, ch);
+			any_child_hovered |= self.react_rec(mouse, cursor, inside, ch);
 			child = self.widget(ch).next;
 		}
 
@@ -697,23 +1043,155 @@ impl UiContext {
 		let can_hover = widget.props.flags.has(WidgetFlags::CAN_HOVER);
 		let can_click = widget.props.flags.has(WidgetFlags::CAN_CLICK);
 
-		let pressed_prev = widget.pressed;
-		let hovered = !any_child_hovered && widget.solved_rect.contains(mouse.x, mouse.y);
-
+		let hovered = inside && !any_child_hovered && widget.solved_rect.contains(cursor.x as f32, cursor.y as f32);
 		widget.hovered = can_hover && hovered;
-		widget.pressed = can_click
-			&& match hovered {
-				true => mouse.l_pressed_start() || (mouse.l_pressed() && pressed_prev),
-				false => mouse.l_pressed() && pressed_prev,
-			};
-		widget.clicked = can_click && hovered && mouse.l_pressed_end() && pressed_prev;
+
+		let (pressed, clicked) = button_reaction(
+			hovered,
+			widget.pressed,
+			mouse.l_pressed(),
+			mouse.l_pressed_start(),
+			mouse.l_pressed_end(),
+		);
+		let shortcut_pressed = (widget.props.shortcut).is_some_and(|key| self.pressed_keys.contains(&key));
+		widget.pressed = can_click && pressed;
+		widget.clicked = can_click && (clicked || shortcut_pressed);
+
+		let (pressed, clicked) = button_reaction(
+			hovered,
+			widget.right_pressed,
+			mouse.r_pressed(),
+			mouse.r_pressed_start(),
+			mouse.r_pressed_end(),
+		);
+		widget.right_pressed = can_click && pressed;
+		widget.right_clicked = can_click && clicked;
+
+		let (pressed, clicked) = button_reaction(
+			hovered,
+			widget.middle_pressed,
+			mouse.m_pressed(),
+			mouse.m_pressed_start(),
+			mouse.m_pressed_end(),
+		);
+		widget.middle_pressed = can_click && pressed;
+		widget.middle_clicked = can_click && clicked;
+
+		// a widget is dragged for as long as it's pressed
+		widget.dropped = None;
+		match widget.pressed && widget.props.flags.has(WidgetFlags::DRAGGABLE) {
+			true => {
+				let origin = *widget.drag_origin.get_or_insert(cursor);
+				widget.drag_delta = cursor - origin;
+			}
+			false => {
+				widget.drag_origin = None;
+				widget.drag_delta = Pos::ZERO;
+			}
+		}
 
 		widget.hovered
 	}
 
-	pub fn react(&mut self, mouse: &Mouse) {
+	/// Finds the topmost drop target under the cursor, other than the dragged widget itself.
+	fn drop_target_at(&self, wid: WidgetId, cursor: Pos, dragged: WidgetId) -> Option<WidgetId> {
+		let mut target = None;
+
+		{
+			let widget = self.widget(wid);
+			let rect = widget.solved_rect;
+			let is_target = widget.props.flags.has(WidgetFlags::DROP_TARGET)
+				&& !widget.props.flags.has(WidgetFlags::DISABLED)
+				&& !widget.freed;
+
+			if wid != dragged && is_target && rect.contains(cursor.x as f32, cursor.y as f32) {
+				target = Some(wid);
+			}
+		}
+
+		// children are drawn on top, so they take priority
+		let mut child = self.widget(wid).first_child;
+		while let Some(ch) = child {
+			if let Some(child_target) = self.drop_target_at(ch, cursor, dragged) {
+				target = Some(child_target);
+			}
+			child = self.widget(ch).next;
+		}
+
+		target
+	}
+
+	/// Sets which keys just got pressed this frame, so that widgets with those shortcuts get clicked on the next
+	/// [`UiContext::react`].
+	pub fn feed_keys(&mut self, pressed: &[Key]) {
+		self.pressed_keys.clear();
+		self.pressed_keys.extend_from_slice(pressed);
+	}
+
+	/// Updates the widgets' hover and click states, with the window showing the UI at some scale.
+	pub fn react(&mut self, mouse: &Mouse, scale: Scale) {
 		// oh no, not React D:
-		self.react_rec(mouse, Self::ROOT_WIDGET);
+		let cursor = mouse.viewport_pos(scale);
+		// a cursor outside the viewport hovers nothing, even widgets overflowing it
+		let inside = mouse.inside
+			&& self
+				.widget(Self::ROOT_WIDGET)
+				.solved_rect
+				.contains(cursor.x as f32, cursor.y as f32);
+		self.react_rec(mouse, cursor, inside, Self::ROOT_WIDGET);
+
+		// drag and drop
+		if let Some(dragged) = self.dragging {
+			let released = {
+				let widget = self.widget(dragged);
+				widget.freed || !widget.pressed
+			};
+
+			if released {
+				self.dragging = None;
+
+				if inside && mouse.l_pressed_end() {
+					if let Some(target) = self.drop_target_at(Self::ROOT_WIDGET, cursor, dragged) {
+						self.widget_mut(target).dropped = Some(dragged);
+					}
+				}
+			}
+		}
+
+		if self.dragging.is_none() {
+			self.dragging = (0..self.widgets.len()).map(Self::id_from_index).find(|&wid| {
+				let widget = self.widget(wid);
+				!widget.freed && widget.pressed && widget.props.flags.has(WidgetFlags::DRAGGABLE)
+			});
+		}
+	}
+
+	/// Rect of a widget as solved by the last call to [`UiContext::solve_layout`].
+	///
+	/// Gives `None` if the widget doesn't exist or was freed.
+	#[allow(unused)]
+	pub fn widget_rect(&self, wid: WidgetId) -> Option<Rect> {
+		let widget = self.widgets.get(Self::index_from_id(wid))?.borrow();
+		(!widget.freed).then_some(widget.solved_rect)
+	}
+
+	/// Points a sprite name to an actual sprite. All widgets using that name draw the new sprite from now on.
+	///
+	/// Binding a name to another named sprite isn't supported, and makes widgets using it draw nothing.
+	#[allow(unused)]
+	pub fn bind_sprite(&mut self, name: NamedSpriteKey, sprite: WidgetSprite) {
+		self.named_sprites.insert(name, sprite);
+	}
+
+	/// Resolves a sprite name to what it's bound to, if anything.
+	fn resolve_sprite<'a>(&'a self, sprite: &'a WidgetSprite) -> Option<&'a WidgetSprite> {
+		match sprite {
+			WidgetSprite::Named(name) => match self.named_sprites.get(name)? {
+				WidgetSprite::Named(_) => None,
+				sprite => Some(sprite),
+			},
+			sprite => Some(sprite),
+		}
 	}
 
 	pub fn widget(&self, wid: WidgetId) -> Ref<'_, Widget> {
@@ -725,10 +1203,27 @@ impl UiContext {
 	}
 }
 
+/// Resolves whether a widget is pressed and clicked by a mouse button, from whether that button is down, just
+/// started being pressed, or just got released.
+///
+/// A press has to start on the widget, and a click is when the button is released on the widget it pressed.
+fn button_reaction(hovered: bool, pressed_prev: bool, down: bool, start: bool, end: bool) -> (bool, bool) {
+	let pressed = match hovered {
+		true => start || (down && pressed_prev),
+		false => down && pressed_prev,
+	};
+	let clicked = hovered && end && pressed_prev;
+
+	(pressed, clicked)
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct Mouse {
+	/// Position of the cursor in the window, in window pixels (not scaled down to the viewport).
 	pub x: f32,
 	pub y: f32,
+	/// Whether the cursor is inside the window. When it isn't, no widget is hovered.
+	pub inside: bool,
 	pub l_pressed: (bool, bool),
 	pub r_pressed: (bool, bool),
 	pub m_pressed: (bool, bool),
@@ -736,6 +1231,22 @@ pub struct Mouse {
 
 #[allow(unused)]
 impl Mouse {
+	/// Viewport pixel the cursor is on, when the window shows the viewport at some scale.
+	pub fn viewport_pos(&self, scale: Scale) -> Pos {
+		let factor = match scale {
+			Scale::X1 => 1.,
+			Scale::X2 => 2.,
+			Scale::X4 => 4.,
+			Scale::X8 => 8.,
+			Scale::X16 => 16.,
+			Scale::X32 => 32.,
+			// the actual factor depends on the screen, which we know nothing about
+			Scale::FitScreen => 1.,
+		};
+
+		pos((self.x / factor).floor() as i16, (self.y / factor).floor() as i16)
+	}
+
 	#[inline]
 	pub const fn l_pressed(&self) -> bool {
 		self.l_pressed.0
@@ -781,3 +1292,1099 @@ impl Mouse {
 		!self.m_pressed.0 && self.m_pressed.1
 	}
 }
+
+/// Draws a 1px border around a rect in some style.
+fn push_border(draw_cmds: &mut Vec<DrawCommand>, rect: Rect, color: Color, style: BorderStyle, acf: BlendMode) {
+	let stroke = |rect: Rect| DrawCommand::Stroke {
+		rect,
+		color,
+		stroke_width: 1,
+		acf,
+	};
+
+	let dash = match style {
+		BorderStyle::Solid => {
+			draw_cmds.push(stroke(rect));
+			return;
+		}
+		BorderStyle::Double => {
+			draw_cmds.push(stroke(rect));
+			if rect.w > 4 && rect.h > 4 {
+				draw_cmds.push(stroke(Rect::from_xywh(rect.x + 2, rect.y + 2, rect.w - 4, rect.h - 4)));
+			}
+			return;
+		}
+		BorderStyle::Dashed(dash) => dash.max(1),
+		BorderStyle::Dotted => 1,
+	};
+
+	if rect.w == 0 || rect.h == 0 {
+		return;
+	}
+
+	let right = rect.x + rect.w as i16 - 1;
+	let bottom = rect.y + rect.h as i16 - 1;
+	let inner_h = rect.h.saturating_sub(2);
+
+	push_dashed_line(draw_cmds, pos(rect.x, rect.y), rect.w, true, dash, color, acf);
+	push_dashed_line(draw_cmds, pos(rect.x, bottom), rect.w, true, dash, color, acf);
+	push_dashed_line(draw_cmds, pos(rect.x, rect.y + 1), inner_h, false, dash, color, acf);
+	push_dashed_line(draw_cmds, pos(right, rect.y + 1), inner_h, false, dash, color, acf);
+}
+
+/// Draws a 1px line made of dashes, each followed by a gap of the same length.
+fn push_dashed_line(
+	draw_cmds: &mut Vec<DrawCommand>,
+	start: Pos,
+	len: u16,
+	horizontal: bool,
+	dash: u16,
+	color: Color,
+	acf: BlendMode,
+) {
+	let mut offset = 0;
+	while offset < len {
+		let dash_len = dash.min(len - offset);
+		let rect = match horizontal {
+			true => Rect::from_xywh(start.x + offset as i16, start.y, dash_len, 1),
+			false => Rect::from_xywh(start.x, start.y + offset as i16, 1, dash_len),
+		};
+
+		draw_cmds.push(DrawCommand::Fill { rect, color, acf });
+		offset = offset.saturating_add(dash.saturating_mul(2));
+	}
+}
+
+/// How much of a pixel in the corner of a rounded rect is covered, between 0 and 1.
+///
+/// `(x, y)` is the pixel's position from the corner, and coverage is taken from the distance of its center to the
+/// center of the corner's circle, so that the very corner pixel is always left out.
+fn corner_coverage(x: u16, y: u16, radius: f32) -> f32 {
+	(radius - corner_distance(x, y, radius)).clamp(0., 1.)
+}
+
+/// Distance from the center of a pixel in the corner of a rounded rect to the center of the corner's circle.
+fn corner_distance(x: u16, y: u16, radius: f32) -> f32 {
+	let dx = radius - (x as f32 + 0.5);
+	let dy = radius - (y as f32 + 0.5);
+	(dx * dx + dy * dy).sqrt()
+}
+
+/// Color with its alpha scaled by some coverage.
+fn with_coverage(color: Color, coverage: f32) -> Color {
+	Color {
+		a: (color.a as f32 * coverage).round() as u8,
+		..color
+	}
+}
+
+/// Fills each of the four mirrored corner pixels of a rect.
+fn push_corner_pixels(draw_cmds: &mut Vec<DrawCommand>, rect: Rect, x: u16, y: u16, color: Color, acf: BlendMode) {
+	let left = rect.x + x as i16;
+	let right = rect.x + rect.w as i16 - 1 - x as i16;
+	let top = rect.y + y as i16;
+	let bottom = rect.y + rect.h as i16 - 1 - y as i16;
+
+	for (px, py) in [(left, top), (right, top), (left, bottom), (right, bottom)] {
+		draw_cmds.push(DrawCommand::Fill {
+			rect: Rect::from_xywh(px, py, 1, 1),
+			color,
+			acf,
+		});
+	}
+}
+
+/// Fills a rect with quarter-circle corners, antialiased through the color's alpha.
+fn push_rounded_fill(draw_cmds: &mut Vec<DrawCommand>, rect: Rect, radius: u16, color: Color, acf: BlendMode) {
+	let radius = radius.min(rect.w / 2).min(rect.h / 2);
+	if radius == 0 {
+		draw_cmds.push(DrawCommand::Fill { rect, color, acf });
+		return;
+	}
+
+	let middle = Rect::from_xywh(rect.x, rect.y + radius as i16, rect.w, rect.h - 2 * radius);
+	draw_cmds.push(DrawCommand::Fill {
+		rect: middle,
+		color,
+		acf,
+	});
+
+	for y in 0..radius {
+		// pixels get more covered towards the center, so the first fully covered one starts a solid span
+		let mut solid_from = radius;
+		for x in 0..radius {
+			let coverage = corner_coverage(x, y, radius as f32);
+			if coverage >= 1. {
+				solid_from = x;
+				break;
+			}
+
+			if coverage > 0. {
+				push_corner_pixels(draw_cmds, rect, x, y, with_coverage(color, coverage), acf);
+			}
+		}
+
+		let span_w = rect.w - 2 * solid_from;
+		for span_y in [rect.y + y as i16, rect.y + rect.h as i16 - 1 - y as i16] {
+			draw_cmds.push(DrawCommand::Fill {
+				rect: Rect::from_xywh(rect.x + solid_from as i16, span_y, span_w, 1),
+				color,
+				acf,
+			});
+		}
+	}
+}
+
+/// Draws a 1px border with quarter-circle corners, antialiased through the color's alpha.
+fn push_rounded_border(draw_cmds: &mut Vec<DrawCommand>, rect: Rect, radius: u16, color: Color, acf: BlendMode) {
+	let radius = radius.min(rect.w / 2).min(rect.h / 2);
+	if radius == 0 {
+		push_border(draw_cmds, rect, color, BorderStyle::Solid, acf);
+		return;
+	}
+
+	let (r, ri) = (radius as i16, radius);
+	let edges = [
+		Rect::from_xywh(rect.x + r, rect.y, rect.w - 2 * ri, 1),
+		Rect::from_xywh(rect.x + r, rect.y + rect.h as i16 - 1, rect.w - 2 * ri, 1),
+		Rect::from_xywh(rect.x, rect.y + r, 1, rect.h - 2 * ri),
+		Rect::from_xywh(rect.x + rect.w as i16 - 1, rect.y + r, 1, rect.h - 2 * ri),
+	];
+	for rect in edges {
+		draw_cmds.push(DrawCommand::Fill { rect, color, acf });
+	}
+
+	for y in 0..radius {
+		for x in 0..radius {
+			// the border is the ring between the outer circle and a circle 1px smaller
+			let inner_coverage = (radius as f32 - 1. - corner_distance(x, y, radius as f32)).clamp(0., 1.);
+			let coverage = corner_coverage(x, y, radius as f32) - inner_coverage;
+			if coverage > 0. {
+				push_corner_pixels(draw_cmds, rect, x, y, with_coverage(color, coverage), acf);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::math::size::size;
+	use crate::render::bitmap::Bitmap;
+	use crate::render::{RenderStats, Renderer};
+
+	const IMG_ASCII_CHARS: &[u8] = include_bytes!("../assets/ascii-chars.png");
+
+	fn renderer() -> Renderer {
+		let framebuffer = Bitmap::new(size(16, 16)).unwrap();
+		Renderer::new(framebuffer, Bitmap::from_png(IMG_ASCII_CHARS).unwrap())
+	}
+
+	/// Builds the root widget of a new frame, filling the viewport.
+	fn root(ui: &mut UiContext) -> WidgetId {
+		ui.build_widget(WidgetProps::new(wk!()).with_size(WidgetSize::fill()))
+			.id()
+	}
+
+	/// Where every text command of a frame draws its text.
+	fn text_positions(ui: &mut UiContext) -> Vec<(String, Pos)> {
+		let mut draw_cmds = Vec::new();
+		ui.draw_widgets(&mut draw_cmds);
+		(draw_cmds.into_iter())
+			.filter_map(|cmd| match cmd {
+				DrawCommand::Text { text, pos, .. } => Some((text.text().to_string(), pos)),
+				_ => None,
+			})
+			.collect()
+	}
+
+	#[test]
+	fn text_alignment_shifts_lines_in_their_box() {
+		let renderer = renderer();
+		let text = renderer.text("hi there");
+		let hi_w = renderer.text("hi").size().w;
+		let there_w = renderer.text("there").size().w;
+		let line_h = (text.size().h + Text::LINE_GAP) as i16;
+		let box_w = there_w + 2;
+
+		for (align, hi_x, there_x) in [
+			(HAlign::Left, 0, 0),
+			(HAlign::Center, (box_w - hi_w) / 2, (box_w - there_w) / 2),
+			(HAlign::Right, box_w - hi_w, box_w - there_w),
+		] {
+			let mut ui = UiContext::new(size(64, 64));
+			let root = root(&mut ui);
+			let label = ui.build_widget(
+				WidgetProps::text(wk!(), text.clone())
+					.with_size(WidgetSize::new(WidgetDim::Fixed(box_w), WidgetDim::Hug))
+					.with_text_align(align),
+			);
+			ui.add_child(root, label.id());
+			ui.solve_layout();
+
+			// "hi there" doesn't fit in the box, so it wraps on two lines
+			assert_eq!(
+				text_positions(&mut ui),
+				[
+					("hi".to_owned(), pos(hi_x as i16, 0)),
+					("there".to_owned(), pos(there_x as i16, line_h)),
+				],
+				"{align:?}"
+			);
+		}
+	}
+
+	#[test]
+	fn debug_colors_are_stable_and_spread_out() {
+		let keys: Vec<WidgetKey> = (0..100u64).map(|i| wk!(i)).collect();
+		for key in &keys {
+			assert_eq!(key.debug_color(), key.debug_color());
+		}
+
+		// there are only so many hues, so a few keys can share one
+		let differing = (keys.windows(2))
+			.filter(|pair| pair[0].debug_color() != pair[1].debug_color())
+			.count();
+		assert!(
+			differing >= 95,
+			"only {differing} of 99 pairs of keys have different colors"
+		);
+	}
+
+	#[test]
+	fn debug_outlines_go_around_every_widget() {
+		let mut ui = UiContext::new(size(32, 32));
+		let root = root(&mut ui);
+		let child = ui.build_widget(WidgetProps::new(wk!()).with_size(WidgetSize::fixed(4, 4)));
+		ui.add_child(root, child.id());
+		ui.solve_layout();
+
+		let outlines = |ui: &mut UiContext| {
+			let mut draw_cmds = Vec::new();
+			ui.draw_widgets(&mut draw_cmds);
+			(draw_cmds.into_iter())
+				.filter_map(|cmd| match cmd {
+					DrawCommand::Stroke { rect, color, .. } => Some((rect, color.to_u32())),
+					_ => None,
+				})
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(outlines(&mut ui), []);
+
+		ui.set_debug_outlines(true);
+		let root_key = ui.widget(root).props.key;
+		let child_key = ui.widget(child.id()).props.key;
+		assert_eq!(
+			outlines(&mut ui),
+			[
+				(Rect::from_xywh(0, 0, 32, 32), root_key.debug_color().to_u32()),
+				(Rect::from_xywh(0, 0, 4, 4), child_key.debug_color().to_u32()),
+			]
+		);
+	}
+
+	/// Plays a frame of a UI holding a single clickable 4x4 button in its top-left corner, shown at some scale, giving
+	/// the button's reaction to the frame before.
+	fn button_frame(ui: &mut UiContext, mouse: &Mouse, scale: Scale) -> WidgetReaction {
+		let root = root(ui);
+		let button = ui.build_widget(
+			WidgetProps::new(wk!())
+				.with_size(WidgetSize::fixed(4, 4))
+				.with_flags(WidgetFlags::CAN_HOVER | WidgetFlags::CAN_CLICK),
+		);
+		ui.add_child(root, button.id());
+
+		ui.solve_layout();
+		ui.free_untouched_widgets();
+		ui.react(mouse, scale);
+		button
+	}
+
+	/// Reactions of the button to a press of a mouse button on it, then to its release.
+	fn press_and_release(button: fn(&mut Mouse) -> &mut (bool, bool)) -> [WidgetReaction; 2] {
+		let mut ui = UiContext::new(size(16, 16));
+		let mut mouse = Mouse {
+			x: 1.,
+			y: 1.,
+			inside: true,
+			..Mouse::default()
+		};
+		button_frame(&mut ui, &mouse, Scale::X1);
+
+		*button(&mut mouse) = (true, false);
+		button_frame(&mut ui, &mouse, Scale::X1);
+		*button(&mut mouse) = (false, true);
+		let pressed = button_frame(&mut ui, &mouse, Scale::X1);
+		*button(&mut mouse) = (false, false);
+		let released = button_frame(&mut ui, &mouse, Scale::X1);
+
+		[pressed, released]
+	}
+
+	#[test]
+	fn right_click_is_not_a_click() {
+		let [pressed, released] = press_and_release(|mouse| &mut mouse.r_pressed);
+		assert!(pressed.right_pressed() && !pressed.pressed() && !pressed.middle_pressed());
+		assert!(!released.right_pressed() && released.right_clicked());
+		assert!(!released.clicked() && !released.middle_clicked());
+	}
+
+	#[test]
+	fn middle_click_is_not_a_click() {
+		let [pressed, released] = press_and_release(|mouse| &mut mouse.m_pressed);
+		assert!(pressed.middle_pressed() && !pressed.pressed() && !pressed.right_pressed());
+		assert!(released.middle_clicked());
+		assert!(!released.clicked() && !released.right_clicked());
+	}
+
+	#[test]
+	fn left_click_is_only_a_click() {
+		let [pressed, released] = press_and_release(|mouse| &mut mouse.l_pressed);
+		assert!(pressed.pressed() && !pressed.right_pressed());
+		assert!(released.clicked());
+		assert!(!released.right_clicked() && !released.middle_clicked());
+	}
+
+	/// Draws a lone widget in the top-left corner of a 16x16 viewport, giving the pixels it drew.
+	fn drawn_widget(props: WidgetProps) -> Bitmap {
+		let mut ui = UiContext::new(size(16, 16));
+		let root = root(&mut ui);
+		let widget = ui.build_widget(props);
+		ui.add_child(root, widget.id());
+		ui.solve_layout();
+
+		let mut draw_cmds = Vec::new();
+		ui.draw_widgets(&mut draw_cmds);
+		let mut renderer = renderer();
+		renderer.draw(&draw_cmds).unwrap();
+		renderer.first_framebuffer().clone()
+	}
+
+	/// A row of a bitmap from its left edge, as `#` for drawn pixels and `.` for untouched ones.
+	fn row(bitmap: &Bitmap, y: i16, w: i16) -> String {
+		(0..w)
+			.map(|x| match bitmap.get(pos(x, y)) {
+				Some(0) | None => '.',
+				Some(_) => '#',
+			})
+			.collect()
+	}
+
+	fn bordered(style: BorderStyle) -> Bitmap {
+		drawn_widget(
+			WidgetProps::new(wk!())
+				.with_size(WidgetSize::fixed(10, 6))
+				.with_flags(WidgetFlags::DRAW_BORDER)
+				.with_border_color(Color::WHITE)
+				.with_border_style(style),
+		)
+	}
+
+	#[test]
+	fn dashed_border_leaves_gaps_as_long_as_its_dashes() {
+		let bitmap = bordered(BorderStyle::Dashed(2));
+		let rows: Vec<String> = (0..7).map(|y| row(&bitmap, y, 11)).collect();
+		assert_eq!(
+			rows,
+			[
+				"##..##..##.",
+				"#........#.",
+				"#........#.",
+				"...........",
+				"...........",
+				"##..##..##.",
+				"...........",
+			]
+		);
+	}
+
+	#[test]
+	fn dotted_border_alternates_pixels() {
+		let bitmap = bordered(BorderStyle::Dotted);
+		assert_eq!(row(&bitmap, 0, 10), "#.#.#.#.#.");
+		assert_eq!(row(&bitmap, 1, 10), "#........#");
+		assert_eq!(row(&bitmap, 2, 10), "..........");
+		assert_eq!(row(&bitmap, 5, 10), "#.#.#.#.#.");
+	}
+
+	#[test]
+	fn double_border_has_a_gap_between_its_lines() {
+		let bitmap = bordered(BorderStyle::Double);
+		assert_eq!(row(&bitmap, 0, 10), "##########");
+		assert_eq!(row(&bitmap, 1, 10), "#........#");
+		assert_eq!(row(&bitmap, 2, 10), "#.######.#");
+		assert_eq!(row(&bitmap, 3, 10), "#.######.#");
+		assert_eq!(row(&bitmap, 5, 10), "##########");
+	}
+
+	#[test]
+	fn solid_border_is_a_plain_frame() {
+		let bitmap = bordered(BorderStyle::Solid);
+		assert_eq!(row(&bitmap, 0, 11), "##########.");
+		for y in 1..5 {
+			assert_eq!(row(&bitmap, y, 11), "#........#.");
+		}
+		assert_eq!(row(&bitmap, 5, 11), "##########.");
+	}
+
+	fn rounded(flags: WidgetFlags, radius: u16) -> Bitmap {
+		drawn_widget(
+			WidgetProps::new(wk!())
+				.with_size(WidgetSize::fixed(8, 6))
+				.with_flags(flags)
+				.with_color(Color::WHITE)
+				.with_border_color(Color::WHITE)
+				.with_corner_radius(radius),
+		)
+	}
+
+	fn alpha_at(bitmap: &Bitmap, x: i16, y: i16) -> u32 {
+		bitmap.get(pos(x, y)).unwrap() >> 24
+	}
+
+	#[test]
+	fn rounded_background_leaves_its_corners_out() {
+		let bitmap = rounded(WidgetFlags::DRAW_BACKGROUND, 2);
+		for (x, y) in [(0, 0), (7, 0), (0, 5), (7, 5)] {
+			assert_eq!(alpha_at(&bitmap, x, y), 0, "({x}, {y})");
+		}
+
+		// next to the corners, pixels are only partly covered
+		for (x, y) in [(1, 0), (0, 1), (6, 0), (7, 4)] {
+			let alpha = alpha_at(&bitmap, x, y);
+			assert!((1..0xff).contains(&alpha), "({x}, {y}) has an alpha of {alpha}");
+		}
+
+		for (x, y) in [(1, 1), (6, 4), (2, 0), (0, 2), (3, 3)] {
+			assert_eq!(alpha_at(&bitmap, x, y), 0xff, "({x}, {y})");
+		}
+	}
+
+	#[test]
+	fn rounded_border_follows_the_corners() {
+		let bitmap = rounded(WidgetFlags::DRAW_BORDER, 2);
+		for (x, y) in [(0, 0), (7, 0), (0, 5), (7, 5)] {
+			assert_eq!(alpha_at(&bitmap, x, y), 0, "({x}, {y})");
+		}
+		assert_eq!(alpha_at(&bitmap, 3, 0), 0xff);
+		assert_eq!(alpha_at(&bitmap, 0, 3), 0xff);
+		assert_eq!(alpha_at(&bitmap, 3, 3), 0);
+	}
+
+	#[test]
+	fn no_radius_fills_the_whole_rect() {
+		let bitmap = rounded(WidgetFlags::DRAW_BACKGROUND, 0);
+		for y in 0..6 {
+			assert_eq!(row(&bitmap, y, 9), "########.");
+		}
+	}
+
+	fn cursor_at(x: f32, y: f32) -> Mouse {
+		Mouse {
+			x,
+			y,
+			inside: true,
+			..Mouse::default()
+		}
+	}
+
+	#[test]
+	fn scaled_cursor_maps_to_the_viewport_pixel_under_it() {
+		assert_eq!(cursor_at(0., 0.).viewport_pos(Scale::X2), pos(0, 0));
+		assert_eq!(cursor_at(1.9, 1.).viewport_pos(Scale::X2), pos(0, 0));
+		assert_eq!(cursor_at(2., 3.).viewport_pos(Scale::X2), pos(1, 1));
+		assert_eq!(cursor_at(17., 9.).viewport_pos(Scale::X2), pos(8, 4));
+
+		assert_eq!(cursor_at(3.99, 4.).viewport_pos(Scale::X4), pos(0, 1));
+		assert_eq!(cursor_at(17., 31.).viewport_pos(Scale::X4), pos(4, 7));
+		assert_eq!(cursor_at(17., 31.).viewport_pos(Scale::X1), pos(17, 31));
+		assert_eq!(cursor_at(-1., 5.).viewport_pos(Scale::X4), pos(-1, 1));
+	}
+
+	#[test]
+	fn scaled_cursor_hovers_what_its_viewport_pixel_is_on() {
+		let hovered = |x, y, scale| {
+			let mut ui = UiContext::new(size(16, 16));
+			button_frame(&mut ui, &cursor_at(x, y), scale);
+			button_frame(&mut ui, &cursor_at(x, y), scale).hovered()
+		};
+
+		// the button covers viewport pixels 0 to 3
+		assert!(hovered(7., 7., Scale::X2));
+		assert!(!hovered(8., 7., Scale::X2));
+		assert!(hovered(15., 15., Scale::X4));
+		assert!(!hovered(16., 0., Scale::X4));
+		assert!(!hovered(7., 7., Scale::X1));
+	}
+
+	#[test]
+	fn laid_out_widgets_report_their_rect() {
+		let mut ui = UiContext::new(size(32, 24));
+		let root = root(&mut ui);
+		let centered = ui.build_widget(
+			WidgetProps::new(wk!())
+				.with_size(WidgetSize::fixed(6, 4))
+				.with_anchor_origin(Anchor::CENTER, Anchor::CENTER),
+		);
+		let offset = ui.build_widget(
+			WidgetProps::new(wk!())
+				.with_size(WidgetSize::fixed(3, 2))
+				.with_pos(pos(1, 2)),
+		);
+		ui.add_child(root, centered.id());
+		ui.add_child(centered.id(), offset.id());
+		ui.solve_layout();
+
+		assert_eq!(ui.widget_rect(root), Some(Rect::from_xywh(0, 0, 32, 24)));
+		assert_eq!(ui.widget_rect(centered.id()), Some(Rect::from_xywh(13, 10, 6, 4)));
+		assert_eq!(ui.widget_rect(offset.id()), Some(Rect::from_xywh(14, 12, 3, 2)));
+	}
+
+	#[test]
+	fn freed_widgets_have_no_rect() {
+		let mut ui = UiContext::new(size(32, 24));
+		let first_root = root(&mut ui);
+		let child = ui.build_widget(WidgetProps::new(wk!()).with_size(WidgetSize::fixed(6, 4)));
+		ui.add_child(first_root, child.id());
+		ui.solve_layout();
+		ui.free_untouched_widgets();
+		assert!(ui.widget_rect(child.id()).is_some());
+
+		root(&mut ui);
+		ui.free_untouched_widgets();
+		assert_eq!(ui.widget_rect(child.id()), None);
+	}
+
+	/// Sprites drawn by a frame, without building it again.
+	fn drawn_sprites(ui: &mut UiContext) -> Vec<(SpritesheetId, Sprite, Pos)> {
+		let mut draw_cmds = Vec::new();
+		ui.draw_widgets(&mut draw_cmds);
+		(draw_cmds.into_iter())
+			.filter_map(|cmd| match cmd {
+				DrawCommand::Sprite {
+					sheet_id, sprite, pos, ..
+				} => Some((sheet_id, sprite, pos)),
+				_ => None,
+			})
+			.collect()
+	}
+
+	#[test]
+	fn rebinding_a_named_sprite_changes_what_gets_drawn() {
+		const ICON: NamedSpriteKey = NamedSpriteKey("icon");
+
+		let mut renderer = renderer();
+		let light = renderer.register_spritesheet(Bitmap::new(size(8, 8)).unwrap());
+		let dark = renderer.register_spritesheet(Bitmap::new(size(8, 8)).unwrap());
+		let light_icon = Sprite::new(Rect::from_xywh(0, 0, 4, 4));
+		let dark_icon = Sprite::new(Rect::from_xywh(4, 4, 4, 4));
+
+		let mut ui = UiContext::new(size(16, 16));
+		ui.bind_sprite(ICON, WidgetSprite::Simple(light, light_icon));
+		let root = root(&mut ui);
+		let icon = ui.build_widget(
+			WidgetProps::new(wk!())
+				.with_size(WidgetSize::hug())
+				.with_pos(pos(2, 3))
+				.with_flags(WidgetFlags::DRAW_SPRITE)
+				.with_sprite(Some(WidgetSprite::Named(ICON))),
+		);
+		ui.add_child(root, icon.id());
+		ui.solve_layout();
+		assert_eq!(drawn_sprites(&mut ui), [(light, light_icon, pos(2, 3))]);
+
+		ui.bind_sprite(ICON, WidgetSprite::Simple(dark, dark_icon));
+		assert_eq!(drawn_sprites(&mut ui), [(dark, dark_icon, pos(2, 3))]);
+	}
+
+	#[test]
+	fn unbound_named_sprites_draw_nothing() {
+		let mut ui = UiContext::new(size(16, 16));
+		ui.bind_sprite(NamedSpriteKey("alias"), WidgetSprite::Named(NamedSpriteKey("icon")));
+		let root = root(&mut ui);
+		for (i, name) in (0u64..).zip(["icon", "alias"]) {
+			let widget = ui.build_widget(
+				WidgetProps::new(wk!(i))
+					.with_size(WidgetSize::fixed(4, 4))
+					.with_flags(WidgetFlags::DRAW_SPRITE)
+					.with_sprite(Some(WidgetSprite::Named(NamedSpriteKey(name)))),
+			);
+			ui.add_child(root, widget.id());
+		}
+		ui.solve_layout();
+		assert_eq!(drawn_sprites(&mut ui), []);
+	}
+
+	/// Builds a frame of a padded column of a text, a fixed box and a filled box, giving every widget's id.
+	fn zero_size_frame(ui: &mut UiContext, renderer: &Renderer) -> [WidgetId; 5] {
+		let root = root(ui);
+		let column = ui.build_widget(
+			WidgetProps::new(wk!())
+				.with_size(WidgetSize::fill())
+				.with_padding(WidgetPadding::all(2))
+				.with_layout(WidgetLayout::flex(FlexDirection::Vertical, 1))
+				.with_flags(WidgetFlags::DRAW_BACKGROUND | WidgetFlags::DRAW_BORDER),
+		);
+		ui.add_child(root, column.id());
+
+		let label = ui.build_widget(WidgetProps::text(wk!(), renderer.text("hello there")));
+		let fixed = ui.build_widget(
+			WidgetProps::new(wk!())
+				.with_size(WidgetSize::fixed(4, 4))
+				.with_flags(WidgetFlags::DRAW_BACKGROUND),
+		);
+		let fill = ui.build_widget(
+			WidgetProps::new(wk!())
+				.with_size(WidgetSize::fill())
+				.with_flags(WidgetFlags::DRAW_BACKGROUND),
+		);
+		for child in [label.id(), fixed.id(), fill.id()] {
+			ui.add_child(column.id(), child);
+		}
+
+		ui.solve_layout();
+		ui.free_untouched_widgets();
+		[root, column.id(), label.id(), fixed.id(), fill.id()]
+	}
+
+	#[test]
+	fn a_zero_size_viewport_lays_out_and_draws_nothing() {
+		let mut renderer = renderer();
+		renderer.resize(size(0, 0)).unwrap();
+
+		let mut ui = UiContext::new(size(32, 32));
+		zero_size_frame(&mut ui, &renderer);
+
+		// e.g. the window getting minimized
+		ui.set_viewport(size(0, 0));
+		for _ in 0..2 {
+			let [root, .., fill] = zero_size_frame(&mut ui, &renderer);
+			assert_eq!(ui.widget_rect(root).unwrap().size(), size(0, 0));
+			assert_eq!(ui.widget_rect(fill).unwrap().size(), size(0, 0));
+
+			let mut draw_cmds = Vec::new();
+			ui.draw_widgets(&mut draw_cmds);
+			assert_eq!(renderer.draw(&draw_cmds), Ok(()));
+			assert_eq!(renderer.last_frame_stats(), RenderStats::default());
+			assert_eq!(renderer.render_into(&mut [], size(0, 0)), Ok(()));
+		}
+
+		// and drawing picks up again once it's back
+		renderer.resize(size(16, 16)).unwrap();
+		ui.set_viewport(size(16, 16));
+		zero_size_frame(&mut ui, &renderer);
+		let [_, column, ..] = zero_size_frame(&mut ui, &renderer);
+		assert_eq!(ui.widget_rect(column).unwrap().size(), size(16, 16));
+
+		let mut draw_cmds = Vec::new();
+		ui.draw_widgets(&mut draw_cmds);
+		renderer.draw(&draw_cmds).unwrap();
+		assert_ne!(renderer.last_frame_stats(), RenderStats::default());
+	}
+
+	/// Plays a frame of a 4x4 button bound to the R key, with the mouse away from it, after feeding some keys.
+	fn shortcut_frame(ui: &mut UiContext, flags: WidgetFlags, keys: &[Key]) -> WidgetReaction {
+		let root = root(ui);
+		let button = ui.build_widget(
+			WidgetProps::new(wk!())
+				.with_size(WidgetSize::fixed(4, 4))
+				.with_flags(flags)
+				.with_shortcut(Key::R),
+		);
+		ui.add_child(root, button.id());
+
+		ui.solve_layout();
+		ui.free_untouched_widgets();
+		ui.feed_keys(keys);
+		ui.react(&Mouse::default(), Scale::X1);
+		button
+	}
+
+	#[test]
+	fn a_shortcut_key_clicks_its_widget() {
+		let clickable = WidgetFlags::CAN_HOVER | WidgetFlags::CAN_CLICK;
+		let mut ui = UiContext::new(size(16, 16));
+		shortcut_frame(&mut ui, clickable, &[Key::R]);
+
+		let clicked = shortcut_frame(&mut ui, clickable, &[]);
+		assert!(clicked.clicked());
+		assert!(!clicked.hovered() && !clicked.pressed());
+
+		// only for the frame it got pressed on
+		assert!(!shortcut_frame(&mut ui, clickable, &[]).clicked());
+	}
+
+	#[test]
+	fn other_keys_and_unclickable_widgets_dont_click() {
+		let clickable = WidgetFlags::CAN_HOVER | WidgetFlags::CAN_CLICK;
+		let mut ui = UiContext::new(size(16, 16));
+		shortcut_frame(&mut ui, clickable, &[Key::T, Key::Space]);
+		assert!(!shortcut_frame(&mut ui, clickable, &[]).clicked());
+
+		let mut ui = UiContext::new(size(16, 16));
+		shortcut_frame(&mut ui, WidgetFlags::CAN_HOVER, &[Key::R]);
+		assert!(!shortcut_frame(&mut ui, WidgetFlags::CAN_HOVER, &[]).clicked());
+	}
+
+	const ROTATIONS: [Rotate; 4] = [Rotate::R0, Rotate::R90, Rotate::R180, Rotate::R270];
+
+	/// Draws a sprite of some size at (8, 8) of a 16x16 framebuffer, rotated about some pivot.
+	///
+	/// The sprite is opaque white, except for its top-left pixel which is red, so it shows where it went.
+	fn drawn_rotated(sprite_size: Size, rotate: Rotate, pivot: Option<Pos>) -> Bitmap {
+		let mut renderer = renderer();
+		let mut sheet = Bitmap::from_buffer(vec![0xffffffff; 64], size(8, 8)).unwrap();
+		sheet.set(pos(0, 0), 0xffff0000);
+		let sheet_id = renderer.register_spritesheet(sheet);
+
+		let mut ui = UiContext::new(size(16, 16));
+		let root = root(&mut ui);
+		let mut props = WidgetProps::new(wk!())
+			.with_size(WidgetSize::fixed(sprite_size.w, sprite_size.h))
+			.with_pos(pos(8, 8))
+			.with_flags(WidgetFlags::DRAW_SPRITE)
+			.with_sprite(Some(WidgetSprite::Simple(
+				sheet_id,
+				Sprite::new(Rect::from_pos_size(Pos::ZERO, sprite_size)),
+			)))
+			.with_rotate(rotate);
+		if let Some(pivot) = pivot {
+			props = props.with_rotate_pivot(pivot);
+		}
+		let sprite = ui.build_widget(props);
+		ui.add_child(root, sprite.id());
+		ui.solve_layout();
+
+		let mut draw_cmds = Vec::new();
+		ui.draw_widgets(&mut draw_cmds);
+		renderer.draw(&draw_cmds).unwrap();
+		renderer.first_framebuffer().clone()
+	}
+
+	/// The rect covering every drawn pixel of a bitmap, and where its red pixel is.
+	fn drawn_bounds(bitmap: &Bitmap) -> (Rect, Pos) {
+		let size = bitmap.size();
+		let drawn: Vec<(Pos, u32)> = (0..size.h as i16)
+			.flat_map(|y| (0..size.w as i16).map(move |x| pos(x, y)))
+			.filter_map(|p| bitmap.get(p).filter(|&px| px != 0).map(|px| (p, px)))
+			.collect();
+
+		let xs = || drawn.iter().map(|(p, _)| p.x);
+		let ys = || drawn.iter().map(|(p, _)| p.y);
+		let (x, y) = (xs().min().unwrap(), ys().min().unwrap());
+		let (w, h) = (xs().max().unwrap() - x + 1, ys().max().unwrap() - y + 1);
+		let red = drawn.iter().find(|&&(_, px)| px == 0xffff0000).unwrap().0;
+		(Rect::from_xywh(x, y, w as u16, h as u16), red)
+	}
+
+	#[test]
+	fn rotating_about_the_center_is_the_default() {
+		for sprite_size in [size(4, 4), size(4, 2), size(2, 4)] {
+			let center = pos(sprite_size.w as i16 / 2, sprite_size.h as i16 / 2);
+			for rotate in ROTATIONS {
+				assert_eq!(
+					drawn_rotated(sprite_size, rotate, Some(center)),
+					drawn_rotated(sprite_size, rotate, None),
+					"{sprite_size:?} {rotate:?}"
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn rotating_about_a_corner_turns_the_sprite_around_it() {
+		// the top-left corner's on (8, 8), so the sprite swings around that point clockwise
+		for (rotate, bounds, red) in [
+			(Rotate::R0, Rect::from_xywh(8, 8, 4, 4), pos(8, 8)),
+			(Rotate::R90, Rect::from_xywh(4, 8, 4, 4), pos(7, 8)),
+			(Rotate::R180, Rect::from_xywh(4, 4, 4, 4), pos(7, 7)),
+			(Rotate::R270, Rect::from_xywh(8, 4, 4, 4), pos(8, 7)),
+		] {
+			let bitmap = drawn_rotated(size(4, 4), rotate, Some(pos(0, 0)));
+			assert_eq!(drawn_bounds(&bitmap), (bounds, red), "{rotate:?}");
+		}
+
+		// about its bottom-right corner, on (12, 12)
+		let bitmap = drawn_rotated(size(4, 4), Rotate::R90, Some(pos(4, 4)));
+		assert_eq!(drawn_bounds(&bitmap), (Rect::from_xywh(12, 8, 4, 4), pos(15, 8)));
+	}
+
+	/// Plays a frame of two hoverable widgets, one of them going past the bottom-right corner of the 16x16 viewport,
+	/// giving their reactions to the previous frame.
+	fn hover_frame(ui: &mut UiContext, mouse: &Mouse) -> [WidgetReaction; 2] {
+		let root = root(ui);
+		let widgets = [(wk!(), pos(0, 0)), (wk!(), pos(12, 12))].map(|(key, at)| {
+			let widget = ui.build_widget(
+				WidgetProps::new(key)
+					.with_size(WidgetSize::fixed(8, 8))
+					.with_pos(at)
+					.with_flags(WidgetFlags::CAN_HOVER),
+			);
+			ui.add_child(root, widget.id());
+			widget
+		});
+
+		ui.solve_layout();
+		ui.free_untouched_widgets();
+		ui.react(mouse, Scale::X1);
+		widgets
+	}
+
+	fn mouse_at(x: f32, y: f32, inside: bool) -> Mouse {
+		Mouse {
+			x,
+			y,
+			inside,
+			..Mouse::default()
+		}
+	}
+
+	#[test]
+	fn leaving_the_window_clears_every_hover() {
+		let mut ui = UiContext::new(size(16, 16));
+		hover_frame(&mut ui, &mouse_at(2., 2., true));
+		hover_frame(&mut ui, &mouse_at(14., 14., true));
+		let [first, second] = hover_frame(&mut ui, &mouse_at(14., 14., true));
+		assert!(!first.hovered() && second.hovered());
+
+		// the last known position is still on the widget, but the cursor isn't in the window anymore
+		hover_frame(&mut ui, &mouse_at(14., 14., false));
+		let [first, second] = hover_frame(&mut ui, &mouse_at(14., 14., false));
+		assert!(!first.hovered() && !second.hovered());
+	}
+
+	#[test]
+	fn a_cursor_outside_the_viewport_hovers_nothing() {
+		let mut ui = UiContext::new(size(16, 16));
+		for (x, y) in [(18., 18.), (-1., 2.), (2., 16.), (f32::MAX, 0.)] {
+			hover_frame(&mut ui, &mouse_at(x, y, true));
+			let [first, second] = hover_frame(&mut ui, &mouse_at(x, y, true));
+
+			// even the part of the widget that goes past the viewport
+			assert!(!first.hovered() && !second.hovered(), "({x}, {y})");
+		}
+	}
+
+	/// Builds a frame of a grid of bordered boxes, some with text and its outline, which draws a lot of commands.
+	fn busy_frame(ui: &mut UiContext, renderer: &Renderer) {
+		let root = root(ui);
+		let grid = ui.build_widget(
+			WidgetProps::new(wk!())
+				.with_size(WidgetSize::fill())
+				.with_layout(WidgetLayout::flex(FlexDirection::Horizontal, 1).with_wrap(true)),
+		);
+		ui.add_child(root, grid.id());
+
+		for i in 0..40u64 {
+			let mut props = WidgetProps::new(wk!(i))
+				.with_size(WidgetSize::fixed(6, 6))
+				.with_flags(WidgetFlags::DRAW_BACKGROUND | WidgetFlags::DRAW_BORDER);
+			if i % 3 == 0 {
+				props = props
+					.with_text(Some(renderer.text_cached("a")))
+					.with_flags(WidgetFlags::DRAW_BACKGROUND | WidgetFlags::DRAW_BORDER | WidgetFlags::DRAW_TEXT)
+					.with_text_outline(Color::WHITE);
+			}
+			let cell = ui.build_widget(props);
+			ui.add_child(grid.id(), cell.id());
+		}
+
+		ui.solve_layout();
+		ui.free_untouched_widgets();
+	}
+
+	#[test]
+	fn drawing_a_stable_scene_into_the_same_buffer_doesnt_reallocate() {
+		let renderer = renderer();
+		let mut ui = UiContext::new(size(64, 64));
+		let mut buf = Vec::new();
+
+		busy_frame(&mut ui, &renderer);
+		ui.draw_widgets_into(&mut buf);
+		let (capacity, ptr) = (buf.capacity(), buf.as_ptr());
+		let count = buf.len();
+
+		for _ in 0..5 {
+			buf.clear();
+			busy_frame(&mut ui, &renderer);
+			ui.draw_widgets_into(&mut buf);
+			assert_eq!(buf.len(), count);
+			assert_eq!((buf.capacity(), buf.as_ptr()), (capacity, ptr));
+		}
+	}
+
+	#[test]
+	fn drawing_into_a_buffer_draws_the_same_as_without_it() {
+		let renderer = renderer();
+		let mut ui = UiContext::new(size(64, 64));
+		busy_frame(&mut ui, &renderer);
+
+		let mut plain = Vec::new();
+		ui.draw_widgets(&mut plain);
+
+		// whatever's already in the buffer is kept
+		let mut buf = vec![DrawCommand::Clear];
+		ui.draw_widgets_into(&mut buf);
+		assert_eq!(format!("{:?}", &buf[1..]), format!("{plain:?}"));
+		assert!(matches!(buf[0], DrawCommand::Clear));
+	}
+
+	/// Pixels drawn by an "o" text widget away from the viewport's edges, with or without an outline and shadow.
+	fn drawn_glyph(outline: Option<Color>, shadow: Option<(Pos, Color)>) -> Bitmap {
+		let mut renderer = renderer();
+		let mut ui = UiContext::new(size(16, 16));
+		let root = root(&mut ui);
+		let mut props = WidgetProps::text(wk!(), renderer.text("o")).with_pos(pos(4, 4));
+		if let Some(color) = outline {
+			props = props.with_text_outline(color);
+		}
+		if let Some((offset, color)) = shadow {
+			props = props.with_text_shadow(offset, color);
+		}
+		let label = ui.build_widget(props);
+		ui.add_child(root, label.id());
+		ui.solve_layout();
+
+		let mut draw_cmds = Vec::new();
+		ui.draw_widgets(&mut draw_cmds);
+		renderer.draw(&draw_cmds).unwrap();
+		renderer.first_framebuffer().clone()
+	}
+
+	#[test]
+	fn outlines_go_around_glyphs_under_them() {
+		const RED: u32 = 0xffff0000;
+		let plain = drawn_glyph(None, None);
+		let outlined = drawn_glyph(Some(Color::from_hex(RED)), None);
+
+		let is_glyph = |p: Pos| plain.get(p).is_some_and(|px| px != 0);
+		let mut glyph_px = 0;
+		let mut ring_px = 0;
+		for y in 0..16 {
+			for x in 0..16 {
+				let p = pos(x, y);
+				let next_to_glyph = (-1..=1).any(|dy| (-1..=1).any(|dx| is_glyph(pos(x + dx, y + dy))));
+				let expected = match (is_glyph(p), next_to_glyph) {
+					(true, _) => plain.get(p).unwrap(),
+					(false, true) => RED,
+					(false, false) => 0,
+				};
+				assert_eq!(outlined.get(p), Some(expected), "{p:?}");
+
+				glyph_px += is_glyph(p) as u32;
+				ring_px += (!is_glyph(p) && next_to_glyph) as u32;
+			}
+		}
+		assert!(glyph_px > 0 && ring_px > 0);
+	}
+
+	#[test]
+	fn shadows_are_offset_copies_under_glyphs() {
+		const RED: u32 = 0xffff0000;
+		let plain = drawn_glyph(None, None);
+		let shadowed = drawn_glyph(None, Some((pos(2, 1), Color::from_hex(RED))));
+
+		let is_glyph = |p: Pos| plain.get(p).is_some_and(|px| px != 0);
+		for y in 0..16 {
+			for x in 0..16 {
+				let p = pos(x, y);
+				let expected = match (is_glyph(p), is_glyph(pos(x - 2, y - 1))) {
+					(true, _) => plain.get(p).unwrap(),
+					(false, true) => RED,
+					(false, false) => 0,
+				};
+				assert_eq!(shadowed.get(p), Some(expected), "{p:?}");
+			}
+		}
+	}
+
+	/// Plays a frame of a UI holding a row of 20 widgets, built with keys from `first_n` on.
+	fn pooled_frame(ui: &mut UiContext, first_n: u64) -> Vec<WidgetId> {
+		let root = root(ui);
+		let widgets = (first_n..first_n + 20)
+			.map(|n| {
+				let widget = ui.build_widget(WidgetProps::new(wk!(n)).with_size(WidgetSize::fixed(1, 1)));
+				ui.add_child(root, widget.id());
+				widget.id()
+			})
+			.collect();
+
+		ui.solve_layout();
+		ui.free_untouched_widgets();
+		widgets
+	}
+
+	#[test]
+	fn widgets_built_every_frame_keep_their_nodes() {
+		let mut ui = UiContext::new(size(16, 16));
+		let first = pooled_frame(&mut ui, 0);
+		let capacity = ui.pooled_capacity();
+		assert_eq!(capacity, 21);
+
+		for _ in 0..50 {
+			assert_eq!(pooled_frame(&mut ui, 0), first);
+			assert_eq!(ui.pooled_capacity(), capacity);
+		}
+	}
+
+	#[test]
+	fn new_widgets_every_frame_reuse_freed_nodes() {
+		let mut ui = UiContext::new(size(16, 16));
+		pooled_frame(&mut ui, 0);
+		pooled_frame(&mut ui, 20);
+		let capacity = ui.pooled_capacity();
+
+		for frame in 2..50 {
+			pooled_frame(&mut ui, frame * 20);
+			assert_eq!(ui.pooled_capacity(), capacity, "frame {frame}");
+		}
+		// the widgets of this frame, and the ones of the frame before waiting to be reused
+		assert_eq!(capacity, 41);
+	}
+
+	#[test]
+	fn freed_widgets_lose_their_keys() {
+		let mut ui = UiContext::new(size(16, 16));
+		for frame in 0..10 {
+			pooled_frame(&mut ui, frame * 20);
+		}
+		assert_eq!(ui.keys.len(), 21);
+	}
+
+	#[test]
+	fn freeing_the_dragged_widget_stops_the_drag() {
+		let mut ui = UiContext::new(size(16, 16));
+		let frame = |ui: &mut UiContext, flags: WidgetFlags, n: u64, mouse: Option<&Mouse>| {
+			let root = root(ui);
+			let widget = ui.build_widget(
+				WidgetProps::new(wk!(n))
+					.with_size(WidgetSize::fixed(4, 4))
+					.with_flags(WidgetFlags::CAN_HOVER | WidgetFlags::CAN_CLICK | flags),
+			);
+			ui.add_child(root, widget.id());
+
+			ui.solve_layout();
+			ui.free_untouched_widgets();
+			if let Some(mouse) = mouse {
+				ui.react(mouse, Scale::X1);
+			}
+			widget.id()
+		};
+
+		let mut mouse = cursor_at(1., 1.);
+		frame(&mut ui, WidgetFlags::DRAGGABLE, 0, Some(&mouse));
+		mouse.l_pressed = (true, false);
+		let dragged = frame(&mut ui, WidgetFlags::DRAGGABLE, 0, Some(&mouse));
+		assert_eq!(ui.dragging, Some(dragged));
+
+		// the dragged widget is freed on a frame that doesn't react, so the next react already sees its node reused
+		frame(&mut ui, WidgetFlags::NONE, 1, None);
+		assert_eq!(ui.dragging, None);
+
+		// the button is still held, over the widget that took the node, which can't be dragged
+		mouse.l_pressed = (true, true);
+		let other = frame(&mut ui, WidgetFlags::NONE, 2, Some(&mouse));
+		assert_eq!(other, dragged);
+		assert_eq!(ui.dragging, None);
+	}
+}
@@ -3,7 +3,7 @@ use std::time::Duration;
 use crate::math::pos::pos;
 use crate::render::color::Color;
 use crate::render::sprite::{NineSlicingSprite, Sprite};
-use crate::render::{SpritesheetId, Text};
+use crate::render::{Renderer, SpritesheetId, Text};
 use crate::ui::WidgetSprite;
 use crate::wk;
 
@@ -12,6 +12,96 @@ use super::{
 	WidgetReaction, WidgetSize,
 };
 
+/// How a number display looks.
+#[derive(Debug, Clone, Copy)]
+pub struct NumberDisplayConfig<'a> {
+	pub sheet_id: SpritesheetId,
+	/// Box around the digits.
+	pub display_box: NineSlicingSprite,
+	/// Sprite drawn behind every digit slot, even empty ones.
+	pub placeholder: Option<Sprite>,
+	/// Sprites of the digits 0 to 9.
+	pub digits: &'a [Sprite; 10],
+	/// Minimum number of digit slots. Numbers with more digits than that widen the display.
+	pub min_digits: usize,
+	pub gap: i16,
+	pub padding: WidgetPadding,
+}
+
+/// Sprites and colors the game's components are drawn with.
+#[derive(Debug, Clone, Copy)]
+pub struct Theme<'a> {
+	pub sheet_id: SpritesheetId,
+	pub button_box: NineSlicingSprite,
+	/// Button box while it's pressed.
+	pub button_pressed_box: NineSlicingSprite,
+	/// Color of the text on buttons.
+	pub button_text_color: Color,
+	/// Color of the outline around titles, to keep them readable over anything.
+	pub title_outline: Option<Color>,
+	/// Box around number and time displays.
+	pub display_box: NineSlicingSprite,
+	/// Sprites of the digits 0 to 9.
+	pub digits: &'a [Sprite; 10],
+	pub colon: Sprite,
+	/// Sprites of the yellow, red and cyan bananas.
+	pub bananas: [Sprite; 3],
+	/// Color behind the playfield's cells.
+	pub playfield_background: Color,
+	/// Color of the border around the playfield, if it has one.
+	pub playfield_border: Option<Color>,
+	/// Color overlays like the game over screen dim what's behind them with. Its alpha is ignored.
+	pub overlay_color: Color,
+	/// How much overlays dim what's behind them, from 0 (not at all) to 255 (fully hidden).
+	pub overlay_dim: u8,
+}
+
+impl Theme<'_> {
+	/// Background color of overlays, with the theme's dim as its alpha.
+	pub fn overlay_background(&self) -> Color {
+		Color {
+			a: self.overlay_dim,
+			..self.overlay_color
+		}
+	}
+}
+
+/// A duration split into what a time display shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeParts {
+	/// Whole hours, however many there are.
+	pub hours: u64,
+	/// Minutes past the hour, below 60.
+	pub minutes: u8,
+	/// Seconds past the minute, below 60.
+	pub seconds: u8,
+	/// Milliseconds past the second, below 1000.
+	pub millis: u16,
+}
+
+impl TimeParts {
+	pub fn from_duration(time: Duration) -> Self {
+		let seconds = time.as_secs();
+		Self {
+			hours: seconds / 3600,
+			minutes: ((seconds / 60) % 60) as u8,
+			seconds: (seconds % 60) as u8,
+			millis: time.subsec_millis() as u16,
+		}
+	}
+}
+
+/// How a run went, as shown when it's over.
+#[derive(Debug, Clone, Copy)]
+pub struct RunStats {
+	pub won: bool,
+	pub score: usize,
+	pub time: Duration,
+	pub length: usize,
+	/// Yellow, red and cyan bananas eaten.
+	pub bananas: [usize; 3],
+}
+
 impl WidgetProps {
 	#[inline]
 	pub fn text(key: WidgetKey, text: Text) -> Self {
@@ -50,7 +140,88 @@ impl WidgetProps {
 	}
 }
 
+/// Builds widgets as children of a parent widget.
+pub struct ChildBuilder<'a> {
+	ui: &'a mut UiContext,
+	parent: WidgetId,
+}
+
+#[allow(unused)]
+impl ChildBuilder<'_> {
+	/// The parent of all the widgets built with this builder.
+	pub fn parent(&self) -> WidgetId {
+		self.parent
+	}
+
+	/// The UI context, to build more complex components.
+	///
+	/// Widgets built through it are not added as children automatically, see [`ChildBuilder::add_child`].
+	pub fn ui(&mut self) -> &mut UiContext {
+		self.ui
+	}
+
+	pub fn add_child(&mut self, child_id: WidgetId) {
+		self.ui.add_child(self.parent, child_id);
+	}
+
+	pub fn build_widget(&mut self, props: WidgetProps) -> WidgetReaction {
+		let child = self.ui.build_widget(props);
+		self.add_child(child.id());
+		child
+	}
+
+	pub fn with_children(&mut self, props: WidgetProps, children: impl FnOnce(&mut ChildBuilder)) -> WidgetReaction {
+		let child = self.ui.with_children(props, children);
+		self.add_child(child.id());
+		child
+	}
+
+	pub fn column(&mut self, props: WidgetProps, children: impl FnOnce(&mut ChildBuilder)) -> WidgetReaction {
+		let child = self.ui.column(props, children);
+		self.add_child(child.id());
+		child
+	}
+
+	pub fn row(&mut self, props: WidgetProps, children: impl FnOnce(&mut ChildBuilder)) -> WidgetReaction {
+		let child = self.ui.row(props, children);
+		self.add_child(child.id());
+		child
+	}
+}
+
 impl UiContext {
+	/// Builds a widget, and then its children inside of the `children` closure.
+	pub fn with_children(&mut self, props: WidgetProps, children: impl FnOnce(&mut ChildBuilder)) -> WidgetReaction {
+		let parent = self.build_widget(props);
+		children(&mut ChildBuilder {
+			ui: self,
+			parent: parent.id(),
+		});
+		parent
+	}
+
+	/// Builds a vertical flex widget with its children.
+	///
+	/// The gap is taken from the layout of `props` if it is a flex layout, otherwise it's 0.
+	pub fn column(&mut self, props: WidgetProps, children: impl FnOnce(&mut ChildBuilder)) -> WidgetReaction {
+		let gap = flex_gap(props.layout);
+		self.with_children(
+			props.with_layout(WidgetLayout::flex(FlexDirection::Vertical, gap)),
+			children,
+		)
+	}
+
+	/// Builds a horizontal flex widget with its children.
+	///
+	/// The gap is taken from the layout of `props` if it is a flex layout, otherwise it's 0.
+	pub fn row(&mut self, props: WidgetProps, children: impl FnOnce(&mut ChildBuilder)) -> WidgetReaction {
+		let gap = flex_gap(props.layout);
+		self.with_children(
+			props.with_layout(WidgetLayout::flex(FlexDirection::Horizontal, gap)),
+			children,
+		)
+	}
+
 	pub fn btn_icon(&mut self, props: WidgetProps, sprite_props: WidgetProps, hover_color: Color) -> WidgetReaction {
 		use WidgetFlags as Wf;
 
@@ -99,31 +270,95 @@ impl UiContext {
 		button
 	}
 
-	pub fn big_3digits_display(
+	/// Builds a widget that can be dragged around with the left mouse button.
+	///
+	/// While it's dragged, it's drawn offset by [`WidgetReaction::drag_delta`].
+	#[allow(unused)]
+	pub fn draggable(&mut self, props: WidgetProps) -> WidgetReaction {
+		let flags = props.flags | WidgetFlags::CAN_HOVER | WidgetFlags::CAN_CLICK | WidgetFlags::DRAGGABLE;
+		let reaction = self.build_widget(props.with_flags(flags));
+
+		self.widget_mut(reaction.id()).props.draw_offset += reaction.drag_delta();
+
+		reaction
+	}
+
+	/// Builds a widget that dragged widgets can be dropped on.
+	///
+	/// [`WidgetReaction::dropped`] gives the widget that just got dropped on it.
+	#[allow(unused)]
+	pub fn drop_target(&mut self, props: WidgetProps) -> WidgetReaction {
+		let flags = props.flags | WidgetFlags::DROP_TARGET;
+		self.build_widget(props.with_flags(flags))
+	}
+
+	/// Builds a vertical list of text items that can be reordered by dragging them onto each other.
+	#[allow(unused)]
+	pub fn reorderable_list<T>(
 		&mut self,
-		key: WidgetKey,
-		n: usize,
-		sheet_id: SpritesheetId,
-		display_box: NineSlicingSprite,
-		placeholder_sprite: Sprite,
-		digit_sprites: &[Sprite; 10],
+		props: WidgetProps,
+		items: &mut [T],
+		mut item_text: impl FnMut(&T) -> Text,
 	) -> WidgetReaction {
+		let key = props.key;
+		let list = self.build_widget(props);
+
+		let mut item_ids = Vec::with_capacity(items.len());
+		let mut moved = None;
+		for (i, item) in items.iter().enumerate() {
+			let i = i as u64;
+			let item_props = WidgetProps::text(wk!([key] i), item_text(item))
+				.with_flags(WidgetFlags::DRAW_TEXT | WidgetFlags::DROP_TARGET);
+			let item = self.draggable(item_props);
+			self.add_child(list.id(), item.id());
+
+			if let Some(dropped) = item.dropped() {
+				moved = Some((dropped, i as usize));
+			}
+			item_ids.push(item.id());
+		}
+
+		if let Some((dropped, to)) = moved {
+			if let Some(from) = item_ids.iter().position(|&id| id == dropped) {
+				match from < to {
+					true => items[from..=to].rotate_left(1),
+					false => items[to..=from].rotate_right(1),
+				}
+			}
+		}
+
+		list
+	}
+
+	/// Displays a number of any width, right-aligned in at least `config.min_digits` slots.
+	///
+	/// Leading zeros are left out, except for the ones place.
+	pub fn number_display(&mut self, key: WidgetKey, n: usize, config: NumberDisplayConfig) -> WidgetReaction {
 		let display = self.build_widget(
-			WidgetProps::nine_slice_sprite(key, sheet_id, display_box)
-				.with_layout(WidgetLayout::flex(FlexDirection::Horizontal, 2))
-				.with_padding(WidgetPadding::hv(3, 2)),
+			WidgetProps::nine_slice_sprite(key, config.sheet_id, config.display_box)
+				.with_layout(WidgetLayout::flex(FlexDirection::Horizontal, config.gap))
+				.with_padding(config.padding),
 		);
 
-		let mut after_first_digit = false;
-		for (i, d) in [(2, (n / 100) % 10), (1, (n / 10) % 10), (0, n % 10)] {
-			let digit_holder =
-				self.build_widget(WidgetProps::simple_sprite(wk!([key] i), sheet_id, placeholder_sprite));
+		let digit_count = n.checked_ilog10().unwrap_or(0) as usize + 1;
+		let slot_count = digit_count.max(config.min_digits);
+		let digit_size = config.digits[0].rect.size();
 
-			if after_first_digit || d > 0 {
-				let digit = self.build_widget(WidgetProps::simple_sprite(wk!([key] i), sheet_id, digit_sprites[d]));
-				self.add_child(digit_holder.id(), digit.id());
+		for i in (0..slot_count as u64).rev() {
+			let holder_props = match config.placeholder {
+				Some(placeholder) => WidgetProps::simple_sprite(wk!([key] i), config.sheet_id, placeholder),
+				None => WidgetProps::new(wk!([key] i)).with_size(WidgetSize::fixed(digit_size.w, digit_size.h)),
+			};
+			let digit_holder = self.build_widget(holder_props);
 
-				after_first_digit = true;
+			if (i as usize) < digit_count {
+				let d = (n / 10usize.pow(i as u32)) % 10;
+				let digit = self.build_widget(WidgetProps::simple_sprite(
+					wk!([key] i),
+					config.sheet_id,
+					config.digits[d],
+				));
+				self.add_child(digit_holder.id(), digit.id());
 			}
 
 			self.add_child(display.id(), digit_holder.id());
@@ -132,6 +367,30 @@ impl UiContext {
 		display
 	}
 
+	pub fn big_3digits_display(
+		&mut self,
+		key: WidgetKey,
+		n: usize,
+		sheet_id: SpritesheetId,
+		display_box: NineSlicingSprite,
+		placeholder_sprite: Sprite,
+		digit_sprites: &[Sprite; 10],
+	) -> WidgetReaction {
+		self.number_display(
+			key,
+			n,
+			NumberDisplayConfig {
+				sheet_id,
+				display_box,
+				placeholder: Some(placeholder_sprite),
+				digits: digit_sprites,
+				min_digits: 3,
+				gap: 2,
+				padding: WidgetPadding::hv(3, 2),
+			},
+		)
+	}
+
 	pub fn time_display(
 		&mut self,
 		key: WidgetKey,
@@ -147,14 +406,35 @@ impl UiContext {
 				.with_padding(WidgetPadding::all(2)),
 		);
 
-		let seconds = time.as_secs();
-		let minutes = ((seconds / 60) % 60) as usize;
-		let seconds = (seconds % 60) as usize;
-		let millis = (time.as_millis() % 1000) as usize;
+		let TimeParts {
+			hours,
+			minutes,
+			seconds,
+			millis,
+		} = TimeParts::from_duration(time);
+		let (minutes, seconds, millis) = (minutes as usize, seconds as usize, millis as usize);
 
 		const BRIGHT_GREEN: Color = Color::from_hex(0xff99e550);
 		const DIMMED_GREEN: Color = Color::from_hex(0xff64a328);
 
+		// hours only show up on long runs, with as many digits as they need
+		if hours > 0 {
+			let hour_digits = hours.to_string();
+			for (i, c) in (0..).zip(hour_digits.bytes()) {
+				let d = (c - b'0') as usize;
+				let digit = self.build_widget(
+					WidgetProps::simple_sprite(wk!([key] i), sheet_id, digit_sprites[d])
+						.with_mask_and(Some(BRIGHT_GREEN)),
+				);
+				self.add_child(display.id(), digit.id());
+			}
+
+			let colon = self.build_widget(
+				WidgetProps::simple_sprite(wk!([key]), sheet_id, colon_sprite).with_mask_and(Some(BRIGHT_GREEN)),
+			);
+			self.add_child(display.id(), colon.id());
+		}
+
 		for (i, d) in [(1, (minutes / 10) % 10), (0, minutes % 10)] {
 			let digit = self.build_widget(
 				WidgetProps::simple_sprite(wk!([key] i), sheet_id, digit_sprites[d]).with_mask_and(Some(BRIGHT_GREEN)),
@@ -188,4 +468,596 @@ impl UiContext {
 
 		display
 	}
+
+	/// Builds the panel shown when a run is over, with its stats and an "OK" button.
+	///
+	/// The returned reaction is the one of the button.
+	pub fn game_over_panel(
+		&mut self,
+		key: WidgetKey,
+		renderer: &Renderer,
+		stats: &RunStats,
+		theme: &Theme,
+	) -> WidgetReaction {
+		let numbers = NumberDisplayConfig {
+			sheet_id: theme.sheet_id,
+			display_box: theme.display_box,
+			placeholder: None,
+			digits: theme.digits,
+			min_digits: 1,
+			gap: 1,
+			padding: WidgetPadding::all(2),
+		};
+
+		let panel = self.build_widget(
+			WidgetProps::new(key)
+				.with_size(WidgetSize::hug())
+				.with_anchor_origin(Anchor::CENTER, Anchor::CENTER)
+				.with_layout(WidgetLayout::flex(FlexDirection::Vertical, 2)),
+		);
+
+		let title = match stats.won {
+			true => "You won! :D",
+			false => "Game Over! :(",
+		};
+		let mut title_props = WidgetProps::text(wk!([key]), renderer.text_cached(title))
+			.with_anchor_origin(Anchor::TOP_CENTER, Anchor::TOP_CENTER);
+		if let Some(outline) = theme.title_outline {
+			title_props = title_props.with_text_outline(outline);
+		}
+		let title = self.build_widget(title_props);
+		self.add_child(panel.id(), title.id());
+
+		let stat_props = |key| {
+			WidgetProps::new(key)
+				.with_size(WidgetSize::hug())
+				.with_anchor_origin(Anchor::TOP_CENTER, Anchor::TOP_CENTER)
+				.with_layout(WidgetLayout::flex(FlexDirection::Horizontal, 2))
+		};
+
+		let score_row = self.build_widget(stat_props(wk!([key])));
+		{
+			let label = self.build_widget(WidgetProps::text(wk!([key]), renderer.text_cached("Score")));
+			self.add_child(score_row.id(), label.id());
+
+			let score = self.number_display(wk!([key]), stats.score, numbers);
+			self.add_child(score_row.id(), score.id());
+		}
+		self.add_child(panel.id(), score_row.id());
+
+		let time_row = self.build_widget(stat_props(wk!([key])));
+		{
+			let label = self.build_widget(WidgetProps::text(wk!([key]), renderer.text_cached("Time")));
+			self.add_child(time_row.id(), label.id());
+
+			let time = self.time_display(
+				wk!([key]),
+				stats.time,
+				theme.sheet_id,
+				theme.display_box,
+				theme.colon,
+				theme.digits,
+			);
+			self.add_child(time_row.id(), time.id());
+		}
+		self.add_child(panel.id(), time_row.id());
+
+		let length_row = self.build_widget(stat_props(wk!([key])));
+		{
+			let label = self.build_widget(WidgetProps::text(wk!([key]), renderer.text_cached("Length")));
+			self.add_child(length_row.id(), label.id());
+
+			let length = self.number_display(wk!([key]), stats.length, numbers);
+			self.add_child(length_row.id(), length.id());
+		}
+		self.add_child(panel.id(), length_row.id());
+
+		let bananas_row = self.build_widget(stat_props(wk!([key])));
+		for (i, (&sprite, &count)) in theme.bananas.iter().zip(&stats.bananas).enumerate() {
+			let i = i as u64;
+			let banana = self.build_widget(
+				WidgetProps::simple_sprite(wk!([key] i), theme.sheet_id, sprite)
+					.with_anchor_origin(Anchor::CENTER_LEFT, Anchor::CENTER_LEFT),
+			);
+			self.add_child(bananas_row.id(), banana.id());
+
+			let count = self.number_display(wk!([key] i), count, numbers);
+			self.add_child(bananas_row.id(), count.id());
+		}
+		self.add_child(panel.id(), bananas_row.id());
+
+		let ok_text = self.build_widget(
+			WidgetProps::text(wk!([key]), renderer.text_cached("OK")).with_mask_and(Some(theme.button_text_color)),
+		);
+		let ok_btn = self.btn_box(
+			WidgetProps::new(wk!([key]))
+				.with_size(WidgetSize::hug())
+				.with_anchor_origin(Anchor::TOP_CENTER, Anchor::TOP_CENTER)
+				.with_padding(WidgetPadding::hv(4, 2)),
+			WidgetSprite::NineSlice(theme.sheet_id, theme.button_box),
+			WidgetSprite::NineSlice(theme.sheet_id, theme.button_pressed_box),
+			ok_text.id(),
+		);
+		self.add_child(panel.id(), ok_btn.id());
+
+		ok_btn
+	}
+}
+
+fn flex_gap(layout: WidgetLayout) -> i16 {
+	match layout {
+		WidgetLayout::Flex { gap, .. } => gap,
+		WidgetLayout::Stacked => 0,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::math::rect::Rect;
+	use crate::math::size::size;
+	use crate::render::bitmap::Bitmap;
+	use crate::ui::layout::LayoutNode;
+	use crate::ui::Mouse;
+	use minifb::Scale;
+
+	const IMG_ASCII_CHARS: &[u8] = include_bytes!("../../assets/ascii-chars.png");
+
+	/// Lays out a window with a column of two rows, the second one holding a single widget.
+	fn window(build: impl FnOnce(&mut UiContext, &[WidgetKey]) -> WidgetId) -> LayoutNode {
+		let keys: Vec<WidgetKey> = (0..6u64).map(|i| wk!(i)).collect();
+		let mut ui = UiContext::new(size(40, 40));
+		let root = build(&mut ui, &keys);
+		ui.solve_layout();
+		ui.dump_layout(root)
+	}
+
+	fn props(key: WidgetKey) -> WidgetProps {
+		WidgetProps::new(key).with_size(WidgetSize::fixed(6, 4))
+	}
+
+	#[test]
+	fn column_and_row_build_the_same_tree_as_by_hand() {
+		let by_hand = window(|ui, keys| {
+			let root = ui.build_widget(WidgetProps::new(keys[0]));
+			let column = ui.build_widget(
+				WidgetProps::new(keys[1])
+					.with_size(WidgetSize::hug())
+					.with_layout(WidgetLayout::flex(FlexDirection::Vertical, 2)),
+			);
+			ui.add_child(root.id(), column.id());
+
+			let first = ui.build_widget(props(keys[2]));
+			ui.add_child(column.id(), first.id());
+			let row = ui.build_widget(
+				WidgetProps::new(keys[3])
+					.with_size(WidgetSize::hug())
+					.with_layout(WidgetLayout::flex(FlexDirection::Horizontal, 1)),
+			);
+			ui.add_child(column.id(), row.id());
+			let last = ui.build_widget(props(keys[4]));
+			ui.add_child(row.id(), last.id());
+			root.id()
+		});
+
+		let with_helpers = window(|ui, keys| {
+			let root = ui.build_widget(WidgetProps::new(keys[0]));
+			let column = ui.column(
+				WidgetProps::new(keys[1])
+					.with_size(WidgetSize::hug())
+					.with_layout(WidgetLayout::flex(FlexDirection::Vertical, 2)),
+				|column| {
+					column.build_widget(props(keys[2]));
+					column.row(
+						WidgetProps::new(keys[3])
+							.with_size(WidgetSize::hug())
+							.with_layout(WidgetLayout::flex(FlexDirection::Horizontal, 1)),
+						|row| {
+							row.build_widget(props(keys[4]));
+						},
+					);
+				},
+			);
+			ui.add_child(root.id(), column.id());
+			root.id()
+		});
+
+		assert_eq!(with_helpers, by_hand);
+		assert_eq!(by_hand.children[0].children.len(), 2);
+	}
+
+	#[test]
+	fn with_children_builds_the_same_tree_as_by_hand() {
+		let padded = |key| {
+			WidgetProps::new(key)
+				.with_size(WidgetSize::hug())
+				.with_padding(WidgetPadding::all(2))
+		};
+
+		let by_hand = window(|ui, keys| {
+			let root = ui.build_widget(WidgetProps::new(keys[0]));
+			let outer = ui.build_widget(padded(keys[1]));
+			ui.add_child(root.id(), outer.id());
+
+			let inner = ui.build_widget(padded(keys[2]));
+			ui.add_child(outer.id(), inner.id());
+			let leaf = ui.build_widget(props(keys[3]));
+			ui.add_child(inner.id(), leaf.id());
+
+			let overlay =
+				ui.build_widget(props(keys[4]).with_anchor_origin(Anchor::BOTTOM_RIGHT, Anchor::BOTTOM_RIGHT));
+			ui.add_child(outer.id(), overlay.id());
+			root.id()
+		});
+
+		let declared = window(|ui, keys| {
+			ui.with_children(WidgetProps::new(keys[0]), |root| {
+				root.with_children(padded(keys[1]), |outer| {
+					outer.with_children(padded(keys[2]), |inner| {
+						inner.build_widget(props(keys[3]));
+					});
+
+					// widgets built through the context itself only get added when asked to
+					let overlay = (outer.ui())
+						.build_widget(props(keys[4]).with_anchor_origin(Anchor::BOTTOM_RIGHT, Anchor::BOTTOM_RIGHT));
+					outer.add_child(overlay.id());
+				});
+			})
+			.id()
+		});
+
+		assert_eq!(declared, by_hand);
+		assert_eq!(by_hand.children[0].children.len(), 2);
+	}
+
+	/// Children of a widget, in order.
+	fn children(ui: &UiContext, wid: WidgetId) -> Vec<WidgetId> {
+		let mut children = Vec::new();
+		let mut child = ui.widget(wid).first_child;
+		while let Some(id) = child {
+			children.push(id);
+			child = ui.widget(id).next;
+		}
+		children
+	}
+
+	/// Digit shown in every slot of a number display, whose digit sprites are 3 pixels apart.
+	fn shown_digits(n: usize, min_digits: usize) -> Vec<Option<i16>> {
+		let mut renderer = Renderer::new(
+			Bitmap::new(size(16, 16)).unwrap(),
+			Bitmap::from_png(IMG_ASCII_CHARS).unwrap(),
+		);
+		let sheet_id = renderer.register_spritesheet(Bitmap::new(size(33, 5)).unwrap());
+		let digits = std::array::from_fn(|d| Sprite::new(Rect::from_xywh(d as i16 * 3, 0, 3, 5)));
+		let config = NumberDisplayConfig {
+			sheet_id,
+			display_box: NineSlicingSprite::new(Rect::from_xywh(0, 0, 3, 3), 1, 2, 1, 2),
+			placeholder: Some(Sprite::new(Rect::from_xywh(30, 0, 3, 5))),
+			digits: &digits,
+			min_digits,
+			gap: 1,
+			padding: WidgetPadding::all(1),
+		};
+
+		let mut ui = UiContext::new(size(64, 16));
+		let display = ui.number_display(wk!(), n, config);
+
+		let slots = children(&ui, display.id());
+		(slots.into_iter())
+			.map(|slot| {
+				let digits = children(&ui, slot);
+				assert!(digits.len() <= 1);
+				digits.first().map(|&digit| match ui.widget(digit).props.sprite {
+					Some(WidgetSprite::Simple(_, sprite)) => sprite.rect.x / 3,
+					ref sprite => panic!("a digit is drawn with {sprite:?}"),
+				})
+			})
+			.collect()
+	}
+
+	#[test]
+	fn numbers_get_as_many_digits_as_they_need() {
+		assert_eq!(shown_digits(1234, 3), [Some(1), Some(2), Some(3), Some(4)]);
+		assert_eq!(shown_digits(1000, 1), [Some(1), Some(0), Some(0), Some(0)]);
+		assert_eq!(shown_digits(usize::MAX, 3).len(), usize::MAX.to_string().len());
+	}
+
+	#[test]
+	fn leading_zeros_are_left_out_but_the_ones_place() {
+		assert_eq!(shown_digits(7, 3), [None, None, Some(7)]);
+		assert_eq!(shown_digits(0, 3), [None, None, Some(0)]);
+		assert_eq!(shown_digits(42, 0), [Some(4), Some(2)]);
+	}
+
+	fn renderer() -> Renderer {
+		Renderer::new(
+			Bitmap::new(size(16, 16)).unwrap(),
+			Bitmap::from_png(IMG_ASCII_CHARS).unwrap(),
+		)
+	}
+
+	/// Plays a frame of a UI built by `build` under its root widget, like the game loop does.
+	fn frame<T>(ui: &mut UiContext, mouse: &Mouse, build: impl FnOnce(&mut UiContext, WidgetId) -> T) -> T {
+		let root = ui.build_widget(WidgetProps::new(wk!()).with_size(WidgetSize::fill()));
+		let built = build(ui, root.id());
+		ui.solve_layout();
+		ui.free_untouched_widgets();
+		ui.react(mouse, Scale::X1);
+		built
+	}
+
+	/// A mouse at some position, with the left button held down or not, having been held down on the frame before
+	/// or not.
+	fn mouse(x: f32, y: f32, held: (bool, bool)) -> Mouse {
+		Mouse {
+			x,
+			y,
+			inside: true,
+			l_pressed: held,
+			..Mouse::default()
+		}
+	}
+
+	/// A draggable square on the top left, and two drop targets, giving the reactions of all three.
+	fn drag_and_drop(ui: &mut UiContext, root: WidgetId) -> [WidgetReaction; 3] {
+		let square = |key, x, y| {
+			WidgetProps::new(key)
+				.with_size(WidgetSize::fixed(4, 4))
+				.with_pos(pos(x, y))
+		};
+
+		let dragged = ui.draggable(square(wk!(), 0, 0));
+		let right = ui.drop_target(square(wk!(), 10, 0));
+		let below = ui.drop_target(square(wk!(), 10, 10));
+		for widget in [dragged, right, below] {
+			ui.add_child(root, widget.id());
+		}
+		[dragged, right, below]
+	}
+
+	/// Drags the square along a path of cursor positions, releasing on the last one, and gives the reactions of the
+	/// frame after that.
+	fn drag_along(path: &[(f32, f32)]) -> [WidgetReaction; 3] {
+		let mut ui = UiContext::new(size(32, 32));
+		let (x, y) = path[0];
+		frame(&mut ui, &mouse(x, y, (false, false)), drag_and_drop);
+		frame(&mut ui, &mouse(x, y, (true, false)), drag_and_drop);
+		for &(x, y) in &path[1..] {
+			frame(&mut ui, &mouse(x, y, (true, true)), drag_and_drop);
+		}
+		let (x, y) = path[path.len() - 1];
+		frame(&mut ui, &mouse(x, y, (false, true)), drag_and_drop);
+		frame(&mut ui, &mouse(x, y, (false, false)), drag_and_drop)
+	}
+
+	#[test]
+	fn releasing_over_a_target_drops_on_it() {
+		let [dragged, right, below] = drag_along(&[(1., 1.), (6., 1.), (11., 2.)]);
+		assert_eq!(right.dropped(), Some(dragged.id()));
+		assert_eq!(below.dropped(), None);
+
+		let [dragged, right, below] = drag_along(&[(1., 1.), (11., 2.), (12., 12.)]);
+		assert_eq!(right.dropped(), None);
+		assert_eq!(below.dropped(), Some(dragged.id()));
+	}
+
+	#[test]
+	fn releasing_over_nothing_drops_nowhere() {
+		for path in [
+			&[(1., 1.), (11., 2.), (20., 20.)][..],
+			// onto itself
+			&[(1., 1.), (11., 2.), (2., 2.)],
+			// the press didn't start on the square
+			&[(6., 6.), (11., 2.)],
+		] {
+			let [_, right, below] = drag_along(path);
+			assert_eq!((right.dropped(), below.dropped()), (None, None), "{path:?}");
+		}
+	}
+
+	#[test]
+	fn dragging_tracks_how_far_the_cursor_went() {
+		let mut ui = UiContext::new(size(32, 32));
+		frame(&mut ui, &mouse(1., 1., (true, false)), drag_and_drop);
+		frame(&mut ui, &mouse(6., 3., (true, true)), drag_and_drop);
+		let [dragged, ..] = frame(&mut ui, &mouse(6., 3., (false, true)), drag_and_drop);
+		assert_eq!(dragged.drag_delta(), pos(5, 2));
+
+		let [dragged, ..] = frame(&mut ui, &mouse(6., 3., (false, false)), drag_and_drop);
+		assert_eq!(dragged.drag_delta(), pos(0, 0));
+	}
+
+	/// Plays a frame of a reorderable list of items, giving the list's id.
+	fn list_frame(ui: &mut UiContext, mouse: &Mouse, renderer: &Renderer, items: &mut [&str]) -> WidgetId {
+		frame(ui, mouse, |ui, root| {
+			let list = ui.reorderable_list(
+				WidgetProps::new(wk!())
+					.with_size(WidgetSize::hug())
+					.with_layout(WidgetLayout::flex(FlexDirection::Vertical, 1)),
+				items,
+				|item| renderer.text(*item),
+			);
+			ui.add_child(root, list.id());
+			list.id()
+		})
+	}
+
+	#[test]
+	fn dropping_an_item_on_another_moves_it_there() {
+		let renderer = renderer();
+		let mut items = ["a", "b", "c", "d"];
+
+		// items are only laid out where they go after the first frame
+		let mut ui = UiContext::new(size(32, 64));
+		let idle = mouse(0., 0., (false, false));
+		list_frame(&mut ui, &idle, &renderer, &mut items);
+		let list = list_frame(&mut ui, &idle, &renderer, &mut items);
+		let centers: Vec<(f32, f32)> = (children(&ui, list).into_iter())
+			.map(|item| {
+				let rect = ui.widget_rect(item).unwrap();
+				(rect.x as f32 + rect.w as f32 / 2., rect.y as f32 + rect.h as f32 / 2.)
+			})
+			.collect();
+
+		let mut drag = |(from_x, from_y), (to_x, to_y)| {
+			for mouse in [
+				mouse(from_x, from_y, (true, false)),
+				mouse(to_x, to_y, (true, true)),
+				mouse(to_x, to_y, (false, true)),
+				mouse(to_x, to_y, (false, false)),
+			] {
+				list_frame(&mut ui, &mouse, &renderer, &mut items);
+			}
+			items
+		};
+		assert_eq!(drag(centers[0], centers[2]), ["b", "c", "a", "d"]);
+		assert_eq!(drag(centers[3], centers[0]), ["d", "b", "c", "a"]);
+		assert_eq!(drag(centers[1], centers[1]), ["d", "b", "c", "a"]);
+	}
+
+	/// Every widget of a subtree, its root included.
+	fn subtree(ui: &UiContext, wid: WidgetId) -> Vec<WidgetId> {
+		let mut widgets = vec![wid];
+		for child in children(ui, wid) {
+			widgets.extend(subtree(ui, child));
+		}
+		widgets
+	}
+
+	/// Builds a game over panel whose digits are the first 10 sprites of its sheet, giving the reaction of its button
+	/// and the panel's id.
+	fn game_over_panel(stats: &RunStats) -> (UiContext, WidgetReaction, WidgetId) {
+		let mut renderer = renderer();
+		let sheet_id = renderer.register_spritesheet(Bitmap::new(size(40, 8)).unwrap());
+		let digits: [Sprite; 10] = std::array::from_fn(|d| Sprite::new(Rect::from_xywh(d as i16 * 3, 0, 3, 5)));
+		let other = Sprite::new(Rect::from_xywh(30, 0, 3, 5));
+		let boxed = NineSlicingSprite::new(Rect::from_xywh(33, 0, 3, 3), 1, 2, 1, 2);
+		let theme = Theme {
+			sheet_id,
+			button_box: boxed,
+			button_pressed_box: boxed,
+			button_text_color: Color::WHITE,
+			title_outline: None,
+			display_box: boxed,
+			digits: &digits,
+			colon: other,
+			bananas: [other; 3],
+			playfield_background: Color::TRANSPARENT,
+			playfield_border: None,
+			overlay_color: Color::TRANSPARENT,
+			overlay_dim: 0,
+		};
+
+		let mut ui = UiContext::new(size(97, 97));
+		let ok = ui.game_over_panel(wk!(), &renderer, stats, &theme);
+		let panel = ui.widget(ok.id()).parent.unwrap();
+
+		(ui, ok, panel)
+	}
+
+	/// How many digit sprites a game over panel shows.
+	fn game_over_digits(stats: &RunStats) -> usize {
+		let (ui, _, panel) = game_over_panel(stats);
+		(subtree(&ui, panel).into_iter())
+			.filter(|&wid| match ui.widget(wid).props.sprite {
+				Some(WidgetSprite::Simple(_, sprite)) => sprite.x < 30,
+				_ => false,
+			})
+			.count()
+	}
+
+	fn run_stats(score: usize, secs: u64, length: usize, bananas: [usize; 3]) -> RunStats {
+		RunStats {
+			won: false,
+			score,
+			time: Duration::from_millis(secs * 1000 + 432),
+			length,
+			bananas,
+		}
+	}
+
+	#[test]
+	fn game_over_panel_shows_a_digit_per_stat_digit() {
+		// score, minutes:seconds:millis, length, and the bananas of each color
+		let stats = run_stats(1234, 65, 12, [7, 0, 105]);
+		assert_eq!(game_over_digits(&stats), 4 + 7 + 2 + (1 + 1 + 3));
+
+		// zeros still take a digit, and hours one more each
+		let stats = run_stats(0, 3725, 2, [0, 0, 0]);
+		assert_eq!(game_over_digits(&stats), 1 + (1 + 7) + 1 + 3);
+	}
+
+	#[test]
+	fn game_over_panel_reacts_with_its_button() {
+		let (ui, ok, panel) = game_over_panel(&run_stats(1, 1, 2, [1, 0, 0]));
+
+		// a title, the rows of stats, and the button last
+		let rows = children(&ui, panel);
+		assert_eq!(rows.len(), 6);
+		assert_eq!(rows.last(), Some(&ok.id()));
+		assert!(ui.widget(ok.id()).props.flags.has(WidgetFlags::CAN_CLICK));
+		assert!(!ok.clicked());
+	}
+
+	#[test]
+	fn long_durations_keep_their_hours() {
+		let time = Duration::from_millis(((2 * 60 + 5) * 60 + 7) * 1000 + 89);
+		assert_eq!(
+			TimeParts::from_duration(time),
+			TimeParts {
+				hours: 2,
+				minutes: 5,
+				seconds: 7,
+				millis: 89,
+			}
+		);
+
+		// nothing rolls over into the minutes
+		let almost_an_hour = Duration::from_millis(3_599_999);
+		assert_eq!(
+			TimeParts::from_duration(almost_an_hour),
+			TimeParts {
+				hours: 0,
+				minutes: 59,
+				seconds: 59,
+				millis: 999,
+			}
+		);
+		let hours =
+			TimeParts::from_duration(almost_an_hour + Duration::from_millis(1) + Duration::from_secs(99 * 3600));
+		assert_eq!(
+			(hours.hours, hours.minutes, hours.seconds, hours.millis),
+			(100, 0, 0, 0)
+		);
+	}
+
+	/// What a time display shows, with sprites read back as characters.
+	fn shown_time(time: Duration) -> String {
+		let mut renderer = renderer();
+		let sheet_id = renderer.register_spritesheet(Bitmap::new(size(40, 8)).unwrap());
+		let digits: [Sprite; 10] = std::array::from_fn(|d| Sprite::new(Rect::from_xywh(d as i16 * 3, 0, 3, 5)));
+		let colon = Sprite::new(Rect::from_xywh(30, 0, 3, 5));
+		let boxed = NineSlicingSprite::new(Rect::from_xywh(33, 0, 3, 3), 1, 2, 1, 2);
+
+		let mut ui = UiContext::new(size(97, 97));
+		let display = ui.time_display(wk!(), time, sheet_id, boxed, colon, &digits);
+		(children(&ui, display.id()).into_iter())
+			.map(|wid| match ui.widget(wid).props.sprite {
+				Some(WidgetSprite::Simple(_, sprite)) if sprite == colon => ':',
+				Some(WidgetSprite::Simple(_, sprite)) => (b'0' + (sprite.x / 3) as u8) as char,
+				_ => '?',
+			})
+			.collect()
+	}
+
+	#[test]
+	fn time_displays_show_hours_only_on_long_runs() {
+		assert_eq!(shown_time(Duration::from_millis(65_432)), "01:05:432");
+		assert_eq!(shown_time(Duration::from_millis(3_599_999)), "59:59:999");
+		assert_eq!(shown_time(Duration::from_secs(3600)), "1:00:00:000");
+		assert_eq!(
+			shown_time(Duration::from_millis(((12 * 60 + 34) * 60 + 56) * 1000 + 7)),
+			"12:34:56:007"
+		);
+	}
 }
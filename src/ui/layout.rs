@@ -1,40 +1,89 @@
+use std::fmt;
+use std::ops::Range;
+
+use serde::Serialize;
+
 use crate::math::pos::Pos;
 use crate::math::rect::Rect;
 use crate::math::size::size;
 use crate::math::LayoutRect;
 use crate::render::Rotate;
 
-use super::{Anchor, FlexDirection, UiContext, WidgetDim, WidgetFlags, WidgetId, WidgetLayout, WidgetSprite};
+use super::{
+	Anchor, FlexDirection, UiContext, WidgetDim, WidgetFlags, WidgetId, WidgetKey, WidgetLayout, WidgetPadding,
+	WidgetSprite,
+};
 
 impl UiContext {
-	fn children_width_fills_count(&self, wid: WidgetId) -> usize {
-		let mut count = 0;
+	/// Children of a flex widget, in the order they're laid out.
+	fn flex_children(&self, first_child: Option<WidgetId>, direction: FlexDirection) -> Vec<WidgetId> {
+		let mut children = Vec::new();
 
-		let mut child_id = self.widget(wid).first_child;
+		let mut child_id = first_child;
 		while let Some(child) = child_id {
-			let child = self.widget(child);
-			if child.props.size.w == WidgetDim::Fill {
-				count += 1;
-			}
-			child_id = child.next;
+			children.push(child);
+			child_id = self.widget(child).next;
 		}
 
-		count
+		if direction.is_reverse() {
+			children.reverse();
+		}
+
+		children
 	}
 
-	fn children_height_fills_count(&self, wid: WidgetId) -> usize {
-		let mut count = 0;
+	/// Length a child takes along the main axis of a flex layout, before filling children get their share.
+	///
+	/// Like when laying them out, it's what the child was solved to last frame, except for filling children.
+	fn flex_child_len(&self, child: WidgetId, horizontal: bool) -> u16 {
+		let child = self.widget(child);
+		let (main_dim, _) = flex_axes(child.props.size.w, child.props.size.h, horizontal);
+		let size = match main_dim {
+			WidgetDim::Fill => child.solved_min_size,
+			_ => child.solved_rect.size(),
+		};
 
-		let mut child_id = self.widget(wid).first_child;
-		while let Some(child) = child_id {
-			let child = self.widget(child);
-			if child.props.size.h == WidgetDim::Fill {
-				count += 1;
+		flex_axes(size.w, size.h, horizontal).0
+	}
+
+	/// Splits the children of a wrapping flex layout into lines that fit in `main_len`, as ranges into `children`.
+	///
+	/// A child that doesn't fit on a line by itself still gets its own line.
+	fn flex_lines(&self, children: &[WidgetId], horizontal: bool, main_len: u16, gap: i16) -> Vec<Range<usize>> {
+		let mut lines = Vec::new();
+		let mut line_start = 0;
+		let mut line_len = 0;
+
+		for (i, &child) in children.iter().enumerate() {
+			let child_len = self.flex_child_len(child, horizontal) as i32;
+
+			if i == line_start {
+				line_len = child_len;
+			} else if line_len + gap as i32 + child_len > main_len as i32 {
+				lines.push(line_start..i);
+				line_start = i;
+				line_len = child_len;
+			} else {
+				line_len += gap as i32 + child_len;
 			}
-			child_id = child.next;
 		}
 
-		count
+		if line_start < children.len() {
+			lines.push(line_start..children.len());
+		}
+
+		lines
+	}
+
+	/// Length a line of a wrapping flex layout takes along the cross axis, which is its tallest child's.
+	fn flex_line_cross_len(&self, line: &[WidgetId], horizontal: bool) -> u16 {
+		(line.iter())
+			.map(|&child| {
+				let size = self.widget(child).solved_min_size;
+				flex_axes(size.w, size.h, horizontal).1
+			})
+			.max()
+			.unwrap_or(0)
 	}
 
 	/// Solve minimum sizes of all widgets recursively.
@@ -63,19 +112,19 @@ impl UiContext {
 
 					min_w
 				}
-				(Some(child), WidgetLayout::Flex { direction, gap }) => {
+				(Some(child), WidgetLayout::Flex { direction, gap, .. }) => {
 					let mut child = self.widget(child);
 					let mut min_w = child.solved_min_size.w;
 
-					match direction {
-						FlexDirection::Horizontal => {
+					match direction.is_horizontal() {
+						true => {
 							// add min sizes with gaps
 							while let Some(next_child) = child.next {
 								child = self.widget(next_child);
 								min_w += child.solved_min_size.w.saturating_add_signed(gap);
 							}
 						}
-						FlexDirection::Vertical => {
+						false => {
 							// take max of min sizes
 							while let Some(next_child) = child.next {
 								child = self.widget(next_child);
@@ -106,19 +155,19 @@ impl UiContext {
 
 					min_h
 				}
-				(Some(child), WidgetLayout::Flex { direction, gap }) => {
+				(Some(child), WidgetLayout::Flex { direction, gap, .. }) => {
 					let mut child = self.widget(child);
 					let mut min_h = child.solved_min_size.h;
 
-					match direction {
-						FlexDirection::Horizontal => {
+					match direction.is_horizontal() {
+						true => {
 							// take max of min sizes
 							while let Some(next_child) = child.next {
 								child = self.widget(next_child);
 								min_h = min_h.max(child.solved_min_size.h);
 							}
 						}
-						FlexDirection::Vertical => {
+						false => {
 							// add min sizes with gaps
 							while let Some(next_child) = child.next {
 								child = self.widget(next_child);
@@ -137,7 +186,12 @@ impl UiContext {
 		// take text into account
 		if widget.props.flags.has(WidgetFlags::DRAW_TEXT) {
 			if let Some(text) = &widget.props.text {
-				let text_size = text.size();
+				// Text in a fill box is wrapped once its width is known, see `solve_rects_rec`.
+				let text_size = match widget.props.size.w {
+					WidgetDim::Hug => text.size(),
+					WidgetDim::Fixed(width) => text.wrapped_size(width),
+					WidgetDim::Fill => size(0, text.size().h),
+				};
 				solved_min_width = solved_min_width.max(text_size.w);
 				solved_min_height = solved_min_height.max(text_size.h);
 			}
@@ -145,7 +199,12 @@ impl UiContext {
 
 		// take sprite into account
 		if widget.props.flags.has(WidgetFlags::DRAW_SPRITE) {
-			if let Some(WidgetSprite::Simple(_, sprite)) = &widget.props.sprite {
+			let sprite = widget
+				.props
+				.sprite
+				.as_ref()
+				.and_then(|sprite| self.resolve_sprite(sprite));
+			if let Some(WidgetSprite::Simple(_, sprite)) = sprite {
 				let (w, h) = match widget.props.rotate {
 					Rotate::R0 | Rotate::R180 => (sprite.w, sprite.h),
 					Rotate::R90 | Rotate::R270 => (sprite.h, sprite.w),
@@ -157,8 +216,8 @@ impl UiContext {
 		}
 
 		let padding = widget.props.padding;
-		widget.solved_min_size.w = solved_min_width.saturating_add_signed(padding.l + padding.r);
-		widget.solved_min_size.h = solved_min_height.saturating_add_signed(padding.t + padding.b);
+		widget.solved_min_size.w = solved_min_width.saturating_add_signed(padding.l.saturating_add(padding.r));
+		widget.solved_min_size.h = solved_min_height.saturating_add_signed(padding.t.saturating_add(padding.b));
 	}
 
 	/// Solve a widget's rect, based on the parent's solved rect.
@@ -166,18 +225,72 @@ impl UiContext {
 		let (current_solved_rect, layout, padding) = {
 			let mut widget = self.widget_mut(wid);
 
-			let solved_width = match widget.props.size.w {
+			let mut solved_width = match widget.props.size.w {
 				WidgetDim::Fixed(width) => width,
 				WidgetDim::Hug => widget.solved_min_size.w,
 				WidgetDim::Fill => parent_solved_rect.w,
 			};
 
-			let solved_height = match widget.props.size.h {
+			let mut solved_height = match widget.props.size.h {
 				WidgetDim::Fixed(height) => height,
 				WidgetDim::Hug => widget.solved_min_size.h,
 				WidgetDim::Fill => parent_solved_rect.h,
 			};
 
+			// wrapped text in a fill box can only know its height now
+			let wraps_text = widget.props.flags.has(WidgetFlags::DRAW_TEXT)
+				&& widget.props.size.w == WidgetDim::Fill
+				&& widget.props.size.h == WidgetDim::Hug;
+
+			if wraps_text {
+				if let Some(text) = &widget.props.text {
+					let padding = widget.props.padding;
+					let text_h = text.wrapped_size(solved_width).h;
+					solved_height =
+						solved_height.max(text_h.saturating_add_signed(padding.t.saturating_add(padding.b)));
+				}
+			}
+
+			// a wrapping flex layout only knows how many lines it has once its length is known
+			if let WidgetLayout::Flex {
+				direction,
+				gap,
+				wrap: true,
+			} = widget.props.layout
+			{
+				let horizontal = direction.is_horizontal();
+				let (_, cross_dim) = flex_axes(widget.props.size.w, widget.props.size.h, horizontal);
+
+				if cross_dim == WidgetDim::Hug {
+					let padding = widget.props.padding;
+					let (main_padding, cross_padding) = flex_axes(
+						padding.l.saturating_add(padding.r),
+						padding.t.saturating_add(padding.b),
+						horizontal,
+					);
+					let (main_len, _) = flex_axes(solved_width, solved_height, horizontal);
+
+					let children = self.flex_children(widget.first_child, direction);
+					let lines = self.flex_lines(
+						&children,
+						horizontal,
+						main_len.saturating_add_signed(-main_padding),
+						gap,
+					);
+
+					let gaps = gap as i32 * lines.len().saturating_sub(1) as i32;
+					let lines_len: i32 = (lines.iter())
+						.map(|line| self.flex_line_cross_len(&children[line.clone()], horizontal) as i32)
+						.sum();
+					let cross_len = (lines_len + gaps + cross_padding as i32).clamp(0, u16::MAX as i32) as u16;
+
+					match horizontal {
+						true => solved_height = solved_height.max(cross_len),
+						false => solved_width = solved_width.max(cross_len),
+					}
+				}
+			}
+
 			let solved_size = size(solved_width, solved_height);
 
 			let parent_layout_rect = LayoutRect::new(parent_solved_rect, Anchor::TOP_LEFT);
@@ -189,12 +302,7 @@ impl UiContext {
 			(widget.solved_rect, widget.props.layout, widget.props.padding)
 		};
 
-		let inner_solved_rect = Rect {
-			x: current_solved_rect.x + padding.l,
-			y: current_solved_rect.y + padding.t,
-			w: current_solved_rect.w.saturating_add_signed(-(padding.l + padding.r)),
-			h: current_solved_rect.h.saturating_add_signed(-(padding.t + padding.b)),
-		};
+		let inner_solved_rect = pad_rect(current_solved_rect, padding);
 
 		match layout {
 			WidgetLayout::Stacked => {
@@ -204,119 +312,489 @@ impl UiContext {
 					child_id = self.widget(child).next;
 				}
 			}
-			WidgetLayout::Flex { direction, gap } => match direction {
-				FlexDirection::Horizontal => {
-					let fills_count = self.children_width_fills_count(wid);
+			WidgetLayout::Flex { direction, gap, wrap } => {
+				let horizontal = direction.is_horizontal();
+				let children = self.flex_children(self.widget(wid).first_child, direction);
 
-					let filling_width = if fills_count == 0 {
-						0
-					} else {
-						let mut fixed_width: isize = 0;
+				let (inner_main_len, inner_cross_len) = flex_axes(inner_solved_rect.w, inner_solved_rect.h, horizontal);
+				let (main_start, mut cross) = flex_axes(inner_solved_rect.x, inner_solved_rect.y, horizontal);
 
-						let widget = self.widget(wid);
-						let mut child_id = widget.first_child;
-						while let Some(child) = child_id {
-							let child = self.widget(child);
-							match child.props.size.w {
-								WidgetDim::Fill => (),
-								_ => fixed_width += child.solved_rect.w as isize,
+				let lines = match wrap {
+					true => self.flex_lines(&children, horizontal, inner_main_len, gap),
+					false => std::iter::once(0..children.len()).collect(),
+				};
+
+				for line in lines {
+					let line = &children[line];
+
+					// without wrapping, the only line takes up the whole widget
+					let line_cross_len = match wrap {
+						true => self.flex_line_cross_len(line, horizontal),
+						false => inner_cross_len,
+					};
+
+					let mut fills_count = 0;
+					let mut fixed_len: isize = 0;
+					for &child in line {
+						let child = self.widget(child);
+						let (main_dim, _) = flex_axes(child.props.size.w, child.props.size.h, horizontal);
+						match main_dim {
+							WidgetDim::Fill => fills_count += 1,
+							_ => {
+								fixed_len += flex_axes(child.solved_rect.w, child.solved_rect.h, horizontal).0 as isize
 							}
-							child_id = child.next;
 						}
+					}
 
-						let gap_width = gap as isize * widget.children_count.saturating_sub(1) as isize;
-						let leftover_width = (inner_solved_rect.w as isize - fixed_width - gap_width).max(0);
+					let filling_len = if fills_count == 0 {
+						0
+					} else {
+						let gaps_len = gap as isize * line.len().saturating_sub(1) as isize;
+						let leftover_len = (inner_main_len as isize - fixed_len - gaps_len).max(0);
 
-						(leftover_width / fills_count as isize) as u16
+						(leftover_len / fills_count as isize) as u16
 					};
 
-					let mut x = inner_solved_rect.x;
-
-					let widget = self.widget(wid);
-					let mut child_id = widget.first_child;
-					while let Some(child) = child_id {
-						let (child_w, solved_w, child_next) = {
+					let mut main = main_start;
+					for &child in line {
+						let child_len = {
 							let child = self.widget(child);
-							(child.props.size.w, child.solved_rect.w, child.next)
+							let (main_dim, _) = flex_axes(child.props.size.w, child.props.size.h, horizontal);
+							match main_dim {
+								WidgetDim::Fill => filling_len,
+								_ => flex_axes(child.solved_rect.w, child.solved_rect.h, horizontal).0,
+							}
 						};
 
-						let child_width = match child_w {
-							WidgetDim::Fill => filling_width,
-							_ => solved_w,
+						let child_parent_rect = match horizontal {
+							true => Rect::from_xywh(main, cross, child_len, line_cross_len),
+							false => Rect::from_xywh(cross, main, line_cross_len, child_len),
 						};
+						self.solve_rects_rec(child, child_parent_rect);
 
-						let inner_solved_rect = Rect {
-							x,
-							w: child_width,
-							..inner_solved_rect
-						};
+						main += child_len as i16 + gap;
+					}
+
+					cross += line_cross_len as i16 + gap;
+				}
+			}
+		}
+	}
+
+	/// Top-left corner of a widget's content area, inside its padding, as solved by the last call to
+	/// [`UiContext::solve_layout`]. This is where its children's positions start from.
+	///
+	/// Gives `None` if the widget doesn't exist or was freed.
+	#[allow(unused)]
+	pub fn widget_content_origin(&self, wid: WidgetId) -> Option<Pos> {
+		let widget = self.widgets.get(Self::index_from_id(wid))?.borrow();
+		(!widget.freed).then(|| pad_rect(widget.solved_rect, widget.props.padding).pos())
+	}
+
+	/// Captures the solved layout of a widget and all its children, to compare layouts without rendering them.
+	///
+	/// This should be called after [`UiContext::solve_layout`].
+	pub fn dump_layout(&self, wid: WidgetId) -> LayoutNode {
+		let widget = self.widget(wid);
+
+		let mut children = Vec::with_capacity(widget.children_count);
+		let mut child_id = widget.first_child;
+		while let Some(child) = child_id {
+			children.push(self.dump_layout(child));
+			child_id = self.widget(child).next;
+		}
+
+		LayoutNode {
+			key: widget.props.key,
+			debug_name: widget.props.debug_name,
+			rect: widget.solved_rect,
+			flags: widget.props.flags,
+			children,
+		}
+	}
+
+	pub fn solve_layout(&self) {
+		self.solve_min_sizes_rec(Self::ROOT_WIDGET);
+
+		// pretend the parent of the root widget is the framebuffer
+		self.solve_rects_rec(Self::ROOT_WIDGET, Rect::from_pos_size(Pos::ZERO, self.viewport_size));
+	}
+}
 
-						self.solve_rects_rec(child, inner_solved_rect);
+/// The solved layout of a widget and its children, as dumped by [`UiContext::dump_layout`].
+///
+/// It can be serialized with serde, and its [`Display`](fmt::Display) implementation also writes it as an indented
+/// tree with one widget per line, ending with the widget's debug name if it has one, which is what golden layouts are
+/// written in:
+///
+/// ```ignore
+/// 0123456789abcdef 0,0 97x124 flags(0x60) window_frame
+///   fedcba9876543210 1,1 95x8 flags(0x8)
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LayoutNode {
+	pub key: WidgetKey,
+	pub debug_name: Option<&'static str>,
+	pub rect: Rect,
+	pub flags: WidgetFlags,
+	pub children: Vec<LayoutNode>,
+}
 
-						x += child_width as i16 + gap;
-						child_id = child_next;
+#[allow(unused)]
+impl LayoutNode {
+	/// Compares this layout to a golden serialized layout, line by line.
+	///
+	/// Returns the line number with the expected and actual lines of the first difference, if any.
+	pub fn diff_golden(&self, golden: &str) -> Option<(usize, String, String)> {
+		let dump = self.to_string();
+		let mut dump_lines = dump.lines();
+		let mut golden_lines = golden.trim().lines();
+
+		for line_number in 1.. {
+			match (golden_lines.next(), dump_lines.next()) {
+				(None, None) => return None,
+				(expected, actual) => {
+					let expected = expected.unwrap_or_default().trim_end();
+					let actual = actual.unwrap_or_default().trim_end();
+					if expected != actual {
+						return Some((line_number, expected.to_owned(), actual.to_owned()));
 					}
 				}
-				FlexDirection::Vertical => {
-					let fills_count = self.children_height_fills_count(wid);
+			}
+		}
 
-					let filling_height = if fills_count == 0 {
-						0
-					} else {
-						let mut fixed_height: isize = 0;
+		None
+	}
 
-						let widget = self.widget(wid);
-						let mut child_id = widget.first_child;
-						while let Some(child) = child_id {
-							let child = self.widget(child);
-							match child.props.size.h {
-								WidgetDim::Fill => (),
-								_ => fixed_height += child.solved_rect.h as isize,
-							}
-							child_id = child.next;
-						}
+	fn fmt_rec(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+		let Rect { x, y, w, h } = self.rect;
+		let indent = depth * 2;
+		write!(
+			f,
+			"{:indent$}{:016x} {x},{y} {w}x{h} flags({:#x})",
+			"", self.key.0, self.flags.0
+		)?;
+		match self.debug_name {
+			Some(name) => writeln!(f, " {name}")?,
+			None => writeln!(f)?,
+		}
 
-						let gap_height = gap as isize * widget.children_count.saturating_sub(1) as isize;
-						let leftover_height = (inner_solved_rect.h as isize - fixed_height - gap_height).max(0);
+		for child in &self.children {
+			child.fmt_rec(f, depth + 1)?;
+		}
 
-						(leftover_height / fills_count as isize) as u16
-					};
+		Ok(())
+	}
+}
 
-					let mut y = inner_solved_rect.y;
+impl fmt::Display for LayoutNode {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.fmt_rec(f, 0)
+	}
+}
 
-					let widget = self.widget(wid);
-					let mut child_id = widget.first_child;
-					while let Some(child) = child_id {
-						let (child_h, solved_h, child_next) = {
-							let child = self.widget(child);
-							(child.props.size.h, child.solved_rect.h, child.next)
-						};
+/// Sorts a pair of horizontal and vertical values into the main and cross axes of a flex layout, as `(main, cross)`.
+#[inline]
+fn flex_axes<T>(x: T, y: T, horizontal: bool) -> (T, T) {
+	match horizontal {
+		true => (x, y),
+		false => (y, x),
+	}
+}
 
-						let child_height = match child_h {
-							WidgetDim::Fill => filling_height,
-							_ => solved_h,
-						};
+/// Clamps the padding on both sides of a dimension so that it never exceeds its length.
+///
+/// The start side takes priority over the end side.
+fn clamp_padding(len: u16, start: i16, end: i16) -> (i32, i32) {
+	let start = (start as i32).min(len as i32);
+	let end = (end as i32).min(len as i32 - start);
+	(start, end)
+}
 
-						let inner_solved_rect = Rect {
-							y,
-							h: child_height,
-							..inner_solved_rect
-						};
+/// Shrinks a rect by some padding to get its content rect.
+///
+/// If the padding is larger than the rect, the content just ends up with a size of zero.
+fn pad_rect(rect: Rect, padding: WidgetPadding) -> Rect {
+	let (l, r) = clamp_padding(rect.w, padding.l, padding.r);
+	let (t, b) = clamp_padding(rect.h, padding.t, padding.b);
+
+	Rect {
+		x: (rect.x as i32 + l).clamp(i16::MIN as i32, i16::MAX as i32) as i16,
+		y: (rect.y as i32 + t).clamp(i16::MIN as i32, i16::MAX as i32) as i16,
+		w: (rect.w as i32 - l - r).clamp(0, u16::MAX as i32) as u16,
+		h: (rect.h as i32 - t - b).clamp(0, u16::MAX as i32) as u16,
+	}
+}
 
-						self.solve_rects_rec(child, inner_solved_rect);
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::math::pos::pos;
+	use crate::math::size::Size;
+	use crate::ui::{FlexDirection, WidgetLayout, WidgetProps, WidgetSize};
+	use crate::wk;
+
+	/// A UI with a root widget filling the viewport, and a fixed-size widget with some padding in it, wrapping a
+	/// content widget that fills it.
+	fn padded_widget(w: u16, h: u16, padding: WidgetPadding) -> (UiContext, WidgetId) {
+		let mut ui = UiContext::new(size(32, 32));
+		let root = ui.build_widget(WidgetProps::new(wk!()).with_size(WidgetSize::fill()));
+		let padded = ui.build_widget(
+			WidgetProps::new(wk!())
+				.with_size(WidgetSize::fixed(w, h))
+				.with_padding(padding),
+		);
+		let content = ui.build_widget(WidgetProps::new(wk!()).with_size(WidgetSize::fill()));
+		ui.add_child(root.id(), padded.id());
+		ui.add_child(padded.id(), content.id());
+
+		ui.solve_layout();
+		(ui, content.id())
+	}
 
-						y += child_height as i16 + gap;
-						child_id = child_next;
-					}
-				}
-			},
+	#[test]
+	fn padding_as_large_as_the_widget_leaves_no_content() {
+		let (ui, content) = padded_widget(4, 4, WidgetPadding::all(3));
+		assert_eq!(ui.widget_rect(content).unwrap().size(), Size::ZERO);
+	}
+
+	#[test]
+	fn oversized_padding_leaves_no_content() {
+		for padding in [
+			WidgetPadding::all(5),
+			WidgetPadding::all(i16::MAX),
+			WidgetPadding::trbl(0, i16::MAX, 0, i16::MAX),
+			WidgetPadding::trbl(i16::MAX, 0, i16::MAX, 0),
+		] {
+			let (ui, content) = padded_widget(4, 4, padding);
+			let content_rect = ui.widget_rect(content).unwrap();
+			assert!(
+				content_rect.w == 0 || content_rect.h == 0,
+				"{padding:?} gave {content_rect:?}"
+			);
 		}
+
+		let (ui, content) = padded_widget(4, 4, WidgetPadding::all(i16::MAX));
+		assert_eq!(ui.widget_rect(content).unwrap().size(), Size::ZERO);
 	}
 
-	pub fn solve_layout(&self) {
-		self.solve_min_sizes_rec(Self::ROOT_WIDGET);
+	#[test]
+	fn padding_never_goes_past_the_rect() {
+		let rect = Rect::from_xywh(10, 10, 4, 4);
+		assert_eq!(pad_rect(rect, WidgetPadding::all(3)), Rect::from_xywh(13, 13, 0, 0));
+		assert_eq!(
+			pad_rect(rect, WidgetPadding::trbl(1, 9, 1, 2)),
+			Rect::from_xywh(12, 11, 0, 2)
+		);
+		assert_eq!(pad_rect(rect, WidgetPadding::all(1)), Rect::from_xywh(11, 11, 2, 2));
+	}
 
-		// pretend the parent of the root widget is the framebuffer
-		self.solve_rects_rec(Self::ROOT_WIDGET, Rect::from_pos_size(Pos::ZERO, self.viewport_size));
+	/// A padded column with a gap, holding two fixed-size widgets, with keys that don't depend on where it's built.
+	fn small_tree() -> LayoutNode {
+		let mut ui = UiContext::new(size(32, 32));
+		let root = ui.build_widget(WidgetProps::new(WidgetKey(1)).with_size(WidgetSize::fill()));
+		let column = ui.build_widget(
+			WidgetProps::new(WidgetKey(2))
+				.with_debug_name("column")
+				.with_size(WidgetSize::hug())
+				.with_padding(WidgetPadding::all(1))
+				.with_layout(WidgetLayout::flex(FlexDirection::Vertical, 2)),
+		);
+		let first = ui.build_widget(WidgetProps::new(WidgetKey(3)).with_size(WidgetSize::fixed(6, 4)));
+		let second = ui.build_widget(WidgetProps::new(WidgetKey(4)).with_size(WidgetSize::fixed(4, 3)));
+		ui.add_child(root.id(), column.id());
+		ui.add_child(column.id(), first.id());
+		ui.add_child(column.id(), second.id());
+
+		// flex children are laid out by what they were solved to last frame, so it takes a second frame to settle
+		ui.solve_layout();
+		ui.solve_layout();
+		ui.dump_layout(root.id())
+	}
+
+	#[test]
+	fn small_tree_matches_its_golden_layout() {
+		let golden = "
+0000000000000001 0,0 32x32 flags(0x0)
+  0000000000000002 0,0 8x11 flags(0x0) column
+    0000000000000003 1,1 6x4 flags(0x0)
+    0000000000000004 1,7 4x3 flags(0x0)
+";
+		assert_eq!(small_tree().diff_golden(golden), None);
+
+		let moved = golden.replace("1,7 4x3", "1,6 4x3");
+		assert_eq!(
+			small_tree().diff_golden(&moved),
+			Some((
+				4,
+				"    0000000000000004 1,6 4x3 flags(0x0)".into(),
+				"    0000000000000004 1,7 4x3 flags(0x0)".into()
+			))
+		);
+		assert_eq!(small_tree().diff_golden("").map(|(line, ..)| line), Some(1));
+	}
+
+	#[test]
+	fn layout_serializes_every_field() {
+		let json = serde_json::to_value(small_tree()).unwrap();
+		let column = &json["children"][0];
+		assert_eq!(column["key"], 2);
+		assert_eq!(column["debug_name"], "column");
+		assert_eq!(column["rect"], serde_json::json!({ "x": 0, "y": 0, "w": 8, "h": 11 }));
+		assert_eq!(column["flags"], 0);
+		assert_eq!(column["children"][1]["rect"]["y"], 7);
+		assert_eq!(json["debug_name"], serde_json::Value::Null);
+	}
+
+	#[test]
+	fn debug_names_show_up_on_their_widgets_in_the_dump() {
+		let mut ui = UiContext::new(size(16, 16));
+		let root = ui.build_widget(WidgetProps::new(WidgetKey(1)).with_size(WidgetSize::fill()));
+		let unnamed = ui.build_widget(WidgetProps::new(WidgetKey(2)).with_size(WidgetSize::fill()));
+		let named = ui.build_widget(
+			WidgetProps::new(WidgetKey(3))
+				.with_debug_name("deep_button")
+				.with_size(WidgetSize::fixed(2, 2)),
+		);
+		ui.add_child(root.id(), unnamed.id());
+		ui.add_child(unnamed.id(), named.id());
+		ui.solve_layout();
+
+		let dump = ui.dump_layout(root.id());
+		assert_eq!(dump.debug_name, None);
+		assert_eq!(dump.children[0].debug_name, None);
+		assert_eq!(dump.children[0].children[0].debug_name, Some("deep_button"));
+
+		let text = dump.to_string();
+		let named_lines: Vec<_> = text.lines().filter(|line| line.contains("deep_button")).collect();
+		assert_eq!(named_lines, ["    0000000000000003 0,0 2x2 flags(0x0) deep_button"]);
+	}
+
+	/// Where a flex layout of some size puts a 4x3, a 5x3 and a 6x3 widget, in that order, with a gap of 1.
+	fn flexed(direction: FlexDirection, wrap: bool, w: u16, h: u16) -> Vec<Rect> {
+		let mut ui = UiContext::new(size(32, 32));
+		let root = ui.build_widget(WidgetProps::new(wk!()).with_size(WidgetSize::fill()));
+		let flex = ui.build_widget(
+			WidgetProps::new(wk!())
+				.with_size(WidgetSize::fixed(w, h))
+				.with_layout(WidgetLayout::flex(direction, 1).with_wrap(wrap)),
+		);
+		ui.add_child(root.id(), flex.id());
+
+		let children: Vec<WidgetId> = (0..3u64)
+			.map(|i| {
+				let child = ui.build_widget(WidgetProps::new(wk!(i)).with_size(WidgetSize::fixed(4 + i as u16, 3)));
+				ui.add_child(flex.id(), child.id());
+				child.id()
+			})
+			.collect();
+
+		ui.solve_layout();
+		ui.solve_layout();
+		children
+			.into_iter()
+			.map(|child| ui.widget_rect(child).unwrap())
+			.collect()
+	}
+
+	#[test]
+	fn reversed_flex_lays_children_out_last_to_first() {
+		assert_eq!(
+			flexed(FlexDirection::Horizontal, false, 20, 20),
+			[
+				Rect::from_xywh(0, 0, 4, 3),
+				Rect::from_xywh(5, 0, 5, 3),
+				Rect::from_xywh(11, 0, 6, 3)
+			]
+		);
+		assert_eq!(
+			flexed(FlexDirection::HorizontalReverse, false, 20, 20),
+			[
+				Rect::from_xywh(13, 0, 4, 3),
+				Rect::from_xywh(7, 0, 5, 3),
+				Rect::from_xywh(0, 0, 6, 3)
+			]
+		);
+		assert_eq!(
+			flexed(FlexDirection::VerticalReverse, false, 20, 20),
+			[
+				Rect::from_xywh(0, 8, 4, 3),
+				Rect::from_xywh(0, 4, 5, 3),
+				Rect::from_xywh(0, 0, 6, 3)
+			]
+		);
+	}
+
+	#[test]
+	fn wrapping_flex_moves_overflowing_children_to_a_new_line() {
+		// the third child doesn't fit after the first two
+		assert_eq!(
+			flexed(FlexDirection::Horizontal, true, 14, 20),
+			[
+				Rect::from_xywh(0, 0, 4, 3),
+				Rect::from_xywh(5, 0, 5, 3),
+				Rect::from_xywh(0, 4, 6, 3)
+			]
+		);
+		// a line always has at least one child, even if it overflows
+		assert_eq!(
+			flexed(FlexDirection::Horizontal, true, 3, 20),
+			[
+				Rect::from_xywh(0, 0, 4, 3),
+				Rect::from_xywh(0, 4, 5, 3),
+				Rect::from_xywh(0, 8, 6, 3)
+			]
+		);
+		// without wrapping, they stay on the same line
+		assert_eq!(
+			flexed(FlexDirection::Horizontal, false, 14, 20)[2],
+			Rect::from_xywh(11, 0, 6, 3)
+		);
+
+		// wrapping vertically starts new columns
+		assert_eq!(
+			flexed(FlexDirection::Vertical, true, 20, 7),
+			[
+				Rect::from_xywh(0, 0, 4, 3),
+				Rect::from_xywh(0, 4, 5, 3),
+				Rect::from_xywh(6, 0, 6, 3)
+			]
+		);
+	}
+
+	#[test]
+	fn content_origins_are_offset_by_the_padding() {
+		let mut ui = UiContext::new(size(32, 32));
+		let root = ui.build_widget(
+			WidgetProps::new(wk!())
+				.with_size(WidgetSize::fill())
+				.with_padding(WidgetPadding::all(2)),
+		);
+		let container = ui.build_widget(
+			WidgetProps::new(wk!())
+				.with_size(WidgetSize::fixed(20, 20))
+				.with_padding(WidgetPadding::trbl(1, 2, 3, 4)),
+		);
+		let child = ui.build_widget(WidgetProps::new(wk!()).with_size(WidgetSize::fixed(2, 2)));
+		ui.add_child(root.id(), container.id());
+		ui.add_child(container.id(), child.id());
+		ui.solve_layout();
+
+		assert_eq!(ui.widget_content_origin(root.id()), Some(pos(2, 2)));
+		assert_eq!(ui.widget_rect(container.id()).unwrap().pos(), pos(2, 2));
+		assert_eq!(ui.widget_content_origin(container.id()), Some(pos(6, 3)));
+		// which is where the children go
+		assert_eq!(ui.widget_rect(child.id()).unwrap().pos(), pos(6, 3));
+		assert_eq!(ui.widget_content_origin(child.id()), Some(pos(6, 3)));
+	}
+
+	#[test]
+	fn freed_widgets_have_no_content_origin() {
+		let (mut ui, content) = padded_widget(8, 8, WidgetPadding::all(1));
+		assert!(ui.widget_content_origin(content).is_some());
+
+		ui.free_untouched_widgets();
+		ui.free_untouched_widgets();
+		assert_eq!(ui.widget_content_origin(content), None);
 	}
 }